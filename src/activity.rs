@@ -0,0 +1,70 @@
+//! Connection activity on local ports, for stopping forwards nobody uses.
+//!
+//! pfman doesn't see the traffic itself (ssh and kubectl own the ports), so
+//! activity is sampled: a forward counts as used while it has established
+//! connections. On Linux they are read from `/proc/net/tcp{,6}`, elsewhere
+//! from `lsof`.
+
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+
+/// Connections through a running session's local port at the last sample
+#[derive(Debug, Clone, PartialEq)]
+pub struct PortActivity {
+    pub connections: usize,
+    /// Last sample that found connections
+    pub last_active: Option<DateTime<Utc>>,
+}
+
+/// Established connections accepted on each of `ports`
+pub fn established_connections(ports: &[u16]) -> HashMap<u16, usize> {
+    let mut counts: HashMap<u16, usize> = ports.iter().map(|&p| (p, 0)).collect();
+    for port in local_ports_of_established() {
+        if let Some(count) = counts.get_mut(&port) {
+            *count += 1;
+        }
+    }
+    counts
+}
+
+/// Local port of every established TCP socket
+#[cfg(target_os = "linux")]
+fn local_ports_of_established() -> Vec<u16> {
+    const ESTABLISHED: &str = "01";
+    ["/proc/net/tcp", "/proc/net/tcp6"]
+        .iter()
+        .filter_map(|path| std::fs::read_to_string(path).ok())
+        .flat_map(|table| {
+            table
+                .lines()
+                .skip(1)
+                .filter_map(|line| {
+                    // sl local_address rem_address st ...
+                    let fields: Vec<&str> = line.split_whitespace().collect();
+                    if fields.get(3) != Some(&ESTABLISHED) {
+                        return None;
+                    }
+                    let (_, port) = fields.get(1)?.rsplit_once(':')?;
+                    u16::from_str_radix(port, 16).ok()
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn local_ports_of_established() -> Vec<u16> {
+    let Ok(output) = std::process::Command::new("lsof")
+        .args(["-nP", "-iTCP", "-sTCP:ESTABLISHED", "-Fn"])
+        .output()
+    else {
+        return Vec::new();
+    };
+    // Name lines look like `n127.0.0.1:5432->127.0.0.1:61234`
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.strip_prefix('n'))
+        .filter_map(|name| name.split_once("->"))
+        .filter_map(|(local, _)| local.rsplit_once(':')?.1.parse().ok())
+        .collect()
+}
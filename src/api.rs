@@ -0,0 +1,524 @@
+//! Local HTTP API for editors, scripts and launcher workflows (Raycast,
+//! Alfred). It only listens on localhost or a unix socket. Connections are
+//! read on background threads; the requests are answered by the UI loop,
+//! which owns the sessions, so the API and the TUI never disagree.
+//!
+//! - `GET /sessions` lists the sessions, in the `status.json` format
+//! - `POST /sessions` creates sessions from a body in the import format
+//! - `POST /sessions/<id|name>/start` and `.../stop`
+//! - `POST /pause` stops every session that is up, `POST /resume` starts
+//!   that set again (see [`crate::pause`])
+//!
+//! There is no authentication, so web pages must not be able to reach it:
+//! requests from a browser (with an `Origin`) or for another host name (DNS
+//! rebinding) are refused, created sessions need a JSON or YAML content type
+//! (which a page can't send without asking first), and they never bring
+//! commands along, e.g. a `pre_start`.
+
+use crate::config::ApiListen;
+use crate::heartbeat::StatusReport;
+use crate::import;
+use crate::models::{Session, SessionStatus};
+use crate::pause;
+use crate::ui::AppState;
+use serde_json::json;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, ToSocketAddrs};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// How long a connection may take to send its request, and how long it
+/// waits for the UI loop to answer
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+/// Largest request body accepted
+const MAX_BODY: usize = 1024 * 1024;
+/// Content types `POST /sessions` accepts
+const BODY_TYPES: &[&str] = &[
+    "application/json",
+    "application/yaml",
+    "application/x-yaml",
+    "text/yaml",
+];
+
+/// What a request asks for, once the HTTP is stripped off
+#[derive(Debug)]
+pub enum ApiCommand {
+    List,
+    Start(String),
+    Stop(String),
+    /// Sessions in the import format (YAML or JSON)
+    Create(String),
+    Pause,
+    Resume,
+}
+
+#[derive(Debug)]
+pub struct ApiResponse {
+    pub(crate) status: u16,
+    pub(crate) body: serde_json::Value,
+}
+
+impl ApiResponse {
+    fn ok(body: serde_json::Value) -> Self {
+        Self { status: 200, body }
+    }
+
+    pub(crate) fn error(status: u16, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            body: json!({ "error": message.into() }),
+        }
+    }
+}
+
+struct ApiRequest {
+    command: ApiCommand,
+    reply: Sender<ApiResponse>,
+}
+
+/// Accepts API connections in the background and queues their requests for
+/// [`ApiServer::serve`]
+pub struct ApiServer {
+    requests: Receiver<ApiRequest>,
+    socket: Option<PathBuf>,
+}
+
+impl ApiServer {
+    /// Start listening. Addresses other than localhost are refused: the API
+    /// has no authentication.
+    pub fn spawn(listen: &ApiListen) -> Result<Self, String> {
+        let (tx, requests) = mpsc::channel();
+        match listen {
+            ApiListen::Tcp(addr) => {
+                let addrs: Vec<_> = addr
+                    .to_socket_addrs()
+                    .map_err(|e| format!("{}: {}", addr, e))?
+                    .collect();
+                if addrs.is_empty() || addrs.iter().any(|a| !a.ip().is_loopback()) {
+                    return Err(format!("{} is not a localhost address", addr));
+                }
+                let listener =
+                    TcpListener::bind(&addrs[..]).map_err(|e| format!("{}: {}", addr, e))?;
+                thread::spawn(move || {
+                    for stream in listener.incoming().flatten() {
+                        let _ = stream.set_read_timeout(Some(REQUEST_TIMEOUT));
+                        spawn_connection(stream, tx.clone());
+                    }
+                });
+                Ok(Self {
+                    requests,
+                    socket: None,
+                })
+            }
+            #[cfg(unix)]
+            ApiListen::Unix(path) => {
+                use std::os::unix::fs::PermissionsExt;
+                use std::os::unix::net::UnixListener;
+
+                let path = crate::kube_config::expand_home(path);
+                // Left behind by an instance that didn't shut down cleanly
+                let _ = std::fs::remove_file(&path);
+                let listener =
+                    UnixListener::bind(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+                let _ = std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600));
+                thread::spawn(move || {
+                    for stream in listener.incoming().flatten() {
+                        let _ = stream.set_read_timeout(Some(REQUEST_TIMEOUT));
+                        spawn_connection(stream, tx.clone());
+                    }
+                });
+                Ok(Self {
+                    requests,
+                    socket: Some(path),
+                })
+            }
+            #[cfg(not(unix))]
+            ApiListen::Unix(_) => Err("unix sockets are not supported here".to_string()),
+        }
+    }
+
+    /// Answer the queued requests. Returns whether sessions were changed,
+    /// so the caller saves them.
+    pub fn serve(&self, state: &mut AppState) -> bool {
+        let mut changed = false;
+        while let Ok(request) = self.requests.try_recv() {
+            let (response, modified) = handle(state, request.command);
+            changed |= modified;
+            let _ = request.reply.send(response);
+        }
+        changed
+    }
+}
+
+impl Drop for ApiServer {
+    fn drop(&mut self) {
+        if let Some(path) = &self.socket {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+/// Carry out `command` on the UI loop's sessions. Also returns whether
+/// sessions were changed.
+pub(crate) fn handle(state: &mut AppState, command: ApiCommand) -> (ApiResponse, bool) {
+    match command {
+        ApiCommand::List => (ApiResponse::ok(report(state, &state.sessions)), false),
+        ApiCommand::Start(selector) => match find(&state.sessions, &selector) {
+            Ok(idx) => {
+                let session = &mut state.sessions[idx];
+                if !matches!(
+                    session.status,
+                    SessionStatus::Running | SessionStatus::Degraded(_) | SessionStatus::Starting
+                ) && let Err(err) = state.process_manager.start_session(session)
+                {
+                    return (ApiResponse::error(500, err.to_string()), true);
+                }
+                (
+                    ApiResponse::ok(report(state, &state.sessions[idx..=idx])),
+                    true,
+                )
+            }
+            Err(response) => (response, false),
+        },
+        ApiCommand::Stop(selector) => match find(&state.sessions, &selector) {
+            Ok(idx) => {
+                if let Err(err) = state.process_manager.stop_session(&mut state.sessions[idx]) {
+                    return (ApiResponse::error(500, err.to_string()), true);
+                }
+                (
+                    ApiResponse::ok(report(state, &state.sessions[idx..=idx])),
+                    true,
+                )
+            }
+            Err(response) => (response, false),
+        },
+        ApiCommand::Create(body) => {
+            let mut sessions = match import::parse_sessions(&body) {
+                Ok(sessions) => sessions,
+                Err(err) => return (ApiResponse::error(400, err), false),
+            };
+            let policy = state.process_manager.policy();
+            if let Some(reason) = sessions
+                .iter()
+                .find_map(|s| policy.check(s).err().map(|r| format!("{}: {}", s.name, r)))
+            {
+                return (
+                    ApiResponse::error(403, format!("Blocked by policy: {}", reason)),
+                    false,
+                );
+            }
+            // Anything that reaches the port could otherwise run a shell
+            // command on the next start
+            for session in &mut sessions {
+                session.health_command = None;
+                session.pre_start = None;
+                session.post_stop = None;
+            }
+            import::resolve_name_conflicts(&mut sessions, &state.sessions);
+            let mut response = ApiResponse::ok(report(state, &sessions));
+            response.status = 201;
+            state.sessions.extend(sessions);
+            (response, true)
+        }
+        ApiCommand::Pause | ApiCommand::Resume => {
+            let outcomes = match command {
+                ApiCommand::Pause => pause::pause(&mut state.sessions, &state.process_manager),
+                _ => pause::resume(&mut state.sessions, &state.process_manager),
+            };
+            let affected: Vec<Session> = state
+                .sessions
+                .iter()
+                .filter(|s| outcomes.iter().any(|(id, _)| *id == s.id))
+                .cloned()
+                .collect();
+            (ApiResponse::ok(report(state, &affected)), true)
+        }
+    }
+}
+
+/// Index of the session with the ID, short ID or name `selector`
+fn find(sessions: &[Session], selector: &str) -> Result<usize, ApiResponse> {
+    // A name that happens to look like an ID prefix means the name
+    if let Some(idx) = sessions
+        .iter()
+        .position(|s| s.name.eq_ignore_ascii_case(selector))
+    {
+        return Ok(idx);
+    }
+    let by_id: Vec<usize> = (0..sessions.len())
+        .filter(|&i| {
+            sessions[i].has_id(selector) || Uuid::parse_str(selector) == Ok(sessions[i].id)
+        })
+        .collect();
+    match by_id[..] {
+        [idx] => Ok(idx),
+        [] => Err(ApiResponse::error(
+            404,
+            format!("no session named {}", selector),
+        )),
+        _ => Err(ApiResponse::error(
+            409,
+            format!("{} matches {} sessions", selector, by_id.len()),
+        )),
+    }
+}
+
+fn report(state: &AppState, sessions: &[Session]) -> serde_json::Value {
+    let report = StatusReport::new(sessions, &state.process_manager.latencies())
+        .with_health(&state.process_manager.health_checks());
+    json!({ "sessions": report.sessions })
+}
+
+fn spawn_connection<S: Read + Write + Send + 'static>(stream: S, requests: Sender<ApiRequest>) {
+    thread::spawn(move || {
+        let mut reader = BufReader::new(stream);
+        let response = match read_request(&mut reader) {
+            Ok(command) => {
+                let (reply, answer) = mpsc::channel();
+                let _ = requests.send(ApiRequest { command, reply });
+                answer
+                    .recv_timeout(REQUEST_TIMEOUT)
+                    .unwrap_or_else(|_| ApiResponse::error(503, "pfman is not answering"))
+            }
+            Err(response) => response,
+        };
+        let _ = write_response(reader.get_mut(), &response);
+    });
+}
+
+/// Parse the request line, headers and body into a command
+fn read_request(reader: &mut impl BufRead) -> Result<ApiCommand, ApiResponse> {
+    let mut request_line = String::new();
+    reader
+        .read_line(&mut request_line)
+        .map_err(|_| ApiResponse::error(400, "unreadable request"))?;
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Err(ApiResponse::error(400, "malformed request line"));
+    };
+
+    let mut content_length = 0;
+    let mut host = None;
+    let mut origin = false;
+    let mut content_type = String::new();
+    loop {
+        let mut header = String::new();
+        match reader.read_line(&mut header) {
+            Ok(0) => break,
+            Ok(_) if header.trim().is_empty() => break,
+            Ok(_) => {
+                let Some((name, value)) = header.split_once(':') else {
+                    continue;
+                };
+                let value = value.trim();
+                match name.trim().to_ascii_lowercase().as_str() {
+                    "content-length" => content_length = value.parse().unwrap_or(0),
+                    "host" => host = Some(value.to_string()),
+                    "origin" => origin = true,
+                    "content-type" => content_type = value.to_ascii_lowercase(),
+                    _ => {}
+                }
+            }
+            Err(_) => return Err(ApiResponse::error(400, "unreadable headers")),
+        }
+    }
+    if origin {
+        return Err(ApiResponse::error(
+            403,
+            "requests from web pages are refused",
+        ));
+    }
+    if !host.as_deref().is_some_and(is_loopback_host) {
+        return Err(ApiResponse::error(403, "the Host must be localhost"));
+    }
+    if content_length > MAX_BODY {
+        return Err(ApiResponse::error(413, "request body too large"));
+    }
+    let mut body = vec![0; content_length];
+    reader
+        .read_exact(&mut body)
+        .map_err(|_| ApiResponse::error(400, "incomplete request body"))?;
+
+    let path = target.split('?').next().unwrap_or_default();
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    match (method, &segments[..]) {
+        ("GET", ["sessions"]) => Ok(ApiCommand::List),
+        ("POST", ["sessions"]) => {
+            let media_type = content_type.split(';').next().unwrap_or_default().trim();
+            if !BODY_TYPES.contains(&media_type) {
+                return Err(ApiResponse::error(
+                    415,
+                    "the Content-Type must be application/json or application/yaml",
+                ));
+            }
+            Ok(ApiCommand::Create(
+                String::from_utf8_lossy(&body).into_owned(),
+            ))
+        }
+        ("POST", ["sessions", selector, "start"]) => Ok(ApiCommand::Start(decode(selector))),
+        ("POST", ["sessions", selector, "stop"]) => Ok(ApiCommand::Stop(decode(selector))),
+        ("POST", ["pause"]) => Ok(ApiCommand::Pause),
+        ("POST", ["resume"]) => Ok(ApiCommand::Resume),
+        (_, ["sessions"] | ["sessions", _, "start" | "stop"] | ["pause" | "resume"]) => {
+            Err(ApiResponse::error(405, "method not allowed"))
+        }
+        _ => Err(ApiResponse::error(404, "not found")),
+    }
+}
+
+/// Whether a `Host` header names this machine: `localhost` or a loopback
+/// address, with or without a port
+fn is_loopback_host(host: &str) -> bool {
+    let name = match host.strip_prefix('[') {
+        // [::1]:7878
+        Some(rest) => rest.split(']').next().unwrap_or_default(),
+        None => host.rsplit_once(':').map_or(host, |(name, _)| name),
+    };
+    name.eq_ignore_ascii_case("localhost")
+        || name
+            .parse::<std::net::IpAddr>()
+            .is_ok_and(|ip| ip.is_loopback())
+}
+
+/// Undo percent-encoding, e.g. a space in a session name sent as `%20`
+fn decode(segment: &str) -> String {
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn write_response(stream: &mut impl Write, response: &ApiResponse) -> std::io::Result<()> {
+    let body = serde_json::to_string_pretty(&response.body)?;
+    let reason = match response.status {
+        200 => "OK",
+        201 => "Created",
+        400 => "Bad Request",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        413 => "Payload Too Large",
+        415 => "Unsupported Media Type",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.status,
+        reason,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(text: &str) -> Result<ApiCommand, ApiResponse> {
+        read_request(&mut text.as_bytes())
+    }
+
+    fn status(text: &str) -> u16 {
+        request(text).map_or_else(|response| response.status, |_| 200)
+    }
+
+    #[test]
+    fn refuses_requests_from_web_pages() {
+        let text =
+            "GET /sessions HTTP/1.1\r\nHost: localhost:7878\r\nOrigin: https://evil.com\r\n\r\n";
+        assert_eq!(status(text), 403);
+    }
+
+    #[test]
+    fn accepts_only_loopback_hosts() {
+        assert!(is_loopback_host("localhost:7878"));
+        assert!(is_loopback_host("localhost"));
+        assert!(is_loopback_host("127.0.0.1:7878"));
+        assert!(is_loopback_host("[::1]:7878"));
+        assert!(!is_loopback_host("evil.com"));
+        assert!(!is_loopback_host("127.0.0.1.evil.com"));
+        assert!(!is_loopback_host("127.0.0.1.evil.com:7878"));
+
+        let without_host = "GET /sessions HTTP/1.1\r\n\r\n";
+        assert_eq!(status(without_host), 403);
+        let rebound = "GET /sessions HTTP/1.1\r\nHost: evil.com:7878\r\n\r\n";
+        assert_eq!(status(rebound), 403);
+        let local = "GET /sessions HTTP/1.1\r\nHost: [::1]:7878\r\n\r\n";
+        assert!(matches!(request(local), Ok(ApiCommand::List)));
+    }
+
+    #[test]
+    fn creating_needs_a_session_content_type() {
+        let create = |content_type: &str| {
+            format!(
+                "POST /sessions HTTP/1.1\r\nHost: localhost\r\n{}Content-Length: 2\r\n\r\n[]",
+                content_type
+            )
+        };
+        assert_eq!(status(&create("")), 415);
+        assert_eq!(status(&create("Content-Type: text/plain\r\n")), 415);
+        assert_eq!(
+            status(&create(
+                "Content-Type: application/x-www-form-urlencoded\r\n"
+            )),
+            415
+        );
+        assert!(matches!(
+            request(&create("Content-Type: application/json; charset=utf-8\r\n")),
+            Ok(ApiCommand::Create(body)) if body == "[]"
+        ));
+        assert!(matches!(
+            request(&create("Content-Type: application/yaml\r\n")),
+            Ok(ApiCommand::Create(_))
+        ));
+    }
+
+    #[test]
+    fn limits_the_body_size() {
+        let text = format!(
+            "POST /sessions HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n",
+            MAX_BODY + 1
+        );
+        assert_eq!(status(&text), 413);
+
+        let short = "POST /sessions HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: 10\r\n\r\n[]";
+        assert_eq!(status(short), 400);
+    }
+
+    #[test]
+    fn decodes_session_names() {
+        assert_eq!(decode("db%20primary"), "db primary");
+        assert_eq!(decode("pf-3fa2"), "pf-3fa2");
+        assert_eq!(decode("100%"), "100%");
+        assert_eq!(decode("%zz"), "%zz");
+
+        let text = "POST /sessions/db%20primary/start HTTP/1.1\r\nHost: localhost\r\n\r\n";
+        assert!(matches!(
+            request(text),
+            Ok(ApiCommand::Start(name)) if name == "db primary"
+        ));
+    }
+}
@@ -0,0 +1,172 @@
+//! `pfman apply <file>`: reconcile saved sessions with a desired-state file.
+//!
+//! The file is a YAML/JSON list in the import format, plus an optional
+//! `running` flag per session (default `true`). Sessions are matched by
+//! name: missing ones are created, changed ones updated (and restarted if
+//! running), and sessions not listed in the file are stopped, never deleted.
+
+use crate::import::SessionSpec;
+use crate::models::{Session, SessionStatus};
+use crate::policy::Policy;
+use crate::process::ProcessManager;
+use crate::provider::ProviderRegistry;
+use crate::storage::Storage;
+use color_eyre::Result;
+use color_eyre::eyre::eyre;
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+struct DesiredSession {
+    #[serde(flatten)]
+    spec: SessionSpec,
+    #[serde(default = "default_running")]
+    running: bool,
+}
+
+fn default_running() -> bool {
+    true
+}
+
+/// Entry point for `pfman apply <file> [--dry-run]`
+pub fn run(args: &[String]) -> Result<()> {
+    let dry_run = args.iter().any(|a| a == "--dry-run");
+    let path = args
+        .iter()
+        .find(|a| !a.starts_with("--"))
+        .ok_or_else(|| eyre!("usage: pfman apply <file> [--dry-run]"))?;
+    let content = std::fs::read_to_string(path)?;
+    let desired: Vec<DesiredSession> = serde_yaml::from_str(&content)
+        .map_err(|e| eyre!("invalid desired state {}: {}", path, e))?;
+
+    let storage = Storage::new()?;
+    let mut sessions = storage.load_sessions()?;
+    let config = storage.load_config()?;
+    let process_manager = ProcessManager::new(storage.clone())
+        .with_policy(Policy::load()?)
+        .with_ssh_options(config.ssh_options)
+        .with_exit_on_forward_failure(config.exit_on_forward_failure)
+        .with_providers(ProviderRegistry::from_config(&config.session_providers));
+
+    let mut wanted_names = Vec::new();
+    let mut changes = 0;
+    for entry in desired {
+        let session = entry.spec.into_session().map_err(|e| eyre!(e))?;
+        wanted_names.push(session.name.clone());
+
+        let idx = match sessions.iter().position(|s| s.name == session.name) {
+            Some(idx) => {
+                if differs(&sessions[idx], &session) {
+                    println!("~ update {}", session.name);
+                    changes += 1;
+                    let existing = &mut sessions[idx];
+                    let restart = is_up(existing) && entry.running;
+                    if !dry_run {
+                        if is_up(existing) {
+                            process_manager.stop_session(existing)?;
+                        }
+                        update(existing, session);
+                        if restart {
+                            println!("> restart {}", existing.name);
+                            process_manager.start_session(existing)?;
+                        }
+                    }
+                }
+                idx
+            }
+            None => {
+                println!("+ create {}", session.name);
+                changes += 1;
+                sessions.push(session);
+                sessions.len() - 1
+            }
+        };
+
+        let session = &mut sessions[idx];
+        if entry.running && !is_up(session) {
+            println!("> start {}", session.name);
+            changes += 1;
+            if !dry_run {
+                process_manager.start_session(session)?;
+            }
+        } else if !entry.running && is_up(session) {
+            println!("- stop {}", session.name);
+            changes += 1;
+            if !dry_run {
+                process_manager.stop_session(session)?;
+            }
+        }
+    }
+
+    for session in sessions.iter_mut() {
+        if !wanted_names.contains(&session.name) && is_up(session) {
+            println!("- stop {} (not in desired state)", session.name);
+            changes += 1;
+            if !dry_run {
+                process_manager.stop_session(session)?;
+            }
+        }
+    }
+
+    if changes == 0 {
+        println!("Already up to date");
+    } else if dry_run {
+        println!("{} change(s) planned, nothing applied (--dry-run)", changes);
+    } else {
+        process_manager.finish_pre_starts(&mut sessions);
+        storage.save_sessions(&sessions)?;
+        println!("{} change(s) applied", changes);
+    }
+    Ok(())
+}
+
+fn is_up(session: &Session) -> bool {
+    matches!(
+        session.status,
+        SessionStatus::Running | SessionStatus::Degraded(_) | SessionStatus::Starting
+    )
+}
+
+/// Whether `existing` needs an update to match `desired`
+pub fn differs(existing: &Session, desired: &Session) -> bool {
+    existing.session_type != desired.session_type
+        || existing.target != desired.target
+        || existing.local_port != desired.local_port
+        || existing.remote_port != desired.remote_port
+        || existing.remote_host != desired.remote_host
+        || existing.address_family != desired.address_family
+        || existing.additional_options != desired.additional_options
+        || existing.kube_context != desired.kube_context
+        || existing.kube_namespace != desired.kube_namespace
+        || existing.tags != desired.tags
+        || existing.health_command != desired.health_command
+        || existing.pre_start != desired.pre_start
+        || existing.post_stop != desired.post_stop
+        || existing.user != desired.user
+        || existing.identity_file != desired.identity_file
+        || existing.kubeconfig_path != desired.kubeconfig_path
+        || existing.idle_timeout_minutes != desired.idle_timeout_minutes
+        || existing.max_lifetime != desired.max_lifetime
+}
+
+/// Copy the connection settings of `desired` onto `existing`, keeping its
+/// id, status and appearance
+pub fn update(existing: &mut Session, desired: Session) {
+    existing.session_type = desired.session_type;
+    existing.target = desired.target;
+    existing.local_port = desired.local_port;
+    existing.remote_port = desired.remote_port;
+    existing.remote_host = desired.remote_host;
+    existing.address_family = desired.address_family;
+    existing.additional_options = desired.additional_options;
+    existing.kube_context = desired.kube_context;
+    existing.kube_namespace = desired.kube_namespace;
+    existing.tags = desired.tags;
+    existing.health_command = desired.health_command;
+    existing.pre_start = desired.pre_start;
+    existing.post_stop = desired.post_stop;
+    existing.user = desired.user;
+    existing.identity_file = desired.identity_file;
+    existing.kubeconfig_path = desired.kubeconfig_path;
+    existing.idle_timeout_minutes = desired.idle_timeout_minutes;
+    existing.max_lifetime = desired.max_lifetime;
+}
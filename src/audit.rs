@@ -0,0 +1,175 @@
+//! Audit trail: who created, edited, deleted, started or stopped which
+//! session and when, one JSON record per line in `audit.jsonl` in the data
+//! directory. Records are only ever appended; `pfman history` lists them.
+//!
+//! Starts and stops are recorded by the process manager and definition
+//! changes by [`Storage::save_sessions`], so every way of changing
+//! sessions (dashboard, API, IPC, `pfman apply`...) ends up here.
+
+use crate::models::Session;
+use crate::storage::Storage;
+use chrono::{DateTime, Utc};
+use color_eyre::Result;
+use color_eyre::eyre::eyre;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Entries `pfman history` shows unless told otherwise
+const DEFAULT_LIMIT: usize = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditAction {
+    Create,
+    Edit,
+    Delete,
+    Start,
+    Stop,
+}
+
+impl AuditAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::Create => "create",
+            AuditAction::Edit => "edit",
+            AuditAction::Delete => "delete",
+            AuditAction::Start => "start",
+            AuditAction::Stop => "stop",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub at: DateTime<Utc>,
+    /// Login name of whoever ran pfman
+    pub user: String,
+    pub action: AuditAction,
+    pub session_id: Uuid,
+    /// Name at the time, kept for sessions deleted or renamed since
+    pub session: String,
+    pub target: String,
+    /// The changed fields of an edit
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details: Option<String>,
+}
+
+impl AuditEntry {
+    pub fn new(action: AuditAction, session: &Session) -> Self {
+        Self {
+            at: Utc::now(),
+            user: current_user(),
+            action,
+            session_id: session.id,
+            session: session.name.clone(),
+            target: session.target.clone(),
+            details: None,
+        }
+    }
+
+    pub fn with_details(mut self, details: String) -> Self {
+        self.details = Some(details);
+        self
+    }
+}
+
+/// Record `action` on `session`. Auditing never gets in the way of the
+/// action itself, so failures to write are ignored.
+pub fn record(storage: &Storage, action: AuditAction, session: &Session) {
+    let _ = storage.append_audit(&AuditEntry::new(action, session));
+}
+
+pub fn current_user() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_default()
+}
+
+/// Entries for the sessions created, edited or deleted between the `saved`
+/// list and the one about to replace it. Runtime state, such as status or
+/// the port picked for an `auto` local port, doesn't count as an edit.
+pub fn definition_changes(saved: &[Session], sessions: &[Session]) -> Vec<AuditEntry> {
+    let mut entries = Vec::new();
+    for session in sessions {
+        match saved.iter().find(|s| s.id == session.id) {
+            None => entries.push(AuditEntry::new(AuditAction::Create, session)),
+            Some(old) => {
+                let changes = old.changes_to(session);
+                if !changes.is_empty() {
+                    let details = changes
+                        .iter()
+                        .map(|c| format!("{}: {} -> {}", c.field, c.old, c.new))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    entries.push(AuditEntry::new(AuditAction::Edit, session).with_details(details));
+                }
+            }
+        }
+    }
+    for old in saved {
+        if !sessions.iter().any(|s| s.id == old.id) {
+            entries.push(AuditEntry::new(AuditAction::Delete, old));
+        }
+    }
+    entries
+}
+
+/// Entry point for `pfman history [<session>] [--limit <n>]`: the latest
+/// audit entries, oldest first, optionally of one session only
+pub fn run(args: &[String]) -> Result<()> {
+    let storage = Storage::new()?;
+    let mut limit = DEFAULT_LIMIT;
+    let mut selector = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--limit" => {
+                limit = args
+                    .next()
+                    .and_then(|n| n.parse().ok())
+                    .ok_or_else(|| eyre!("--limit needs a number"))?;
+            }
+            other if other.starts_with("--") => return Err(eyre!("unknown option '{}'", other)),
+            other => selector = Some(other),
+        }
+    }
+
+    let mut entries = storage.read_audit();
+    if let Some(selector) = selector {
+        // By ID when it names a current session, so renames are followed;
+        // by the recorded name for deleted ones
+        let sessions = storage.load_sessions()?;
+        let id = sessions
+            .iter()
+            .find(|s| s.name == selector)
+            .or_else(|| sessions.iter().find(|s| s.has_id(selector)))
+            .map(|s| s.id);
+        entries.retain(|e| Some(e.session_id) == id || e.session == selector);
+    }
+    if entries.is_empty() {
+        match selector {
+            Some(selector) => println!("Nothing recorded for {}", selector),
+            None => println!("Nothing recorded yet"),
+        }
+        return Ok(());
+    }
+
+    for entry in &entries[entries.len().saturating_sub(limit)..] {
+        let mut line = format!(
+            "{}  {:<10} {:<6}  {}  {}",
+            entry
+                .at
+                .with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M:%S"),
+            entry.user,
+            entry.action.as_str(),
+            entry.session,
+            entry.target
+        );
+        if let Some(details) = &entry.details {
+            line.push_str(&format!("  ({})", details));
+        }
+        println!("{}", line);
+    }
+    Ok(())
+}
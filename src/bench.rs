@@ -0,0 +1,191 @@
+//! `pfman bench`: timings for large session counts.
+//!
+//! Generates synthetic sessions in a scratch directory and measures the
+//! hot paths of the UI and the monitor, so changes to them can be compared
+//! before and after. Nothing is started and the real config is not read.
+
+use crate::heartbeat::StatusReport;
+use crate::models::{Session, SessionError, SessionStatus, SessionType};
+use crate::process::{ProcessManager, StatusMonitor};
+use crate::storage::Storage;
+use crate::ui::{AppState, dashboard};
+use chrono::Utc;
+use color_eyre::Result;
+use color_eyre::eyre::eyre;
+use ratatui::{Terminal, backend::TestBackend};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use sysinfo::{Pid, System};
+
+const USAGE: &str = "usage: pfman bench [--sessions <n>] [--iterations <n>]";
+/// Terminal size the dashboard is rendered at
+const WIDTH: u16 = 160;
+const HEIGHT: u16 = 50;
+
+/// Entry point for `pfman bench [--sessions <n>] [--iterations <n>]`
+pub fn run(args: &[String]) -> Result<()> {
+    let mut count = 1000;
+    let mut iterations = 50;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let value = |v: Option<&String>| -> Result<usize> {
+            v.and_then(|v| v.parse().ok())
+                .filter(|&n| n > 0)
+                .ok_or_else(|| eyre!("{} needs a positive number\n{}", arg, USAGE))
+        };
+        match arg.as_str() {
+            "--sessions" => count = value(args.next())?,
+            "--iterations" => iterations = value(args.next())?,
+            other => return Err(eyre!("unexpected argument '{}'\n{}", other, USAGE)),
+        }
+    }
+
+    let root = std::env::temp_dir().join(format!("pfman-bench-{}", std::process::id()));
+    let result = bench(&root, count, iterations);
+    let _ = std::fs::remove_dir_all(&root);
+    result
+}
+
+fn bench(root: &std::path::Path, count: usize, iterations: usize) -> Result<()> {
+    let sessions = synthetic_sessions(count);
+    let storage = Storage::in_dir(root.to_path_buf())?;
+    storage.save_sessions(&sessions)?;
+    let mut state = AppState::with_storage(storage.clone(), ProcessManager::simulated(storage))?;
+
+    println!(
+        "pfman bench: {} sessions, {} iterations, {}x{} terminal",
+        count, iterations, WIDTH, HEIGHT
+    );
+
+    let mut terminal = Terminal::new(TestBackend::new(WIDTH, HEIGHT))?;
+    report(
+        "render dashboard",
+        measure(iterations, || {
+            let _ = terminal.draw(|frame| dashboard::render(frame, &state, frame.area()));
+        }),
+    );
+
+    for query in ["api", "tag:team-7", "pf-", "no-such-session"] {
+        state.search_query = query.to_string();
+        report(
+            &format!("filter {:?}", query),
+            measure(iterations, || {
+                std::hint::black_box(state.filtered_sessions());
+            }),
+        );
+    }
+    state.search_query = "tag:prod".to_string();
+    report(
+        "render filtered",
+        measure(iterations, || {
+            let _ = terminal.draw(|frame| dashboard::render(frame, &state, frame.area()));
+        }),
+    );
+    state.search_query.clear();
+
+    // The monitor refreshes every monitored pid in its process table and,
+    // with `status_file` on, serializes a report of all sessions
+    let pids: Vec<Pid> = (0..count).map(|i| Pid::from_u32(i as u32 + 1)).collect();
+    let mut sys = System::new();
+    report(
+        "monitor process scan",
+        measure(iterations, || {
+            StatusMonitor::refresh_processes(&mut sys, &pids);
+            std::hint::black_box(
+                pids.iter()
+                    .filter(|pid| sys.process(**pid).is_some())
+                    .count(),
+            );
+        }),
+    );
+    let latencies = HashMap::new();
+    report(
+        "monitor status report",
+        measure(iterations, || {
+            let report = StatusReport::new(&state.sessions, &latencies);
+            std::hint::black_box(serde_json::to_vec(&report).ok());
+        }),
+    );
+    report(
+        "save sessions",
+        measure(iterations, || {
+            let _ = state.save();
+        }),
+    );
+    Ok(())
+}
+
+/// Sessions spread over all types and statuses, with tags and long targets
+fn synthetic_sessions(count: usize) -> Vec<Session> {
+    let services = ["api", "postgres", "redis", "grafana", "kafka", "search"];
+    (0..count)
+        .map(|i| {
+            let service = services[i % services.len()];
+            let env = if i % 3 == 0 { "prod" } else { "staging" };
+            let local_port = 10000 + (i % 50000) as u16;
+            let (session_type, target) = match i % 3 {
+                0 => (
+                    SessionType::SSH,
+                    format!("bastion-{}.{}.example.com", i, env),
+                ),
+                1 => (
+                    SessionType::Kubectl,
+                    format!("deployment/{}-{}-{}-worker-pool", service, env, i),
+                ),
+                _ => (SessionType::Socks5, format!("jump-{}.example.com", i)),
+            };
+            let remote_port = (session_type != SessionType::Socks5).then_some(8080);
+            let mut session = Session::new(
+                format!("{}-{}-{}", service, env, i),
+                session_type,
+                target,
+                local_port,
+                remote_port,
+            );
+            session.tags = vec![env.to_string(), format!("team-{}", i % 10)];
+            session.status = match i % 4 {
+                0 => SessionStatus::Running,
+                1 => SessionStatus::Stopped,
+                2 => SessionStatus::Error(SessionError::new("Connection refused")),
+                _ => SessionStatus::Starting,
+            };
+            if session.status == SessionStatus::Running {
+                session.last_started = Some(Utc::now() - chrono::Duration::minutes(i as i64));
+            }
+            session
+        })
+        .collect()
+}
+
+fn measure(iterations: usize, mut f: impl FnMut()) -> Vec<Duration> {
+    // One untimed run so first-use allocations don't skew the numbers
+    f();
+    let mut samples: Vec<Duration> = (0..iterations)
+        .map(|_| {
+            let started = Instant::now();
+            f();
+            started.elapsed()
+        })
+        .collect();
+    samples.sort();
+    samples
+}
+
+fn report(label: &str, samples: Vec<Duration>) {
+    let percentile = |p: usize| samples[(samples.len() - 1) * p / 100];
+    println!(
+        "{:<28} median {:>9}  p95 {:>9}  max {:>9}",
+        label,
+        format_duration(percentile(50)),
+        format_duration(percentile(95)),
+        format_duration(percentile(100)),
+    );
+}
+
+fn format_duration(d: Duration) -> String {
+    if d < Duration::from_millis(1) {
+        format!("{}µs", d.as_micros())
+    } else {
+        format!("{:.2}ms", d.as_secs_f64() * 1000.0)
+    }
+}
@@ -0,0 +1,117 @@
+use crate::config::Canary;
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+const PROBE_INTERVAL: Duration = Duration::from_secs(5);
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Probes the configured canary in the background. The last result gates
+/// automatic restarts and drives the "network degraded" banner.
+pub struct CanaryMonitor {
+    healthy: Arc<Mutex<Option<bool>>>,
+}
+
+impl CanaryMonitor {
+    pub fn spawn(canary: Canary) -> Self {
+        let healthy = Arc::new(Mutex::new(None));
+        let shared = Arc::clone(&healthy);
+        // Detached: the thread only holds a clone of the result slot and exits
+        // once the monitor is dropped
+        thread::spawn(move || {
+            while Arc::strong_count(&shared) > 1 {
+                let result = probe(&canary);
+                *shared.lock().unwrap() = Some(result);
+                thread::sleep(PROBE_INTERVAL);
+            }
+        });
+        Self { healthy }
+    }
+
+    /// Result of the last probe, `None` until the first one finished
+    pub fn healthy(&self) -> Option<bool> {
+        *self.healthy.lock().unwrap()
+    }
+}
+
+pub fn probe(canary: &Canary) -> bool {
+    match canary {
+        Canary::Tcp(addr) => tcp_connect(addr).is_some(),
+        Canary::Http(url) => probe_http(url),
+        Canary::Ping(host) => probe_ping(host),
+        Canary::Interface(name) => interface_up(name),
+    }
+}
+
+fn tcp_connect(addr: &str) -> Option<TcpStream> {
+    addr.to_socket_addrs()
+        .ok()?
+        .find_map(|addr| TcpStream::connect_timeout(&addr, PROBE_TIMEOUT).ok())
+}
+
+/// Plain `http://` URLs must answer with a 2xx/3xx status. There is no TLS
+/// client, so for `https://` a successful TCP connect is good enough.
+fn probe_http(url: &str) -> bool {
+    let (tls, rest) = if let Some(rest) = url.strip_prefix("https://") {
+        (true, rest)
+    } else {
+        (false, url.strip_prefix("http://").unwrap_or(url))
+    };
+    let (authority, path) = match rest.find('/') {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, "/"),
+    };
+    let default_port = if tls { 443 } else { 80 };
+    let (host, addr) = match authority.rsplit_once(':') {
+        Some((host, _)) => (host, authority.to_string()),
+        None => (authority, format!("{}:{}", authority, default_port)),
+    };
+
+    let Some(mut stream) = tcp_connect(&addr) else {
+        return false;
+    };
+    if tls {
+        return true;
+    }
+
+    let _ = stream.set_read_timeout(Some(PROBE_TIMEOUT));
+    let request = format!(
+        "GET {} HTTP/1.0\r\nHost: {}\r\nUser-Agent: pfman\r\n\r\n",
+        path, host
+    );
+    if stream.write_all(request.as_bytes()).is_err() {
+        return false;
+    }
+    let mut head = [0u8; 16];
+    let Ok(n) = stream.read(&mut head) else {
+        return false;
+    };
+    // "HTTP/1.1 200 ..." - status code starts at byte 9
+    let head = String::from_utf8_lossy(&head[..n]);
+    matches!(head.get(9..10), Some("2" | "3"))
+}
+
+fn probe_ping(host: &str) -> bool {
+    let mut cmd = Command::new("ping");
+    #[cfg(windows)]
+    cmd.args(["-n", "1", "-w", "3000", host]);
+    #[cfg(not(windows))]
+    cmd.args(["-c", "1", "-W", "3", host]);
+    cmd.output().is_ok_and(|output| output.status.success())
+}
+
+fn interface_up(name: &str) -> bool {
+    // Linux: tun devices report "unknown" while they carry traffic
+    if let Ok(state) = std::fs::read_to_string(format!("/sys/class/net/{}/operstate", name)) {
+        return matches!(state.trim(), "up" | "unknown");
+    }
+    Command::new("ifconfig")
+        .arg(name)
+        .output()
+        .is_ok_and(|output| {
+            output.status.success() && String::from_utf8_lossy(&output.stdout).contains("UP")
+        })
+}
@@ -0,0 +1,129 @@
+//! Capture of a session's output. The forward writes its stdout and stderr
+//! straight into files next to its run log, so it never depends on pfman
+//! reading a pipe: a session left running after pfman quits writes on as
+//! before, where a closed pipe would end it at its next line (kubectl logs
+//! every connection). pfman copies what was written into the run log,
+//! prefixing each line with the time it was copied and marking stderr,
+//! every [`COLLECT_INTERVAL`] while it runs, and catches up on what was
+//! written in the meantime on its next start.
+
+use crate::process::STDERR_MARKER;
+use chrono::Utc;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// How often the output of running sessions is copied into their logs
+pub const COLLECT_INTERVAL: Duration = Duration::from_millis(250);
+/// Most output copied per stream at once; the rest follows next time
+const MAX_COLLECT_BYTES: u64 = 1024 * 1024;
+
+/// Held while copying, so two threads never copy the same bytes twice
+static COLLECTING: Mutex<()> = Mutex::new(());
+
+/// Files for the stdout and stderr of the run logging to `log_file`, to
+/// hand to its process
+pub fn open(log_file: &Path) -> io::Result<(File, File)> {
+    let open = |path: PathBuf| OpenOptions::new().create(true).append(true).open(path);
+    Ok((open(stdout_file(log_file))?, open(stderr_file(log_file))?))
+}
+
+/// Copy the complete lines the run logging to `log_file` wrote since the
+/// last call. Once `finished`, a last line without a line break is copied
+/// too and the output files are removed.
+pub fn collect(log_file: &Path, finished: bool) {
+    let stdout = stdout_file(log_file);
+    let stderr = stderr_file(log_file);
+    if !stdout.exists() && !stderr.exists() {
+        return;
+    }
+    let _collecting = COLLECTING.lock().unwrap_or_else(|e| e.into_inner());
+
+    let positions = positions_file(log_file);
+    let (mut out_pos, mut err_pos) = read_positions(&positions);
+    let mut entries = Vec::new();
+    let copied_out = copy_lines(&stdout, &mut out_pos, "", finished, &mut entries);
+    let copied_err = copy_lines(&stderr, &mut err_pos, STDERR_MARKER, finished, &mut entries);
+    if !entries.is_empty() {
+        let written = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(log_file)
+            .and_then(|mut log| log.write_all(&entries));
+        // Tried again next time rather than lost
+        if written.is_err() {
+            return;
+        }
+    }
+
+    if finished {
+        let _ = fs::remove_file(&stdout);
+        let _ = fs::remove_file(&stderr);
+        let _ = fs::remove_file(&positions);
+    } else if copied_out || copied_err {
+        let _ = fs::write(&positions, format!("{} {}\n", out_pos, err_pos));
+    }
+}
+
+/// Append the timestamped lines of `path` after `position` to `entries`,
+/// moving `position` past them; returns whether there were any
+fn copy_lines(
+    path: &Path,
+    position: &mut u64,
+    marker: &str,
+    finished: bool,
+    entries: &mut Vec<u8>,
+) -> bool {
+    let Ok(mut file) = File::open(path) else {
+        return false;
+    };
+    let mut buf = Vec::new();
+    if file.seek(SeekFrom::Start(*position)).is_err()
+        || file.take(MAX_COLLECT_BYTES).read_to_end(&mut buf).is_err()
+    {
+        return false;
+    }
+    // A line still being written waits for its line break
+    let end = match buf.iter().rposition(|&b| b == b'\n') {
+        Some(last) if !finished => last + 1,
+        None if !finished => return false,
+        _ => buf.len(),
+    };
+    if end == 0 {
+        return false;
+    }
+
+    let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S");
+    for line in buf[..end].split_inclusive(|&b| b == b'\n') {
+        entries.extend_from_slice(format!("[{}] {}", timestamp, marker).as_bytes());
+        entries.extend_from_slice(line);
+        if !line.ends_with(b"\n") {
+            entries.push(b'\n');
+        }
+    }
+    *position += end as u64;
+    true
+}
+
+fn read_positions(path: &Path) -> (u64, u64) {
+    let content = fs::read_to_string(path).unwrap_or_default();
+    let mut numbers = content
+        .split_whitespace()
+        .map(|n| n.parse::<u64>().unwrap_or(0));
+    (numbers.next().unwrap_or(0), numbers.next().unwrap_or(0))
+}
+
+fn stdout_file(log_file: &Path) -> PathBuf {
+    log_file.with_extension("out")
+}
+
+fn stderr_file(log_file: &Path) -> PathBuf {
+    log_file.with_extension("err")
+}
+
+/// How far each output file has been copied, as `<stdout> <stderr>`
+fn positions_file(log_file: &Path) -> PathBuf {
+    log_file.with_extension("pos")
+}
@@ -0,0 +1,169 @@
+use crate::config::CatalogSource;
+use crate::import::parse_sessions;
+use crate::models::Session;
+use crate::storage::Storage;
+use chrono::{DateTime, Utc};
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// File read from a git catalog when the URL has no `#path`
+const DEFAULT_CATALOG_PATH: &str = "catalog.yaml";
+/// How often the sync thread checks for a manual refresh or shutdown
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Last known catalog and how the latest sync went
+#[derive(Debug, Clone, Default)]
+pub struct CatalogSnapshot {
+    pub entries: Vec<Session>,
+    pub synced_at: Option<DateTime<Utc>>,
+    pub error: Option<String>,
+}
+
+/// Periodically pulls the team catalog in the background. Entries are
+/// read-only templates; local sessions are created from them on demand.
+pub struct CatalogSync {
+    snapshot: Arc<Mutex<CatalogSnapshot>>,
+    refresh: Arc<AtomicBool>,
+}
+
+impl CatalogSync {
+    pub fn spawn(source: CatalogSource, interval: Duration, storage: Storage) -> Self {
+        // Start from the cached copy so the catalog is usable offline
+        let cached = fs::read_to_string(storage.catalog_file())
+            .ok()
+            .and_then(|text| parse_entries(&text).ok())
+            .unwrap_or_default();
+        let snapshot = Arc::new(Mutex::new(CatalogSnapshot {
+            entries: cached,
+            ..Default::default()
+        }));
+        let refresh = Arc::new(AtomicBool::new(true));
+
+        let shared = Arc::clone(&snapshot);
+        let requested = Arc::clone(&refresh);
+        // Exits once the sync handle is dropped
+        thread::spawn(move || {
+            let mut last_sync: Option<Instant> = None;
+            while Arc::strong_count(&shared) > 1 {
+                let due = last_sync.is_none_or(|at| at.elapsed() >= interval);
+                if requested.swap(false, Ordering::Relaxed) || due {
+                    let result = sync(&source, &storage);
+                    let mut snapshot = shared.lock().unwrap();
+                    match result {
+                        Ok(entries) => {
+                            snapshot.entries = entries;
+                            snapshot.synced_at = Some(Utc::now());
+                            snapshot.error = None;
+                        }
+                        Err(err) => snapshot.error = Some(err),
+                    }
+                    last_sync = Some(Instant::now());
+                }
+                thread::sleep(POLL_INTERVAL);
+            }
+        });
+
+        Self { snapshot, refresh }
+    }
+
+    pub fn snapshot(&self) -> CatalogSnapshot {
+        self.snapshot.lock().unwrap().clone()
+    }
+
+    /// Sync again without waiting for the interval
+    pub fn refresh(&self) {
+        self.refresh.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Fetch and parse the catalog, caching the raw text on success
+fn sync(source: &CatalogSource, storage: &Storage) -> Result<Vec<Session>, String> {
+    let text = match source {
+        CatalogSource::Https(url) => fetch_https(url)?,
+        CatalogSource::Git(spec) => fetch_git(spec, &storage.catalog_repo_dir())?,
+    };
+    let entries = parse_entries(&text).map_err(|e| format!("Invalid catalog: {}", e))?;
+    let _ = fs::write(storage.catalog_file(), &text);
+    Ok(entries)
+}
+
+/// Catalog entries without shell commands: whoever can change the catalog
+/// could otherwise run one on every machine that takes an entry
+fn parse_entries(text: &str) -> Result<Vec<Session>, String> {
+    let mut entries = parse_sessions(text)?;
+    for entry in &mut entries {
+        entry.health_command = None;
+        entry.pre_start = None;
+        entry.post_stop = None;
+    }
+    Ok(entries)
+}
+
+/// `entry` with the shell commands of the local session `local`, which
+/// updating it from the catalog keeps
+pub fn with_local_commands(mut entry: Session, local: &Session) -> Session {
+    entry.health_command = local.health_command.clone();
+    entry.pre_start = local.pre_start.clone();
+    entry.post_stop = local.post_stop.clone();
+    entry
+}
+
+fn fetch_https(url: &str) -> Result<String, String> {
+    let output = Command::new("curl")
+        .args(["-fsSL", "--max-time", "30", url])
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| format!("Failed to run curl: {}", e))?;
+    if !output.status.success() {
+        return Err(command_error("curl", &output.stderr));
+    }
+    String::from_utf8(output.stdout).map_err(|_| "Catalog is not valid UTF-8".to_string())
+}
+
+/// Clone the repository once, then fast-forward it on every sync
+fn fetch_git(spec: &str, repo_dir: &Path) -> Result<String, String> {
+    let (url, path) = spec.split_once('#').unwrap_or((spec, DEFAULT_CATALOG_PATH));
+
+    let current_url = git(repo_dir, &["remote", "get-url", "origin"]).ok();
+    if current_url.as_deref().map(str::trim) != Some(url) {
+        let _ = fs::remove_dir_all(repo_dir);
+        let dir = repo_dir.to_string_lossy();
+        git(
+            Path::new("."),
+            &["clone", "--quiet", "--depth", "1", url, &dir],
+        )?;
+    } else {
+        git(repo_dir, &["pull", "--quiet", "--ff-only"])?;
+    }
+
+    fs::read_to_string(repo_dir.join(path)).map_err(|e| format!("Failed to read {}: {}", path, e))
+}
+
+fn git(dir: &Path, args: &[&str]) -> Result<String, String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        // Never block the UI on a credential prompt
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| format!("Failed to run git: {}", e))?;
+    if !output.status.success() {
+        return Err(command_error("git", &output.stderr));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn command_error(program: &str, stderr: &[u8]) -> String {
+    let stderr = String::from_utf8_lossy(stderr);
+    match stderr.lines().rev().find(|l| !l.trim().is_empty()) {
+        Some(line) => format!("{}: {}", program, line.trim()),
+        None => format!("{} failed", program),
+    }
+}
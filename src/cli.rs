@@ -0,0 +1,627 @@
+//! The command line: [`OPTIONS`] and [`COMMANDS`] describe every option
+//! and subcommand once. Subcommands are dispatched from them, options
+//! they don't list are rejected, and `pfman completions bash|zsh|fish`
+//! and `pfman man` are generated from them, so none of these can drift
+//! apart. Session names and tags are completed dynamically: the scripts
+//! ask `pfman completions --sessions` (or `--tags`), which reads the
+//! saved sessions.
+
+use crate::storage::Storage;
+use crate::{apply, audit, bench, pause, restore, share, watch};
+use color_eyre::Result;
+use color_eyre::eyre::eyre;
+
+const USAGE: &str = "usage: pfman completions bash|zsh|fish";
+const SHELLS: &[&str] = &["bash", "zsh", "fish"];
+
+/// How an option's value or a positional argument is completed
+#[derive(Clone, Copy)]
+enum Value {
+    File,
+    Dir,
+    /// Session names, short IDs and tags
+    Session,
+    Tag,
+    Choice(&'static [&'static str]),
+    /// Anything, nothing to suggest
+    Text,
+}
+
+struct Flag {
+    long: &'static str,
+    /// Placeholder shown in usage, and how the value is completed
+    value: Option<(&'static str, Value)>,
+    about: &'static str,
+}
+
+struct Command {
+    name: &'static str,
+    /// Positional argument as written in usage, e.g. `[<file>|-]`
+    positional: Option<(&'static str, Value)>,
+    flags: &'static [Flag],
+    about: &'static str,
+    /// Runs it, given the arguments after its name
+    run: fn(&[String]) -> Result<()>,
+}
+
+/// Options of the TUI itself, i.e. without a subcommand
+const OPTIONS: &[Flag] = &[
+    Flag {
+        long: "--start",
+        value: Some(("<name|tag>", Value::Session)),
+        about: "Start the session with this name or short ID, or every session with this tag, on launch (repeatable)",
+    },
+    Flag {
+        long: "--start-all-auto",
+        value: None,
+        about: "Start every session tagged auto on launch",
+    },
+    Flag {
+        long: "--inline",
+        value: None,
+        about: "Draw a live session list below the prompt instead of the full screen UI",
+    },
+    Flag {
+        long: "--demo",
+        value: None,
+        about: "Explore the UI with fake sessions in a temporary directory",
+    },
+    Flag {
+        long: "--simulate",
+        value: None,
+        about: "Same as --demo",
+    },
+    Flag {
+        long: "--config-dir",
+        value: Some(("<dir>", Value::Dir)),
+        about: "Read config and sessions from this directory instead of the default one",
+    },
+];
+
+const COMMANDS: &[Command] = &[
+    Command {
+        name: "apply",
+        positional: Some(("<file>", Value::File)),
+        flags: &[Flag {
+            long: "--dry-run",
+            value: None,
+            about: "Only show the plan",
+        }],
+        about: "Create, update and start or stop sessions to match a desired-state file",
+        run: apply::run,
+    },
+    Command {
+        name: "watch",
+        positional: None,
+        flags: &[],
+        about: "Compact read-only status list, refreshed every second",
+        run: watch::run,
+    },
+    Command {
+        name: "bench",
+        positional: None,
+        flags: &[
+            Flag {
+                long: "--sessions",
+                value: Some(("<n>", Value::Text)),
+                about: "Number of generated sessions (default: 1000)",
+            },
+            Flag {
+                long: "--iterations",
+                value: Some(("<n>", Value::Text)),
+                about: "Repetitions of each timing (default: 50)",
+            },
+        ],
+        about: "Print timings of the UI and monitor hot paths for many sessions",
+        run: bench::run,
+    },
+    Command {
+        name: "export",
+        positional: Some(("[<file>|-]", Value::File)),
+        flags: &[
+            Flag {
+                long: "--format",
+                value: Some(("<format>", Value::Choice(&["yaml", "json", "toml"]))),
+                about: "Output format, by default taken from the file extension or yaml",
+            },
+            Flag {
+                long: "--tag",
+                value: Some(("<tag>", Value::Tag)),
+                about: "Only export sessions with this tag (repeatable)",
+            },
+        ],
+        about: "Write the sessions to a file, or stdout, for sharing",
+        run: share::run_export,
+    },
+    Command {
+        name: "import",
+        positional: Some(("<file>|-", Value::File)),
+        flags: &[],
+        about: "Add the sessions from a file, or stdin",
+        run: share::run_import,
+    },
+    Command {
+        name: "pause",
+        positional: None,
+        flags: &[],
+        about: "Stop every session that is up, remembering them for resume",
+        run: pause::run_pause,
+    },
+    Command {
+        name: "resume",
+        positional: None,
+        flags: &[],
+        about: "Start the sessions stopped by the last pause again",
+        run: pause::run_resume,
+    },
+    Command {
+        name: "restore",
+        positional: Some(("[<number>]", Value::Text)),
+        flags: &[],
+        about: "List the session backups, or roll back to one",
+        run: restore::run,
+    },
+    Command {
+        name: "history",
+        positional: Some(("[<session>]", Value::Session)),
+        flags: &[Flag {
+            long: "--limit",
+            value: Some(("<n>", Value::Text)),
+            about: "Number of entries shown (default: 50)",
+        }],
+        about: "Show who created, edited, deleted, started or stopped sessions, and when",
+        run: audit::run,
+    },
+    Command {
+        name: "completions",
+        positional: Some(("<shell>", Value::Choice(SHELLS))),
+        flags: &[
+            Flag {
+                long: "--sessions",
+                value: None,
+                about: "Print the session names, short IDs and tags the scripts complete",
+            },
+            Flag {
+                long: "--tags",
+                value: None,
+                about: "Print the tags the scripts complete",
+            },
+        ],
+        about: "Print the completion script for bash, zsh or fish",
+        run,
+    },
+    Command {
+        name: "man",
+        positional: None,
+        flags: &[],
+        about: "Print this man page in roff format",
+        run: run_man,
+    },
+];
+
+/// Run the subcommand `args` start with, if they start with one. Options
+/// it doesn't take are rejected before it runs.
+pub fn dispatch(args: &[String]) -> Option<Result<()>> {
+    let name = args.first()?;
+    let command = COMMANDS.iter().find(|c| c.name == name)?;
+    let args = &args[1..];
+    Some(check_flags(command.flags, args).and_then(|()| (command.run)(args)))
+}
+
+/// Check the arguments of the TUI itself: only [`OPTIONS`], no stray words
+pub fn check_options(args: &[String]) -> Result<()> {
+    check_flags(OPTIONS, args)?;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match OPTIONS.iter().find(|f| f.long == arg.as_str()) {
+            // Its value, e.g. the name after --start
+            Some(flag) if flag.value.is_some() => {
+                args.next();
+            }
+            Some(_) => {}
+            None if arg.starts_with("--") => {}
+            None => return Err(eyre!("unknown command '{}', see `pfman man`", arg)),
+        }
+    }
+    Ok(())
+}
+
+/// Every `--option` in `args` is one of `flags`, given as `--option` or
+/// `--option=value`
+fn check_flags(flags: &[Flag], args: &[String]) -> Result<()> {
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if !arg.starts_with("--") {
+            continue;
+        }
+        let name = arg.split_once('=').map_or(arg.as_str(), |(name, _)| name);
+        let Some(flag) = flags.iter().find(|f| f.long == name) else {
+            return Err(eyre!("unknown option '{}', see `pfman man`", name));
+        };
+        if flag.value.is_some() && !arg.contains('=') {
+            args.next();
+        }
+    }
+    Ok(())
+}
+
+/// Entry point for `pfman completions bash|zsh|fish`; `--sessions` and
+/// `--tags` print what the scripts complete dynamically
+pub fn run(args: &[String]) -> Result<()> {
+    match args.first().map(String::as_str) {
+        Some("--sessions") => print_candidates(true),
+        Some("--tags") => print_candidates(false),
+        Some("bash") => {
+            print!("{}", bash());
+            Ok(())
+        }
+        Some("zsh") => {
+            print!("{}", zsh());
+            Ok(())
+        }
+        Some("fish") => {
+            print!("{}", fish());
+            Ok(())
+        }
+        Some(other) => Err(eyre!("unknown shell '{}'\n{}", other, USAGE)),
+        None => Err(eyre!(USAGE)),
+    }
+}
+
+/// Entry point for `pfman man`
+pub fn run_man(_args: &[String]) -> Result<()> {
+    print!("{}", man());
+    Ok(())
+}
+
+/// One per line: the session names and short IDs followed by the tags, or
+/// only the tags
+fn print_candidates(sessions: bool) -> Result<()> {
+    // Completion must never fail noisily, e.g. before the first session
+    let saved = Storage::new()
+        .and_then(|storage| storage.load_sessions())
+        .unwrap_or_default();
+    let mut candidates = Vec::new();
+    if sessions {
+        for session in &saved {
+            candidates.push(session.name.clone());
+            candidates.push(session.short_id());
+        }
+    }
+    let mut tags: Vec<String> = saved.iter().flat_map(|s| s.tags.clone()).collect();
+    tags.sort();
+    tags.dedup();
+    candidates.extend(tags);
+    for candidate in candidates {
+        println!("{}", candidate);
+    }
+    Ok(())
+}
+
+fn bash() -> String {
+    let value_case = |flags: &[&Flag]| -> String {
+        flags
+            .iter()
+            .filter_map(|flag| {
+                let (_, value) = flag.value?;
+                Some(format!(
+                    "        {}) {}; return ;;\n",
+                    flag.long,
+                    bash_complete(value)
+                ))
+            })
+            .collect()
+    };
+    let all_flags: Vec<&Flag> = OPTIONS
+        .iter()
+        .chain(COMMANDS.iter().flat_map(|c| c.flags))
+        .collect();
+    let names: Vec<&str> = COMMANDS.iter().map(|c| c.name).collect();
+    let top_level: Vec<&str> = names
+        .iter()
+        .copied()
+        .chain(OPTIONS.iter().map(|f| f.long))
+        .collect();
+
+    let mut script = String::from(
+        "# bash completion for pfman, generated by `pfman completions bash`\n\
+         _pfman() {\n    \
+         local cur=\"${COMP_WORDS[COMP_CWORD]}\" prev=\"${COMP_WORDS[COMP_CWORD-1]}\"\n    \
+         # Session names may contain spaces\n    \
+         local IFS=$'\\n'\n    \
+         case \"$prev\" in\n",
+    );
+    script.push_str(&value_case(&all_flags));
+    script.push_str("    esac\n\n    local command=\"\" word\n");
+    script.push_str("    for word in \"${COMP_WORDS[@]:1:COMP_CWORD-1}\"; do\n");
+    script.push_str(&format!(
+        "        case \"$word\" in {}) command=\"$word\"; break ;; esac\n",
+        names.join("|")
+    ));
+    script.push_str("    done\n\n    case \"$command\" in\n");
+    script.push_str(&format!(
+        "        \"\") COMPREPLY=($(compgen -W \"{}\" -- \"$cur\")) ;;\n",
+        top_level.join("\n")
+    ));
+    for command in COMMANDS {
+        let flags: Vec<&str> = command.flags.iter().map(|f| f.long).collect();
+        let mut completions = Vec::new();
+        if !flags.is_empty() {
+            completions.push(format!(
+                "COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))",
+                flags.join("\n")
+            ));
+        }
+        if let Some((_, value)) = command.positional {
+            completions.push(bash_positional(value));
+        }
+        if completions.is_empty() {
+            continue;
+        }
+        script.push_str(&format!(
+            "        {}) {} ;;\n",
+            command.name,
+            completions.join("; ")
+        ));
+    }
+    script.push_str("    esac\n}\ncomplete -F _pfman pfman\n");
+    script
+}
+
+fn bash_complete(value: Value) -> String {
+    match value {
+        Value::File => "COMPREPLY=($(compgen -f -- \"$cur\"))".to_string(),
+        Value::Dir => "COMPREPLY=($(compgen -d -- \"$cur\"))".to_string(),
+        Value::Session => {
+            "COMPREPLY=($(compgen -W \"$(pfman completions --sessions 2>/dev/null)\" -- \"$cur\"))"
+                .to_string()
+        }
+        Value::Tag => {
+            "COMPREPLY=($(compgen -W \"$(pfman completions --tags 2>/dev/null)\" -- \"$cur\"))"
+                .to_string()
+        }
+        Value::Choice(choices) => format!(
+            "COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))",
+            choices.join("\n")
+        ),
+        Value::Text => "COMPREPLY=()".to_string(),
+    }
+}
+
+/// Like [`bash_complete`], but adding to the flags already in COMPREPLY
+fn bash_positional(value: Value) -> String {
+    bash_complete(value).replacen("COMPREPLY=(", "COMPREPLY+=(", 1)
+}
+
+fn zsh() -> String {
+    let mut script = String::from(
+        "#compdef pfman\n# zsh completion for pfman, generated by `pfman completions zsh`\n\n\
+         _pfman_sessions() {\n    \
+         local -a sessions\n    \
+         sessions=(\"${(@f)$(pfman completions --sessions 2>/dev/null)}\")\n    \
+         compadd -a sessions\n}\n\n\
+         _pfman_tags() {\n    \
+         local -a tags\n    \
+         tags=(\"${(@f)$(pfman completions --tags 2>/dev/null)}\")\n    \
+         compadd -a tags\n}\n\n\
+         _pfman() {\n    \
+         local context state state_descr line\n    \
+         typeset -A opt_args\n\n    \
+         _arguments -C \\\n",
+    );
+    for flag in OPTIONS {
+        script.push_str(&format!("        {} \\\n", zsh_spec(flag)));
+    }
+    script.push_str("        '1: :->command' \\\n        '*:: :->args'\n\n");
+    script.push_str("    case $state in\n        command)\n            local -a commands=(\n");
+    for command in COMMANDS {
+        script.push_str(&format!(
+            "                '{}:{}'\n",
+            command.name,
+            zsh_escape(command.about)
+        ));
+    }
+    script.push_str(
+        "            )\n            _describe command commands ;;\n        args)\n            case $line[1] in\n",
+    );
+    for command in COMMANDS {
+        let mut specs: Vec<String> = command.flags.iter().map(zsh_spec).collect();
+        if let Some((placeholder, value)) = command.positional {
+            specs.push(format!(
+                "'1:{}:{}'",
+                placeholder.replace(['[', ']', '<', '>'], ""),
+                zsh_action(value)
+            ));
+        }
+        if specs.is_empty() {
+            continue;
+        }
+        script.push_str(&format!(
+            "                {}) _arguments {} ;;\n",
+            command.name,
+            specs.join(" ")
+        ));
+    }
+    script.push_str("            esac ;;\n    esac\n}\n\n_pfman \"$@\"\n");
+    script
+}
+
+fn zsh_spec(flag: &Flag) -> String {
+    let repeatable = if flag.about.contains("(repeatable)") {
+        "*"
+    } else {
+        ""
+    };
+    match flag.value {
+        Some((placeholder, value)) => format!(
+            "'{}{}[{}]:{}:{}'",
+            repeatable,
+            flag.long,
+            zsh_escape(flag.about),
+            placeholder.replace(['<', '>'], ""),
+            zsh_action(value)
+        ),
+        None => format!("'{}[{}]'", flag.long, zsh_escape(flag.about)),
+    }
+}
+
+fn zsh_action(value: Value) -> String {
+    match value {
+        Value::File => "_files".to_string(),
+        Value::Dir => "_directories".to_string(),
+        Value::Session => "_pfman_sessions".to_string(),
+        Value::Tag => "_pfman_tags".to_string(),
+        Value::Choice(choices) => format!("({})", choices.join(" ")),
+        Value::Text => " ".to_string(),
+    }
+}
+
+/// Descriptions go inside single-quoted `[...]` and `name:description`
+fn zsh_escape(text: &str) -> String {
+    text.replace('\'', "'\\''")
+        .replace('[', "\\[")
+        .replace(']', "\\]")
+        .replace(':', "\\:")
+}
+
+fn fish() -> String {
+    let mut script = String::from(
+        "# fish completion for pfman, generated by `pfman completions fish`\ncomplete -c pfman -f\n",
+    );
+    for flag in OPTIONS {
+        script.push_str(&fish_flag("__fish_use_subcommand", flag));
+    }
+    for command in COMMANDS {
+        script.push_str(&format!(
+            "complete -c pfman -n __fish_use_subcommand -a {} -d '{}'\n",
+            command.name,
+            fish_escape(command.about)
+        ));
+    }
+    for command in COMMANDS {
+        let condition = format!("'__fish_seen_subcommand_from {}'", command.name);
+        for flag in command.flags {
+            script.push_str(&fish_flag(&condition, flag));
+        }
+        if let Some(values) = command.positional.and_then(|(_, value)| fish_values(value)) {
+            script.push_str(&format!("complete -c pfman -n {} {}\n", condition, values));
+        }
+    }
+    script
+}
+
+fn fish_flag(condition: &str, flag: &Flag) -> String {
+    let values = flag
+        .value
+        .map(|(_, value)| match fish_values(value) {
+            Some(values) => format!(" -r {}", values),
+            None => " -r".to_string(),
+        })
+        .unwrap_or_default();
+    format!(
+        "complete -c pfman -n {} -l {}{} -d '{}'\n",
+        condition,
+        flag.long.trim_start_matches("--"),
+        values,
+        fish_escape(flag.about)
+    )
+}
+
+/// Arguments adding the candidates for `value`; none for free text
+fn fish_values(value: Value) -> Option<String> {
+    match value {
+        Value::File => Some("-F".to_string()),
+        Value::Dir => Some("-a '(__fish_complete_directories)'".to_string()),
+        Value::Session => Some("-a '(pfman completions --sessions 2>/dev/null)'".to_string()),
+        Value::Tag => Some("-a '(pfman completions --tags 2>/dev/null)'".to_string()),
+        Value::Choice(choices) => Some(format!("-a '{}'", choices.join(" "))),
+        Value::Text => None,
+    }
+}
+
+fn fish_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('\'', "\\'")
+}
+
+fn man() -> String {
+    let mut page = format!(
+        ".TH PFMAN 1 \"\" \"pfman {}\" \"User Commands\"\n\
+         .SH NAME\npfman \\- {}\n\
+         .SH SYNOPSIS\n.B pfman\n[\\fIOPTIONS\\fR]\n.br\n.B pfman\n\\fICOMMAND\\fR [\\fIARGS\\fR]\n\
+         .SH DESCRIPTION\n\
+         A terminal UI for managing SSH, kubectl and SOCKS5 port\\-forwarding sessions. \
+         Sessions keep running in the background after pfman quits and are picked up \
+         again on the next start.\n\
+         .SH OPTIONS\n",
+        env!("CARGO_PKG_VERSION"),
+        roff_escape(env!("CARGO_PKG_DESCRIPTION")),
+    );
+    for flag in OPTIONS {
+        page.push_str(&roff_flag(flag));
+    }
+    page.push_str(".SH COMMANDS\n");
+    for command in COMMANDS {
+        page.push_str(&format!(
+            ".TP\n{}\n{}\n",
+            synopsis(command),
+            roff_escape(command.about)
+        ));
+        if !command.flags.is_empty() {
+            page.push_str(".RS\n");
+            for flag in command.flags {
+                page.push_str(&roff_flag(flag));
+            }
+            page.push_str(".RE\n");
+        }
+    }
+    page.push_str(
+        ".SH FILES\n\
+         .TP\n\\fIconfig.yaml\\fR, \\fIsessions.yaml\\fR\n\
+         Settings and saved sessions, in the platform's config directory \
+         (e.g. \\fI~/.config/pfman\\fR) or the one given with \\fB\\-\\-config\\-dir\\fR, \
+         whose logs and other data go to \\fIdata/\\fR inside it.\n\
+         .TP\n\\fI/etc/pfman/policy.yaml\\fR\n\
+         Restrictions on what may be created or started on managed machines.\n\
+         .SH ENVIRONMENT\n\
+         .TP\n.B KUBECONFIG\n\
+         Kubeconfig files whose contexts are offered, merged like kubectl does.\n\
+         .TP\n.B PFMAN_POLICY\n\
+         Policy file to read instead of \\fI/etc/pfman/policy.yaml\\fR.\n\
+         .TP\n.B NO_COLOR\n\
+         Disable all colors.\n",
+    );
+    page
+}
+
+/// Bold command with its arguments in italics, e.g. `pfman apply <file>`
+fn synopsis(command: &Command) -> String {
+    let mut line = format!("\\fBpfman {}\\fR", command.name);
+    if let Some((placeholder, _)) = command.positional {
+        line.push_str(&format!(" \\fI{}\\fR", roff_escape(placeholder)));
+    }
+    for flag in command.flags {
+        line.push_str(&format!(" [\\fB{}\\fR", roff_escape(flag.long)));
+        if let Some((placeholder, _)) = flag.value {
+            line.push_str(&format!(" \\fI{}\\fR", roff_escape(placeholder)));
+        }
+        line.push(']');
+    }
+    line
+}
+
+fn roff_flag(flag: &Flag) -> String {
+    let value = flag
+        .value
+        .map(|(placeholder, _)| format!(" \\fI{}\\fR", roff_escape(placeholder)))
+        .unwrap_or_default();
+    format!(
+        ".TP\n\\fB{}\\fR{}\n{}\n",
+        roff_escape(flag.long),
+        value,
+        roff_escape(flag.about)
+    )
+}
+
+fn roff_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('-', "\\-")
+}
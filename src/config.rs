@@ -0,0 +1,300 @@
+use crate::usage::SummaryPeriod;
+use serde::{Deserialize, Serialize};
+
+/// User preferences loaded from `config.yaml` in the config directory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Replace color-only signaling with text and plain ASCII glyphs
+    pub accessible: bool,
+    /// Ask whether to start a session right after creating it
+    pub prompt_start_on_create: bool,
+    /// Ask before stopping or restarting a running session from the
+    /// dashboard or logs viewer
+    pub confirm_stop: bool,
+    /// Connectivity check that gates automatic restarts
+    pub canary: Option<Canary>,
+    /// Open the usage summary for this period on startup
+    pub startup_summary: Option<SummaryPeriod>,
+    /// Remote endpoint receiving session lifecycle events and logs
+    pub log_sink: Option<LogSink>,
+    /// Ask whether a session should stay up once it ran this many hours
+    pub remind_after_hours: Option<u64>,
+    /// Tag sessions with the terminal they were started from and stop them
+    /// when that terminal's shell exits
+    pub terminal_scoping: bool,
+    /// Show each session's short ID (e.g. `pf-3fa2`) in the sessions table
+    pub show_ids: bool,
+    /// Start with the log preview pane open on the dashboard
+    pub preview_pane: bool,
+    /// Options added to every SSH/SOCKS5 command, e.g. `ServerAliveInterval=30`
+    pub ssh_options: Vec<String>,
+    /// Pass `-o ExitOnForwardFailure=yes` to ssh so failed binds are errors
+    pub exit_on_forward_failure: bool,
+    /// Team-curated list of sessions synced into the catalog screen
+    pub catalog: Option<CatalogSource>,
+    /// Minutes between catalog syncs
+    pub catalog_sync_minutes: u64,
+    /// Commands printing extra host/kubectl target suggestions as JSON
+    pub suggestion_scripts: Vec<String>,
+    /// Color scheme: `dark`, `light` or `high-contrast`
+    pub theme: ThemeName,
+    /// Keep `status.json` in the data directory up to date for watchdogs
+    /// and status bars
+    pub status_file: bool,
+    /// Where long values are cut in the sessions table: `middle` or `end`
+    pub truncation: Truncation,
+    /// Width limits for the name, target and status columns, e.g.
+    /// `column_widths: { target: { min: 20, max: 60 } }`
+    pub column_widths: ColumnWidths,
+    /// Where the local HTTP API listens, e.g.
+    /// `api: { kind: tcp, target: "127.0.0.1:7878" }`; off when unset
+    pub api: Option<ApiListen>,
+    /// Unix socket streaming session events as JSON lines and accepting
+    /// commands, e.g. `ipc_socket: ~/.pfman.sock`; off when unset
+    pub ipc_socket: Option<String>,
+    /// Webhooks and commands run when sessions start, stop or fail
+    pub hooks: Vec<Hook>,
+    /// Pauses between automatic restarts of sessions with auto-restart on
+    pub reconnect: ReconnectBackoff,
+    /// Probe running forwards through to the remote side and mark those
+    /// that don't get through as Degraded
+    pub verify_tunnels: bool,
+    /// Milliseconds between redraws that only advance spinners and uptimes;
+    /// input and status changes redraw right away
+    pub tick_rate_ms: u64,
+    /// Extra session types run by other tools, e.g. a company's own
+    /// tunnel client, see [`crate::provider`]
+    pub session_providers: Vec<ProviderConfig>,
+}
+
+/// Session type run by an external command, e.g.
+/// `{ name: corp-tunnel, command: "corp-tunnel open {target} --port {local_port}" }`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ProviderConfig {
+    /// Type name shown in the form and the sessions table, and saved with
+    /// the sessions
+    pub name: String,
+    /// One line shown when choosing a session type
+    #[serde(default)]
+    pub description: String,
+    /// Command line run for a session, split on whitespace (no shell).
+    /// `{target}`, `{local_port}`, `{local_host}` and `{remote_port}` are
+    /// filled in.
+    pub command: String,
+    /// Shell command printing one target per line, offered in the form
+    pub list_targets: Option<String>,
+    /// Shell command run before saving or starting a session, with
+    /// `PFMAN_TARGET`, `PFMAN_LOCAL_PORT` and `PFMAN_REMOTE_PORT` set. A
+    /// non-zero exit rejects the session; its output says why.
+    pub validate: Option<String>,
+}
+
+/// Exponential backoff for automatic restarts: the pause doubles with each
+/// failure in a row, from `initial_delay_secs` up to `max_delay_secs`
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ReconnectBackoff {
+    pub initial_delay_secs: u64,
+    pub max_delay_secs: u64,
+}
+
+impl Default for ReconnectBackoff {
+    fn default() -> Self {
+        Self {
+            initial_delay_secs: 2,
+            max_delay_secs: 60,
+        }
+    }
+}
+
+/// Which part of a value that doesn't fit its column is replaced by an ellipsis
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Truncation {
+    /// Keep the start and the end, e.g. `arn:aws:rds:…:db/orders`
+    #[default]
+    Middle,
+    End,
+}
+
+/// Per-column width limits for the sessions table
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ColumnWidths {
+    pub name: WidthLimits,
+    pub target: WidthLimits,
+    pub status: WidthLimits,
+}
+
+/// Lower and upper bound for a column's width, in characters
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct WidthLimits {
+    pub min: Option<u16>,
+    pub max: Option<u16>,
+}
+
+impl WidthLimits {
+    pub fn is_set(&self) -> bool {
+        self.min.is_some() || self.max.is_some()
+    }
+
+    /// `width` brought within the limits; `max` wins if they conflict
+    pub fn apply(&self, width: u16) -> u16 {
+        let width = self.min.map_or(width, |min| width.max(min));
+        self.max.map_or(width, |max| width.min(max))
+    }
+}
+
+/// Built-in color schemes
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ThemeName {
+    #[default]
+    Dark,
+    Light,
+    HighContrast,
+}
+
+/// Audit sink, e.g. `log_sink: { kind: syslog, target: "logs.corp:6514" }`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", content = "target", rename_all = "lowercase")]
+pub enum LogSink {
+    /// URL receiving JSON arrays of records via POST
+    Http(String),
+    /// `host:port` of a syslog server accepting TLS (RFC 5425)
+    Syslog(String),
+}
+
+/// Where the catalog comes from, e.g.
+/// `catalog: { kind: git, target: "git@github.com:team/tunnels.git" }`.
+/// Both serve a YAML/JSON list of sessions in the import format.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", content = "target", rename_all = "lowercase")]
+pub enum CatalogSource {
+    /// URL of the file, fetched with curl
+    Https(String),
+    /// Repository URL, optionally `#path/to/file.yaml` (default `catalog.yaml`)
+    Git(String),
+}
+
+/// Address of the local HTTP API. Only localhost is accepted.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", content = "target", rename_all = "lowercase")]
+pub enum ApiListen {
+    /// `host:port` on the loopback interface
+    Tcp(String),
+    /// Path of a unix socket, created readable by the user only
+    Unix(String),
+}
+
+/// Run when a session's status changes, e.g.
+/// `{ on: [error], sessions: [prod-readonly], action: { kind: http, target: "https://hooks.slack.com/..." } }`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Hook {
+    /// Events that fire the hook; every event when empty
+    #[serde(default)]
+    pub on: Vec<HookEvent>,
+    /// Names or tags of the sessions the hook is for; every session when
+    /// empty
+    #[serde(default)]
+    pub sessions: Vec<String>,
+    pub action: HookAction,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum HookEvent {
+    /// The session is up (its local port accepts connections)
+    Started,
+    Stopped,
+    Error,
+}
+
+impl HookEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HookEvent::Started => "started",
+            HookEvent::Stopped => "stopped",
+            HookEvent::Error => "error",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", content = "target", rename_all = "lowercase")]
+pub enum HookAction {
+    /// URL receiving the event as a JSON POST (needs curl)
+    Http(String),
+    /// Shell command run with `PFMAN_*` variables describing the session
+    Command(String),
+}
+
+/// Global connectivity probe, e.g. `canary: { kind: tcp, target: "bastion:22" }`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", content = "target", rename_all = "lowercase")]
+pub enum Canary {
+    /// `host:port` that must accept a TCP connection
+    Tcp(String),
+    /// URL that must answer (2xx/3xx for plain http)
+    Http(String),
+    /// Host that must answer a single ping
+    Ping(String),
+    /// Network interface that must be up, e.g. a VPN's `tun0`/`utun3`
+    Interface(String),
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            accessible: false,
+            prompt_start_on_create: true,
+            confirm_stop: false,
+            canary: None,
+            startup_summary: None,
+            log_sink: None,
+            remind_after_hours: None,
+            terminal_scoping: false,
+            show_ids: false,
+            preview_pane: false,
+            ssh_options: Vec::new(),
+            exit_on_forward_failure: true,
+            catalog: None,
+            catalog_sync_minutes: 60,
+            suggestion_scripts: Vec::new(),
+            theme: ThemeName::Dark,
+            status_file: false,
+            truncation: Truncation::Middle,
+            column_widths: ColumnWidths::default(),
+            api: None,
+            ipc_socket: None,
+            hooks: Vec::new(),
+            reconnect: ReconnectBackoff::default(),
+            verify_tunnels: false,
+            tick_rate_ms: 250,
+            session_providers: Vec::new(),
+        }
+    }
+}
+
+impl Config {
+    /// Colors are dropped when accessibility mode is on or `NO_COLOR` is set
+    /// (see https://no-color.org)
+    pub fn monochrome(&self) -> bool {
+        self.accessible || std::env::var("NO_COLOR").is_ok_and(|v| !v.is_empty())
+    }
+
+    pub fn catalog_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.catalog_sync_minutes.max(1) * 60)
+    }
+
+    pub fn tick_rate(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(self.tick_rate_ms.max(50))
+    }
+
+    pub fn remind_after(&self) -> Option<chrono::Duration> {
+        self.remind_after_hours
+            .map(|hours| chrono::Duration::hours(hours as i64))
+    }
+}
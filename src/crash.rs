@@ -0,0 +1,41 @@
+use crate::storage::Storage;
+use chrono::Utc;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Install a panic hook that restores the terminal first, then records the
+/// panic and a backtrace in the debug log and leaves a marker so the next
+/// start can tell the user what happened.
+pub fn install_panic_hook(storage: &Storage) {
+    let debug_log = storage.debug_log_file();
+    let marker = storage.crash_marker_file();
+    let previous = std::panic::take_hook();
+
+    std::panic::set_hook(Box::new(move |info| {
+        let _ = crossterm::execute!(std::io::stdout(), crossterm::event::DisableBracketedPaste);
+        ratatui::restore();
+
+        let backtrace = std::backtrace::Backtrace::force_capture();
+        let now = Utc::now().format("%Y-%m-%d %H:%M:%S");
+        if let Ok(mut file) = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&debug_log)
+        {
+            let _ = writeln!(file, "[{}] pfman panicked: {}\n{}\n", now, info, backtrace);
+        }
+        let _ = fs::write(&marker, debug_log.to_string_lossy().as_bytes());
+
+        previous(info);
+    }));
+}
+
+/// Path of the debug log if the previous run crashed. The marker is removed,
+/// so the notice is shown only once.
+pub fn take_crash_notice(storage: &Storage) -> Option<PathBuf> {
+    let marker = storage.crash_marker_file();
+    let content = fs::read_to_string(&marker).ok()?;
+    let _ = fs::remove_file(&marker);
+    Some(PathBuf::from(content.trim()))
+}
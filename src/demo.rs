@@ -0,0 +1,146 @@
+use crate::models::{Session, SessionType};
+use color_eyre::Result;
+use color_eyre::eyre::eyre;
+use std::io::Write;
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Hidden argument that turns the pfman binary into a simulated tunnel
+pub const LISTENER_FLAG: &str = "--simulate-listener";
+
+/// Scripted behaviour option: exit with an error after this many seconds
+const CRASH_AFTER: &str = "--crash-after";
+/// Scripted behaviour option: wait this many seconds before listening
+const LISTEN_DELAY: &str = "--listen-delay";
+
+/// Scratch directory demo mode keeps its sessions and logs in, deleted
+/// when dropped so demos don't pile up in the temp directory
+#[derive(Debug)]
+pub(crate) struct DemoDir(PathBuf);
+
+impl DemoDir {
+    pub(crate) fn new() -> Self {
+        Self(std::env::temp_dir().join(format!("pfman-demo-{}", std::process::id())))
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for DemoDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+pub fn demo_sessions() -> Vec<Session> {
+    let mut sessions = vec![
+        demo_session(
+            "postgres-prod",
+            SessionType::SSH,
+            "bastion.prod",
+            15432,
+            &[],
+        ),
+        demo_session(
+            "redis-cache",
+            SessionType::Kubectl,
+            "services/redis",
+            16379,
+            &[LISTEN_DELAY, "3"],
+        ),
+        demo_session(
+            "flaky-api",
+            SessionType::SSH,
+            "api.staging",
+            18080,
+            &[CRASH_AFTER, "20"],
+        ),
+        demo_session("socks-proxy", SessionType::Socks5, "jump.dev", 11080, &[]),
+        demo_session(
+            "grafana",
+            SessionType::Kubectl,
+            "pods/grafana-0",
+            13000,
+            &[CRASH_AFTER, "5"],
+        ),
+    ];
+
+    sessions[0].icon = Some("🔴".to_string());
+    sessions[1].kube_context = Some("demo-cluster".to_string());
+    sessions[1].kube_namespace = Some("cache".to_string());
+    sessions[4].kube_namespace = Some("monitoring".to_string());
+    sessions
+}
+
+fn demo_session(
+    name: &str,
+    session_type: SessionType,
+    target: &str,
+    local_port: u16,
+    script: &[&str],
+) -> Session {
+    let remote_port = match session_type {
+        SessionType::Socks5 => None,
+        _ => Some(local_port % 10000),
+    };
+    let mut session = Session::new(
+        name.to_string(),
+        session_type,
+        target.to_string(),
+        local_port,
+        remote_port,
+    );
+    session.additional_options = script.iter().map(|s| s.to_string()).collect();
+    session
+}
+
+/// Entry point for `pfman --simulate-listener <port> [options]`: accept
+/// connections on the port, log activity and follow the scripted behaviour.
+pub fn run_listener(args: &[String]) -> Result<()> {
+    let port: u16 = args
+        .first()
+        .and_then(|p| p.parse().ok())
+        .ok_or_else(|| eyre!("missing port"))?;
+    let option = |name: &str| {
+        args.iter()
+            .position(|a| a == name)
+            .and_then(|i| args.get(i + 1))
+            .and_then(|v| v.parse::<u64>().ok())
+    };
+    let crash_after = option(CRASH_AFTER).map(Duration::from_secs);
+    let listen_delay = option(LISTEN_DELAY).map(Duration::from_secs);
+
+    println!("[demo] simulated tunnel for local port {}", port);
+    if let Some(delay) = listen_delay {
+        println!("[demo] negotiating for {}s...", delay.as_secs());
+        thread::sleep(delay);
+    }
+
+    let listener = TcpListener::bind(("127.0.0.1", port))?;
+    println!("[demo] listening on 127.0.0.1:{}", port);
+    let _ = std::io::stdout().flush();
+
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            if let Ok(peer) = stream.peer_addr() {
+                println!("[demo] connection from {}", peer);
+                let _ = std::io::stdout().flush();
+            }
+        }
+    });
+
+    let started = Instant::now();
+    loop {
+        thread::sleep(Duration::from_secs(1));
+        if let Some(limit) = crash_after
+            && started.elapsed() >= limit
+        {
+            eprintln!("[demo] simulated crash: connection reset by peer");
+            std::process::exit(255);
+        }
+    }
+}
@@ -0,0 +1,222 @@
+//! Embedding API for other ratatui apps.
+//!
+//! [`SessionStore`] owns the saved sessions and the background status
+//! monitor, and [`SessionTable`] renders them the same way the pfman
+//! dashboard does:
+//!
+//! ```no_run
+//! use pfman::embed::{SessionEvent, SessionStore};
+//!
+//! let mut store = SessionStore::open()?;
+//! let events = store.subscribe();
+//!
+//! // In the host app's event loop:
+//! store.poll();
+//! while let Ok(event) = events.try_recv() {
+//!     if let SessionEvent::StatusChanged { id, status } = event {
+//!         println!("{id}: {}", status.as_str());
+//!     }
+//! }
+//! // and when drawing: frame.render_widget(store.table(), area);
+//! # Ok::<(), color_eyre::Report>(())
+//! ```
+//!
+//! Tools without a UI can manage sessions the same way, keeping them
+//! apart from the user's own with [`SessionStore::with_storage`]:
+//!
+//! ```no_run
+//! use pfman::embed::SessionStore;
+//! use pfman::models::{Session, SessionType};
+//! use pfman::storage::Storage;
+//!
+//! let storage = Storage::in_dir(std::env::temp_dir().join("pfman-tool"))?;
+//! let mut store = SessionStore::with_storage(storage)?;
+//! let session = Session::new("db".into(), SessionType::SSH, "bastion".into(), 15432, Some(5432));
+//! let id = store.add(session)?;
+//! store.start(id)?;
+//! # Ok::<(), color_eyre::Report>(())
+//! ```
+
+use crate::models::{Session, SessionStatus};
+use crate::policy::Policy;
+use crate::process::ProcessManager;
+use crate::provider::ProviderRegistry;
+use crate::runs;
+use crate::saver::SessionSaver;
+use crate::storage::Storage;
+use color_eyre::Result;
+use color_eyre::eyre::eyre;
+use std::sync::mpsc::{self, Receiver, Sender};
+use uuid::Uuid;
+
+pub use crate::ui::session_table::SessionTable;
+pub use crate::ui::theme::Theme;
+
+/// Change notifications delivered to every [`SessionStore::subscribe`] receiver
+#[derive(Debug, Clone, PartialEq)]
+pub enum SessionEvent {
+    /// A session's status changed, either through the store or because the
+    /// background monitor noticed (tunnel up, process crashed, ...)
+    StatusChanged { id: Uuid, status: SessionStatus },
+    /// A session was saved through [`SessionStore::add`]
+    Added { id: Uuid },
+    /// A session was deleted through [`SessionStore::remove`]
+    Removed { id: Uuid },
+}
+
+/// Handle to pfman's saved sessions and their processes
+pub struct SessionStore {
+    saver: SessionSaver,
+    process_manager: ProcessManager,
+    sessions: Vec<Session>,
+    subscribers: Vec<Sender<SessionEvent>>,
+}
+
+impl SessionStore {
+    /// Load sessions from pfman's default locations and start monitoring them
+    pub fn open() -> Result<Self> {
+        Self::with_storage(Storage::new()?)
+    }
+
+    /// Load sessions from `storage` instead, e.g. [`Storage::in_dir`] for a
+    /// tool or test that must not touch the user's own sessions
+    pub fn with_storage(storage: Storage) -> Result<Self> {
+        runs::migrate_legacy_logs(&storage);
+        let mut sessions = storage.load_sessions()?;
+        let config = storage.load_config()?;
+        let process_manager = ProcessManager::new(storage.clone())
+            .with_policy(Policy::load()?)
+            .with_ssh_options(config.ssh_options)
+            .with_exit_on_forward_failure(config.exit_on_forward_failure)
+            .with_status_file(config.status_file.then(|| storage.status_file()))
+            .with_tunnel_verification(config.verify_tunnels)
+            .with_providers(ProviderRegistry::from_config(&config.session_providers));
+        process_manager.sync_monitored_sessions(&sessions);
+        if process_manager.adopt_sessions(&mut sessions) {
+            storage.save_sessions(&sessions)?;
+        }
+
+        Ok(Self {
+            saver: SessionSaver::spawn(storage),
+            process_manager,
+            sessions,
+            subscribers: Vec::new(),
+        })
+    }
+
+    pub fn sessions(&self) -> &[Session] {
+        &self.sessions
+    }
+
+    pub fn get(&self, id: Uuid) -> Option<&Session> {
+        self.sessions.iter().find(|s| s.id == id)
+    }
+
+    /// Receive [`SessionEvent`]s. Events are sent from [`Self::poll`] and
+    /// the methods changing sessions; dropped receivers are pruned.
+    pub fn subscribe(&mut self) -> Receiver<SessionEvent> {
+        let (tx, rx) = mpsc::channel();
+        self.subscribers.push(tx);
+        rx
+    }
+
+    /// Apply pending updates from the status monitor. Call this from the
+    /// host app's event loop; returns true if anything changed. Status
+    /// changes are saved in the background a moment later.
+    pub fn poll(&mut self) -> bool {
+        self.saver.save_if_due(&self.sessions);
+        let before: Vec<(Uuid, SessionStatus)> = self
+            .sessions
+            .iter()
+            .map(|s| (s.id, s.status.clone()))
+            .collect();
+
+        if !self.process_manager.poll_status_updates(&mut self.sessions) {
+            return false;
+        }
+        self.saver.mark_dirty();
+
+        for (id, old_status) in before {
+            let status = self.get(id).map(|s| s.status.clone());
+            if let Some(status) = status
+                && status != old_status
+            {
+                self.emit(SessionEvent::StatusChanged { id, status });
+            }
+        }
+        true
+    }
+
+    /// Save a new session, stopped; start it with [`Self::start`]
+    pub fn add(&mut self, session: Session) -> Result<Uuid> {
+        let id = session.id;
+        if self.get(id).is_some() {
+            return Err(eyre!("a session with id {} already exists", id));
+        }
+        self.sessions.push(session);
+        self.process_manager.sync_monitored_sessions(&self.sessions);
+        self.saver.save_now(&self.sessions)?;
+        self.emit(SessionEvent::Added { id });
+        Ok(id)
+    }
+
+    /// Delete a session, stopping it first if it is running
+    pub fn remove(&mut self, id: Uuid) -> Result<Session> {
+        let idx = self
+            .sessions
+            .iter()
+            .position(|s| s.id == id)
+            .ok_or_else(|| eyre!("no session with id {}", id))?;
+        if self.sessions[idx].pid.is_some() {
+            self.process_manager.stop_session(&mut self.sessions[idx])?;
+        }
+        let session = self.sessions.remove(idx);
+        self.process_manager.sync_monitored_sessions(&self.sessions);
+        self.saver.save_now(&self.sessions)?;
+        self.emit(SessionEvent::Removed { id });
+        Ok(session)
+    }
+
+    pub fn start(&mut self, id: Uuid) -> Result<()> {
+        let session = find_mut(&mut self.sessions, id)?;
+        self.process_manager.start_session(session)?;
+        self.after_change(id)
+    }
+
+    pub fn stop(&mut self, id: Uuid) -> Result<()> {
+        let session = find_mut(&mut self.sessions, id)?;
+        self.process_manager.stop_session(session)?;
+        self.after_change(id)
+    }
+
+    /// Widget showing all sessions, ready to hand to `Frame::render_widget`
+    pub fn table(&self) -> SessionTable<'_> {
+        SessionTable::new(&self.sessions)
+    }
+
+    fn after_change(&mut self, id: Uuid) -> Result<()> {
+        self.saver.save_now(&self.sessions)?;
+        if let Some(status) = self.get(id).map(|s| s.status.clone()) {
+            self.emit(SessionEvent::StatusChanged { id, status });
+        }
+        Ok(())
+    }
+
+    fn emit(&mut self, event: SessionEvent) {
+        self.subscribers
+            .retain(|subscriber| subscriber.send(event.clone()).is_ok());
+    }
+}
+
+impl Drop for SessionStore {
+    fn drop(&mut self) {
+        let _ = self.saver.flush(&self.sessions);
+    }
+}
+
+fn find_mut(sessions: &mut [Session], id: Uuid) -> Result<&mut Session> {
+    sessions
+        .iter_mut()
+        .find(|s| s.id == id)
+        .ok_or_else(|| eyre!("no session with id {}", id))
+}
@@ -0,0 +1,106 @@
+//! The dashboard's event channel. Terminal input, status updates from the
+//! process monitor and suggestion lookups finishing arrive on one tokio
+//! channel, so the main loop wakes up as soon as any of them happens.
+//!
+//! Only those three go through it. The monitor itself, the API and IPC
+//! socket listeners, hooks, the canary and catalog sync run on threads of
+//! their own, and what they queue (e.g. API requests) is picked up on the
+//! main loop's tick.
+
+use crate::kube_config::{KubeScope, KubeTarget};
+use crate::process::StatusUpdate;
+use crate::ssh_config::SshHost;
+use crossterm::event::{self, Event};
+use std::thread;
+use std::time::Duration;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+
+/// How often the terminal reader checks whether the app is still listening
+const READER_POLL: Duration = Duration::from_millis(100);
+
+#[derive(Debug)]
+pub enum AppEvent {
+    /// Key press, paste or resize
+    Terminal(Event),
+    /// A session's status changed, as seen by the monitor
+    Status(StatusUpdate),
+    /// A session form's lookup finished
+    Suggestions(Suggestions),
+}
+
+/// Results of the lookups a session form runs in the background
+#[derive(Debug)]
+pub enum Suggestions {
+    SshHosts(Vec<SshHost>),
+    Namespaces(KubeScope, Vec<String>),
+    KubeTargets(KubeScope, Vec<KubeTarget>),
+    /// Targets listed by the session provider of this name
+    ProviderTargets(String, Vec<String>),
+}
+
+pub type EventSender = UnboundedSender<AppEvent>;
+
+pub struct Events {
+    sender: EventSender,
+    receiver: UnboundedReceiver<AppEvent>,
+}
+
+impl Default for Events {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Events {
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        Self { sender, receiver }
+    }
+
+    pub fn sender(&self) -> EventSender {
+        self.sender.clone()
+    }
+
+    /// Read terminal input on a thread of its own, which ends once the
+    /// events are dropped
+    pub fn spawn_terminal_reader(&self) {
+        let sender = self.sender();
+        thread::spawn(move || {
+            while !sender.is_closed() {
+                match event::poll(READER_POLL) {
+                    Ok(false) => continue,
+                    Ok(true) => {}
+                    Err(_) => break,
+                }
+                let Ok(event) = event::read() else {
+                    break;
+                };
+                if sender.send(AppEvent::Terminal(event)).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+
+    /// Pass the monitor's status updates on as events. Needs a tokio runtime.
+    pub fn forward_status_updates(&self, mut updates: UnboundedReceiver<StatusUpdate>) {
+        let sender = self.sender();
+        tokio::spawn(async move {
+            while let Some(update) = updates.recv().await {
+                if sender.send(AppEvent::Status(update)).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+
+    /// Wait for the next event
+    pub async fn next(&mut self) -> Option<AppEvent> {
+        self.receiver.recv().await
+    }
+
+    /// The next event if one is already waiting
+    pub fn try_next(&mut self) -> Option<AppEvent> {
+        self.receiver.try_recv().ok()
+    }
+}
@@ -0,0 +1,194 @@
+//! Per-session health commands, for protocols where a TCP connect to the
+//! local port doesn't prove the forward works (e.g. `pg_isready`), and the
+//! generic end-to-end probe through a forward.
+
+use crate::models::{AddressFamily, Session};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::io::Read;
+use std::net::{SocketAddr, TcpStream};
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// How often the monitor runs each running session's health command
+pub const HEALTH_INTERVAL: Duration = Duration::from_secs(15);
+/// Commands still running after this count as failed
+const HEALTH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long a probe through a tunnel waits for the far end to hang up
+const TUNNEL_PROBE_WAIT: Duration = Duration::from_millis(1500);
+
+/// Latest end-to-end probe of each running forward: `None` when it got
+/// through, otherwise why not
+pub type TunnelChecks = Arc<Mutex<HashMap<Uuid, Option<String>>>>;
+
+/// Outcome of the latest health command run
+#[derive(Debug, Clone, PartialEq)]
+pub struct HealthCheck {
+    pub healthy: bool,
+    pub checked_at: DateTime<Utc>,
+    /// Last line of output, or the exit code when there was none
+    pub detail: String,
+}
+
+/// Latest check of each running session that has a health command
+pub type HealthChecks = Arc<Mutex<HashMap<Uuid, HealthCheck>>>;
+
+/// The session's health command with its placeholders filled in, see
+/// [`fill_placeholders`]
+pub fn command_for(session: &Session) -> Option<String> {
+    let command = session.health_command.as_deref()?.trim();
+    if command.is_empty() {
+        return None;
+    }
+    Some(fill_placeholders(command, session))
+}
+
+/// `command` with `{local_port}`, `{local_host}` (the loopback address to
+/// reach the forward on), `{remote_port}`, `{remote_host}` and `{target}`
+/// filled in
+pub fn fill_placeholders(command: &str, session: &Session) -> String {
+    command
+        .replace("{local_port}", &session.local_port.to_string())
+        .replace(
+            "{local_host}",
+            &session.address_family.loopbacks()[0].to_string(),
+        )
+        .replace(
+            "{remote_port}",
+            &session
+                .remote_port
+                .map(|p| p.to_string())
+                .unwrap_or_default(),
+        )
+        .replace("{remote_host}", session.forward_host())
+        .replace("{target}", &session.target)
+}
+
+/// Run a health command through `sh -c`; exit code 0 means healthy
+pub fn check(command: &str) -> HealthCheck {
+    let failed = |detail: String| HealthCheck {
+        healthy: false,
+        checked_at: Utc::now(),
+        detail,
+    };
+
+    let mut child = match Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(e) => return failed(format!("Failed to run health command: {}", e)),
+    };
+
+    // Drain both pipes off this thread so a chatty command can't block on a
+    // full pipe, and a leftover grandchild holding them can't block us
+    let (output_sender, output_receiver) = mpsc::channel();
+    for pipe in [
+        child
+            .stdout
+            .take()
+            .map(|p| Box::new(p) as Box<dyn Read + Send>),
+        child
+            .stderr
+            .take()
+            .map(|p| Box::new(p) as Box<dyn Read + Send>),
+    ]
+    .into_iter()
+    .flatten()
+    {
+        let sender = output_sender.clone();
+        thread::spawn(move || {
+            let mut pipe = pipe;
+            let mut text = String::new();
+            let _ = pipe.read_to_string(&mut text);
+            let _ = sender.send(text);
+        });
+    }
+    drop(output_sender);
+
+    let started = Instant::now();
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break Some(status),
+            Ok(None) if started.elapsed() < HEALTH_TIMEOUT => {
+                thread::sleep(Duration::from_millis(100))
+            }
+            _ => {
+                let _ = child.kill();
+                let _ = child.wait();
+                break None;
+            }
+        }
+    };
+    let Some(status) = status else {
+        return failed(format!("Timed out after {}s", HEALTH_TIMEOUT.as_secs()));
+    };
+
+    let mut output = String::new();
+    while let Ok(text) = output_receiver.recv_timeout(Duration::from_millis(500)) {
+        output.push_str(&text);
+    }
+    let detail = match output.lines().rev().find(|l| !l.trim().is_empty()) {
+        Some(line) => line.trim().to_string(),
+        None => match status.code() {
+            Some(code) => format!("Exit code {}", code),
+            None => "Killed by a signal".to_string(),
+        },
+    };
+
+    HealthCheck {
+        healthy: status.success(),
+        checked_at: Utc::now(),
+        detail,
+    }
+}
+
+/// Connect to a local listener on the first of the family's loopbacks that
+/// accepts, e.g. `[::1]` for a forward only bound there
+pub fn connect_loopback(
+    port: u16,
+    family: AddressFamily,
+    timeout: Duration,
+) -> std::io::Result<TcpStream> {
+    let mut last_error = None;
+    for ip in family.loopbacks() {
+        match TcpStream::connect_timeout(&SocketAddr::new(ip, port), timeout) {
+            Ok(stream) => return Ok(stream),
+            Err(e) => last_error = Some(e),
+        }
+    }
+    Err(last_error.unwrap_or_else(|| std::io::ErrorKind::AddrNotAvailable.into()))
+}
+
+/// Connect through the forward on `local_port` and see whether it stays up.
+/// ssh and kubectl accept every local connection and close it right away
+/// when the remote port can't be reached, while a service that answers
+/// sends something or waits for the client to speak first.
+pub fn probe_tunnel(local_port: u16, family: AddressFamily) -> Result<(), String> {
+    let mut stream = connect_loopback(local_port, family, Duration::from_secs(1))
+        .map_err(|e| format!("local port {} refused the connection: {}", local_port, e))?;
+    let _ = stream.set_read_timeout(Some(TUNNEL_PROBE_WAIT));
+    let mut byte = [0u8; 1];
+    match stream.read(&mut byte) {
+        Ok(0) => Err("remote port unreachable".to_string()),
+        Ok(_) => Ok(()),
+        Err(e)
+            if matches!(
+                e.kind(),
+                std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+            ) =>
+        {
+            Ok(())
+        }
+        Err(e) => Err(format!("connection through the tunnel failed: {}", e)),
+    }
+}
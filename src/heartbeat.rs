@@ -0,0 +1,101 @@
+//! Machine-readable status file for external watchdogs, status bars and
+//! scripts. The process monitor rewrites it on every tick, so a stale
+//! `updated_at` means no pfman instance is watching the sessions.
+
+use crate::health::HealthCheck;
+use crate::models::{ErrorCategory, Session, SessionStatus};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+use uuid::Uuid;
+
+#[derive(Debug, Serialize)]
+pub struct StatusReport {
+    pub updated_at: DateTime<Utc>,
+    /// PID of the pfman instance that wrote the file
+    pub pfman_pid: u32,
+    pub sessions: Vec<SessionReport>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SessionReport {
+    pub id: Uuid,
+    pub short_id: String,
+    pub name: String,
+    pub session_type: String,
+    pub target: String,
+    pub local_port: u16,
+    pub remote_port: Option<u16>,
+    /// `Starting`, `Running`, `Stopped` or `Error`
+    pub status: String,
+    pub pid: Option<u32>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub uptime_secs: Option<i64>,
+    pub latency_ms: Option<f64>,
+    /// Result of the session's health command, if it has one and it ran
+    pub healthy: Option<bool>,
+    pub error: Option<ErrorReport>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorReport {
+    pub kind: ErrorCategory,
+    pub message: String,
+    pub at: DateTime<Utc>,
+    pub exit_code: Option<i32>,
+}
+
+impl StatusReport {
+    pub fn new(sessions: &[Session], latencies: &HashMap<Uuid, Duration>) -> Self {
+        Self {
+            updated_at: Utc::now(),
+            pfman_pid: std::process::id(),
+            sessions: sessions
+                .iter()
+                .map(|session| SessionReport {
+                    id: session.id,
+                    short_id: session.short_id(),
+                    name: session.name.clone(),
+                    session_type: session.session_type.as_str().to_string(),
+                    target: session.target.clone(),
+                    local_port: session.local_port,
+                    remote_port: session.remote_port,
+                    status: session.status.as_str().to_string(),
+                    pid: session.pid,
+                    started_at: session.last_started,
+                    uptime_secs: session.uptime().map(|d| d.num_seconds()),
+                    latency_ms: latencies.get(&session.id).map(|l| l.as_secs_f64() * 1000.0),
+                    healthy: None,
+                    error: match &session.status {
+                        SessionStatus::Error(err) => Some(ErrorReport {
+                            kind: err.kind,
+                            message: err.message.clone(),
+                            at: err.at,
+                            exit_code: err.exit_code,
+                        }),
+                        _ => None,
+                    },
+                })
+                .collect(),
+        }
+    }
+
+    /// Fill in the health command results
+    pub fn with_health(mut self, checks: &HashMap<Uuid, HealthCheck>) -> Self {
+        for session in self.sessions.iter_mut() {
+            session.healthy = checks.get(&session.id).map(|c| c.healthy);
+        }
+        self
+    }
+
+    /// Replace the file in one step so readers never see a partial write
+    pub fn write(&self, path: &Path) -> std::io::Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, json)?;
+        fs::rename(tmp, path)
+    }
+}
@@ -0,0 +1,192 @@
+//! Hooks fired when sessions start, stop or fail: a JSON POST to a webhook
+//! (e.g. Slack) or a shell command with `PFMAN_*` environment variables.
+//! They run on background threads so a slow endpoint can't hold up the UI;
+//! failures are noted in the session's log.
+
+use crate::config::{Hook, HookAction, HookEvent};
+use crate::models::{Session, SessionStatus, StatusTracker};
+use crate::storage::Storage;
+use chrono::Utc;
+use serde::Serialize;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::thread;
+
+/// What a hook gets to know about the change, as the POST body or as
+/// environment variables
+#[derive(Debug, Clone, Serialize)]
+struct HookPayload {
+    /// One-line summary, also what Slack-compatible webhooks display
+    text: String,
+    event: &'static str,
+    timestamp: String,
+    host: String,
+    id: String,
+    short_id: String,
+    name: String,
+    session_type: String,
+    target: String,
+    local_port: u16,
+    remote_port: Option<u16>,
+    tags: Vec<String>,
+    status: String,
+    previous_status: String,
+    error: Option<String>,
+}
+
+impl HookPayload {
+    fn new(event: HookEvent, session: &Session, previous: &SessionStatus) -> Self {
+        let error = match &session.status {
+            SessionStatus::Error(err) => Some(err.message.clone()),
+            _ => None,
+        };
+        let host = sysinfo::System::host_name().unwrap_or_default();
+        let mut text = format!("pfman: {} {} on {}", session.name, event.as_str(), host);
+        if let Some(error) = &error {
+            text.push_str(&format!(": {}", error));
+        }
+        Self {
+            text,
+            event: event.as_str(),
+            timestamp: Utc::now().to_rfc3339(),
+            host,
+            id: session.id.to_string(),
+            short_id: session.short_id(),
+            name: session.name.clone(),
+            session_type: session.session_type.as_str().to_string(),
+            target: session.target.clone(),
+            local_port: session.local_port,
+            remote_port: session.remote_port,
+            tags: session.tags.clone(),
+            status: session.status.as_str().to_string(),
+            previous_status: previous.as_str().to_string(),
+            error,
+        }
+    }
+
+    fn env(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("PFMAN_EVENT", self.event.to_string()),
+            ("PFMAN_SESSION_ID", self.id.clone()),
+            ("PFMAN_SESSION_SHORT_ID", self.short_id.clone()),
+            ("PFMAN_SESSION_NAME", self.name.clone()),
+            ("PFMAN_SESSION_TYPE", self.session_type.clone()),
+            ("PFMAN_TARGET", self.target.clone()),
+            ("PFMAN_LOCAL_PORT", self.local_port.to_string()),
+            (
+                "PFMAN_REMOTE_PORT",
+                self.remote_port.map(|p| p.to_string()).unwrap_or_default(),
+            ),
+            ("PFMAN_TAGS", self.tags.join(",")),
+            ("PFMAN_STATUS", self.status.clone()),
+            ("PFMAN_PREVIOUS_STATUS", self.previous_status.clone()),
+            ("PFMAN_ERROR", self.error.clone().unwrap_or_default()),
+        ]
+    }
+}
+
+/// Watches session statuses and runs the configured hooks on changes
+pub struct HookRunner {
+    hooks: Vec<Hook>,
+    tracker: StatusTracker,
+    storage: Storage,
+}
+
+impl HookRunner {
+    pub fn new(hooks: Vec<Hook>, storage: Storage) -> Self {
+        Self {
+            hooks,
+            tracker: StatusTracker::default(),
+            storage,
+        }
+    }
+
+    /// Run the hooks matching the status changes since the last call
+    pub fn fire(&mut self, sessions: &[Session]) {
+        for (session, previous) in self.tracker.changes(sessions) {
+            let Some(event) = event_for(&session.status) else {
+                continue;
+            };
+            for hook in self.hooks.iter().filter(|h| applies(h, event, session)) {
+                let action = hook.action.clone();
+                let payload = HookPayload::new(event, session, &previous);
+                let storage = self.storage.clone();
+                let id = session.id;
+                thread::spawn(move || {
+                    if let Err(err) = run(&action, &payload) {
+                        let _ = storage.append_log(
+                            &id,
+                            &format!("Hook for {} failed: {}\n", payload.event, err),
+                        );
+                    }
+                });
+            }
+        }
+    }
+}
+
+/// Starting and stopping aren't events of their own: hooks hear about the
+/// outcome
+fn event_for(status: &SessionStatus) -> Option<HookEvent> {
+    match status {
+        SessionStatus::Running => Some(HookEvent::Started),
+        SessionStatus::Stopped => Some(HookEvent::Stopped),
+        SessionStatus::Error(_) => Some(HookEvent::Error),
+        SessionStatus::Starting | SessionStatus::Degraded(_) | SessionStatus::Stopping => None,
+    }
+}
+
+fn applies(hook: &Hook, event: HookEvent, session: &Session) -> bool {
+    (hook.on.is_empty() || hook.on.contains(&event))
+        && (hook.sessions.is_empty()
+            || hook
+                .sessions
+                .iter()
+                .any(|s| s.eq_ignore_ascii_case(&session.name) || session.tags.contains(s)))
+}
+
+fn run(action: &HookAction, payload: &HookPayload) -> Result<(), String> {
+    let (mut cmd, input) = match action {
+        HookAction::Http(url) => {
+            let body = serde_json::to_vec(payload).map_err(|e| e.to_string())?;
+            let mut cmd = Command::new("curl");
+            cmd.args([
+                "-sS",
+                "-f",
+                "-m",
+                "10",
+                "-X",
+                "POST",
+                "-H",
+                "Content-Type: application/json",
+                "--data-binary",
+                "@-",
+                url,
+            ]);
+            (cmd, body)
+        }
+        HookAction::Command(command) => {
+            let mut cmd = Command::new("sh");
+            cmd.arg("-c").arg(command).envs(payload.env());
+            (cmd, Vec::new())
+        }
+    };
+    let mut child = cmd
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| e.to_string())?;
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = stdin.write_all(&input);
+    }
+    let output = child.wait_with_output().map_err(|e| e.to_string())?;
+    if output.status.success() {
+        return Ok(());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    match stderr.lines().rev().find(|l| !l.trim().is_empty()) {
+        Some(line) => Err(line.trim().to_string()),
+        None => Err(output.status.to_string()),
+    }
+}
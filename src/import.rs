@@ -0,0 +1,483 @@
+use crate::models::{AddressFamily, Lifetime, MAX_IDLE_TIMEOUT_MINUTES, Session, SessionType};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Loose session description accepted when importing snippets. Only the
+/// target and local port are required, everything else has a sensible default.
+#[derive(Debug, Deserialize)]
+pub(crate) struct SessionSpec {
+    name: Option<String>,
+    #[serde(default = "default_session_type")]
+    session_type: SessionType,
+    target: String,
+    /// Not needed by a reverse proxy, which only has a remote port
+    local_port: Option<u16>,
+    remote_port: Option<u16>,
+    remote_host: Option<String>,
+    #[serde(default)]
+    address_family: AddressFamily,
+    #[serde(default)]
+    additional_options: Vec<String>,
+    kube_context: Option<String>,
+    kube_namespace: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    health_command: Option<String>,
+    pre_start: Option<String>,
+    post_stop: Option<String>,
+    #[serde(default)]
+    login_prompts: bool,
+    user: Option<String>,
+    identity_file: Option<String>,
+    kubeconfig_path: Option<String>,
+    idle_timeout_minutes: Option<u64>,
+    max_lifetime: Option<Lifetime>,
+    #[serde(default)]
+    auto_restart: bool,
+}
+
+pub(crate) fn default_session_type() -> SessionType {
+    SessionType::SSH
+}
+
+impl SessionSpec {
+    pub(crate) fn into_session(self) -> Result<Session, String> {
+        let (local_port, remote_port) = match self.session_type {
+            SessionType::ReverseSocks5 => {
+                let port = self.remote_port.or(self.local_port).ok_or_else(|| {
+                    format!("Reverse SOCKS5 session {} needs a remote_port", self.target)
+                })?;
+                (0, Some(port))
+            }
+            _ => {
+                let local_port = self
+                    .local_port
+                    .ok_or_else(|| format!("Session {} needs a local_port", self.target))?;
+                let remote_port = match self.session_type {
+                    SessionType::Socks5 => None,
+                    _ => Some(self.remote_port.unwrap_or(local_port)),
+                };
+                (local_port, remote_port)
+            }
+        };
+        let mut session = Session::new(
+            String::new(),
+            self.session_type,
+            self.target,
+            local_port,
+            remote_port,
+        );
+        session.name = self
+            .name
+            .unwrap_or_else(|| default_name(&session.target, session.listen_port()));
+        if session.session_type == SessionType::SSH {
+            session.remote_host = self.remote_host;
+        }
+        session.address_family = self.address_family;
+        session.additional_options = self.additional_options;
+        session.kube_context = self.kube_context;
+        session.kube_namespace = self.kube_namespace;
+        session.tags = self.tags;
+        session.health_command = self.health_command;
+        session.pre_start = self.pre_start;
+        session.post_stop = self.post_stop;
+        session.login_prompts = self.login_prompts;
+        session.user = self.user;
+        session.identity_file = self.identity_file;
+        session.kubeconfig_path = self.kubeconfig_path;
+        session.idle_timeout_minutes = match self.idle_timeout_minutes {
+            Some(minutes) if minutes > MAX_IDLE_TIMEOUT_MINUTES => {
+                return Err(format!(
+                    "Session {} has an idle stop over {} minutes",
+                    session.name, MAX_IDLE_TIMEOUT_MINUTES
+                ));
+            }
+            minutes => minutes,
+        };
+        session.max_lifetime = self.max_lifetime;
+        session.auto_restart = self.auto_restart;
+        Ok(session)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum SessionSpecs {
+    Many(Vec<SessionSpec>),
+    /// `pfman export` document
+    Export {
+        sessions: Vec<SessionSpec>,
+    },
+    One(Box<SessionSpec>),
+}
+
+fn default_name(target: &str, local_port: u16) -> String {
+    format!("{}:{}", target, local_port)
+}
+
+/// Parse pasted text into new sessions. Accepts a YAML/JSON list (or single
+/// object) of sessions, a `pfman export` document, or plain
+/// `host local:remote` / `host port` lines.
+pub fn parse_sessions(text: &str) -> Result<Vec<Session>, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err("Nothing to import".to_string());
+    }
+
+    if let Ok(specs) = serde_yaml::from_str::<SessionSpecs>(text) {
+        let specs = match specs {
+            SessionSpecs::Many(specs) | SessionSpecs::Export { sessions: specs } => specs,
+            SessionSpecs::One(spec) => vec![*spec],
+        };
+        return specs.into_iter().map(SessionSpec::into_session).collect();
+    }
+
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(parse_line)
+        .collect()
+}
+
+fn parse_line(line: &str) -> Result<Session, String> {
+    let parts: Vec<&str> = line.split_whitespace().collect();
+    let [host, ports] = parts[..] else {
+        return Err(format!("Expected `host local:remote`, got: {}", line));
+    };
+
+    let (local, remote) = ports.split_once(':').unwrap_or((ports, ports));
+    let local_port = local
+        .parse::<u16>()
+        .map_err(|_| format!("Invalid local port in: {}", line))?;
+    let remote_port = remote
+        .parse::<u16>()
+        .map_err(|_| format!("Invalid remote port in: {}", line))?;
+
+    Ok(Session::new(
+        default_name(host, local_port),
+        SessionType::SSH,
+        host.to_string(),
+        local_port,
+        Some(remote_port),
+    ))
+}
+
+/// SSH flags that take a value, per ssh(1)
+const SSH_FLAGS_WITH_VALUE: &str = "BbcDEeFIiJLlmOoPpQRSWw";
+
+/// Scan bash, zsh and fish history for `kubectl port-forward` and
+/// `ssh -L/-D` invocations, without duplicates
+pub fn scan_shell_history() -> Result<Vec<Session>, String> {
+    let home = PathBuf::from(std::env::var("HOME").unwrap_or_else(|_| ".".to_string()));
+    let files = [
+        home.join(".bash_history"),
+        home.join(".zsh_history"),
+        home.join(".local/share/fish/fish_history"),
+    ];
+
+    let mut seen = HashSet::new();
+    let mut sessions = Vec::new();
+    for file in files {
+        let Ok(bytes) = std::fs::read(&file) else {
+            continue;
+        };
+        // zsh history isn't always valid UTF-8
+        let text = String::from_utf8_lossy(&bytes);
+        for line in text.lines() {
+            for session in parse_history_line(line) {
+                if seen.insert(session_key(&session)) {
+                    sessions.push(session);
+                }
+            }
+        }
+    }
+
+    if sessions.is_empty() {
+        return Err("No port-forward commands found in shell history".to_string());
+    }
+    Ok(sessions)
+}
+
+/// Rename sessions whose names are already taken, by `existing` or by an
+/// earlier session in the batch, so names stay unique (e.g. for
+/// `pfman --start` and `pfman apply`). kubectl sessions get their context
+/// appended first, everything else an incrementing number.
+/// Returns the `(old, new)` pairs.
+pub fn resolve_name_conflicts(
+    sessions: &mut [Session],
+    existing: &[Session],
+) -> Vec<(String, String)> {
+    let mut taken: Vec<String> = existing.iter().map(|s| s.name.clone()).collect();
+    let mut renames = Vec::new();
+
+    for session in sessions.iter_mut() {
+        if taken.contains(&session.name) {
+            let with_context = session
+                .kube_context
+                .as_ref()
+                .map(|ctx| format!("{}-{}", session.name, ctx))
+                .filter(|name| !taken.contains(name));
+            let new_name = with_context.unwrap_or_else(|| {
+                (2..)
+                    .map(|n| format!("{}-{}", session.name, n))
+                    .find(|name| !taken.contains(name))
+                    .unwrap_or_default()
+            });
+            renames.push((session.name.clone(), new_name.clone()));
+            session.name = new_name;
+        }
+        taken.push(session.name.clone());
+    }
+    renames
+}
+
+/// Identity used to de-duplicate imported sessions against each other and
+/// against existing ones
+pub fn session_key(session: &Session) -> String {
+    format!(
+        "{}|{}|{}|{:?}|{:?}|{:?}",
+        session.session_type.as_str(),
+        session.target,
+        session.local_port,
+        session.remote_port,
+        session.kube_context,
+        session.kube_namespace
+    )
+}
+
+fn parse_history_line(line: &str) -> Vec<Session> {
+    // zsh extended history: ": 1700000000:0;command", fish: "- cmd: command"
+    let command = match line
+        .strip_prefix(": ")
+        .and_then(|rest| rest.split_once(';'))
+    {
+        Some((_, cmd)) => cmd,
+        None => line.strip_prefix("- cmd: ").unwrap_or(line),
+    };
+    let tokens: Vec<String> = command
+        .split_whitespace()
+        .map(|t| t.trim_matches(|c| c == '\'' || c == '"').to_string())
+        .collect();
+
+    match tokens.first().map(String::as_str) {
+        Some("kubectl") => parse_kubectl(&tokens[1..]).into_iter().collect(),
+        Some("ssh") => parse_ssh(&tokens[1..]),
+        _ => Vec::new(),
+    }
+}
+
+fn parse_kubectl(args: &[String]) -> Option<Session> {
+    let mut context = None;
+    let mut namespace = None;
+    let mut positional = Vec::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--context" => context = iter.next().cloned(),
+            "-n" | "--namespace" => namespace = iter.next().cloned(),
+            _ if arg.starts_with("--context=") => context = Some(arg[10..].to_string()),
+            _ if arg.starts_with("--namespace=") => namespace = Some(arg[12..].to_string()),
+            _ if arg.starts_with('-') => {}
+            _ => positional.push(arg.as_str()),
+        }
+    }
+
+    let ["port-forward", target, ports, ..] = positional[..] else {
+        return None;
+    };
+    let (local, remote) = ports.split_once(':').unwrap_or((ports, ports));
+    let local_port = local.parse::<u16>().ok()?;
+    let remote_port = remote.parse::<u16>().ok()?;
+
+    let mut session = Session::new(
+        default_name(target, local_port),
+        SessionType::Kubectl,
+        target.to_string(),
+        local_port,
+        Some(remote_port),
+    );
+    session.kube_context = context;
+    session.kube_namespace = namespace;
+    Some(session)
+}
+
+fn parse_ssh(args: &[String]) -> Vec<Session> {
+    let mut forwards = Vec::new();
+    let mut socks = Vec::new();
+    let mut reverse_socks = Vec::new();
+    let mut options = Vec::new();
+    let mut host = None;
+    let mut family = AddressFamily::Any;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if host.is_some() {
+            // Anything after the destination is a remote command
+            break;
+        }
+        let Some(flags) = arg.strip_prefix('-') else {
+            host = Some(arg.clone());
+            continue;
+        };
+        // Flags may be combined (-NL ...); the first one taking a value ends the group
+        for c in flags
+            .chars()
+            .take_while(|c| !SSH_FLAGS_WITH_VALUE.contains(*c))
+        {
+            match c {
+                '4' => family = AddressFamily::Ipv4,
+                '6' => family = AddressFamily::Ipv6,
+                _ => {}
+            }
+        }
+        let Some((i, flag)) = flags
+            .char_indices()
+            .find(|(_, c)| SSH_FLAGS_WITH_VALUE.contains(*c))
+        else {
+            continue;
+        };
+        // Value either attached (-L8080:...) or in the next argument
+        let attached = &flags[i + 1..];
+        let value = if attached.is_empty() {
+            iter.next().cloned()
+        } else {
+            Some(attached.to_string())
+        };
+        let Some(value) = value else {
+            break;
+        };
+        match flag {
+            'L' => forwards.push(value),
+            'D' => socks.push(value),
+            // [bind:]port alone is a dynamic forward; with a destination
+            // it's a plain reverse forward, kept as an option
+            'R' if split_forward_spec(&value).len() <= 2 => reverse_socks.push(value),
+            _ => {
+                options.push(format!("-{}", flag));
+                options.push(value);
+            }
+        }
+    }
+
+    let Some(host) = host else {
+        return Vec::new();
+    };
+
+    let mut sessions = Vec::new();
+    for spec in forwards {
+        // [bind:]local:host:remote
+        let parts = split_forward_spec(&spec);
+        let (bind, local, dest, remote) = match &parts[..] {
+            [local, dest, remote] => (None, local, dest, remote),
+            [bind, local, dest, remote] => (Some(bind), local, dest, remote),
+            _ => continue,
+        };
+        let (Ok(local_port), Ok(remote_port)) = (local.parse::<u16>(), remote.parse::<u16>())
+        else {
+            continue;
+        };
+        let mut session = Session::new(
+            default_name(&host, local_port),
+            SessionType::SSH,
+            host.clone(),
+            local_port,
+            Some(remote_port),
+        );
+        if !matches!(dest.as_str(), "localhost" | "127.0.0.1") {
+            session.remote_host = Some(dest.to_string());
+        }
+        session.address_family = bind.and_then(|b| bind_family(b)).unwrap_or(family);
+        session.additional_options = options.clone();
+        sessions.push(session);
+    }
+    for spec in socks {
+        let parts = split_forward_spec(&spec);
+        let (bind, port) = match &parts[..] {
+            [port] => (None, port),
+            [bind, port] => (Some(bind), port),
+            _ => continue,
+        };
+        let Ok(local_port) = port.parse::<u16>() else {
+            continue;
+        };
+        let mut session = Session::new(
+            default_name(&host, local_port),
+            SessionType::Socks5,
+            host.clone(),
+            local_port,
+            None,
+        );
+        session.address_family = bind.and_then(|b| bind_family(b)).unwrap_or(family);
+        session.additional_options = options.clone();
+        sessions.push(session);
+    }
+    for spec in reverse_socks {
+        let port = spec.rsplit(':').next().unwrap_or(&spec);
+        let Ok(remote_port) = port.parse::<u16>() else {
+            continue;
+        };
+        let mut session = Session::new(
+            default_name(&host, remote_port),
+            SessionType::ReverseSocks5,
+            host.clone(),
+            0,
+            Some(remote_port),
+        );
+        session.address_family = family;
+        session.additional_options = options.clone();
+        sessions.push(session);
+    }
+    sessions
+}
+
+/// Fields of a forward spec such as `[::1]:8080:[fd00::5]:5432`, split at
+/// the colons outside brackets, with the brackets dropped. ssh also takes
+/// `/` as the separator, which leaves IPv6 addresses unbracketed.
+fn split_forward_spec(spec: &str) -> Vec<String> {
+    if spec.contains('/') {
+        return spec.split('/').map(str::to_string).collect();
+    }
+    let mut parts = Vec::new();
+    let mut part = String::new();
+    let mut bracketed = false;
+    for c in spec.chars() {
+        match c {
+            '[' => bracketed = true,
+            ']' => bracketed = false,
+            ':' if !bracketed => parts.push(std::mem::take(&mut part)),
+            _ => part.push(c),
+        }
+    }
+    parts.push(part);
+    parts
+}
+
+/// The IP version a forward's bind address pins, e.g. IPv6 for `::1`
+fn bind_family(bind: &str) -> Option<AddressFamily> {
+    match bind.parse::<IpAddr>().ok()? {
+        IpAddr::V4(_) => Some(AddressFamily::Ipv4),
+        IpAddr::V6(_) => Some(AddressFamily::Ipv6),
+    }
+}
+
+/// Read the system clipboard using whichever helper is available
+pub fn read_clipboard() -> Option<String> {
+    let candidates: [(&str, &[&str]); 4] = [
+        ("pbpaste", &[]),
+        ("wl-paste", &["--no-newline"]),
+        ("xclip", &["-o", "-selection", "clipboard"]),
+        ("xsel", &["--clipboard", "--output"]),
+    ];
+
+    candidates.iter().find_map(|(cmd, args)| {
+        let output = Command::new(cmd).args(*args).output().ok()?;
+        if output.status.success() {
+            Some(String::from_utf8_lossy(&output.stdout).to_string())
+        } else {
+            None
+        }
+    })
+}
@@ -0,0 +1,165 @@
+//! One supervising pfman per profile. The instance that monitors and saves
+//! the sessions holds an exclusive lock on a file next to `sessions.yaml`
+//! and records its PID in another one beside it; another start finds it
+//! there and asks what to do instead of fighting over the same PIDs and
+//! file. The lock, not the
+//! PID, decides: of two pfmans started at once only one gets it, and it is
+//! let go when its holder exits, crashed or not.
+
+use crate::storage::Storage;
+use color_eyre::Result;
+use color_eyre::eyre::eyre;
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{BufRead, IsTerminal, Write};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, RefreshKind, System};
+
+/// How long a take-over waits for the previous instance to let go
+const TAKE_OVER_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// What to do when another instance already supervises the profile
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Choice {
+    /// Open `pfman watch`, which only reads the saved sessions
+    ReadOnly,
+    /// Open the watch view attached to the other instance, which starts
+    /// and stops sessions for it over its IPC socket
+    Attach,
+    /// Make the other instance quit and supervise from here
+    TakeOver,
+    Quit,
+}
+
+/// Held by the supervising instance; let go when dropped
+pub struct InstanceLock {
+    file: File,
+    pid_file: PathBuf,
+    pid: u32,
+}
+
+impl InstanceLock {
+    /// Become the supervisor. With `take_over`, the instance holding the
+    /// lock is asked to quit by writing this PID over its own: it notices
+    /// through [`is_held`], and this waits until it has let go, so its last
+    /// save can't overwrite what this instance loads. Without, another
+    /// holder is an error.
+    ///
+    /// [`is_held`]: InstanceLock::is_held
+    pub fn acquire(storage: &Storage, take_over: bool) -> Result<Self> {
+        let pid_file = storage.pid_file();
+        let pid = std::process::id();
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(storage.lock_file())?;
+
+        let deadline = Instant::now() + TAKE_OVER_TIMEOUT;
+        let mut asked = false;
+        loop {
+            match file.try_lock() {
+                Ok(()) => break,
+                Err(TryLockError::WouldBlock) if take_over && Instant::now() < deadline => {
+                    if !asked {
+                        fs::write(&pid_file, pid.to_string())?;
+                        asked = true;
+                    }
+                    std::thread::sleep(Duration::from_millis(200));
+                }
+                Err(TryLockError::WouldBlock) => {
+                    let holder = read_pid(&pid_file)
+                        .map(|pid| format!(" (PID {})", pid))
+                        .unwrap_or_default();
+                    return Err(eyre!(
+                        "another pfman{} is already managing these sessions",
+                        holder
+                    ));
+                }
+                Err(TryLockError::Error(err)) => return Err(err.into()),
+            }
+        }
+        fs::write(&pid_file, pid.to_string())?;
+        Ok(Self {
+            file,
+            pid_file,
+            pid,
+        })
+    }
+
+    /// False once another instance took over supervision
+    pub fn is_held(&self) -> bool {
+        read_pid(&self.pid_file) == Some(self.pid)
+    }
+}
+
+impl Drop for InstanceLock {
+    fn drop(&mut self) {
+        // Left to the instance that took over otherwise
+        if self.is_held() {
+            let _ = fs::remove_file(&self.pid_file);
+        }
+        let _ = self.file.unlock();
+    }
+}
+
+/// PID of another live pfman supervising the profile, if any. A PID left
+/// behind by a crash, or that now belongs to another program, is ignored.
+pub fn running_instance(storage: &Storage) -> Option<u32> {
+    let pid = read_pid(&storage.pid_file())?;
+    (pid != std::process::id() && is_pfman(pid)).then_some(pid)
+}
+
+/// Ask on the terminal what to do about the instance running as `pid`.
+/// Attaching is offered when it serves an IPC socket.
+pub fn ask(pid: u32, can_attach: bool) -> Result<Choice> {
+    if !std::io::stdin().is_terminal() {
+        return Err(eyre!(
+            "another pfman (PID {}) is already managing these sessions",
+            pid
+        ));
+    }
+
+    println!(
+        "Another pfman (PID {}) is already managing these sessions.",
+        pid
+    );
+    loop {
+        if can_attach {
+            print!("[w]atch read-only, [a]ttach, [t]ake over, [q]uit? ");
+        } else {
+            print!("[w]atch read-only, [t]ake over, [q]uit? ");
+        }
+        std::io::stdout().flush()?;
+        let mut answer = String::new();
+        if std::io::stdin().lock().read_line(&mut answer)? == 0 {
+            return Ok(Choice::Quit);
+        }
+        match answer.trim().to_lowercase().as_str() {
+            "w" | "watch" => return Ok(Choice::ReadOnly),
+            "a" | "attach" if can_attach => return Ok(Choice::Attach),
+            "t" | "take over" => return Ok(Choice::TakeOver),
+            "q" | "quit" | "" => return Ok(Choice::Quit),
+            _ => {}
+        }
+    }
+}
+
+fn read_pid(path: &PathBuf) -> Option<u32> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// Whether `pid` is running the same program as this process
+fn is_pfman(pid: u32) -> bool {
+    let Ok(own_pid) = sysinfo::get_current_pid() else {
+        return false;
+    };
+    let pid = Pid::from_u32(pid);
+    let mut sys =
+        System::new_with_specifics(RefreshKind::new().with_processes(ProcessRefreshKind::new()));
+    sys.refresh_processes(ProcessesToUpdate::Some(&[own_pid, pid]), true);
+    match (sys.process(own_pid), sys.process(pid)) {
+        (Some(own), Some(other)) => own.name() == other.name(),
+        _ => false,
+    }
+}
@@ -0,0 +1,270 @@
+//! Unix socket IPC for tools that react to sessions changing. Every client
+//! gets a stream of JSON lines: an event whenever a session's status
+//! changes, so a forward dying is noticed without polling `sessions.yaml`,
+//! and a reply to each command it writes, one JSON object per line:
+//!
+//! - `{"command": "list"}`
+//! - `{"command": "start", "session": "<id|name>"}`, same for `stop`
+//! - `{"command": "create", "sessions": [...]}` with sessions in the import
+//!   format
+//! - `{"command": "pause"}` and `{"command": "resume"}`, see [`crate::pause`]
+//!
+//! Commands are carried out by the UI loop like those of [`crate::api`].
+
+use crate::api::{self, ApiCommand, ApiResponse};
+use crate::models::{Session, SessionStatus, StatusTracker};
+use crate::ui::AppState;
+use chrono::Utc;
+use serde::Deserialize;
+use serde_json::json;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+
+/// How long a client waits for the UI loop to answer a command
+#[cfg(unix)]
+const REPLY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// One line written by a client
+#[derive(Debug, Deserialize)]
+#[serde(tag = "command", rename_all = "lowercase")]
+enum IpcCommand {
+    List,
+    Start { session: String },
+    Stop { session: String },
+    Create { sessions: serde_json::Value },
+    Pause,
+    Resume,
+}
+
+impl From<IpcCommand> for ApiCommand {
+    fn from(command: IpcCommand) -> Self {
+        match command {
+            IpcCommand::List => ApiCommand::List,
+            IpcCommand::Start { session } => ApiCommand::Start(session),
+            IpcCommand::Stop { session } => ApiCommand::Stop(session),
+            IpcCommand::Create { sessions } => ApiCommand::Create(sessions.to_string()),
+            IpcCommand::Pause => ApiCommand::Pause,
+            IpcCommand::Resume => ApiCommand::Resume,
+        }
+    }
+}
+
+struct IpcRequest {
+    command: ApiCommand,
+    reply: Sender<ApiResponse>,
+}
+
+/// Accepts clients in the background, queues their commands for
+/// [`IpcServer::serve`] and sends them the events of [`IpcServer::publish`]
+pub struct IpcServer {
+    requests: Receiver<IpcRequest>,
+    /// Line writers of the connected clients
+    clients: Arc<Mutex<Vec<Sender<String>>>>,
+    tracker: StatusTracker,
+    socket: PathBuf,
+}
+
+impl IpcServer {
+    /// Listen on the unix socket at `path`, replacing a stale one
+    #[cfg(unix)]
+    pub fn spawn(path: &str) -> Result<Self, String> {
+        use std::os::unix::fs::PermissionsExt;
+        use std::os::unix::net::UnixListener;
+
+        let socket = crate::kube_config::expand_home(path);
+        // Left behind by an instance that didn't shut down cleanly
+        let _ = std::fs::remove_file(&socket);
+        let listener =
+            UnixListener::bind(&socket).map_err(|e| format!("{}: {}", socket.display(), e))?;
+        let _ = std::fs::set_permissions(&socket, std::fs::Permissions::from_mode(0o600));
+
+        let (tx, requests) = mpsc::channel();
+        let clients = Arc::new(Mutex::new(Vec::new()));
+        let shared = Arc::clone(&clients);
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                spawn_client(stream, tx.clone(), &shared);
+            }
+        });
+        Ok(Self {
+            requests,
+            clients,
+            tracker: StatusTracker::default(),
+            socket,
+        })
+    }
+
+    #[cfg(not(unix))]
+    pub fn spawn(_path: &str) -> Result<Self, String> {
+        Err("unix sockets are not supported here".to_string())
+    }
+
+    /// Answer the queued commands. Returns whether sessions were changed,
+    /// so the caller saves them.
+    pub fn serve(&self, state: &mut AppState) -> bool {
+        let mut changed = false;
+        while let Ok(request) = self.requests.try_recv() {
+            let (response, modified) = api::handle(state, request.command);
+            changed |= modified;
+            let _ = request.reply.send(response);
+        }
+        changed
+    }
+
+    /// Send an event to every client for each session whose status changed
+    /// since the last call
+    pub fn publish(&mut self, sessions: &[Session]) {
+        let events: Vec<String> = self
+            .tracker
+            .changes(sessions)
+            .into_iter()
+            .map(|(session, previous)| event_line(session, &previous))
+            .collect();
+        if events.is_empty() {
+            return;
+        }
+        let mut clients = self.clients.lock().unwrap();
+        clients.retain(|client| events.iter().all(|line| client.send(line.clone()).is_ok()));
+    }
+}
+
+impl Drop for IpcServer {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.socket);
+    }
+}
+
+/// `started`, `stopped` and `crashed` for the statuses tools usually wait
+/// for, `status_changed` for the rest (e.g. going back to Starting,
+/// Degraded or Stopping)
+fn event_name(status: &SessionStatus) -> &'static str {
+    match status {
+        SessionStatus::Running => "started",
+        SessionStatus::Stopped => "stopped",
+        SessionStatus::Error(_) => "crashed",
+        SessionStatus::Starting | SessionStatus::Degraded(_) | SessionStatus::Stopping => {
+            "status_changed"
+        }
+    }
+}
+
+/// Send `command` to the instance listening on the socket at `path` and
+/// return the body of its reply, or the error it answered with
+#[cfg(unix)]
+pub fn request(path: &str, command: serde_json::Value) -> Result<serde_json::Value, String> {
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::net::UnixStream;
+
+    let socket = crate::kube_config::expand_home(path);
+    let mut stream =
+        UnixStream::connect(&socket).map_err(|e| format!("{}: {}", socket.display(), e))?;
+    let _ = stream.set_read_timeout(Some(REPLY_TIMEOUT * 2));
+    writeln!(stream, "{}", command).map_err(|e| e.to_string())?;
+
+    // Events may arrive before the reply
+    for line in BufReader::new(stream).lines() {
+        let line = line.map_err(|e| e.to_string())?;
+        let Ok(mut reply) = serde_json::from_str::<serde_json::Value>(&line) else {
+            continue;
+        };
+        if reply["type"] != "reply" {
+            continue;
+        }
+        if reply["status"].as_u64().is_some_and(|status| status >= 400) {
+            return Err(reply["body"]["error"]
+                .as_str()
+                .unwrap_or("request failed")
+                .to_string());
+        }
+        return Ok(reply["body"].take());
+    }
+    Err("pfman closed the connection without answering".to_string())
+}
+
+#[cfg(not(unix))]
+pub fn request(_path: &str, _command: serde_json::Value) -> Result<serde_json::Value, String> {
+    Err("unix sockets are not supported here".to_string())
+}
+
+fn event_line(session: &Session, previous: &SessionStatus) -> String {
+    let error = match &session.status {
+        SessionStatus::Error(err) => Some(err.message.clone()),
+        SessionStatus::Degraded(reason) => Some(reason.clone()),
+        _ => None,
+    };
+    json!({
+        "type": "event",
+        "event": event_name(&session.status),
+        "timestamp": Utc::now().to_rfc3339(),
+        "id": session.id,
+        "short_id": session.short_id(),
+        "name": session.name,
+        "status": session.status.as_str(),
+        "previous": previous.as_str(),
+        "error": error,
+    })
+    .to_string()
+}
+
+fn reply_line(response: &ApiResponse) -> String {
+    json!({
+        "type": "reply",
+        "status": response.status,
+        "body": response.body,
+    })
+    .to_string()
+}
+
+/// Register the client for events, write its lines from one thread and
+/// read its commands on another
+#[cfg(unix)]
+fn spawn_client(
+    stream: std::os::unix::net::UnixStream,
+    requests: Sender<IpcRequest>,
+    clients: &Mutex<Vec<Sender<String>>>,
+) {
+    use std::io::{BufRead, BufReader, Write};
+
+    let Ok(mut writer) = stream.try_clone() else {
+        return;
+    };
+    let (lines, outgoing) = mpsc::channel::<String>();
+    clients.lock().unwrap().push(lines.clone());
+
+    // Ends once the client is gone and its sender has been pruned
+    std::thread::spawn(move || {
+        for line in outgoing {
+            if writeln!(writer, "{}", line).is_err() {
+                break;
+            }
+        }
+        let _ = writer.shutdown(std::net::Shutdown::Both);
+    });
+
+    std::thread::spawn(move || {
+        for line in BufReader::new(stream).lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            let response = match serde_json::from_str::<IpcCommand>(&line) {
+                Ok(command) => {
+                    let (reply, answer) = mpsc::channel();
+                    let _ = requests.send(IpcRequest {
+                        command: command.into(),
+                        reply,
+                    });
+                    answer
+                        .recv_timeout(REPLY_TIMEOUT)
+                        .unwrap_or_else(|_| ApiResponse::error(503, "pfman is not answering"))
+                }
+                Err(err) => ApiResponse::error(400, format!("invalid command: {}", err)),
+            };
+            // Commands sent before the client hung up are still carried out
+            let _ = lines.send(reply_line(&response));
+        }
+    });
+}
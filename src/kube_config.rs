@@ -1,7 +1,14 @@
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
 use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How long looked-up namespaces and targets are reused before kubectl is
+/// asked again
+pub const KUBE_CACHE_TTL: Duration = Duration::from_secs(60);
 
 #[derive(Debug, Clone)]
 pub struct KubeContext {
@@ -22,7 +29,7 @@ impl KubeContext {
 
 #[derive(Debug, Clone)]
 pub struct KubeTarget {
-    pub kind: String, // "pod" or "service"
+    pub kind: String, // "pods", "services", "deployments", "statefulsets" or "replicasets"
     pub name: String,
     pub namespace: String,
     pub ports: Vec<u16>,
@@ -48,6 +55,85 @@ impl KubeTarget {
     pub fn target_string(&self) -> String {
         format!("{}/{}", self.kind, self.name)
     }
+
+    /// Whether a port-forward target such as `svc/api` refers to this one
+    pub fn matches(&self, target: &str) -> bool {
+        target.split_once('/').is_some_and(|(kind, name)| {
+            name == self.name && canonical_kind(kind) == canonical_kind(&self.kind)
+        })
+    }
+}
+
+/// Plural resource name for the short and singular forms kubectl accepts
+fn canonical_kind(kind: &str) -> &str {
+    match kind {
+        "po" | "pod" | "pods" => "pods",
+        "svc" | "service" | "services" => "services",
+        "deploy" | "deployment" | "deployments" => "deployments",
+        "sts" | "statefulset" | "statefulsets" => "statefulsets",
+        "rs" | "replicaset" | "replicasets" => "replicasets",
+        other => other,
+    }
+}
+
+/// kubeconfig file, context and namespace a lookup was made for; None
+/// means kubectl's default
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct KubeScope {
+    pub kubeconfig: Option<String>,
+    pub context: Option<String>,
+    pub namespace: Option<String>,
+}
+
+/// Namespaces and targets looked up recently. Clones share the entries, so
+/// session forms opened one after another don't query the cluster again.
+#[derive(Clone, Default)]
+pub struct KubeCache {
+    entries: Arc<Mutex<KubeCacheEntries>>,
+}
+
+#[derive(Default)]
+struct KubeCacheEntries {
+    namespaces: HashMap<KubeScope, (Instant, Vec<String>)>,
+    targets: HashMap<KubeScope, (Instant, Vec<KubeTarget>)>,
+}
+
+impl KubeCache {
+    /// Namespaces of the scope's cluster, unless they are older than the TTL
+    pub fn namespaces(&self, scope: &KubeScope) -> Option<Vec<String>> {
+        let entries = self.entries.lock().unwrap();
+        let (at, namespaces) = entries.namespaces.get(scope)?;
+        (at.elapsed() < KUBE_CACHE_TTL).then(|| namespaces.clone())
+    }
+
+    pub fn set_namespaces(&self, scope: KubeScope, namespaces: Vec<String>) {
+        let mut entries = self.entries.lock().unwrap();
+        entries
+            .namespaces
+            .insert(scope, (Instant::now(), namespaces));
+    }
+
+    /// Targets in the scope, unless they are older than the TTL
+    pub fn targets(&self, scope: &KubeScope) -> Option<Vec<KubeTarget>> {
+        let entries = self.entries.lock().unwrap();
+        let (at, targets) = entries.targets.get(scope)?;
+        (at.elapsed() < KUBE_CACHE_TTL).then(|| targets.clone())
+    }
+
+    pub fn set_targets(&self, scope: KubeScope, targets: Vec<KubeTarget>) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.targets.insert(scope, (Instant::now(), targets));
+    }
+
+    /// Forget everything looked up in the scope's cluster, whatever the
+    /// namespace
+    pub fn invalidate(&self, scope: &KubeScope) {
+        let same_cluster =
+            |s: &KubeScope| s.kubeconfig == scope.kubeconfig && s.context == scope.context;
+        let mut entries = self.entries.lock().unwrap();
+        entries.namespaces.retain(|s, _| !same_cluster(s));
+        entries.targets.retain(|s, _| !same_cluster(s));
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -102,6 +188,37 @@ struct ContainerPort {
     container_port: u16,
 }
 
+/// Deployment, statefulset or replicaset; port-forwarding picks one of
+/// their pods, whose ports come from the pod template
+#[derive(Debug, Deserialize)]
+struct WorkloadList {
+    items: Vec<Workload>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Workload {
+    metadata: WorkloadMetadata,
+    spec: WorkloadSpec,
+}
+
+#[derive(Debug, Deserialize)]
+struct WorkloadMetadata {
+    name: String,
+    namespace: String,
+    #[serde(rename = "ownerReferences", default)]
+    owner_references: Vec<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WorkloadSpec {
+    template: PodTemplate,
+}
+
+#[derive(Debug, Deserialize)]
+struct PodTemplate {
+    spec: PodSpec,
+}
+
 #[derive(Debug, Deserialize)]
 struct ServiceList {
     items: Vec<Service>,
@@ -129,40 +246,80 @@ struct ServicePort {
     port: u16,
 }
 
-pub fn get_kubeconfig_path() -> PathBuf {
-    if let Ok(kubeconfig) = std::env::var("KUBECONFIG") {
-        PathBuf::from(kubeconfig)
-    } else {
-        let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
-        PathBuf::from(home).join(".kube").join("config")
+/// Files kubectl reads its configuration from: the `KUBECONFIG` list, or
+/// `~/.kube/config` when it isn't set
+pub fn get_kubeconfig_paths() -> Vec<PathBuf> {
+    match std::env::var_os("KUBECONFIG") {
+        Some(list) if !list.is_empty() => std::env::split_paths(&list)
+            .filter(|p| !p.as_os_str().is_empty())
+            .collect(),
+        _ => vec![expand_home("~/.kube/config")],
     }
 }
 
-pub fn parse_kube_config() -> Option<(String, Vec<KubeContext>)> {
-    let config_path = get_kubeconfig_path();
-    if !config_path.exists() {
-        return None;
+/// `path` with a leading `~/` replaced by the home directory, which kubectl
+/// doesn't do itself
+pub fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), std::env::var_os("HOME")) {
+        (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => PathBuf::from(path),
     }
+}
 
-    let content = fs::read_to_string(&config_path).ok()?;
-    let config: KubeConfig = serde_yaml::from_str(&content).ok()?;
+/// Current context and contexts of `kubeconfig`, or of the default files
+pub fn parse_kube_config(kubeconfig: Option<&str>) -> Option<(String, Vec<KubeContext>)> {
+    let paths = match kubeconfig {
+        Some(path) => vec![expand_home(path)],
+        None => get_kubeconfig_paths(),
+    };
+    parse_kube_config_files(&paths)
+}
 
-    let current_context = config.current_context?;
-    let contexts = config
-        .contexts?
-        .into_iter()
-        .map(|ctx| KubeContext {
-            name: ctx.name,
-            cluster: ctx.context.cluster,
-            namespace: ctx.context.namespace,
-        })
-        .collect();
+/// Merge the files the way kubectl does: the first file to set the current
+/// context or to define a context name wins. Missing files are skipped.
+fn parse_kube_config_files(paths: &[PathBuf]) -> Option<(String, Vec<KubeContext>)> {
+    let mut current_context = None;
+    let mut contexts: Vec<KubeContext> = Vec::new();
+
+    for path in paths {
+        let Some(config) = fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_yaml::from_str::<KubeConfig>(&content).ok())
+        else {
+            continue;
+        };
+        if current_context.is_none() {
+            current_context = config.current_context.filter(|c| !c.is_empty());
+        }
+        for ctx in config.contexts.unwrap_or_default() {
+            if contexts.iter().any(|c| c.name == ctx.name) {
+                continue;
+            }
+            contexts.push(KubeContext {
+                name: ctx.name,
+                cluster: ctx.context.cluster,
+                namespace: ctx.context.namespace,
+            });
+        }
+    }
 
-    Some((current_context, contexts))
+    Some((current_context?, contexts))
 }
 
-pub fn get_current_context() -> Option<String> {
-    let output = Command::new("kubectl")
+/// `kubectl` talking to `context` of `kubeconfig`, each None for the default
+fn kubectl(kubeconfig: Option<&str>, context: Option<&str>) -> Command {
+    let mut cmd = Command::new("kubectl");
+    if let Some(path) = kubeconfig {
+        cmd.arg("--kubeconfig").arg(expand_home(path));
+    }
+    if let Some(ctx) = context {
+        cmd.args(["--context", ctx]);
+    }
+    cmd
+}
+
+pub fn get_current_context(kubeconfig: Option<&str>) -> Option<String> {
+    let output = kubectl(kubeconfig, None)
         .args(["config", "current-context"])
         .output()
         .ok()?;
@@ -176,14 +333,14 @@ pub fn get_current_context() -> Option<String> {
     None
 }
 
-pub fn get_pods_with_ports(context: Option<&str>, namespace: Option<&str>) -> Vec<KubeTarget> {
-    let mut cmd = Command::new("kubectl");
+pub fn get_pods_with_ports(
+    kubeconfig: Option<&str>,
+    context: Option<&str>,
+    namespace: Option<&str>,
+) -> Vec<KubeTarget> {
+    let mut cmd = kubectl(kubeconfig, context);
     cmd.args(["get", "pods", "--all-namespaces", "-o", "json"]);
 
-    if let Some(ctx) = context {
-        cmd.args(["--context", ctx]);
-    }
-
     let output = match cmd.output() {
         Ok(out) if out.status.success() => out,
         _ => return Vec::new(),
@@ -211,10 +368,10 @@ pub fn get_pods_with_ports(context: Option<&str>, namespace: Option<&str>) -> Ve
                 return None;
             }
 
-            if let Some(ns) = namespace {
-                if pod.metadata.namespace != ns {
-                    return None;
-                }
+            if let Some(ns) = namespace
+                && pod.metadata.namespace != ns
+            {
+                return None;
             }
 
             Some(KubeTarget {
@@ -227,14 +384,14 @@ pub fn get_pods_with_ports(context: Option<&str>, namespace: Option<&str>) -> Ve
         .collect()
 }
 
-pub fn get_services_with_ports(context: Option<&str>, namespace: Option<&str>) -> Vec<KubeTarget> {
-    let mut cmd = Command::new("kubectl");
+pub fn get_services_with_ports(
+    kubeconfig: Option<&str>,
+    context: Option<&str>,
+    namespace: Option<&str>,
+) -> Vec<KubeTarget> {
+    let mut cmd = kubectl(kubeconfig, context);
     cmd.args(["get", "services", "--all-namespaces", "-o", "json"]);
 
-    if let Some(ctx) = context {
-        cmd.args(["--context", ctx]);
-    }
-
     let output = match cmd.output() {
         Ok(out) if out.status.success() => out,
         _ => return Vec::new(),
@@ -255,10 +412,10 @@ pub fn get_services_with_ports(context: Option<&str>, namespace: Option<&str>) -
                 return None;
             }
 
-            if let Some(ns) = namespace {
-                if svc.metadata.namespace != ns {
-                    return None;
-                }
+            if let Some(ns) = namespace
+                && svc.metadata.namespace != ns
+            {
+                return None;
             }
 
             Some(KubeTarget {
@@ -271,10 +428,77 @@ pub fn get_services_with_ports(context: Option<&str>, namespace: Option<&str>) -
         .collect()
 }
 
-pub fn get_targets(context: Option<&str>, namespace: Option<&str>) -> Vec<KubeTarget> {
+/// Workloads of `kind` (deployments, statefulsets or replicasets) whose pod
+/// template declares ports. Replicasets managed by a deployment are left
+/// out: the deployment is listed already.
+pub fn get_workloads_with_ports(
+    kind: &str,
+    kubeconfig: Option<&str>,
+    context: Option<&str>,
+    namespace: Option<&str>,
+) -> Vec<KubeTarget> {
+    let mut cmd = kubectl(kubeconfig, context);
+    cmd.args(["get", kind, "--all-namespaces", "-o", "json"]);
+
+    let output = match cmd.output() {
+        Ok(out) if out.status.success() => out,
+        _ => return Vec::new(),
+    };
+
+    let workload_list: WorkloadList = match serde_json::from_slice(&output.stdout) {
+        Ok(list) => list,
+        Err(_) => return Vec::new(),
+    };
+
+    workload_list
+        .items
+        .into_iter()
+        .filter(|w| w.metadata.owner_references.is_empty())
+        .filter_map(|workload| {
+            let ports: Vec<u16> = workload
+                .spec
+                .template
+                .spec
+                .containers
+                .iter()
+                .filter_map(|c| c.ports.as_ref())
+                .flatten()
+                .map(|p| p.container_port)
+                .collect();
+
+            if ports.is_empty() {
+                return None;
+            }
+
+            if let Some(ns) = namespace
+                && workload.metadata.namespace != ns
+            {
+                return None;
+            }
+
+            Some(KubeTarget {
+                kind: kind.to_string(),
+                name: workload.metadata.name,
+                namespace: workload.metadata.namespace,
+                ports,
+            })
+        })
+        .collect()
+}
+
+pub fn get_targets(
+    kubeconfig: Option<&str>,
+    context: Option<&str>,
+    namespace: Option<&str>,
+) -> Vec<KubeTarget> {
     let mut targets = Vec::new();
-    targets.extend(get_pods_with_ports(context, namespace));
-    targets.extend(get_services_with_ports(context, namespace));
+    targets.extend(get_pods_with_ports(kubeconfig, context, namespace));
+    targets.extend(get_services_with_ports(kubeconfig, context, namespace));
+    for kind in ["deployments", "statefulsets", "replicasets"] {
+        targets.extend(get_workloads_with_ports(
+            kind, kubeconfig, context, namespace,
+        ));
+    }
     targets
 }
 
@@ -295,8 +519,8 @@ pub fn filter_targets(targets: &[KubeTarget], query: &str) -> Vec<KubeTarget> {
         .collect()
 }
 
-pub fn get_namespaces(context: Option<&str>) -> Vec<String> {
-    let mut cmd = Command::new("kubectl");
+pub fn get_namespaces(kubeconfig: Option<&str>, context: Option<&str>) -> Vec<String> {
+    let mut cmd = kubectl(kubeconfig, context);
     cmd.args([
         "get",
         "namespaces",
@@ -304,10 +528,6 @@ pub fn get_namespaces(context: Option<&str>) -> Vec<String> {
         "jsonpath={.items[*].metadata.name}",
     ]);
 
-    if let Some(ctx) = context {
-        cmd.args(["--context", ctx]);
-    }
-
     let output = match cmd.output() {
         Ok(out) if out.status.success() => out,
         _ => return Vec::new(),
@@ -0,0 +1,54 @@
+//! pfman - Port-Forwarding Manager
+//!
+//! The binary is a thin TUI shell over these modules. Other ratatui apps,
+//! scripts and integration tests can load, add, start and stop sessions
+//! through [`embed`], or use the building blocks directly: [`models`] for
+//! sessions, [`storage`] for where they are kept, [`process`] for running
+//! and monitoring them, and [`ssh_config`]/[`kube_config`] for the
+//! suggestions the session form offers. Sessions run as children of the
+//! calling process, with their output copied into the logs by a thread in
+//! it, so a host app has nothing to set up in `main`.
+
+pub mod activity;
+pub mod api;
+pub mod apply;
+pub mod audit;
+pub mod bench;
+pub mod canary;
+pub mod capture;
+pub mod catalog;
+pub mod cli;
+pub mod config;
+pub mod crash;
+pub mod demo;
+pub mod embed;
+pub mod events;
+pub mod health;
+pub mod heartbeat;
+pub mod hooks;
+pub mod import;
+pub mod instance;
+pub mod ipc;
+pub mod kube_config;
+pub mod models;
+pub mod pause;
+pub mod policy;
+pub mod preflight;
+pub mod process;
+pub mod provider;
+pub mod pty;
+pub mod reconnect;
+pub mod restore;
+pub mod retry;
+pub mod runs;
+pub mod saver;
+pub mod scope;
+pub mod share;
+pub mod sink;
+pub mod ssh_config;
+pub mod storage;
+pub mod suggest;
+pub mod ui;
+pub mod usage;
+pub mod vars;
+pub mod watch;
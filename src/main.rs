@@ -1,28 +1,162 @@
-mod kube_config;
-mod models;
-mod process;
-mod ssh_config;
-mod storage;
-mod ui;
-
 use color_eyre::Result;
-use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
-use ratatui::{DefaultTerminal, Frame};
-use ui::session_form::{FormState, FormStep};
-use ui::{AppState, FormMode, Screen};
+use crossterm::event::{
+    self, DisableBracketedPaste, EnableBracketedPaste, Event, KeyCode, KeyEvent, KeyEventKind,
+    KeyModifiers,
+};
+use pfman::events::{AppEvent, EventSender, Events};
+use pfman::instance::{self, Choice, InstanceLock};
+use pfman::storage::{self, Storage};
+use pfman::ui::catalog::EntryState;
+use pfman::ui::import::ImportPreview;
+use pfman::ui::palette::{Palette, PaletteAction};
+use pfman::ui::session_form::{FormState, FormStep};
+use pfman::ui::{self, AppState, FormMode, Screen};
+use pfman::usage::SummaryPeriod;
+use pfman::{apply, catalog, cli, crash, demo, import, models, pause, restore, watch};
+use ratatui::layout::Rect;
+use ratatui::text::Line;
+use ratatui::widgets::Widget;
+use ratatui::{DefaultTerminal, Frame, TerminalOptions, Viewport};
+use std::io::IsTerminal;
+use std::time::{Duration, Instant};
+use tokio::time::MissedTickBehavior;
+
+/// How often restarts, idle stops, API and IPC requests and the other
+/// background work outside the event channel are handled; the screen is
+/// only redrawn this often when that work changed something
+const TICK_RATE: Duration = Duration::from_millis(250);
+/// Sessions' IDs, statuses and PIDs, and the session an expiry reminder
+/// is shown for
+type VisibleState = (
+    Vec<(uuid::Uuid, models::SessionStatus, Option<u32>)>,
+    Option<uuid::Uuid>,
+);
+/// Minimum time between two writes of the form draft
+const DRAFT_SAVE_INTERVAL: Duration = Duration::from_secs(2);
+/// Tag started by `--start-all-auto`
+const AUTO_START_TAG: &str = "auto";
+/// Columns the logs viewer moves per sideways scroll
+const LOG_HSCROLL_STEP: isize = 8;
+/// How often sessions of closed terminals are looked for (terminal scoping)
+const SCOPE_CHECK_INTERVAL: Duration = Duration::from_secs(5);
 
 fn main() -> color_eyre::Result<()> {
     color_eyre::install()?;
+
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    // Demo mode's simulated tunnels are this binary started again
+    if args.first().map(String::as_str) == Some(demo::LISTENER_FLAG) {
+        return demo::run_listener(&args[1..]);
+    }
+    if let Some(dir) = take_config_dir(&mut args) {
+        storage::set_config_dir(dir.into());
+    }
+    if let Some(result) = cli::dispatch(&args) {
+        return result;
+    }
+    cli::check_options(&args)?;
+    let demo_mode = args.iter().any(|a| a == "--demo" || a == "--simulate");
+    let storage = Storage::new()?;
+    let lock = if demo_mode {
+        None
+    } else {
+        let mut take_over = false;
+        if let Some(pid) = instance::running_instance(&storage) {
+            // Attaching talks to the other instance over its IPC socket
+            let socket = storage
+                .load_config()?
+                .ipc_socket
+                .filter(|path| pfman::kube_config::expand_home(path).exists());
+            match instance::ask(pid, socket.is_some())? {
+                Choice::ReadOnly => return watch::run(&[]),
+                Choice::Attach => return watch::attach(&socket.unwrap_or_default()),
+                Choice::Quit => return Ok(()),
+                Choice::TakeOver => take_over = true,
+            }
+        }
+        Some(InstanceLock::acquire(&storage, take_over)?)
+    };
+    let mut app = if demo_mode { App::demo()? } else { App::new()? };
+    app.instance = lock;
+    for selector in start_selectors(&args) {
+        app.state.start_matching(&selector)?;
+    }
+    if args.iter().any(|a| a == "--start-all-auto") {
+        // Having no `auto` sessions is fine here
+        let _ = app.state.start_matching(AUTO_START_TAG);
+    }
+
+    crash::install_panic_hook(&storage);
+    if args.iter().any(|a| a == "--inline") {
+        // No popups inline; the notice goes above the status lines instead
+        if let Some(log_path) = crash::take_crash_notice(&storage) {
+            eprintln!("pfman crashed last time. Details: {}", log_path.display());
+        }
+        let result = app.run_inline();
+        app.flush_saves();
+        result?;
+        app.report_take_over();
+        return Ok(());
+    }
+    app.state.crash_notice = crash::take_crash_notice(&storage);
+
     let terminal = ratatui::init();
-    let result = App::new()?.run(terminal);
+    // Bracketed paste lets the import screen receive pasted text in one event
+    let _ = crossterm::execute!(std::io::stdout(), EnableBracketedPaste);
+    let result = app.run(terminal);
+    app.flush_saves();
+    let _ = crossterm::execute!(std::io::stdout(), DisableBracketedPaste);
     ratatui::restore();
+    app.report_take_over();
     result
 }
 
+/// Removes `--config-dir <dir>` (or `--config-dir=<dir>`) from the arguments,
+/// so subcommands never see it, and returns the directory
+fn take_config_dir(args: &mut Vec<String>) -> Option<String> {
+    let index = args
+        .iter()
+        .position(|a| a == "--config-dir" || a.starts_with("--config-dir="))?;
+    let arg = args.remove(index);
+    match arg.strip_prefix("--config-dir=") {
+        Some(dir) => Some(dir.to_string()),
+        None if index < args.len() => Some(args.remove(index)),
+        None => None,
+    }
+}
+
+/// Sessions or tags to bring up on launch: every `--start <name|tag>`
+/// (or `--start=<name|tag>`)
+fn start_selectors(args: &[String]) -> Vec<String> {
+    let mut selectors = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--start" {
+            if let Some(selector) = args.next() {
+                selectors.push(selector.clone());
+            }
+        } else if let Some(selector) = arg.strip_prefix("--start=") {
+            selectors.push(selector.to_string());
+        }
+    }
+    selectors
+}
+
 pub struct App {
     running: bool,
     state: AppState,
     form_state: Option<FormState>,
+    last_draft: Option<models::FormDraft>,
+    last_draft_save: Instant,
+    last_scope_check: Option<Instant>,
+    /// For two-key sequences such as `gg`
+    last_key: Option<KeyCode>,
+    /// Terminal area at the last render, for paging
+    viewport: Rect,
+    /// Marks this as the instance supervising the sessions; `None` in demo mode
+    instance: Option<InstanceLock>,
+    /// Sends to the event loop while it runs, e.g. results of form lookups
+    events: Option<EventSender>,
 }
 
 impl App {
@@ -31,33 +165,253 @@ impl App {
             running: true,
             state: AppState::new()?,
             form_state: None,
+            last_draft: None,
+            last_draft_save: Instant::now(),
+            last_scope_check: None,
+            last_key: None,
+            viewport: Rect::default(),
+            instance: None,
+            events: None,
         })
     }
 
-    pub fn run(mut self, mut terminal: DefaultTerminal) -> Result<()> {
+    pub fn demo() -> Result<Self> {
+        Ok(Self {
+            running: true,
+            state: AppState::demo()?,
+            form_state: None,
+            last_draft: None,
+            last_draft_save: Instant::now(),
+            last_scope_check: None,
+            last_key: None,
+            viewport: Rect::default(),
+            instance: None,
+            events: None,
+        })
+    }
+
+    pub fn run(&mut self, mut terminal: DefaultTerminal) -> Result<()> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        runtime.block_on(self.run_events(&mut terminal))
+    }
+
+    /// Redraw whenever an event comes in, and every tick for spinners and
+    /// the periodic work
+    async fn run_events(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        let mut events = Events::new();
+        events.spawn_terminal_reader();
+        if let Some(updates) = self.state.process_manager.take_status_updates() {
+            events.forward_status_updates(updates);
+        }
+        self.events = Some(events.sender());
+        let mut ticks = tokio::time::interval(TICK_RATE);
+        ticks.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        // Redraws that only move spinners and uptimes along, which over a
+        // slow SSH connection can be spaced out in the config
+        let mut redraws = tokio::time::interval(self.state.config.tick_rate());
+        redraws.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+        let mut dirty = true;
+        while self.running {
+            if dirty {
+                terminal.draw(|frame| self.render(frame))?;
+                dirty = false;
+            }
+            tokio::select! {
+                _ = ticks.tick() => {}
+                _ = redraws.tick() => dirty = true,
+                Some(event) = events.next() => {
+                    // Take everything already waiting before drawing again
+                    let mut batch = vec![event];
+                    while let Some(event) = events.try_next() {
+                        batch.push(event);
+                    }
+                    self.on_app_events(batch);
+                    dirty = true;
+                }
+            }
+
+            let before = self.visible_state();
+            self.update_sessions();
+            self.check_expiry_reminders();
+            self.state.sync_selection();
+            self.autosave_draft();
+            dirty |= self.visible_state() != before;
+        }
+        self.events = None;
+        Ok(())
+    }
+
+    /// `--inline`: a live session list below the prompt, with status changes
+    /// printed above it into the scrollback. Without a terminal (e.g. CI
+    /// logs) only the status lines are printed.
+    pub fn run_inline(&mut self) -> Result<()> {
+        let mut statuses: Vec<_> = self
+            .state
+            .sessions
+            .iter()
+            .map(|s| s.status.clone())
+            .collect();
+
+        let terminal = if std::io::stdout().is_terminal() {
+            let options = TerminalOptions {
+                viewport: Viewport::Inline(ui::inline::height(&self.state.sessions)),
+            };
+            // Fails when the terminal doesn't report its cursor position
+            ratatui::try_init_with_options(options)
+                .inspect_err(|_| ratatui::restore())
+                .ok()
+        } else {
+            None
+        };
+        let Some(mut terminal) = terminal else {
+            for session in &self.state.sessions {
+                println!("{}", ui::inline::transition_line(session));
+            }
+            while self.running {
+                self.update_sessions();
+                for line in self.status_changes(&mut statuses) {
+                    println!("{}", line);
+                }
+                std::thread::sleep(TICK_RATE);
+            }
+            return Ok(());
+        };
+
         while self.running {
-            terminal.draw(|frame| self.render(frame))?;
-            self.handle_crossterm_events()?;
+            terminal.draw(|frame| {
+                ui::inline::render(frame, &self.state);
+                if self.state.config.monochrome() {
+                    ui::strip_colors(frame, &self.state.theme);
+                }
+            })?;
 
-            // Poll for status updates from background monitor
-            if self
-                .state
-                .process_manager
-                .poll_status_updates(&mut self.state.sessions)
+            if event::poll(TICK_RATE)?
+                && let Event::Key(key) = event::read()?
+                && key.kind == KeyEventKind::Press
             {
-                // Save updated statuses if any changed
-                let _ = self.state.save();
+                match (key.modifiers, key.code) {
+                    (KeyModifiers::CONTROL, KeyCode::Char('c'))
+                    | (_, KeyCode::Char('q') | KeyCode::Esc) => self.quit(),
+                    _ => {}
+                }
             }
 
-            // Poll for kubectl target updates if in form mode
-            if let Some(form_state) = &mut self.form_state {
-                form_state.poll_target_updates();
+            self.update_sessions();
+            for line in self.status_changes(&mut statuses) {
+                terminal.insert_before(1, |buf| Line::raw(line).render(buf.area, buf))?;
             }
         }
+        ratatui::restore();
         Ok(())
     }
 
+    /// Lines for sessions whose status differs from `statuses`, which is
+    /// brought up to date
+    fn status_changes(&self, statuses: &mut Vec<models::SessionStatus>) -> Vec<String> {
+        let changed = self
+            .state
+            .sessions
+            .iter()
+            .enumerate()
+            .filter(|(idx, s)| statuses.get(*idx) != Some(&s.status))
+            .map(|(_, s)| ui::inline::transition_line(s))
+            .collect();
+        *statuses = self
+            .state
+            .sessions
+            .iter()
+            .map(|s| s.status.clone())
+            .collect();
+        changed
+    }
+
+    /// Background work done every tick whatever is on screen, including
+    /// answering what the API and IPC listener threads queued
+    fn update_sessions(&mut self) {
+        if self.lost_supervision() {
+            // The instance that took over adopts the running sessions
+            self.quit();
+            return;
+        }
+
+        // Poll for status updates from background monitor
+        if self
+            .state
+            .process_manager
+            .poll_status_updates(&mut self.state.sessions)
+        {
+            self.state.save_later();
+        }
+        if self.state.start_stopped_restarts() {
+            let _ = self.state.save();
+        }
+        self.state.save_if_due();
+
+        self.serve_api();
+        self.serve_ipc();
+        self.coordinate_retries();
+        self.restart_failed_sessions();
+        self.stop_orphaned_scopes();
+        self.stop_idle_sessions();
+        if let Some(shipper) = &self.state.log_shipper {
+            shipper.track(&self.state.sessions);
+        }
+        if let Some(ipc) = &mut self.state.ipc {
+            ipc.publish(&self.state.sessions);
+        }
+        if let Some(hooks) = &mut self.state.hooks {
+            hooks.fire(&self.state.sessions);
+        }
+    }
+
+    /// Answer requests made through the local API
+    fn serve_api(&mut self) {
+        let Some(api) = self.state.api.take() else {
+            return;
+        };
+        if api.serve(&mut self.state) {
+            let _ = self.state.save();
+        }
+        self.state.api = Some(api);
+    }
+
+    /// Answer commands sent over the IPC socket
+    fn serve_ipc(&mut self) {
+        let Some(ipc) = self.state.ipc.take() else {
+            return;
+        };
+        if ipc.serve(&mut self.state) {
+            let _ = self.state.save();
+        }
+        self.state.ipc = Some(ipc);
+    }
+
     fn render(&mut self, frame: &mut Frame) {
+        self.viewport = frame.area();
+        if ui::is_too_small(frame.area()) {
+            ui::render_too_small(frame, &self.state.theme);
+        } else {
+            self.render_screen(frame);
+            if let Some((id, prompt)) = self
+                .state
+                .process_manager
+                .login_prompt(&self.state.sessions)
+            {
+                ui::login_prompt::render(frame, &self.state, id, &prompt, frame.area());
+            } else if let Some(palette) = &self.state.palette {
+                ui::palette::render(frame, &self.state, palette, frame.area());
+            }
+        }
+
+        if self.state.config.monochrome() {
+            ui::strip_colors(frame, &self.state.theme);
+        }
+    }
+
+    fn render_screen(&mut self, frame: &mut Frame) {
         match &self.state.current_screen {
             Screen::Dashboard => ui::dashboard::render(frame, &self.state, frame.area()),
             Screen::LogsViewer(idx) => {
@@ -65,27 +419,210 @@ impl App {
             }
             Screen::SessionForm(mode) => {
                 if let Some(form_state) = &self.form_state {
-                    ui::session_form::render(frame, form_state, mode, frame.area());
+                    ui::session_form::render(
+                        frame,
+                        form_state,
+                        mode,
+                        &self.state.theme,
+                        frame.area(),
+                    );
                 }
             }
+            Screen::Import => ui::import::render(frame, &self.state, frame.area()),
+            Screen::Triage => ui::triage::render(frame, &self.state, frame.area()),
+            Screen::Usage => ui::usage::render(frame, &self.state, frame.area()),
+            Screen::Catalog => ui::catalog::render(frame, &self.state, frame.area()),
+            Screen::Backups => ui::backups::render(frame, &self.state, frame.area()),
+            Screen::RunHistory(idx) => {
+                ui::run_history::render(frame, &self.state, *idx, frame.area())
+            }
+            Screen::ErrorDetails(idx) => {
+                ui::error_details::render(frame, &self.state, *idx, frame.area())
+            }
         }
     }
 
-    fn handle_crossterm_events(&mut self) -> Result<()> {
-        if event::poll(std::time::Duration::from_secs(1))? {
-            match event::read()? {
-                Event::Key(key) if key.kind == KeyEventKind::Press => self.on_key_event(key),
-                _ => {}
+    /// What background work may change on screen: sessions coming and
+    /// going, their statuses and PIDs, and the expiry reminder
+    fn visible_state(&self) -> VisibleState {
+        let sessions = self
+            .state
+            .sessions
+            .iter()
+            .map(|s| (s.id, s.status.clone(), s.pid))
+            .collect();
+        (sessions, self.state.expiry_prompt)
+    }
+
+    fn on_app_events(&mut self, events: Vec<AppEvent>) {
+        let mut resized = false;
+        for event in events {
+            match event {
+                // A burst of resizes (e.g. dragging a tmux split) is handled once
+                AppEvent::Terminal(Event::Resize(..)) => resized = true,
+                AppEvent::Terminal(event) => self.on_event(event),
+                AppEvent::Status(update) => {
+                    if self
+                        .state
+                        .process_manager
+                        .apply_status_update(&mut self.state.sessions, update)
+                    {
+                        self.state.save_later();
+                    }
+                }
+                AppEvent::Suggestions(suggestions) => {
+                    if let Some(form_state) = &mut self.form_state {
+                        form_state.on_suggestions(suggestions);
+                    }
+                }
             }
         }
-        Ok(())
+        if resized {
+            self.state.clamp_selections();
+        }
+    }
+
+    fn on_event(&mut self, event: Event) {
+        match event {
+            Event::Key(key) if key.kind == KeyEventKind::Press => self.on_key_event(key),
+            Event::Paste(text) if self.state.current_screen == Screen::Import => {
+                self.state.import_preview =
+                    Some(ImportPreview::from_text(&text, &self.state.sessions));
+            }
+            _ => {}
+        }
     }
 
     fn on_key_event(&mut self, key: KeyEvent) {
+        // A session waiting for a password takes the keyboard on every screen
+        if let Some((id, _)) = self
+            .state
+            .process_manager
+            .login_prompt(&self.state.sessions)
+        {
+            self.handle_login_prompt_keys(id, key);
+            return;
+        }
+        if self.state.palette.is_some() {
+            self.handle_palette_keys(key);
+            return;
+        }
+
         match &self.state.current_screen {
             Screen::Dashboard => self.handle_dashboard_keys(key),
             Screen::LogsViewer(_) => self.handle_logs_keys(key),
             Screen::SessionForm(_) => self.handle_form_keys(key),
+            Screen::Import => self.handle_import_keys(key),
+            Screen::Triage => self.handle_triage_keys(key),
+            Screen::Usage => self.handle_usage_keys(key),
+            Screen::Catalog => self.handle_catalog_keys(key),
+            Screen::Backups => self.handle_backups_keys(key),
+            Screen::RunHistory(_) => self.handle_run_history_keys(key),
+            Screen::ErrorDetails(_) => self.handle_error_details_keys(key),
+        }
+        self.last_key = Some(key.code);
+    }
+
+    fn handle_login_prompt_keys(&mut self, id: uuid::Uuid, key: KeyEvent) {
+        match key.code {
+            KeyCode::Enter => {
+                let answer = std::mem::take(&mut self.state.login_answer);
+                let _ = self.state.process_manager.answer_login_prompt(&id, &answer);
+            }
+            KeyCode::Esc => {
+                self.state.login_answer.clear();
+                if let Some(session) = self.state.sessions.iter_mut().find(|s| s.id == id) {
+                    let _ = self.state.process_manager.stop_session(session);
+                    let _ = self.state.save();
+                }
+            }
+            KeyCode::Backspace => {
+                self.state.login_answer.pop();
+            }
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => self.quit(),
+            KeyCode::Char(c) => self.state.login_answer.push(c),
+            _ => {}
+        }
+    }
+
+    fn handle_palette_keys(&mut self, key: KeyEvent) {
+        // Put back below unless the key closes it
+        let Some(mut palette) = self.state.palette.take() else {
+            return;
+        };
+        match (key.modifiers, key.code) {
+            (KeyModifiers::CONTROL, KeyCode::Char('c')) => self.quit(),
+            (_, KeyCode::Esc) => {}
+            (_, KeyCode::Enter) => {
+                if let Some(entry) = palette.selected_entry(&self.state) {
+                    self.run_palette_action(entry.action);
+                }
+            }
+            (_, KeyCode::Up) | (KeyModifiers::CONTROL, KeyCode::Char('k')) => {
+                palette.selected = palette.selected.saturating_sub(1);
+                self.state.palette = Some(palette);
+            }
+            (_, KeyCode::Down) | (KeyModifiers::CONTROL, KeyCode::Char('j')) => {
+                if palette.selected + 1 < palette.matches(&self.state).len() {
+                    palette.selected += 1;
+                }
+                self.state.palette = Some(palette);
+            }
+            (_, KeyCode::Backspace) => {
+                palette.query.pop();
+                palette.selected = 0;
+                self.state.palette = Some(palette);
+            }
+            (_, KeyCode::Char(c)) => {
+                palette.query.push(c);
+                palette.selected = 0;
+                self.state.palette = Some(palette);
+            }
+            _ => self.state.palette = Some(palette),
+        }
+    }
+
+    /// Carry out what was picked in the palette, through the same methods
+    /// as the dashboard keys
+    fn run_palette_action(&mut self, action: PaletteAction) {
+        match action {
+            PaletteAction::Start(id) | PaletteAction::Stop(id) => {
+                self.state.select_session(id);
+                self.toggle_session();
+            }
+            PaletteAction::Restart(id) => self.restart_session(id),
+            PaletteAction::Logs(id) => {
+                self.state.select_session(id);
+                self.view_logs();
+            }
+            PaletteAction::Runs(id) => {
+                if let Some(idx) = self.state.sessions.iter().position(|s| s.id == id) {
+                    self.open_run_history(idx);
+                }
+            }
+            PaletteAction::Edit(id) => {
+                self.state.select_session(id);
+                self.edit_session();
+            }
+            PaletteAction::Delete(id) => {
+                self.state.select_session(id);
+                self.delete_session();
+            }
+            PaletteAction::ToggleAutoRestart(id) => {
+                self.state.select_session(id);
+                self.toggle_auto_restart();
+            }
+            PaletteAction::Select(id) => self.state.select_session(id),
+            PaletteAction::Create => self.create_session(),
+            PaletteAction::Import => self.open_import(),
+            PaletteAction::Triage => self.open_triage(),
+            PaletteAction::Usage => self.state.show_usage(SummaryPeriod::Daily),
+            PaletteAction::Catalog => self.open_catalog(),
+            PaletteAction::Backups => self.open_backups(),
+            PaletteAction::PauseAll => self.pause_all(),
+            PaletteAction::ResumePaused => self.resume_paused(),
+            PaletteAction::TogglePreview => self.state.preview_pane = !self.state.preview_pane,
+            PaletteAction::Quit => self.request_quit(),
         }
     }
 
@@ -99,6 +636,75 @@ impl App {
             }
             return;
         }
+        if self.handle_stop_confirmation_keys(key) {
+            return;
+        }
+
+        // Crash notice from the previous run is dismissed by any key
+        if self.state.crash_notice.is_some() {
+            self.state.crash_notice = None;
+            return;
+        }
+
+        if self.state.value_view.is_some() {
+            self.state.value_view = None;
+            return;
+        }
+
+        if self.state.quit_prompt {
+            match key.code {
+                KeyCode::Char('k') | KeyCode::Char('K') | KeyCode::Enter => self.quit(),
+                KeyCode::Char('s') | KeyCode::Char('S') => {
+                    self.stop_all();
+                    self.quit();
+                }
+                KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                    self.state.quit_prompt = false;
+                }
+                _ => {}
+            }
+            return;
+        }
+
+        // Handle restore prompt for a form left unsaved by a previous run
+        if self.state.pending_draft.is_some() {
+            match key.code {
+                KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Enter => self.restore_draft(),
+                KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => self.discard_draft(),
+                _ => {}
+            }
+            return;
+        }
+
+        // Handle "start now?" prompt for a newly created session
+        if self.state.start_confirmation.is_some() {
+            match key.code {
+                KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Enter => self.confirm_start(),
+                KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                    self.state.start_confirmation = None
+                }
+                _ => {}
+            }
+            return;
+        }
+
+        // Handle "keep this tunnel up?" reminder
+        if let Some(id) = self.state.expiry_prompt {
+            match key.code {
+                KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Enter | KeyCode::Esc => {
+                    self.state.expiry_prompt = None;
+                }
+                KeyCode::Char('n') | KeyCode::Char('N') => {
+                    self.state.expiry_prompt = None;
+                    if let Some(session) = self.state.sessions.iter_mut().find(|s| s.id == id) {
+                        let _ = self.state.process_manager.stop_session(session);
+                        let _ = self.state.save();
+                    }
+                }
+                _ => {}
+            }
+            return;
+        }
 
         if self.state.search_mode {
             match key.code {
@@ -114,13 +720,11 @@ impl App {
                         .insert(self.state.search_cursor_pos, c);
                     self.state.search_cursor_pos += 1;
                 }
-                KeyCode::Backspace => {
-                    if self.state.search_cursor_pos > 0 {
-                        self.state
-                            .search_query
-                            .remove(self.state.search_cursor_pos - 1);
-                        self.state.search_cursor_pos -= 1;
-                    }
+                KeyCode::Backspace if self.state.search_cursor_pos > 0 => {
+                    self.state
+                        .search_query
+                        .remove(self.state.search_cursor_pos - 1);
+                    self.state.search_cursor_pos -= 1;
                 }
                 _ => {}
             }
@@ -128,42 +732,292 @@ impl App {
         }
 
         match (key.modifiers, key.code) {
-            (KeyModifiers::CONTROL, KeyCode::Char('c') | KeyCode::Char('C'))
-            | (_, KeyCode::Char('q')) => self.quit(),
-            (_, KeyCode::Up) => self.move_selection(-1),
-            (_, KeyCode::Down) => self.move_selection(1),
+            (KeyModifiers::CONTROL, KeyCode::Char('c') | KeyCode::Char('C')) => self.quit(),
+            (_, KeyCode::Char('q')) => self.request_quit(),
+            (_, KeyCode::Up | KeyCode::Char('k')) => self.move_selection(-1),
+            (_, KeyCode::Down | KeyCode::Char('j')) => self.move_selection(1),
+            (KeyModifiers::CONTROL, KeyCode::Char('u')) => {
+                self.page_selection(-(self.table_page() as isize));
+            }
+            (KeyModifiers::CONTROL, KeyCode::Char('d')) => {
+                self.page_selection(self.table_page() as isize);
+            }
+            (_, KeyCode::Char('g')) if self.last_key == Some(KeyCode::Char('g')) => {
+                self.page_selection(isize::MIN);
+            }
+            (_, KeyCode::Char('G')) => self.page_selection(isize::MAX),
             (_, KeyCode::Char('c')) => self.create_session(),
             (_, KeyCode::Char('e')) => self.edit_session(),
             (_, KeyCode::Char('d')) => self.delete_session(),
             (_, KeyCode::Char('s')) => self.toggle_session(),
+            (_, KeyCode::Char('S')) => self.toggle_login_prompts(),
+            (_, KeyCode::Char('R')) => self.toggle_auto_restart(),
+            (_, KeyCode::Char('P')) => self.pause_all(),
+            (_, KeyCode::Char('r')) => self.resume_paused(),
             (_, KeyCode::Char('l')) => self.view_logs(),
+            (_, KeyCode::Char('i')) => self.open_import(),
+            (_, KeyCode::Char('t')) => self.open_triage(),
+            (_, KeyCode::Char('u')) => self.state.show_usage(SummaryPeriod::Daily),
+            (_, KeyCode::Char('X')) => self.stop_scoped_here(),
+            (_, KeyCode::Char('C')) => self.open_catalog(),
+            (_, KeyCode::Char('b')) => self.open_backups(),
+            (KeyModifiers::CONTROL, KeyCode::Char('p')) => {
+                self.state.palette = Some(Palette::default());
+            }
+            (_, KeyCode::Char('p')) => self.state.preview_pane = !self.state.preview_pane,
+            (_, KeyCode::Char('v')) => self.state.value_view = self.state.selected_row_id(),
+            (_, KeyCode::Enter) => self.open_error_details(),
             (_, KeyCode::Char('/')) => self.state.search_mode = true,
             _ => {}
         }
     }
 
-    fn handle_logs_keys(&mut self, key: KeyEvent) {
+    fn handle_logs_keys(&mut self, key: KeyEvent) {
+        if let Some(id) = self.state.clear_logs_confirmation {
+            match key.code {
+                KeyCode::Char('y') | KeyCode::Char('Y') => {
+                    if let Some(session) = self.state.sessions.iter().find(|s| s.id == id) {
+                        let log_file = ui::logs_viewer::shown_log_file(&self.state, session);
+                        let _ = self.state.storage.clear_log(&log_file);
+                    }
+                    self.state.clear_logs_confirmation = None;
+                }
+                KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                    self.state.clear_logs_confirmation = None;
+                }
+                _ => {}
+            }
+            return;
+        }
+        if self.handle_stop_confirmation_keys(key) {
+            return;
+        }
+
+        match (key.modifiers, key.code) {
+            (KeyModifiers::CONTROL, KeyCode::Char('u')) => {
+                return self.scroll_logs(self.logs_page() as isize);
+            }
+            (KeyModifiers::CONTROL, KeyCode::Char('d')) => {
+                return self.scroll_logs(-(self.logs_page() as isize));
+            }
+            _ => {}
+        }
+
+        match key.code {
+            KeyCode::Esc => {
+                self.state.current_screen =
+                    match (self.state.current_screen.clone(), self.state.log_run.take()) {
+                        (Screen::LogsViewer(idx), Some(_)) => Screen::RunHistory(idx),
+                        _ => Screen::Dashboard,
+                    };
+                self.state.log_scroll = 0;
+            }
+            KeyCode::Up | KeyCode::Char('k') => self.scroll_logs(1),
+            KeyCode::Down | KeyCode::Char('j') => self.scroll_logs(-1),
+            KeyCode::Char('g') if self.last_key == Some(KeyCode::Char('g')) => {
+                self.scroll_logs(isize::MAX);
+            }
+            KeyCode::Char('G') => self.state.log_scroll = 0,
+            KeyCode::Char('w') => {
+                self.state.log_wrap = !self.state.log_wrap;
+                self.state.log_hscroll = 0;
+            }
+            KeyCode::Char('f') => {
+                self.state.log_stderr_only = !self.state.log_stderr_only;
+                self.state.log_scroll = 0;
+            }
+            KeyCode::Char('H') => {
+                if let Screen::LogsViewer(idx) = self.state.current_screen {
+                    self.open_run_history(idx);
+                }
+            }
+            KeyCode::Left | KeyCode::Char('h') => self.scroll_logs_sideways(-LOG_HSCROLL_STEP),
+            KeyCode::Right | KeyCode::Char('l') => self.scroll_logs_sideways(LOG_HSCROLL_STEP),
+            KeyCode::Char('s') => {
+                if let Screen::LogsViewer(idx) = self.state.current_screen
+                    && let Some(session) = self.state.sessions.get(idx)
+                {
+                    self.state.select_session(session.id);
+                    self.toggle_session();
+                }
+            }
+            KeyCode::Char('r') => {
+                if let Screen::LogsViewer(idx) = self.state.current_screen
+                    && let Some(session) = self.state.sessions.get(idx)
+                {
+                    self.restart_session(session.id);
+                }
+            }
+            KeyCode::Char('e') => {
+                if let Screen::LogsViewer(idx) = self.state.current_screen
+                    && let Some(session) = self.state.sessions.get(idx)
+                {
+                    self.state.select_session(session.id);
+                    self.edit_session();
+                }
+            }
+            KeyCode::Char('c') => {
+                if let Screen::LogsViewer(idx) = self.state.current_screen
+                    && let Some(session) = self.state.sessions.get(idx)
+                {
+                    self.state.clear_logs_confirmation = Some(session.id);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_import_keys(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Esc => {
+                self.state.import_preview = None;
+                self.state.current_screen = Screen::Dashboard;
+            }
+            KeyCode::Char('p') => self.read_clipboard_for_import(),
+            KeyCode::Char('h') => {
+                self.state.import_preview = Some(ImportPreview::from_history(&self.state.sessions));
+            }
+            KeyCode::Enter => self.confirm_import(),
+            _ => {}
+        }
+    }
+
+    fn handle_triage_keys(&mut self, key: KeyEvent) {
+        let group_count = self.state.error_groups().len();
+        match key.code {
+            KeyCode::Esc => self.state.current_screen = Screen::Dashboard,
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.state.triage_selected = self.state.triage_selected.saturating_sub(1);
+            }
+            KeyCode::Down | KeyCode::Char('j') if self.state.triage_selected + 1 < group_count => {
+                self.state.triage_selected += 1;
+            }
+            KeyCode::Char('r') => self.retry_error_group(),
+            _ => {}
+        }
+    }
+
+    fn handle_backups_keys(&mut self, key: KeyEvent) {
+        if self.state.restore_confirmation {
+            match key.code {
+                KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Enter => self.restore_backup(),
+                KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                    self.state.restore_confirmation = false;
+                }
+                _ => {}
+            }
+            return;
+        }
+
+        let backup_count = self.state.backups.len();
+        match key.code {
+            KeyCode::Esc => self.state.current_screen = Screen::Dashboard,
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.state.backup_selected = self.state.backup_selected.saturating_sub(1);
+            }
+            KeyCode::Down | KeyCode::Char('j') if self.state.backup_selected + 1 < backup_count => {
+                self.state.backup_selected += 1;
+            }
+            KeyCode::Enter if backup_count > 0 => self.state.restore_confirmation = true,
+            _ => {}
+        }
+    }
+
+    fn handle_run_history_keys(&mut self, key: KeyEvent) {
+        let Screen::RunHistory(idx) = self.state.current_screen else {
+            return;
+        };
+        let Some(session) = self.state.sessions.get(idx) else {
+            return;
+        };
+        let runs = ui::run_history::runs_newest_first(&self.state, session);
+        match key.code {
+            KeyCode::Esc => self.state.current_screen = Screen::LogsViewer(idx),
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.state.run_selected = self.state.run_selected.saturating_sub(1);
+            }
+            KeyCode::Down | KeyCode::Char('j') if self.state.run_selected + 1 < runs.len() => {
+                self.state.run_selected += 1;
+            }
+            KeyCode::Enter => {
+                if let Some(run) = runs.into_iter().nth(self.state.run_selected) {
+                    // The latest run's log is the live one
+                    let latest = run.log_file(&self.state.storage, &session.id)
+                        == self.state.storage.log_file(&session.id);
+                    self.state.log_run = (!latest).then_some(run);
+                    self.state.log_scroll = 0;
+                    self.state.log_hscroll = 0;
+                    self.state.current_screen = Screen::LogsViewer(idx);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_error_details_keys(&mut self, key: KeyEvent) {
+        let Screen::ErrorDetails(idx) = self.state.current_screen else {
+            return;
+        };
+        let Some(id) = self.state.sessions.get(idx).map(|s| s.id) else {
+            return;
+        };
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => self.state.current_screen = Screen::Dashboard,
+            KeyCode::Char('s') => {
+                if let Some(session) = self.state.sessions.get_mut(idx) {
+                    session.pending_restart = None;
+                    let _ = self.state.process_manager.start_session(session);
+                    let _ = self.state.save();
+                }
+                self.state.current_screen = Screen::Dashboard;
+            }
+            KeyCode::Char('e') => {
+                self.state.select_session(id);
+                self.edit_session();
+            }
+            KeyCode::Char('l') => {
+                self.state.select_session(id);
+                self.view_logs();
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_catalog_keys(&mut self, key: KeyEvent) {
+        self.state.catalog_message = None;
+        let entry_count = self
+            .state
+            .catalog
+            .as_ref()
+            .map_or(0, |c| c.snapshot().entries.len());
         match key.code {
             KeyCode::Esc => self.state.current_screen = Screen::Dashboard,
-            KeyCode::Char('s') => {
-                if let Screen::LogsViewer(idx) = self.state.current_screen {
-                    self.state.selected_index = idx;
-                    self.toggle_session();
-                }
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.state.catalog_selected = self.state.catalog_selected.saturating_sub(1);
+            }
+            KeyCode::Down | KeyCode::Char('j') if self.state.catalog_selected + 1 < entry_count => {
+                self.state.catalog_selected += 1;
             }
             KeyCode::Char('r') => {
-                if let Screen::LogsViewer(idx) = self.state.current_screen {
-                    if let Some(session) = self.state.sessions.get_mut(idx) {
-                        let _ = self.state.process_manager.stop_session(session);
-                        let _ = self.state.process_manager.start_session(session);
-                        let _ = self.state.save();
-                    }
+                if let Some(catalog) = &self.state.catalog {
+                    catalog.refresh();
                 }
             }
-            KeyCode::Char('e') => {
-                if let Screen::LogsViewer(idx) = self.state.current_screen {
-                    self.state.selected_index = idx;
-                    self.edit_session();
+            KeyCode::Enter => self.add_from_catalog(),
+            _ => {}
+        }
+    }
+
+    fn handle_usage_keys(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.state.usage_summary = None;
+                self.state.current_screen = Screen::Dashboard;
+            }
+            KeyCode::Char('w') => {
+                if let Some(summary) = &self.state.usage_summary {
+                    let period = summary.period.toggle();
+                    self.state.show_usage(period);
                 }
             }
             _ => {}
@@ -172,16 +1026,29 @@ impl App {
 
     fn handle_form_keys(&mut self, key: KeyEvent) {
         if let Some(form_state) = &mut self.form_state {
+            form_state.error = None;
+
+            // Confirming the diff of an edit
+            if form_state.pending_changes.is_some() {
+                match key.code {
+                    KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Enter => {
+                        self.save_form(true)
+                    }
+                    KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                        form_state.pending_changes = None
+                    }
+                    _ => {}
+                }
+                return;
+            }
+
             // Type selection step
             if form_state.step == FormStep::SelectType {
                 match key.code {
                     KeyCode::Up => form_state.move_type_selection(-1),
                     KeyCode::Down => form_state.move_type_selection(1),
                     KeyCode::Enter => form_state.confirm_type_selection(),
-                    KeyCode::Esc => {
-                        self.state.current_screen = Screen::Dashboard;
-                        self.form_state = None;
-                    }
+                    KeyCode::Esc => self.close_form(),
                     _ => {}
                 }
                 return;
@@ -211,12 +1078,12 @@ impl App {
             }
 
             match (key.modifiers, key.code) {
-                (_, KeyCode::Esc) => {
-                    self.state.current_screen = Screen::Dashboard;
-                    self.form_state = None;
-                }
+                (_, KeyCode::Esc) => self.close_form(),
                 (KeyModifiers::CONTROL, KeyCode::Char('s') | KeyCode::Char('S')) => {
-                    self.save_form();
+                    self.save_form(false);
+                }
+                (KeyModifiers::CONTROL, KeyCode::Char('r') | KeyCode::Char('R')) => {
+                    form_state.refresh_kube();
                 }
                 (_, KeyCode::Tab) => {
                     let is_create_mode = matches!(
@@ -230,7 +1097,7 @@ impl App {
                     if is_create_mode {
                         if form_state.session_type == models::SessionType::Kubectl {
                             if old_field == 4
-                                && !form_state.local_port.is_empty()
+                                && form_state.local_port.parse::<u16>().is_ok()
                                 && form_state.remote_port.is_empty()
                             {
                                 form_state.remote_port = form_state.local_port.clone();
@@ -240,9 +1107,9 @@ impl App {
                             {
                                 form_state.local_port = form_state.remote_port.clone();
                             }
-                        } else if field_count == 4 {
+                        } else if form_state.session_type == models::SessionType::SSH {
                             if old_field == 2
-                                && !form_state.local_port.is_empty()
+                                && form_state.local_port.parse::<u16>().is_ok()
                                 && form_state.remote_port.is_empty()
                             {
                                 form_state.remote_port = form_state.local_port.clone();
@@ -255,10 +1122,13 @@ impl App {
                         }
                     }
 
+                    form_state.check_field(old_field);
                     form_state.focused_field = (form_state.focused_field + 1) % field_count;
                     form_state.hide_suggestions();
                     form_state.cursor_pos =
-                        if form_state.session_type == models::SessionType::Kubectl {
+                        if let Some(value) = form_state.text_field_mut(form_state.focused_field) {
+                            value.len()
+                        } else if form_state.session_type == models::SessionType::Kubectl {
                             match form_state.focused_field {
                                 0 => form_state.context_field.len(),
                                 1 => form_state.name.len(),
@@ -292,7 +1162,7 @@ impl App {
                     if is_create_mode {
                         if form_state.session_type == models::SessionType::Kubectl {
                             if old_field == 4
-                                && !form_state.local_port.is_empty()
+                                && form_state.local_port.parse::<u16>().is_ok()
                                 && form_state.remote_port.is_empty()
                             {
                                 form_state.remote_port = form_state.local_port.clone();
@@ -302,9 +1172,9 @@ impl App {
                             {
                                 form_state.local_port = form_state.remote_port.clone();
                             }
-                        } else if field_count == 4 {
+                        } else if form_state.session_type == models::SessionType::SSH {
                             if old_field == 2
-                                && !form_state.local_port.is_empty()
+                                && form_state.local_port.parse::<u16>().is_ok()
                                 && form_state.remote_port.is_empty()
                             {
                                 form_state.remote_port = form_state.local_port.clone();
@@ -317,6 +1187,7 @@ impl App {
                         }
                     }
 
+                    form_state.check_field(old_field);
                     form_state.focused_field = if form_state.focused_field == 0 {
                         field_count - 1
                     } else {
@@ -324,7 +1195,9 @@ impl App {
                     };
                     form_state.hide_suggestions();
                     form_state.cursor_pos =
-                        if form_state.session_type == models::SessionType::Kubectl {
+                        if let Some(value) = form_state.text_field_mut(form_state.focused_field) {
+                            value.len()
+                        } else if form_state.session_type == models::SessionType::Kubectl {
                             match form_state.focused_field {
                                 0 => form_state.context_field.len(),
                                 1 => form_state.name.len(),
@@ -347,7 +1220,13 @@ impl App {
                     form_state.show_port_suggestions();
                 }
                 (_, KeyCode::Char(c)) => {
-                    if form_state.session_type == models::SessionType::Kubectl {
+                    let cursor_pos = form_state.cursor_pos;
+                    if let Some(value) = form_state.text_field_mut(form_state.focused_field) {
+                        // Icons are usually multi-byte emoji, so step by the char's byte length
+                        value.insert(cursor_pos, c);
+                        form_state.cursor_pos += c.len_utf8();
+                        form_state.update_path_suggestions();
+                    } else if form_state.session_type == models::SessionType::Kubectl {
                         match form_state.focused_field {
                             0 => {
                                 let mut new_context = form_state.context_field.clone();
@@ -364,8 +1243,6 @@ impl App {
                                 new_namespace.insert(form_state.cursor_pos, c);
                                 form_state.cursor_pos += 1;
                                 form_state.update_namespace(new_namespace);
-                                // Reload targets when namespace changes
-                                form_state.kube_targets.clear();
                             }
                             3 => {
                                 let mut new_target = form_state.target.clone();
@@ -408,77 +1285,62 @@ impl App {
                     }
                 }
                 (_, KeyCode::Backspace) => {
-                    if form_state.session_type == models::SessionType::Kubectl {
+                    let cursor_pos = form_state.cursor_pos;
+                    if let Some(value) = form_state.text_field_mut(form_state.focused_field) {
+                        if let Some(prev) = value[..cursor_pos].chars().next_back() {
+                            value.remove(cursor_pos - prev.len_utf8());
+                            form_state.cursor_pos -= prev.len_utf8();
+                        }
+                        form_state.update_path_suggestions();
+                    } else if form_state.session_type == models::SessionType::Kubectl {
                         match form_state.focused_field {
-                            0 => {
-                                if form_state.cursor_pos > 0 {
-                                    form_state.context_field.remove(form_state.cursor_pos - 1);
-                                    form_state.cursor_pos -= 1;
-                                    form_state.update_context(form_state.context_field.clone());
-                                }
+                            0 if form_state.cursor_pos > 0 => {
+                                form_state.context_field.remove(form_state.cursor_pos - 1);
+                                form_state.cursor_pos -= 1;
+                                form_state.update_context(form_state.context_field.clone());
                             }
-                            1 => {
-                                if form_state.cursor_pos > 0 {
-                                    form_state.name.remove(form_state.cursor_pos - 1);
-                                    form_state.cursor_pos -= 1;
-                                }
+                            1 if form_state.cursor_pos > 0 => {
+                                form_state.name.remove(form_state.cursor_pos - 1);
+                                form_state.cursor_pos -= 1;
                             }
-                            2 => {
-                                if form_state.cursor_pos > 0 {
-                                    form_state.namespace_field.remove(form_state.cursor_pos - 1);
-                                    form_state.cursor_pos -= 1;
-                                    form_state.update_namespace(form_state.namespace_field.clone());
-                                    // Reload targets when namespace changes
-                                    form_state.kube_targets.clear();
-                                }
+                            2 if form_state.cursor_pos > 0 => {
+                                form_state.namespace_field.remove(form_state.cursor_pos - 1);
+                                form_state.cursor_pos -= 1;
+                                form_state.update_namespace(form_state.namespace_field.clone());
                             }
-                            3 => {
-                                if form_state.cursor_pos > 0 {
-                                    form_state.target.remove(form_state.cursor_pos - 1);
-                                    form_state.cursor_pos -= 1;
-                                    form_state.update_target(form_state.target.clone());
-                                }
+                            3 if form_state.cursor_pos > 0 => {
+                                form_state.target.remove(form_state.cursor_pos - 1);
+                                form_state.cursor_pos -= 1;
+                                form_state.update_target(form_state.target.clone());
                             }
-                            4 => {
-                                if form_state.cursor_pos > 0 {
-                                    form_state.local_port.remove(form_state.cursor_pos - 1);
-                                    form_state.cursor_pos -= 1;
-                                }
+                            4 if form_state.cursor_pos > 0 => {
+                                form_state.local_port.remove(form_state.cursor_pos - 1);
+                                form_state.cursor_pos -= 1;
                             }
-                            5 => {
-                                if form_state.cursor_pos > 0 {
-                                    form_state.remote_port.remove(form_state.cursor_pos - 1);
-                                    form_state.cursor_pos -= 1;
-                                }
+                            5 if form_state.cursor_pos > 0 => {
+                                form_state.remote_port.remove(form_state.cursor_pos - 1);
+                                form_state.cursor_pos -= 1;
                             }
                             _ => {}
                         }
                     } else {
                         match form_state.focused_field {
-                            0 => {
-                                if form_state.cursor_pos > 0 {
-                                    form_state.name.remove(form_state.cursor_pos - 1);
-                                    form_state.cursor_pos -= 1;
-                                }
+                            0 if form_state.cursor_pos > 0 => {
+                                form_state.name.remove(form_state.cursor_pos - 1);
+                                form_state.cursor_pos -= 1;
                             }
-                            1 => {
-                                if form_state.cursor_pos > 0 {
-                                    form_state.target.remove(form_state.cursor_pos - 1);
-                                    form_state.cursor_pos -= 1;
-                                    form_state.update_target(form_state.target.clone());
-                                }
+                            1 if form_state.cursor_pos > 0 => {
+                                form_state.target.remove(form_state.cursor_pos - 1);
+                                form_state.cursor_pos -= 1;
+                                form_state.update_target(form_state.target.clone());
                             }
-                            2 => {
-                                if form_state.cursor_pos > 0 {
-                                    form_state.local_port.remove(form_state.cursor_pos - 1);
-                                    form_state.cursor_pos -= 1;
-                                }
+                            2 if form_state.cursor_pos > 0 => {
+                                form_state.local_port.remove(form_state.cursor_pos - 1);
+                                form_state.cursor_pos -= 1;
                             }
-                            3 => {
-                                if form_state.cursor_pos > 0 {
-                                    form_state.remote_port.remove(form_state.cursor_pos - 1);
-                                    form_state.cursor_pos -= 1;
-                                }
+                            3 if form_state.cursor_pos > 0 => {
+                                form_state.remote_port.remove(form_state.cursor_pos - 1);
+                                form_state.cursor_pos -= 1;
                             }
                             _ => {}
                         }
@@ -490,42 +1352,121 @@ impl App {
     }
 
     fn move_selection(&mut self, delta: i32) {
-        let count = self.state.filtered_sessions().len();
+        let filtered = self.state.filtered_sessions();
+        let count = filtered.len();
         if count == 0 {
             return;
         }
-        let new_idx = (self.state.selected_index as i32 + delta).rem_euclid(count as i32) as usize;
-        self.state.selected_index = new_idx;
+        let current = self.state.selected_index().unwrap_or(0);
+        let new_idx = (current as i32 + delta).rem_euclid(count as i32) as usize;
+        self.state.selected_id = Some(filtered[new_idx].1.id);
+    }
+
+    /// Move the selection by `delta` rows without wrapping around
+    fn page_selection(&mut self, delta: isize) {
+        let filtered = self.state.filtered_sessions();
+        if filtered.is_empty() {
+            return;
+        }
+        let current = self.state.selected_index().unwrap_or(0);
+        let new_idx = current.saturating_add_signed(delta).min(filtered.len() - 1);
+        self.state.selected_id = Some(filtered[new_idx].1.id);
+    }
+
+    /// Half the rows of the dashboard table (title, help, borders and header
+    /// take the rest)
+    fn table_page(&self) -> usize {
+        (self.viewport.height.saturating_sub(9) as usize / 2).max(1)
+    }
+
+    /// Scroll the logs viewer up (positive) or down (negative) by `delta`
+    /// lines, stopping at the oldest line and at the live tail
+    fn scroll_logs(&mut self, delta: isize) {
+        let Screen::LogsViewer(idx) = self.state.current_screen else {
+            return;
+        };
+        let Some(session) = self.state.sessions.get(idx) else {
+            return;
+        };
+        let visible = ui::logs_viewer::visible_lines(&self.state, session, self.viewport);
+        let total = ui::logs_viewer::read_shown_log(&self.state, session, usize::MAX)
+            .map(|logs| logs.lines().count())
+            .unwrap_or(0);
+        self.state.log_scroll = self
+            .state
+            .log_scroll
+            .saturating_add_signed(delta)
+            .min(total.saturating_sub(visible));
+    }
+
+    /// Move the unwrapped logs `delta` columns right (left when negative)
+    fn scroll_logs_sideways(&mut self, delta: isize) {
+        let Screen::LogsViewer(idx) = self.state.current_screen else {
+            return;
+        };
+        let Some(session) = self.state.sessions.get(idx) else {
+            return;
+        };
+        if self.state.log_wrap {
+            return;
+        }
+        let max = ui::logs_viewer::max_hscroll(&self.state, session, self.viewport);
+        self.state.log_hscroll = self.state.log_hscroll.saturating_add_signed(delta).min(max);
+    }
+
+    fn logs_page(&self) -> usize {
+        let Screen::LogsViewer(idx) = self.state.current_screen else {
+            return 1;
+        };
+        self.state.sessions.get(idx).map_or(1, |session| {
+            (ui::logs_viewer::visible_lines(&self.state, session, self.viewport) / 2).max(1)
+        })
+    }
+
+    /// Hook a new session form up to the suggestion sources, with lookups
+    /// running in the background while the event loop is there to take
+    /// their results
+    fn prepare_form(&self, form: FormState) -> FormState {
+        let form = match &self.events {
+            Some(events) => form.with_events(events.clone()),
+            None => form,
+        };
+        form.with_providers(self.state.suggestion_providers())
+            .with_kube_cache(self.state.kube_cache.clone())
+            .with_history(&self.state.sessions)
+            .with_session_providers(self.state.process_manager.providers().clone())
     }
 
     fn create_session(&mut self) {
-        self.form_state = Some(FormState::new());
+        self.form_state = Some(self.prepare_form(FormState::new()));
         self.state.current_screen = Screen::SessionForm(FormMode::Create);
     }
 
     fn edit_session(&mut self) {
-        let filtered = self.state.filtered_sessions();
-        if let Some((real_idx, session)) = filtered.get(self.state.selected_index) {
-            self.form_state = Some(FormState::from_session(session));
-            self.state.current_screen = Screen::SessionForm(FormMode::Edit(*real_idx));
+        if let Some((real_idx, session)) = self.state.selected_session() {
+            self.form_state = Some(self.prepare_form(FormState::from_session(session)));
+            self.state.current_screen = Screen::SessionForm(FormMode::Edit(real_idx));
         }
     }
 
     fn delete_session(&mut self) {
-        let filtered = self.state.filtered_sessions();
-        if let Some((real_idx, _)) = filtered.get(self.state.selected_index) {
-            self.state.delete_confirmation = Some(*real_idx);
+        if let Some((_, session)) = self.state.selected_session() {
+            self.state.delete_confirmation = Some(session.id);
         }
     }
 
     fn confirm_delete(&mut self) {
-        if let Some(idx) = self.state.delete_confirmation {
+        if let Some(id) = self.state.delete_confirmation.take()
+            && let Some(idx) = self.state.session_index(id)
+        {
+            // Select the neighbouring row so the cursor doesn't jump to the top
+            let pos = self.state.selected_index().unwrap_or(0);
             self.state.sessions.remove(idx);
             let _ = self.state.save();
-            if self.state.selected_index > 0 {
-                self.state.selected_index -= 1;
-            }
-            self.state.delete_confirmation = None;
+            let filtered = self.state.filtered_sessions();
+            self.state.selected_id = filtered
+                .get(pos.min(filtered.len().saturating_sub(1)))
+                .map(|(_, s)| s.id);
         }
     }
 
@@ -534,54 +1475,636 @@ impl App {
     }
 
     fn toggle_session(&mut self) {
-        let filtered = self.state.filtered_sessions();
-        if let Some((real_idx, _)) = filtered.get(self.state.selected_index) {
-            let real_idx = *real_idx;
-            if let Some(session) = self.state.sessions.get_mut(real_idx) {
-                match session.status {
-                    models::SessionStatus::Running => {
-                        let _ = self.state.process_manager.stop_session(session);
-                    }
-                    _ => {
-                        let _ = self.state.process_manager.start_session(session);
-                    }
+        if let Some((real_idx, _)) = self.state.selected_session()
+            && let Some(session) = self.state.sessions.get_mut(real_idx)
+        {
+            match session.status {
+                models::SessionStatus::Running | models::SessionStatus::Degraded(_)
+                    if self.state.config.confirm_stop =>
+                {
+                    self.state.stop_confirmation = Some(session.id);
+                    return;
+                }
+                models::SessionStatus::Running
+                | models::SessionStatus::Degraded(_)
+                | models::SessionStatus::Starting => {
+                    let _ = self.state.process_manager.stop_session(session);
+                }
+                models::SessionStatus::Stopping => {}
+                // Calls off the automatic restart
+                models::SessionStatus::Error(_) if session.pending_restart.is_some() => {
+                    let _ = self.state.process_manager.stop_session(session);
+                    session.pending_restart = None;
+                }
+                _ => {
+                    let _ = self.state.process_manager.start_session(session);
+                }
+            }
+            let _ = self.state.save();
+        }
+    }
+
+    /// Switch the selected session to or from running under a terminal
+    /// that can answer login prompts, starting it if it's stopped
+    fn toggle_login_prompts(&mut self) {
+        if let Some((real_idx, _)) = self.state.selected_session()
+            && let Some(session) = self.state.sessions.get_mut(real_idx)
+        {
+            session.login_prompts = !session.login_prompts;
+            if session.login_prompts && session.pid.is_none() {
+                let _ = self.state.process_manager.start_session(session);
+            }
+            let _ = self.state.save();
+        }
+    }
+
+    /// Switch automatic restarts with backoff on or off for the selected
+    /// session
+    fn toggle_auto_restart(&mut self) {
+        if let Some((real_idx, _)) = self.state.selected_session()
+            && let Some(session) = self.state.sessions.get_mut(real_idx)
+        {
+            session.auto_restart = !session.auto_restart;
+            let _ = self.state.save();
+        }
+    }
+
+    /// Stop every session that is up, remembering them for
+    /// [`resume_paused`](Self::resume_paused)
+    fn pause_all(&mut self) {
+        pause::pause(&mut self.state.sessions, &self.state.process_manager);
+        let _ = self.state.save();
+    }
+
+    /// Start the sessions stopped by the last pause again
+    fn resume_paused(&mut self) {
+        pause::resume(&mut self.state.sessions, &self.state.process_manager);
+        let _ = self.state.save();
+    }
+
+    /// Restart the session with `id`, asking first if it's running and
+    /// `confirm_stop` is on
+    fn restart_session(&mut self, id: uuid::Uuid) {
+        let Some(session) = self.state.sessions.iter_mut().find(|s| s.id == id) else {
+            return;
+        };
+        if self.state.config.confirm_stop
+            && matches!(
+                session.status,
+                models::SessionStatus::Running | models::SessionStatus::Degraded(_)
+            )
+        {
+            self.state.restart_confirmation = Some(id);
+            return;
+        }
+        let _ = self.state.restart_session(id);
+        let _ = self.state.save();
+    }
+
+    /// Answer to the stop or restart confirmation, if one is shown
+    fn handle_stop_confirmation_keys(&mut self, key: KeyEvent) -> bool {
+        if self.state.stop_confirmation.is_none() && self.state.restart_confirmation.is_none() {
+            return false;
+        }
+        match key.code {
+            KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Enter => {
+                if let Some(id) = self.state.stop_confirmation.take()
+                    && let Some(session) = self.state.sessions.iter_mut().find(|s| s.id == id)
+                {
+                    let _ = self.state.process_manager.stop_session(session);
+                }
+                if let Some(id) = self.state.restart_confirmation.take() {
+                    let _ = self.state.restart_session(id);
                 }
                 let _ = self.state.save();
             }
+            KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                self.state.stop_confirmation = None;
+                self.state.restart_confirmation = None;
+            }
+            _ => {}
         }
+        true
     }
 
     fn view_logs(&mut self) {
-        let filtered = self.state.filtered_sessions();
-        if let Some((real_idx, _)) = filtered.get(self.state.selected_index) {
-            self.state.current_screen = Screen::LogsViewer(*real_idx);
-        }
-    }
-
-    fn save_form(&mut self) {
-        if let Some(form_state) = &self.form_state {
-            if let Some(session) = form_state.to_session() {
-                if let Screen::SessionForm(FormMode::Edit(idx)) = self.state.current_screen {
-                    if let Some(existing) = self.state.sessions.get_mut(idx) {
-                        existing.name = session.name;
-                        existing.session_type = session.session_type;
-                        existing.target = session.target;
-                        existing.local_port = session.local_port;
-                        existing.remote_port = session.remote_port;
-                        existing.kube_context = session.kube_context;
-                        existing.kube_namespace = session.kube_namespace;
+        if let Some((real_idx, _)) = self.state.selected_session() {
+            self.state.log_scroll = 0;
+            self.state.log_hscroll = 0;
+            self.state.log_run = None;
+            self.state.current_screen = Screen::LogsViewer(real_idx);
+        }
+    }
+
+    /// Save the form. Edits that change something are shown as a diff
+    /// first and only saved once `confirmed`.
+    fn save_form(&mut self, confirmed: bool) {
+        if let Some(form_state) = &mut self.form_state {
+            let invalid = form_state.check_all_fields();
+            if invalid > 0 {
+                form_state.error = Some(format!("Fix the {} marked field(s) to save", invalid));
+                return;
+            }
+        }
+        if let Some(form_state) = &mut self.form_state
+            && let Some(session) = form_state.to_session()
+        {
+            if let Err(reason) = self.state.process_manager.policy().check(&session) {
+                form_state.error = Some(format!("Blocked by policy: {}", reason));
+                return;
+            }
+            if let Err(reason) = self.state.process_manager.providers().validate(&session) {
+                form_state.error = Some(reason);
+                return;
+            }
+            if let Some(warning) = form_state.remote_port_warning()
+                && form_state.acknowledged_warning.as_ref() != Some(&warning)
+            {
+                form_state.error = Some(warning.clone());
+                form_state.acknowledged_warning = Some(warning);
+                return;
+            }
+            if let Screen::SessionForm(FormMode::Edit(idx)) = self.state.current_screen {
+                if let Some(existing) = self.state.sessions.get_mut(idx) {
+                    if !confirmed {
+                        let changes = existing.changes_to(&session);
+                        if !changes.is_empty() {
+                            form_state.pending_changes = Some((existing.name.clone(), changes));
+                            return;
+                        }
                     }
-                } else {
-                    self.state.sessions.push(session);
+                    existing.name = session.name;
+                    existing.session_type = session.session_type;
+                    existing.target = session.target;
+                    existing.local_port = session.local_port;
+                    existing.auto_local_port = session.auto_local_port;
+                    existing.remote_port = session.remote_port;
+                    existing.kube_context = session.kube_context;
+                    existing.kube_namespace = session.kube_namespace;
+                    existing.icon = session.icon;
+                    existing.color = session.color;
+                    existing.tags = session.tags;
+                    existing.health_command = session.health_command;
+                    existing.pre_start = session.pre_start;
+                    existing.post_stop = session.post_stop;
+                    existing.user = session.user;
+                    existing.identity_file = session.identity_file;
+                    existing.remote_host = session.remote_host;
+                    existing.address_family = session.address_family;
+                    existing.kubeconfig_path = session.kubeconfig_path;
+                    existing.idle_timeout_minutes = session.idle_timeout_minutes;
+                    existing.max_lifetime = session.max_lifetime;
                 }
+            } else {
+                let id = session.id;
+                self.state.sessions.push(session);
+                self.state.select_session(id);
+                if self.state.config.prompt_start_on_create {
+                    self.state.start_confirmation = Some(id);
+                }
+            }
+            let _ = self.state.save();
+            self.close_form();
+        }
+    }
+
+    fn close_form(&mut self) {
+        self.state.current_screen = Screen::Dashboard;
+        self.form_state = None;
+        self.last_draft = None;
+        let _ = self.state.storage.clear_draft();
+    }
+
+    /// Persist the open form at most every DRAFT_SAVE_INTERVAL, and only if
+    /// something changed since the last write
+    fn autosave_draft(&mut self) {
+        if self.last_draft_save.elapsed() < DRAFT_SAVE_INTERVAL {
+            return;
+        }
+        let editing = match self.state.current_screen {
+            Screen::SessionForm(FormMode::Edit(idx)) => self.state.sessions.get(idx).map(|s| s.id),
+            _ => None,
+        };
+        let draft = self
+            .form_state
+            .as_ref()
+            .and_then(|form_state| form_state.to_draft(editing));
+        if draft.is_some() && draft != self.last_draft {
+            if let Some(draft) = &draft {
+                let _ = self.state.storage.save_draft(draft);
+            }
+            self.last_draft = draft;
+        }
+        self.last_draft_save = Instant::now();
+    }
+
+    fn restore_draft(&mut self) {
+        if let Some(draft) = self.state.pending_draft.take() {
+            let mode = match draft.editing.and_then(|id| self.state.session_index(id)) {
+                Some(idx) => FormMode::Edit(idx),
+                None => FormMode::Create,
+            };
+            self.form_state = Some(self.prepare_form(FormState::from_draft(&draft)));
+            self.last_draft = Some(draft);
+            self.state.current_screen = Screen::SessionForm(mode);
+        }
+    }
+
+    fn discard_draft(&mut self) {
+        self.state.pending_draft = None;
+        let _ = self.state.storage.clear_draft();
+    }
+
+    fn confirm_start(&mut self) {
+        if let Some(id) = self.state.start_confirmation.take()
+            && let Some(session) = self.state.sessions.iter_mut().find(|s| s.id == id)
+        {
+            let _ = self.state.process_manager.start_session(session);
+            let _ = self.state.save();
+        }
+    }
+
+    fn open_import(&mut self) {
+        self.state.current_screen = Screen::Import;
+        self.read_clipboard_for_import();
+    }
+
+    fn read_clipboard_for_import(&mut self) {
+        self.state.import_preview = import::read_clipboard()
+            .map(|text| ImportPreview::from_text(&text, &self.state.sessions));
+    }
+
+    fn confirm_import(&mut self) {
+        if let Some(ImportPreview {
+            parsed: Ok(sessions),
+            ..
+        }) = &self.state.import_preview
+        {
+            let policy = self.state.process_manager.policy();
+            if let Some(reason) = sessions
+                .iter()
+                .find_map(|s| policy.check(s).err().map(|r| format!("{}: {}", s.name, r)))
+            {
+                self.state.import_preview = Some(ImportPreview::error(format!(
+                    "Blocked by policy: {}",
+                    reason
+                )));
+                return;
+            }
+        }
+
+        if let Some(ImportPreview {
+            parsed: Ok(sessions),
+            ..
+        }) = self.state.import_preview.take()
+        {
+            let first_id = sessions.first().map(|s| s.id);
+            self.state.sessions.extend(sessions);
+            let _ = self.state.save();
+            if let Some(id) = first_id {
+                self.state.select_session(id);
+            }
+            self.state.current_screen = Screen::Dashboard;
+        }
+    }
+
+    /// Start whatever the retry coordinator released: a canary during an
+    /// outage, or the whole held wave once the canary is up
+    fn coordinate_retries(&mut self) {
+        let network_up = self.state.network_up();
+        let to_start = self
+            .state
+            .retry
+            .observe(&self.state.sessions, network_up, Instant::now());
+        if to_start.is_empty() {
+            return;
+        }
+        for id in to_start {
+            // Auto-restart may have been turned off since the session was held
+            if let Some(session) = self
+                .state
+                .sessions
+                .iter_mut()
+                .find(|s| s.id == id && s.auto_restart)
+            {
+                let _ = self.state.process_manager.start_session(session);
+            }
+        }
+        let _ = self.state.save();
+    }
+
+    /// Start failed sessions with auto-restart on once their backoff is
+    /// over. Those held during an outage are left to the retry coordinator.
+    fn restart_failed_sessions(&mut self) {
+        let held = self
+            .state
+            .retry
+            .storm()
+            .map(|storm| storm.held.clone())
+            .unwrap_or_default();
+        let due =
+            self.state
+                .reconnector
+                .observe(&mut self.state.sessions, &held, chrono::Utc::now());
+        if due.is_empty() {
+            return;
+        }
+        for (id, attempt) in due {
+            if let Some(session) = self.state.sessions.iter_mut().find(|s| s.id == id) {
+                let _ = self.state.storage.append_log(
+                    &session.id,
+                    &format!("Restarting automatically (attempt {})\n", attempt),
+                );
+                let _ = self.state.process_manager.start_session(session);
+            }
+        }
+        let _ = self.state.save();
+    }
+
+    /// Stop running sessions whose local port had no connections for their
+    /// idle timeout. Sessions count as active from when they started.
+    fn stop_idle_sessions(&mut self) {
+        let activity = self.state.process_manager.activity();
+        let now = chrono::Utc::now();
+        let mut changed = false;
+        for session in &mut self.state.sessions {
+            let Some(minutes) = session.idle_timeout_minutes else {
+                continue;
+            };
+            // Out of range only in a hand-edited sessions.yaml
+            let Some(timeout) = i64::try_from(minutes)
+                .ok()
+                .and_then(chrono::Duration::try_minutes)
+            else {
+                continue;
+            };
+            // Not sampled yet, e.g. right after start or an adopted session
+            let Some(port) = activity.get(&session.id) else {
+                continue;
+            };
+            let idle_since = port.last_active.max(session.last_started);
+            if !session.status.is_running()
+                || port.connections > 0
+                || idle_since.is_none_or(|at| now - at < timeout)
+            {
+                continue;
+            }
+            let _ = self.state.storage.append_log(
+                &session.id,
+                &format!("Stopping: no connections for {} min\n", minutes),
+            );
+            let _ = self.state.process_manager.stop_session(session);
+            changed = true;
+        }
+        if changed {
+            let _ = self.state.save();
+        }
+    }
+
+    /// Ask about the first session running longer than `remind_after_hours`.
+    /// Answering "keep" snoozes it for another period.
+    fn check_expiry_reminders(&mut self) {
+        let Some(after) = self.state.config.remind_after() else {
+            return;
+        };
+        let busy = self.state.current_screen != Screen::Dashboard
+            || self.state.expiry_prompt.is_some()
+            || self.state.quit_prompt
+            || self.state.delete_confirmation.is_some()
+            || self.state.stop_confirmation.is_some()
+            || self.state.restart_confirmation.is_some()
+            || self.state.start_confirmation.is_some()
+            || self.state.pending_draft.is_some()
+            || self.state.crash_notice.is_some()
+            || self.state.value_view.is_some()
+            || self.state.palette.is_some();
+        if busy {
+            return;
+        }
+
+        let snooze = after.to_std().unwrap_or_default();
+        let due = self.state.sessions.iter().find(|s| {
+            s.is_overdue(after)
+                && self
+                    .state
+                    .reminded_at
+                    .get(&s.id)
+                    .is_none_or(|at| at.elapsed() >= snooze)
+        });
+        if let Some(id) = due.map(|s| s.id) {
+            self.state.reminded_at.insert(id, Instant::now());
+            self.state.expiry_prompt = Some(id);
+        }
+    }
+
+    /// Stop everything started from this terminal
+    fn stop_scoped_here(&mut self) {
+        for id in self.state.scoped_here() {
+            if let Some(session) = self.state.sessions.iter_mut().find(|s| s.id == id) {
+                let _ = self.state.process_manager.stop_session(session);
+            }
+        }
+        let _ = self.state.save();
+    }
+
+    /// Stop sessions whose owning terminal has exited
+    fn stop_orphaned_scopes(&mut self) {
+        if !self.state.config.terminal_scoping
+            || self
+                .last_scope_check
+                .is_some_and(|at| at.elapsed() < SCOPE_CHECK_INTERVAL)
+        {
+            return;
+        }
+        self.last_scope_check = Some(Instant::now());
+
+        let mut changed = false;
+        for session in self.state.sessions.iter_mut() {
+            if session.pid.is_some()
+                && let Some(scope) = &session.scope
+                && !pfman::scope::is_alive(scope)
+            {
+                let _ = self.state.process_manager.stop_session(session);
+                changed = true;
+            }
+        }
+        if changed {
+            let _ = self.state.save();
+        }
+    }
+
+    fn open_catalog(&mut self) {
+        self.state.catalog_selected = 0;
+        self.state.catalog_message = None;
+        self.state.current_screen = Screen::Catalog;
+    }
+
+    /// Create a local session from the highlighted catalog entry, or bring
+    /// the local session of the same name up to date with it
+    fn add_from_catalog(&mut self) {
+        let Some(entry) = self.state.catalog.as_ref().and_then(|c| {
+            c.snapshot()
+                .entries
+                .get(self.state.catalog_selected)
+                .cloned()
+        }) else {
+            return;
+        };
+        if let Err(reason) = self.state.process_manager.policy().check(&entry) {
+            self.state.catalog_message = Some(format!("Blocked by policy: {}", reason));
+            return;
+        }
+
+        match EntryState::of(&entry, &self.state.sessions) {
+            EntryState::New => {
+                let mut session = entry;
+                session.id = uuid::Uuid::new_v4();
+                let id = session.id;
+                self.state.sessions.push(session);
                 let _ = self.state.save();
+                self.state.select_session(id);
                 self.state.current_screen = Screen::Dashboard;
-                self.form_state = None;
             }
+            EntryState::Outdated => {
+                if let Some(existing) = self
+                    .state
+                    .sessions
+                    .iter_mut()
+                    .find(|s| s.name == entry.name)
+                {
+                    let was_up = existing.pid.is_some();
+                    if was_up {
+                        let _ = self.state.process_manager.stop_session(existing);
+                    }
+                    let entry = catalog::with_local_commands(entry, existing);
+                    apply::update(existing, entry);
+                    let (id, name) = (existing.id, existing.name.clone());
+                    let message = if !was_up {
+                        format!("Updated {}", name)
+                    } else if let Err(err) = self.state.start_after_stop(id) {
+                        format!("Updated {}, but it failed to start: {}", name, err)
+                    } else if self.state.restarts_after_stop.contains(&id) {
+                        format!("Updated {}, restarting once it has stopped", name)
+                    } else {
+                        format!("Updated {}", name)
+                    };
+                    self.state.catalog_message = Some(message);
+                }
+                let _ = self.state.save();
+            }
+            EntryState::Added => {
+                self.state.catalog_message = Some(format!("{} is already up to date", entry.name));
+            }
+        }
+    }
+
+    /// Full error of the selected session, if it is in error
+    fn open_error_details(&mut self) {
+        if let Some((real_idx, session)) = self.state.selected_session()
+            && matches!(session.status, models::SessionStatus::Error(_))
+        {
+            self.state.current_screen = Screen::ErrorDetails(real_idx);
+        }
+    }
+
+    /// Past runs of the session at `idx`, newest first
+    fn open_run_history(&mut self, idx: usize) {
+        self.state.log_run = None;
+        self.state.run_selected = 0;
+        self.state.current_screen = Screen::RunHistory(idx);
+    }
+
+    fn open_backups(&mut self) {
+        self.state.backups = self.state.storage.session_backups();
+        self.state.backup_selected = 0;
+        self.state.restore_confirmation = false;
+        self.state.current_screen = Screen::Backups;
+    }
+
+    /// Replace the sessions with the highlighted backup. The replaced list
+    /// becomes the newest backup, so a restore can itself be undone.
+    fn restore_backup(&mut self) {
+        self.state.restore_confirmation = false;
+        let Some(backup) = self.state.backups.get(self.state.backup_selected).cloned() else {
+            return;
+        };
+        self.state.sessions = restore::restore(
+            backup.sessions,
+            &mut self.state.sessions,
+            &self.state.process_manager,
+        );
+        let _ = self.state.save();
+        self.state
+            .process_manager
+            .sync_monitored_sessions(&self.state.sessions);
+        self.state.current_screen = Screen::Dashboard;
+    }
+
+    fn open_triage(&mut self) {
+        self.state.triage_selected = 0;
+        self.state.current_screen = Screen::Triage;
+    }
+
+    /// Restart every session in the highlighted triage group
+    fn retry_error_group(&mut self) {
+        let groups = self.state.error_groups();
+        if let Some((_, indices)) = groups.get(self.state.triage_selected) {
+            for &idx in indices {
+                if let Some(session) = self.state.sessions.get_mut(idx) {
+                    let _ = self.state.process_manager.start_session(session);
+                }
+            }
+            let _ = self.state.save();
         }
+        let remaining = self.state.error_groups().len();
+        self.state.triage_selected = self.state.triage_selected.min(remaining.saturating_sub(1));
     }
 
     fn quit(&mut self) {
         self.running = false;
     }
+
+    /// Quit, first asking whether running sessions should be left up. Kept
+    /// ones are picked up again by the next pfman.
+    fn request_quit(&mut self) {
+        if self.state.sessions.iter().any(|s| s.pid.is_some()) {
+            self.state.quit_prompt = true;
+        } else {
+            self.quit();
+        }
+    }
+
+    fn stop_all(&mut self) {
+        for session in self.state.sessions.iter_mut() {
+            if session.pid.is_some() {
+                let _ = self.state.process_manager.stop_session(session);
+            }
+        }
+        // Nothing would be left to see the stragglers out
+        for session in self.state.sessions.iter_mut() {
+            self.state.process_manager.finish_stopping(session);
+        }
+        let _ = self.state.save();
+    }
+
+    /// Write the last status changes before exiting, unless another pfman
+    /// took the sessions over and keeps them up to date itself
+    fn flush_saves(&mut self) {
+        if !self.lost_supervision() {
+            let _ = self.state.flush_saves();
+        }
+    }
+
+    /// Another pfman took over supervision of the sessions
+    fn lost_supervision(&self) -> bool {
+        self.instance.as_ref().is_some_and(|lock| !lock.is_held())
+    }
+
+    /// Explain a quit caused by another instance taking over, once the
+    /// terminal is restored
+    fn report_take_over(&self) {
+        if self.lost_supervision() {
+            eprintln!("Another pfman instance took over; running sessions were left to it.");
+        }
+    }
 }
@@ -1,12 +1,62 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use uuid::Uuid;
 
+/// Value accepted in the local port field to pick a free port on each start
+pub const AUTO_PORT: &str = "auto";
+
+/// Prefix of the short IDs shown for sessions, e.g. `pf-3fa2`
+pub const SHORT_ID_PREFIX: &str = "pf-";
+/// Hex digits of the UUID in a short ID
+const SHORT_ID_LEN: usize = 4;
+
+/// How long after start a session is still considered unverified; also the
+/// time a starting session gets to open its local listener
+pub const VERIFICATION_WINDOW_SECS: i64 = 15;
+
+/// Longest idle stop a session may have, a year in minutes
+pub const MAX_IDLE_TIMEOUT_MINUTES: u64 = 525_600;
+
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(from = "String", into = "String")]
 pub enum SessionType {
     SSH,
     Kubectl,
     Socks5,
+    /// SOCKS proxy opened on the target with `ssh -R <port>`, so the remote
+    /// host reaches the network through this machine
+    ReverseSocks5,
+    /// Forward run by the session provider of this name, see
+    /// [`crate::provider`]
+    Custom(String),
+}
+
+/// Saved as the variant name, or the provider's name for plugin types
+impl From<String> for SessionType {
+    fn from(name: String) -> Self {
+        match name.as_str() {
+            "SSH" => SessionType::SSH,
+            "Kubectl" => SessionType::Kubectl,
+            "Socks5" => SessionType::Socks5,
+            "ReverseSocks5" => SessionType::ReverseSocks5,
+            _ => SessionType::Custom(name),
+        }
+    }
+}
+
+impl From<SessionType> for String {
+    fn from(session_type: SessionType) -> Self {
+        match session_type {
+            SessionType::SSH => "SSH".to_string(),
+            SessionType::Kubectl => "Kubectl".to_string(),
+            SessionType::Socks5 => "Socks5".to_string(),
+            SessionType::ReverseSocks5 => "ReverseSocks5".to_string(),
+            SessionType::Custom(name) => name,
+        }
+    }
 }
 
 impl SessionType {
@@ -15,25 +65,371 @@ impl SessionType {
             SessionType::SSH => "SSH",
             SessionType::Kubectl => "kubectl",
             SessionType::Socks5 => "SOCKS5",
+            SessionType::ReverseSocks5 => "Reverse SOCKS5",
+            SessionType::Custom(name) => name,
         }
     }
+
+    /// Name fitting the session table's type column
+    pub fn short_str(&self) -> &str {
+        match self {
+            SessionType::ReverseSocks5 => "rSOCKS5",
+            _ => self.as_str(),
+        }
+    }
+
+    /// Whether the forward listens on this machine; a reverse proxy only
+    /// listens on the target
+    pub fn listens_locally(&self) -> bool {
+        *self != SessionType::ReverseSocks5
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum SessionStatus {
+    /// Process spawned, waiting for the local port to accept connections
+    Starting,
     Running,
+    /// Process and local port are up, but the end-to-end probe can't get
+    /// through to the remote side
+    Degraded(String),
+    /// Asked to exit, waiting for the process to go away
+    Stopping,
     Stopped,
-    Error(String),
+    Error(SessionError),
 }
 
 impl SessionStatus {
     pub fn as_str(&self) -> &str {
         match self {
+            SessionStatus::Starting => "Starting",
             SessionStatus::Running => "Running",
+            SessionStatus::Degraded(_) => "Degraded",
+            SessionStatus::Stopping => "Stopping",
             SessionStatus::Stopped => "Stopped",
             SessionStatus::Error(_) => "Error",
         }
     }
+
+    /// Process up and the local port accepting connections, whether or not
+    /// the tunnel gets through
+    pub fn is_running(&self) -> bool {
+        matches!(self, SessionStatus::Running | SessionStatus::Degraded(_))
+    }
+}
+
+/// IP version a session uses, both to reach its target and for its local
+/// listener
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AddressFamily {
+    /// Whatever the tools pick; ssh and kubectl listen on both loopbacks
+    #[default]
+    Any,
+    Ipv4,
+    Ipv6,
+}
+
+impl AddressFamily {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AddressFamily::Any => "any",
+            AddressFamily::Ipv4 => "ipv4",
+            AddressFamily::Ipv6 => "ipv6",
+        }
+    }
+
+    /// Loopback addresses the local listener may be on, in the order to try
+    pub fn loopbacks(&self) -> Vec<IpAddr> {
+        let v4 = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let v6 = IpAddr::V6(Ipv6Addr::LOCALHOST);
+        match self {
+            AddressFamily::Any => vec![v4, v6],
+            AddressFamily::Ipv4 => vec![v4],
+            AddressFamily::Ipv6 => vec![v6],
+        }
+    }
+}
+
+impl std::str::FromStr for AddressFamily {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text.trim().to_lowercase().as_str() {
+            "" | "any" => Ok(AddressFamily::Any),
+            "ipv4" | "inet" | "4" => Ok(AddressFamily::Ipv4),
+            "ipv6" | "inet6" | "6" => Ok(AddressFamily::Ipv6),
+            _ => Err(format!(
+                "Invalid address family '{}' (ipv4, ipv6 or any)",
+                text
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for AddressFamily {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// `host` as ssh expects it inside a forward spec: IPv6 addresses in
+/// brackets, e.g. `[fd00::5]`
+pub fn forward_spec_host(host: &str) -> String {
+    if host.contains(':') && !host.starts_with('[') {
+        format!("[{}]", host)
+    } else {
+        host.to_string()
+    }
+}
+
+/// How long a session may stay up, written like `8h`, `90m`, `1h30m` or
+/// `2d`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Lifetime(chrono::Duration);
+
+impl Lifetime {
+    pub fn duration(&self) -> chrono::Duration {
+        self.0
+    }
+}
+
+impl std::str::FromStr for Lifetime {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid lifetime '{}' (e.g. 8h, 90m, 1h30m, 2d)", text);
+        let mut total = chrono::Duration::zero();
+        let mut number = String::new();
+        for c in text.trim().chars() {
+            if c.is_ascii_digit() {
+                number.push(c);
+                continue;
+            }
+            let value: i64 = number.parse().map_err(|_| invalid())?;
+            number.clear();
+            let part = match c {
+                'd' => chrono::Duration::try_days(value),
+                'h' => chrono::Duration::try_hours(value),
+                'm' => chrono::Duration::try_minutes(value),
+                _ => return Err(invalid()),
+            };
+            total = part
+                .and_then(|part| total.checked_add(&part))
+                .ok_or_else(invalid)?;
+        }
+        if !number.is_empty() || total <= chrono::Duration::zero() {
+            return Err(invalid());
+        }
+        // A session started now has to be able to reach its end
+        if Utc::now().checked_add_signed(total).is_none() {
+            return Err(invalid());
+        }
+        Ok(Self(total))
+    }
+}
+
+impl TryFrom<String> for Lifetime {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        text.parse()
+    }
+}
+
+impl From<Lifetime> for String {
+    fn from(lifetime: Lifetime) -> Self {
+        lifetime.to_string()
+    }
+}
+
+impl std::fmt::Display for Lifetime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let minutes = self.0.num_minutes();
+        let parts = [
+            (minutes / 1440, 'd'),
+            (minutes / 60 % 24, 'h'),
+            (minutes % 60, 'm'),
+        ];
+        for (value, unit) in parts.into_iter().filter(|(v, _)| *v > 0) {
+            write!(f, "{}{}", value, unit)?;
+        }
+        Ok(())
+    }
+}
+
+/// Remembers each session's status to tell which ones changed since the
+/// last look
+#[derive(Debug, Default)]
+pub struct StatusTracker {
+    statuses: HashMap<Uuid, SessionStatus>,
+}
+
+impl StatusTracker {
+    /// Sessions whose status differs from the last call, each with its
+    /// previous status. Sessions seen for the first time only set the
+    /// baseline.
+    pub fn changes<'a>(&mut self, sessions: &'a [Session]) -> Vec<(&'a Session, SessionStatus)> {
+        let mut changed = Vec::new();
+        for session in sessions {
+            if let Some(previous) = self.statuses.insert(session.id, session.status.clone())
+                && previous != session.status
+            {
+                changed.push((session, previous));
+            }
+        }
+        self.statuses
+            .retain(|id, _| sessions.iter().any(|s| s.id == *id));
+        changed
+    }
+}
+
+/// Why a session ended up in the Error state
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(from = "SessionErrorRepr")]
+pub struct SessionError {
+    pub kind: ErrorCategory,
+    pub message: String,
+    pub at: DateTime<Utc>,
+    /// Exit code of the process, when pfman saw it exit
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+}
+
+impl SessionError {
+    /// An error that happened now, classified from its message
+    pub fn new(message: impl Into<String>) -> Self {
+        let message = message.into();
+        Self {
+            kind: ErrorCategory::classify(&message),
+            message,
+            at: Utc::now(),
+            exit_code: None,
+        }
+    }
+
+    pub fn with_exit_code(mut self, exit_code: Option<i32>) -> Self {
+        self.exit_code = exit_code;
+        self
+    }
+}
+
+/// State files written before errors were structured hold a bare message
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SessionErrorRepr {
+    Message(String),
+    Full {
+        kind: ErrorCategory,
+        message: String,
+        at: DateTime<Utc>,
+        #[serde(default)]
+        exit_code: Option<i32>,
+    },
+}
+
+impl From<SessionErrorRepr> for SessionError {
+    fn from(repr: SessionErrorRepr) -> Self {
+        match repr {
+            SessionErrorRepr::Message(message) => SessionError::new(message),
+            SessionErrorRepr::Full {
+                kind,
+                message,
+                at,
+                exit_code,
+            } => SessionError {
+                kind,
+                message,
+                at,
+                exit_code,
+            },
+        }
+    }
+}
+
+/// Likely cause of a session error, guessed from the error message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ErrorCategory {
+    Auth,
+    Network,
+    PortConflict,
+    TargetMissing,
+    /// ssh or kubectl isn't installed, or is too old
+    MissingTool,
+    Other,
+}
+
+impl ErrorCategory {
+    pub const ALL: [ErrorCategory; 6] = [
+        ErrorCategory::Network,
+        ErrorCategory::Auth,
+        ErrorCategory::PortConflict,
+        ErrorCategory::TargetMissing,
+        ErrorCategory::MissingTool,
+        ErrorCategory::Other,
+    ];
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            ErrorCategory::Auth => "Authentication",
+            ErrorCategory::Network => "Network",
+            ErrorCategory::PortConflict => "Port conflict",
+            ErrorCategory::TargetMissing => "Target missing",
+            ErrorCategory::MissingTool => "Missing tool",
+            ErrorCategory::Other => "Other",
+        }
+    }
+
+    pub fn classify(message: &str) -> Self {
+        let message = message.to_lowercase();
+        let matches = |patterns: &[&str]| patterns.iter().any(|p| message.contains(p));
+
+        if matches(&["not found on path", "command not found", "is too old for"]) {
+            ErrorCategory::MissingTool
+        } else if matches(&[
+            "address already in use",
+            "is already in use",
+            "cannot listen to port",
+            "could not request local forwarding",
+            "unable to listen on port",
+            "remote port forwarding failed",
+        ]) {
+            ErrorCategory::PortConflict
+        } else if matches(&[
+            "permission denied",
+            "authentication",
+            "unauthorized",
+            "forbidden",
+            "host key verification failed",
+            "too many authentication failures",
+        ]) {
+            ErrorCategory::Auth
+        } else if matches(&[
+            "could not resolve hostname",
+            "name or service not known",
+            "not found",
+            "does not exist",
+            "no such host",
+        ]) {
+            ErrorCategory::TargetMissing
+        } else if matches(&[
+            "connection refused",
+            "connection timed out",
+            "operation timed out",
+            "network is unreachable",
+            "no route to host",
+            "connection reset",
+            "connection closed",
+            "broken pipe",
+            "not accepting connections",
+            "not responding",
+        ]) {
+            ErrorCategory::Network
+        } else {
+            ErrorCategory::Other
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -43,7 +439,18 @@ pub struct Session {
     pub session_type: SessionType,
     pub target: String,
     pub local_port: u16,
+    /// Pick a free local port on every start; `local_port` holds the last one
+    #[serde(default)]
+    pub auto_local_port: bool,
     pub remote_port: Option<u16>,
+    /// Host the far end of an SSH forward connects to, as seen from the
+    /// target, e.g. a database only the bastion can reach; None for the
+    /// target itself
+    #[serde(default)]
+    pub remote_host: Option<String>,
+    /// Use only IPv4 or only IPv6, for the target and the local listener
+    #[serde(default)]
+    pub address_family: AddressFamily,
     pub status: SessionStatus,
     pub pid: Option<u32>,
     pub created_at: DateTime<Utc>,
@@ -53,6 +460,92 @@ pub struct Session {
     pub kube_context: Option<String>,
     #[serde(default)]
     pub kube_namespace: Option<String>,
+    /// Optional icon/emoji shown in front of the name
+    #[serde(default)]
+    pub icon: Option<String>,
+    /// Optional accent color for the name (e.g. "red", "#ff8800")
+    #[serde(default)]
+    pub color: Option<String>,
+    /// Free-form labels, filterable with `tag:<name>` in the search
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Terminal the session was started from, when terminal scoping is on
+    #[serde(default)]
+    pub scope: Option<TerminalScope>,
+    /// Command whose exit code tells whether the forward works, e.g.
+    /// `pg_isready -h 127.0.0.1 -p {local_port}`
+    #[serde(default)]
+    pub health_command: Option<String>,
+    /// Shell command run before each start, e.g. `aws sso login`; the
+    /// session isn't started when it fails
+    #[serde(default)]
+    pub pre_start: Option<String>,
+    /// Shell command run once the session's process has ended, however it
+    /// ended
+    #[serde(default)]
+    pub post_stop: Option<String>,
+    /// Run under a terminal so ssh can ask for a password, key passphrase
+    /// or one-time code, answered from pfman
+    #[serde(default)]
+    pub login_prompts: bool,
+    /// SSH login user, overriding one given in the target or ssh config
+    #[serde(default)]
+    pub user: Option<String>,
+    /// Private key passed to ssh with `-i`
+    #[serde(default)]
+    pub identity_file: Option<String>,
+    /// kubeconfig file for a kubectl session whose cluster isn't in the
+    /// default ones
+    #[serde(default)]
+    pub kubeconfig_path: Option<String>,
+    /// Stop the session once its local port had no connections for this
+    /// many minutes
+    #[serde(default)]
+    pub idle_timeout_minutes: Option<u64>,
+    /// Stop the session once it has been up this long
+    #[serde(default)]
+    pub max_lifetime: Option<Lifetime>,
+    /// Times a kubectl session was forwarded again after losing its pod
+    /// since it was started
+    #[serde(default)]
+    pub reconnects: u32,
+    /// Start the session again, with growing pauses, whenever it fails
+    #[serde(default)]
+    pub auto_restart: bool,
+    /// Stopped by a pause of all sessions; the next resume starts it again
+    #[serde(default)]
+    pub paused: bool,
+    /// Counted over every run since the session was created
+    #[serde(default)]
+    pub stats: SessionStats,
+    /// Automatic restart waiting for its turn after a failure
+    #[serde(skip)]
+    pub pending_restart: Option<PendingRestart>,
+}
+
+/// Running totals kept with the session, so they survive cleared logs
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct SessionStats {
+    pub starts: u64,
+    /// Runs that ended in an error rather than a stop
+    pub crashes: u64,
+    pub uptime_secs: u64,
+}
+
+/// Automatic restart scheduled for a failed session
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingRestart {
+    /// Failures in a row this restart follows, counting from 1
+    pub attempt: u32,
+    pub at: DateTime<Utc>,
+}
+
+/// Terminal (tmux pane or TTY) that owns a running session
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TerminalScope {
+    pub label: String,
+    /// Shell that launched pfman; the scope ends when it exits
+    pub owner_pid: u32,
 }
 
 impl Session {
@@ -69,7 +562,10 @@ impl Session {
             session_type,
             target,
             local_port,
+            auto_local_port: false,
             remote_port,
+            remote_host: None,
+            address_family: AddressFamily::Any,
             status: SessionStatus::Stopped,
             pid: None,
             created_at: Utc::now(),
@@ -77,17 +573,96 @@ impl Session {
             additional_options: Vec::new(),
             kube_context: None,
             kube_namespace: None,
+            icon: None,
+            color: None,
+            tags: Vec::new(),
+            scope: None,
+            health_command: None,
+            pre_start: None,
+            post_stop: None,
+            login_prompts: false,
+            user: None,
+            identity_file: None,
+            kubeconfig_path: None,
+            idle_timeout_minutes: None,
+            max_lifetime: None,
+            reconnects: 0,
+            auto_restart: false,
+            paused: false,
+            stats: SessionStats::default(),
+            pending_restart: None,
+        }
+    }
+
+    pub fn error_category(&self) -> Option<ErrorCategory> {
+        match &self.status {
+            SessionStatus::Error(err) => Some(err.kind),
+            _ => None,
         }
     }
 
     pub fn uptime(&self) -> Option<chrono::Duration> {
-        if let SessionStatus::Running = self.status {
+        if self.status.is_running() {
             self.last_started.map(|start| Utc::now() - start)
         } else {
             None
         }
     }
 
+    /// Running, but still inside the post-start verification window
+    pub fn is_verifying(&self) -> bool {
+        self.uptime()
+            .is_some_and(|d| d.num_seconds() < VERIFICATION_WINDOW_SECS)
+    }
+
+    /// Starting, still verifying, stopping or waiting for an automatic
+    /// restart - shown with an activity indicator
+    pub fn is_transitional(&self) -> bool {
+        matches!(
+            self.status,
+            SessionStatus::Starting | SessionStatus::Stopping
+        ) || self.is_verifying()
+            || self.pending_restart.is_some()
+    }
+
+    /// Human-friendly ID derived from the UUID, stable across restarts
+    pub fn short_id(&self) -> String {
+        let hex = self.id.simple().to_string();
+        format!("{}{}", SHORT_ID_PREFIX, &hex[..SHORT_ID_LEN])
+    }
+
+    /// Whether `reference` is this session's short ID or a longer prefix of
+    /// its UUID (with or without the `pf-` prefix). Callers match names
+    /// first: a name like `cafe` also reads as an ID prefix.
+    pub fn has_id(&self, reference: &str) -> bool {
+        let hex = reference
+            .strip_prefix(SHORT_ID_PREFIX)
+            .unwrap_or(reference)
+            .replace('-', "")
+            .to_lowercase();
+        hex.len() >= SHORT_ID_LEN && self.id.simple().to_string().starts_with(&hex)
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t.eq_ignore_ascii_case(tag))
+    }
+
+    /// Count the run that ended at `at` into the stats: its time since the
+    /// last start, and a crash unless it was stopped
+    pub fn count_run_end(&mut self, at: DateTime<Utc>, crashed: bool) {
+        if let Some(started) = self.last_started {
+            self.stats.uptime_secs += (at - started).num_seconds().max(0) as u64;
+        }
+        if crashed {
+            self.stats.crashes += 1;
+        }
+    }
+
+    /// Running for at least `after`, i.e. possibly a forgotten tunnel
+    pub fn is_overdue(&self, after: chrono::Duration) -> bool {
+        self.status.is_running() && self.uptime().is_some_and(|u| u >= after)
+    }
+
     pub fn uptime_string(&self) -> String {
         if let Some(duration) = self.uptime() {
             let hours = duration.num_hours();
@@ -110,10 +685,218 @@ impl Session {
         }
     }
 
+    pub fn display_name(&self) -> String {
+        match &self.icon {
+            Some(icon) => format!("{} {}", icon, self.name),
+            None => self.name.clone(),
+        }
+    }
+
+    /// Where ssh connects: the target, with the user override in place of
+    /// any user it names
+    pub fn ssh_destination(&self) -> String {
+        let (target_user, host) = match self.target.rsplit_once('@') {
+            Some((user, host)) => (Some(user), host),
+            None => (None, self.target.as_str()),
+        };
+        // ssh takes IPv6 addresses bare, but they are often written in brackets
+        let host = host
+            .strip_prefix('[')
+            .and_then(|h| h.strip_suffix(']'))
+            .unwrap_or(host);
+        match self.user.as_deref().or(target_user) {
+            Some(user) => format!("{}@{}", user, host),
+            None => host.to_string(),
+        }
+    }
+
+    /// Local port for display; "auto" until a port has been allocated
+    pub fn local_port_label(&self) -> String {
+        if self.auto_local_port && self.local_port == 0 {
+            AUTO_PORT.to_string()
+        } else {
+            self.local_port.to_string()
+        }
+    }
+
+    /// Port the forward listens on: the local one, or the one opened on the
+    /// target for a reverse proxy
+    pub fn listen_port(&self) -> u16 {
+        if self.session_type.listens_locally() {
+            self.local_port
+        } else {
+            self.remote_port.unwrap_or(0)
+        }
+    }
+
+    /// Fields that differ in `edited`, for confirming an edit before saving
+    pub fn changes_to(&self, edited: &Session) -> Vec<FieldChange> {
+        fn opt<T: ToString>(value: &Option<T>) -> String {
+            value.as_ref().map(|v| v.to_string()).unwrap_or_default()
+        }
+        fn local_port(session: &Session) -> String {
+            if session.auto_local_port {
+                AUTO_PORT.to_string()
+            } else {
+                session.local_port.to_string()
+            }
+        }
+
+        let fields = [
+            ("Name", self.name.clone(), edited.name.clone()),
+            (
+                "Type",
+                self.session_type.as_str().to_string(),
+                edited.session_type.as_str().to_string(),
+            ),
+            (
+                "Kubeconfig",
+                opt(&self.kubeconfig_path),
+                opt(&edited.kubeconfig_path),
+            ),
+            (
+                "Context",
+                opt(&self.kube_context),
+                opt(&edited.kube_context),
+            ),
+            (
+                "Namespace",
+                opt(&self.kube_namespace),
+                opt(&edited.kube_namespace),
+            ),
+            ("Target", self.target.clone(), edited.target.clone()),
+            ("User", opt(&self.user), opt(&edited.user)),
+            (
+                "Identity File",
+                opt(&self.identity_file),
+                opt(&edited.identity_file),
+            ),
+            ("Local Port", local_port(self), local_port(edited)),
+            (
+                "Remote Host",
+                opt(&self.remote_host),
+                opt(&edited.remote_host),
+            ),
+            (
+                "Remote Port",
+                opt(&self.remote_port),
+                opt(&edited.remote_port),
+            ),
+            (
+                "IP Version",
+                self.address_family.to_string(),
+                edited.address_family.to_string(),
+            ),
+            ("Icon", opt(&self.icon), opt(&edited.icon)),
+            ("Color", opt(&self.color), opt(&edited.color)),
+            ("Tags", self.tags.join(", "), edited.tags.join(", ")),
+            (
+                "Health Check",
+                opt(&self.health_command),
+                opt(&edited.health_command),
+            ),
+            ("Pre-start", opt(&self.pre_start), opt(&edited.pre_start)),
+            ("Post-stop", opt(&self.post_stop), opt(&edited.post_stop)),
+            (
+                "Idle Stop",
+                opt(&self.idle_timeout_minutes),
+                opt(&edited.idle_timeout_minutes),
+            ),
+            (
+                "Max Lifetime",
+                opt(&self.max_lifetime),
+                opt(&edited.max_lifetime),
+            ),
+        ];
+        fields
+            .into_iter()
+            .filter(|(_, old, new)| old != new)
+            .map(|(field, old, new)| FieldChange { field, old, new })
+            .collect()
+    }
+
+    /// Where the forward listens: ssh and kubectl both bind localhost
+    /// unless told otherwise, a reverse proxy localhost on the target
+    pub fn bind_address(&self) -> String {
+        if !self.session_type.listens_locally() {
+            return format!(
+                "localhost:{} on {}",
+                self.remote_port.unwrap_or(0),
+                self.target
+            );
+        }
+        match self.address_family {
+            AddressFamily::Any => format!("localhost:{}", self.local_port_label()),
+            AddressFamily::Ipv4 => format!("127.0.0.1:{}", self.local_port_label()),
+            AddressFamily::Ipv6 => format!("[::1]:{}", self.local_port_label()),
+        }
+    }
+
+    /// Host the forward's far end connects to, from the target's side
+    pub fn forward_host(&self) -> &str {
+        match (&self.session_type, &self.remote_host) {
+            (SessionType::SSH, Some(host)) => host,
+            _ => "localhost",
+        }
+    }
+
     pub fn port_mapping(&self) -> String {
-        match self.session_type {
-            SessionType::Socks5 => format!("{}", self.local_port),
-            _ => format!("{} → {}", self.local_port, self.remote_port.unwrap_or(0)),
+        let remote_port = self.remote_port.unwrap_or(0);
+        match (&self.session_type, &self.remote_host) {
+            (SessionType::Socks5, _) => self.local_port_label(),
+            (SessionType::ReverseSocks5, _) => format!("← {}", remote_port),
+            (SessionType::Custom(_), _) if self.remote_port.is_none() => self.local_port_label(),
+            (SessionType::SSH, Some(host)) => {
+                format!("{} → {}:{}", self.local_port_label(), host, remote_port)
+            }
+            _ => format!("{} → {}", self.local_port_label(), remote_port),
         }
     }
 }
+
+/// A field whose value an edit changes
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldChange {
+    pub field: &'static str,
+    pub old: String,
+    pub new: String,
+}
+
+/// Snapshot of an unsaved session form, persisted periodically so edits
+/// survive a crash or a killed terminal
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FormDraft {
+    /// Session being edited, or None when creating a new one
+    pub editing: Option<Uuid>,
+    pub session_type: SessionType,
+    pub name: String,
+    pub context: String,
+    pub namespace: String,
+    pub target: String,
+    pub local_port: String,
+    pub remote_port: String,
+    pub icon: String,
+    pub color: String,
+    #[serde(default)]
+    pub tags: String,
+    #[serde(default)]
+    pub health_command: String,
+    #[serde(default)]
+    pub pre_start: String,
+    #[serde(default)]
+    pub post_stop: String,
+    #[serde(default)]
+    pub user: String,
+    #[serde(default)]
+    pub identity_file: String,
+    #[serde(default)]
+    pub remote_host: String,
+    #[serde(default)]
+    pub address_family: String,
+    #[serde(default)]
+    pub kubeconfig: String,
+    #[serde(default)]
+    pub idle_timeout: String,
+    #[serde(default)]
+    pub max_lifetime: String,
+}
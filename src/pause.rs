@@ -0,0 +1,155 @@
+//! Pausing: stop every session that is up in one go and later start exactly
+//! that set again, e.g. around switching VPNs. The set is kept as the
+//! `paused` flag of its sessions, so it survives quitting pfman and is
+//! shared by the dashboard (`P`/`r`), the API and `pfman pause`/`resume`.
+
+use crate::instance;
+use crate::ipc;
+use crate::models::{Session, SessionStatus};
+use crate::policy::Policy;
+use crate::process::ProcessManager;
+use crate::provider::ProviderRegistry;
+use crate::storage::Storage;
+use color_eyre::Result;
+use color_eyre::eyre::eyre;
+use serde_json::json;
+use uuid::Uuid;
+
+/// Stop the sessions that are up or waiting for an automatic restart and
+/// mark them paused. Sessions paused earlier stay paused. Returns the
+/// sessions stopped, with the outcome of each stop.
+pub fn pause(
+    sessions: &mut [Session],
+    process_manager: &ProcessManager,
+) -> Vec<(Uuid, Result<()>)> {
+    let mut paused = Vec::new();
+    for session in sessions.iter_mut() {
+        let active = matches!(
+            session.status,
+            SessionStatus::Running | SessionStatus::Degraded(_) | SessionStatus::Starting
+        ) || session.pending_restart.is_some();
+        if !active {
+            continue;
+        }
+        let result = process_manager.stop_session(session);
+        session.pending_restart = None;
+        session.paused = true;
+        paused.push((session.id, result));
+    }
+    paused
+}
+
+/// Start the paused sessions again. A session that fails to start stays
+/// paused, so the next resume tries it again. Returns the sessions
+/// started, with the outcome of each start.
+pub fn resume(
+    sessions: &mut [Session],
+    process_manager: &ProcessManager,
+) -> Vec<(Uuid, Result<()>)> {
+    let mut resumed = Vec::new();
+    for session in sessions.iter_mut().filter(|s| s.paused) {
+        // Started by hand since the pause
+        if session.status.is_running() || session.status == SessionStatus::Starting {
+            session.paused = false;
+            continue;
+        }
+        let result = process_manager.start_session(session);
+        resumed.push((session.id, result));
+    }
+    resumed
+}
+
+/// Entry point for `pfman pause`
+pub fn run_pause(_args: &[String]) -> Result<()> {
+    run("pause")
+}
+
+/// Entry point for `pfman resume`
+pub fn run_resume(_args: &[String]) -> Result<()> {
+    run("resume")
+}
+
+/// Pause or resume through the supervising instance's IPC socket when one
+/// is running, otherwise on the saved sessions directly
+fn run(command: &str) -> Result<()> {
+    let storage = Storage::new()?;
+    let config = storage.load_config()?;
+
+    if let Some(pid) = instance::running_instance(&storage) {
+        let socket = config.ipc_socket.ok_or_else(|| {
+            eyre!(
+                "pfman (PID {}) is managing these sessions; press {} there, or set ipc_socket so this command can ask it",
+                pid,
+                if command == "pause" { "P" } else { "r" }
+            )
+        })?;
+        let body = ipc::request(&socket, json!({ "command": command })).map_err(|e| eyre!(e))?;
+        let mut names = Vec::new();
+        let mut failures = 0;
+        for session in body["sessions"].as_array().into_iter().flatten() {
+            let name = session["name"].as_str().unwrap_or_default();
+            match session["error"]["message"].as_str() {
+                Some(message) if session["status"] == "Error" => {
+                    eprintln!("{}: {}", name, message);
+                    failures += 1;
+                }
+                _ => names.push(name),
+            }
+        }
+        return report(command, &names, failures);
+    }
+
+    let mut sessions = storage.load_sessions()?;
+    let process_manager = ProcessManager::new(storage.clone())
+        .with_policy(Policy::load()?)
+        .with_ssh_options(config.ssh_options)
+        .with_exit_on_forward_failure(config.exit_on_forward_failure)
+        .with_providers(ProviderRegistry::from_config(&config.session_providers));
+    let outcomes = match command {
+        "pause" => pause(&mut sessions, &process_manager),
+        _ => resume(&mut sessions, &process_manager),
+    };
+    // Nothing would be left to see the stragglers out
+    process_manager.finish_pre_starts(&mut sessions);
+    for session in sessions.iter_mut() {
+        process_manager.finish_stopping(session);
+    }
+    storage.save_sessions(&sessions)?;
+
+    let mut names = Vec::new();
+    let mut failures = 0;
+    for (id, result) in &outcomes {
+        let Some(session) = sessions.iter().find(|s| s.id == *id) else {
+            continue;
+        };
+        match result {
+            // A failed pre-start command only shows in the status
+            Ok(()) if let SessionStatus::Error(err) = &session.status => {
+                eprintln!("{}: {}", session.name, err.message);
+                failures += 1;
+            }
+            Ok(()) => names.push(session.name.as_str()),
+            Err(err) => {
+                eprintln!("{}: {}", session.name, err);
+                failures += 1;
+            }
+        }
+    }
+    report(command, &names, failures)
+}
+
+/// Print what was paused or resumed; failures make the command fail, so
+/// scripts notice
+fn report(command: &str, names: &[&str], failures: usize) -> Result<()> {
+    match (command, names) {
+        (_, []) if failures > 0 => {}
+        ("pause", []) => println!("Nothing running to pause"),
+        (_, []) => println!("Nothing paused to resume"),
+        ("pause", names) => println!("Paused {}", names.join(", ")),
+        (_, names) => println!("Resumed {}", names.join(", ")),
+    }
+    if failures > 0 {
+        return Err(eyre!("{} session(s) failed to {}", failures, command));
+    }
+    Ok(())
+}
@@ -0,0 +1,140 @@
+use crate::models::{Session, SessionType};
+use color_eyre::Result;
+use color_eyre::eyre::eyre;
+use serde::Deserialize;
+use std::fs;
+use std::path::PathBuf;
+
+/// System-wide policy location, meant to be root-owned on managed machines
+const POLICY_FILE: &str = "/etc/pfman/policy.yaml";
+/// Overrides the policy location
+const POLICY_ENV: &str = "PFMAN_POLICY";
+
+/// Restrictions on what sessions may be created or started. Target and
+/// context patterns accept `*` wildcards, e.g. `"*prod*"`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Policy {
+    pub deny_session_types: Vec<SessionType>,
+    pub deny_targets: Vec<String>,
+    pub deny_kube_contexts: Vec<String>,
+    pub deny_kube_namespaces: Vec<String>,
+    pub min_local_port: Option<u16>,
+    pub deny_remote_ports: Vec<u16>,
+    /// Reject options that expose the local port beyond localhost
+    pub deny_bind_all: bool,
+}
+
+impl Policy {
+    /// Load the policy, if one is installed. A policy that exists but can't
+    /// be read is an error rather than silently allowing everything.
+    pub fn load() -> Result<Self> {
+        let path = std::env::var(POLICY_ENV)
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| PathBuf::from(POLICY_FILE));
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(&path)
+            .map_err(|e| eyre!("cannot read policy {}: {}", path.display(), e))?;
+        serde_yaml::from_str(&content)
+            .map_err(|e| eyre!("invalid policy {}: {}", path.display(), e))
+    }
+
+    /// Explain why the session isn't allowed, if it isn't
+    pub fn check(&self, session: &Session) -> Result<(), String> {
+        if self.deny_session_types.contains(&session.session_type) {
+            return Err(format!(
+                "{} sessions are not allowed",
+                session.session_type.as_str()
+            ));
+        }
+        if let Some(pattern) = find_match(&self.deny_targets, &session.target) {
+            return Err(format!(
+                "target {} matches denied `{}`",
+                session.target, pattern
+            ));
+        }
+        // A forward through the target can reach a denied host behind it
+        if let Some(host) = &session.remote_host
+            && let Some(pattern) = find_match(&self.deny_targets, host)
+        {
+            return Err(format!("remote host {} matches denied `{}`", host, pattern));
+        }
+        if let Some(context) = &session.kube_context
+            && let Some(pattern) = find_match(&self.deny_kube_contexts, context)
+        {
+            return Err(format!("context {} matches denied `{}`", context, pattern));
+        }
+        if let Some(namespace) = &session.kube_namespace
+            && let Some(pattern) = find_match(&self.deny_kube_namespaces, namespace)
+        {
+            return Err(format!(
+                "namespace {} matches denied `{}`",
+                namespace, pattern
+            ));
+        }
+        if let Some(min) = self.min_local_port
+            && !session.auto_local_port
+            && session.session_type.listens_locally()
+            && session.local_port < min
+        {
+            return Err(format!("local ports below {} are not allowed", min));
+        }
+        if let Some(port) = session.remote_port
+            && self.deny_remote_ports.contains(&port)
+        {
+            return Err(format!("remote port {} is not allowed", port));
+        }
+        if self.deny_bind_all
+            && let Some(opt) = session.additional_options.iter().find(|o| binds_all(o))
+        {
+            return Err(format!("`{}` would expose the port beyond localhost", opt));
+        }
+        Ok(())
+    }
+}
+
+fn find_match<'a>(patterns: &'a [String], value: &str) -> Option<&'a str> {
+    patterns
+        .iter()
+        .find(|p| glob_match(&p.to_lowercase(), &value.to_lowercase()))
+        .map(String::as_str)
+}
+
+/// `*` matches any run of characters, everything else matches literally
+pub(crate) fn glob_match(pattern: &str, value: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return pattern == value;
+    }
+
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+    if !value.starts_with(first) || !value.ends_with(last) || value.len() < first.len() + last.len()
+    {
+        return false;
+    }
+    let mut rest = &value[first.len()..value.len() - last.len()];
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+fn binds_all(option: &str) -> bool {
+    let option = option.to_lowercase();
+    let any_address = |address: &str| matches!(address, "::" | "[::]" | "0.0.0.0");
+    option == "-g"
+        || option.contains("0.0.0.0")
+        || option.contains("gatewayports=yes")
+        || option.starts_with("*:")
+        || option.starts_with("[::]:")
+        // kubectl's --address, given as one argument or two
+        || any_address(&option)
+        || option
+            .strip_prefix("--address=")
+            .is_some_and(|list| list.split(',').any(any_address))
+}
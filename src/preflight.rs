@@ -0,0 +1,113 @@
+//! Pre-flight check run before a session starts: the tool it runs must be
+//! on PATH, and new enough for what the session asks of it. A missing or
+//! outdated tool is reported with what to do about it, rather than as a
+//! spawn failure or an exit with a cryptic log.
+
+use crate::models::{Session, SessionType};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// What a session type needs installed
+struct Requirement {
+    program: &'static str,
+    /// How to get it, shown when it is missing
+    install_hint: &'static str,
+    /// Oldest OpenSSH release that has the feature, and the feature
+    min_openssh: Option<((u32, u32), &'static str)>,
+}
+
+/// Plugin types are checked by their provider, see
+/// [`crate::provider::ProviderRegistry::check`]
+fn requirement(session_type: &SessionType) -> Option<Requirement> {
+    Some(match session_type {
+        SessionType::SSH | SessionType::Socks5 => Requirement {
+            program: "ssh",
+            install_hint: "install the OpenSSH client",
+            min_openssh: None,
+        },
+        SessionType::ReverseSocks5 => Requirement {
+            program: "ssh",
+            install_hint: "install the OpenSSH client",
+            min_openssh: Some(((7, 6), "reverse SOCKS5 proxies")),
+        },
+        SessionType::Kubectl => Requirement {
+            program: "kubectl",
+            install_hint: "install kubectl, see https://kubernetes.io/docs/tasks/tools/",
+            min_openssh: None,
+        },
+        SessionType::Custom(_) => return None,
+    })
+}
+
+/// Whether the tool `session` runs is installed and recent enough, with
+/// the reason and a fix when it isn't
+pub fn check(session: &Session) -> Result<(), String> {
+    let Some(requirement) = requirement(&session.session_type) else {
+        return Ok(());
+    };
+    let Some(path) = find_program(requirement.program) else {
+        return Err(format!(
+            "{} not found on PATH: {}",
+            requirement.program, requirement.install_hint
+        ));
+    };
+    if let Some((min, feature)) = requirement.min_openssh {
+        // Versions that can't be read are given the benefit of the doubt,
+        // e.g. ssh implementations other than OpenSSH
+        if let Some(version) = openssh_version(&path)
+            && version < min
+        {
+            return Err(format!(
+                "OpenSSH {}.{} at {} is too old for {} (needs {}.{} or later): upgrade the OpenSSH client",
+                version.0,
+                version.1,
+                path.display(),
+                feature,
+                min.0,
+                min.1
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Where `program` would be run from, looking through PATH like the
+/// shell does
+pub fn find_program(program: &str) -> Option<PathBuf> {
+    let paths = std::env::var_os("PATH")?;
+    std::env::split_paths(&paths)
+        .flat_map(|dir| {
+            let plain = dir.join(program);
+            let exe = dir.join(format!("{}.exe", program));
+            [plain, exe]
+        })
+        .find(|candidate| is_executable(candidate))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+/// Major and minor version of the OpenSSH client at `path`, from the
+/// `OpenSSH_9.6p1 Ubuntu-3ubuntu13, OpenSSL ...` line `ssh -V` prints
+fn openssh_version(path: &Path) -> Option<(u32, u32)> {
+    let output = Command::new(path).arg("-V").output().ok()?;
+    // Printed on stderr, though some builds use stdout
+    let text = format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stderr),
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let version = text.split("OpenSSH_").nth(1)?;
+    let (major, rest) = version.split_once('.')?;
+    let minor: String = rest.chars().take_while(char::is_ascii_digit).collect();
+    Some((major.parse().ok()?, minor.parse().ok()?))
+}
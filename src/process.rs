@@ -1,15 +1,100 @@
-use crate::models::{Session, SessionStatus, SessionType};
+use crate::activity::{self, PortActivity};
+use crate::audit::{self, AuditAction};
+use crate::capture;
+use crate::health::{self, HEALTH_INTERVAL, HealthCheck, HealthChecks, TunnelChecks};
+use crate::heartbeat::StatusReport;
+use crate::kube_config::expand_home;
+use crate::models::{
+    AddressFamily, ErrorCategory, Lifetime, Session, SessionError, SessionStatus, SessionType,
+    TerminalScope, VERIFICATION_WINDOW_SECS, forward_spec_host,
+};
+use crate::policy::Policy;
+use crate::preflight;
+use crate::provider::ProviderRegistry;
+use crate::pty::{self, Terminals};
+use crate::runs::{self, LIFECYCLE_MARKER, RunEvent, RunRecord};
 use crate::storage::Storage;
+use crate::vars;
 use chrono::{DateTime, Utc};
 use color_eyre::Result;
+use color_eyre::eyre::eyre;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::{self, Receiver, Sender};
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, Weak};
 use std::thread;
-use std::time::Duration;
-use sysinfo::{ProcessRefreshKind, RefreshKind, System};
+use std::time::{Duration, Instant};
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System};
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender, unbounded_channel};
 use uuid::Uuid;
 
+/// Written after the timestamp of every line the session wrote to stderr
+pub const STDERR_MARKER: &str = "[err] ";
+
+/// Pod re-forwards in a row that may end before the session stays up for
+/// STABLE_AFTER_SECS; after that the failure is reported like any other
+const REFORWARD_LIMIT: u32 = 5;
+const STABLE_AFTER_SECS: i64 = 30;
+
+/// How long a stop waits for the process to exit before reporting the
+/// session as Stopping
+const STOP_WAIT: Duration = Duration::from_millis(500);
+/// Time a stopping process gets to exit before it is killed outright
+const STOP_GRACE_SECS: i64 = 5;
+
+/// How often running sessions' local ports are timed
+const LATENCY_INTERVAL: Duration = Duration::from_secs(10);
+/// Connects slower than this count as failed probes
+const LATENCY_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Time a reverse proxy has to stay connected before it counts as up. ssh
+/// exits on its own when the target refuses the forward, but says nothing
+/// when it accepts it.
+const REMOTE_FORWARD_SETTLE_SECS: i64 = 5;
+/// Longest a session's pre-start or post-stop command may run
+const SESSION_COMMAND_TIMEOUT: Duration = Duration::from_secs(30);
+/// Keepalives for reverse proxies, which have no local port to probe: ssh
+/// exits, and the session shows as failed, once the target stops answering
+const REVERSE_KEEPALIVE_OPTIONS: [&str; 2] = ["ServerAliveInterval=15", "ServerAliveCountMax=3"];
+
+/// Last measured TCP connect time to each running session's local port
+pub type Latencies = Arc<Mutex<HashMap<Uuid, Duration>>>;
+
+/// Connections through each running session's local port, sampled with
+/// the latencies
+pub type Activity = Arc<Mutex<HashMap<Uuid, PortActivity>>>;
+
+/// Exit codes of session processes reaped by this pfman instance, by PID
+type ExitCodes = Arc<Mutex<HashMap<u32, i32>>>;
+
+/// Where the monitor writes the status report, if anywhere
+type StatusFile = Arc<Mutex<Option<PathBuf>>>;
+
+/// State the process manager shares with its monitor thread
+#[derive(Clone, Default)]
+pub(crate) struct MonitorShared {
+    latencies: Latencies,
+    activity: Activity,
+    exit_codes: ExitCodes,
+    status_file: StatusFile,
+    health: HealthChecks,
+    terminals: Terminals,
+    /// Probe running forwards end to end with the latencies
+    verify_tunnels: Arc<AtomicBool>,
+    tunnels: TunnelChecks,
+}
+
+/// A start waiting for the session's pre-start command
+struct PendingStart {
+    cmd: Command,
+    /// Run log name, see [`Storage::new_run_log`]
+    log: String,
+}
+
 #[derive(Debug, Clone)]
 pub struct StatusUpdate {
     pub session_id: Uuid,
@@ -22,6 +107,55 @@ pub(crate) struct MonitoredSession {
     id: Uuid,
     pid: Option<u32>,
     started_at: Option<DateTime<Utc>>,
+    local_port: u16,
+    /// Listens on `local_port`, so it can be timed and watched for
+    /// connections; false for a reverse proxy
+    local_listener: bool,
+    address_family: AddressFamily,
+    /// Process is alive but the local listener hasn't been confirmed yet
+    starting: bool,
+    /// Health command with the ports filled in
+    health_command: Option<String>,
+    /// kubectl forward, which breaks when its pod goes away
+    kubectl: bool,
+    max_lifetime: Option<Lifetime>,
+    /// When the session was asked to stop, if it's still exiting
+    stopping_since: Option<DateTime<Utc>>,
+    /// Killed outright after not exiting within STOP_GRACE_SECS
+    force_killed: bool,
+    /// Forwards a single port, so a probe can tell whether it gets through
+    /// (a SOCKS proxy needs a destination first)
+    probe_remote: bool,
+    /// Last reported as Degraded
+    degraded: bool,
+    /// Post-stop command with its placeholders filled in
+    post_stop: Option<String>,
+}
+
+impl MonitoredSession {
+    fn from_session(session: &Session) -> Self {
+        Self {
+            id: session.id,
+            pid: session.pid,
+            started_at: session.last_started,
+            local_port: session.local_port,
+            local_listener: session.session_type.listens_locally(),
+            address_family: session.address_family,
+            starting: session.status == SessionStatus::Starting,
+            health_command: health::command_for(session),
+            kubectl: session.session_type == SessionType::Kubectl,
+            max_lifetime: session.max_lifetime,
+            stopping_since: (session.status == SessionStatus::Stopping).then(Utc::now),
+            force_killed: false,
+            probe_remote: session.session_type == SessionType::SSH
+                || session.session_type == SessionType::Kubectl,
+            degraded: matches!(session.status, SessionStatus::Degraded(_)),
+            post_stop: session
+                .post_stop
+                .as_deref()
+                .map(|command| health::fill_placeholders(command, session)),
+        }
+    }
 }
 
 pub struct StatusMonitor {
@@ -30,15 +164,16 @@ pub struct StatusMonitor {
 }
 
 impl StatusMonitor {
-    pub fn new(
+    pub(crate) fn new(
         sessions: Arc<Mutex<Vec<MonitoredSession>>>,
         storage: Storage,
-        update_sender: Sender<StatusUpdate>,
+        update_sender: UnboundedSender<StatusUpdate>,
+        shared: MonitorShared,
     ) -> Self {
         let (shutdown_sender, shutdown_receiver) = mpsc::channel();
 
         let thread_handle = thread::spawn(move || {
-            Self::monitor_loop(sessions, storage, update_sender, shutdown_receiver);
+            Self::monitor_loop(sessions, storage, update_sender, shared, shutdown_receiver);
         });
 
         Self {
@@ -50,9 +185,25 @@ impl StatusMonitor {
     fn monitor_loop(
         sessions: Arc<Mutex<Vec<MonitoredSession>>>,
         storage: Storage,
-        update_sender: Sender<StatusUpdate>,
+        update_sender: UnboundedSender<StatusUpdate>,
+        shared: MonitorShared,
         shutdown_receiver: Receiver<()>,
     ) {
+        let MonitorShared {
+            latencies,
+            activity,
+            exit_codes,
+            status_file,
+            health,
+            terminals,
+            verify_tunnels,
+            tunnels,
+        } = shared;
+        let mut last_latency_probe: Option<Instant> = None;
+        // When each session's health command last ran
+        let mut health_checked: HashMap<Uuid, Instant> = HashMap::new();
+        // Kept between ticks, holding only the monitored processes
+        let mut sys = System::new();
         loop {
             // Get snapshot of sessions
             let sessions_snapshot = {
@@ -61,65 +212,187 @@ impl StatusMonitor {
             };
 
             let now = Utc::now();
-            let mut sys = System::new_with_specifics(
-                RefreshKind::new().with_processes(ProcessRefreshKind::everything()),
-            );
-            sys.refresh_processes(sysinfo::ProcessesToUpdate::All, true);
+            let pids: Vec<Pid> = sessions_snapshot
+                .iter()
+                .filter_map(|s| s.pid)
+                .map(Pid::from_u32)
+                .collect();
+            Self::refresh_processes(&mut sys, &pids);
 
             let mut crashed_sessions = Vec::new();
+            let mut listening_sessions = Vec::new();
+            let mut force_killed_sessions = Vec::new();
 
             for session in sessions_snapshot {
+                if let Some(pid) = session.pid
+                    && let Some(since) = session.stopping_since
+                {
+                    if !is_alive(&sys, pid) {
+                        runs::record(
+                            &storage,
+                            &session.id,
+                            RunRecord::new(RunEvent::Stopped, now, pid),
+                        );
+                        let _ = update_sender.send(StatusUpdate {
+                            session_id: session.id,
+                            status: SessionStatus::Stopped,
+                            pid: None,
+                        });
+                        crashed_sessions.push(session.id);
+                    } else if !session.force_killed
+                        && (now - since).num_seconds() >= STOP_GRACE_SECS
+                    {
+                        let _ = force_kill_process(pid);
+                        let _ = storage.append_log(
+                            &session.id,
+                            &format!(
+                                "Still running {}s after being asked to stop; killing it\n",
+                                STOP_GRACE_SECS
+                            ),
+                        );
+                        force_killed_sessions.push(session.id);
+                    }
+                    continue;
+                }
                 if let Some(pid) = session.pid {
                     // Check if process still exists
-                    if sys.process(sysinfo::Pid::from_u32(pid)).is_none() {
-                        // Process died - write separator and read error from logs
-                        let crashed_at = now;
-                        let separator = format!(
-                            "\n{}\nSession Crashed/Exited: {} | PID: {}\n{}\n\n",
-                            "=".repeat(80),
-                            crashed_at.format("%Y-%m-%d %H:%M:%S"),
-                            pid,
-                            "=".repeat(80)
+                    if sys.process(Pid::from_u32(pid)).is_none() {
+                        // Process died - read error from logs, then record the crash
+                        let mut error_msg = Self::get_last_log_lines(&storage, &session.id, 3)
+                            .unwrap_or_else(|_| "Process terminated".to_string());
+                        let exit_code = exit_codes.lock().unwrap().remove(&pid);
+                        runs::record(
+                            &storage,
+                            &session.id,
+                            RunRecord::new(RunEvent::Crashed, now, pid).with_exit_code(exit_code),
                         );
-                        let _ = storage.append_log(&session.id, &separator);
 
-                        let error_msg = Self::get_last_log_lines(&storage, &session.id, 3)
-                            .unwrap_or_else(|_| "Process terminated".to_string());
+                        if session.local_listener
+                            && ErrorCategory::classify(&error_msg) == ErrorCategory::PortConflict
+                        {
+                            error_msg = format!(
+                                "Local port {} is already in use: {}",
+                                session.local_port, error_msg
+                            );
+                        }
 
                         let _ = update_sender.send(StatusUpdate {
                             session_id: session.id,
-                            status: SessionStatus::Error(error_msg),
+                            status: SessionStatus::Error(
+                                SessionError::new(error_msg).with_exit_code(exit_code),
+                            ),
                             pid: None,
                         });
 
                         crashed_sessions.push(session.id);
                     } else {
-                        // Check if recently started (within 15 seconds) - verify it's stable
+                        // Older kubectl keeps running after losing its pod, forwarding
+                        // nothing. Ending it reports the loss like an exit would.
+                        if session.kubectl
+                            && !session.starting
+                            && Self::get_last_log_lines(&storage, &session.id, 3)
+                                .is_ok_and(|tail| lost_pod(&tail))
+                        {
+                            let _ = kill_process(pid);
+                            continue;
+                        }
+
+                        // Access to sensitive targets may only stay open so long
+                        if let Some(lifetime) = session.max_lifetime
+                            && session
+                                .started_at
+                                .is_some_and(|t| now - t >= lifetime.duration())
+                        {
+                            let _ = kill_process(pid);
+                            runs::record(
+                                &storage,
+                                &session.id,
+                                RunRecord::new(RunEvent::Stopped, now, pid).with_reason(format!(
+                                    "reached its max lifetime of {}",
+                                    lifetime
+                                )),
+                            );
+                            pty::close(&terminals, &session.id);
+
+                            let _ = update_sender.send(StatusUpdate {
+                                session_id: session.id,
+                                status: SessionStatus::Stopped,
+                                pid: None,
+                            });
+                            crashed_sessions.push(session.id);
+                            continue;
+                        }
+
+                        // A live PID doesn't mean the tunnel works - wait until the
+                        // local port accepts connections before reporting Running
+                        if session.starting {
+                            // Time spent waiting on a login prompt doesn't count
+                            let since =
+                                pty::answered_at(&terminals, &session.id).max(session.started_at);
+                            let elapsed = since.map(|t| (now - t).num_seconds());
+                            let prompting = pty::prompt(&terminals, &session.id).is_some();
+                            let up = if session.local_listener {
+                                Self::is_listening(session.local_port, session.address_family)
+                            } else {
+                                !prompting
+                                    && elapsed.is_some_and(|e| e >= REMOTE_FORWARD_SETTLE_SECS)
+                            };
+                            if up {
+                                let _ = update_sender.send(StatusUpdate {
+                                    session_id: session.id,
+                                    status: SessionStatus::Running,
+                                    pid: Some(pid),
+                                });
+                                listening_sessions.push(session.id);
+                            } else if !prompting
+                                && elapsed.is_some_and(|e| e >= VERIFICATION_WINDOW_SECS)
+                            {
+                                let _ = kill_process(pid);
+                                let message = format!(
+                                    "Local port {} not accepting connections after {}s",
+                                    session.local_port, VERIFICATION_WINDOW_SECS
+                                );
+                                runs::record(
+                                    &storage,
+                                    &session.id,
+                                    RunRecord::new(RunEvent::FailedHealthCheck, now, pid)
+                                        .with_reason(message.clone()),
+                                );
+
+                                let _ = update_sender.send(StatusUpdate {
+                                    session_id: session.id,
+                                    status: SessionStatus::Error(SessionError::new(message)),
+                                    pid: None,
+                                });
+                                crashed_sessions.push(session.id);
+                            }
+                        }
+
+                        // Check if recently started (within verification window) - verify it's stable
                         if let Some(started_at) = session.started_at {
                             let elapsed = now - started_at;
-                            if elapsed.num_seconds() < 15 {
+                            if elapsed.num_seconds() < VERIFICATION_WINDOW_SECS {
                                 // Still in verification window - ensure it stays running
-                                if sys.process(sysinfo::Pid::from_u32(pid)).is_none() {
-                                    // Process died early - write separator
-                                    let crashed_at = now;
-                                    let separator = format!(
-                                        "\n{}\nSession Failed Early: {} | PID: {}\n{}\n\n",
-                                        "=".repeat(80),
-                                        crashed_at.format("%Y-%m-%d %H:%M:%S"),
-                                        pid,
-                                        "=".repeat(80)
-                                    );
-                                    let _ = storage.append_log(&session.id, &separator);
-
+                                if sys.process(Pid::from_u32(pid)).is_none() {
+                                    // Process died early - record it
                                     let error_msg =
                                         Self::get_last_log_lines(&storage, &session.id, 3)
                                             .unwrap_or_else(|_| {
                                                 "Process exited shortly after start".to_string()
                                             });
 
+                                    let exit_code = exit_codes.lock().unwrap().remove(&pid);
+                                    runs::record(
+                                        &storage,
+                                        &session.id,
+                                        RunRecord::new(RunEvent::FailedEarly, now, pid)
+                                            .with_exit_code(exit_code),
+                                    );
                                     let _ = update_sender.send(StatusUpdate {
                                         session_id: session.id,
-                                        status: SessionStatus::Error(error_msg),
+                                        status: SessionStatus::Error(
+                                            SessionError::new(error_msg).with_exit_code(exit_code),
+                                        ),
                                         pid: None,
                                     });
 
@@ -135,36 +408,214 @@ impl StatusMonitor {
             {
                 let mut monitored = sessions.lock().unwrap();
                 if !crashed_sessions.is_empty() {
+                    for session in monitored.iter() {
+                        if crashed_sessions.contains(&session.id)
+                            && let Some(command) = &session.post_stop
+                        {
+                            spawn_post_stop(&storage, session.id, command.clone());
+                        }
+                    }
                     monitored.retain(|s| !crashed_sessions.contains(&s.id));
                 }
                 // Also remove any sessions without PIDs (shouldn't happen but good cleanup)
                 monitored.retain(|s| s.pid.is_some());
+                for session in monitored.iter_mut() {
+                    if listening_sessions.contains(&session.id) {
+                        session.starting = false;
+                    }
+                    if force_killed_sessions.contains(&session.id) {
+                        session.force_killed = true;
+                    }
+                }
+            }
+
+            if last_latency_probe.is_none_or(|at| at.elapsed() >= LATENCY_INTERVAL) {
+                last_latency_probe = Some(Instant::now());
+                let running: Vec<(Uuid, u16, AddressFamily)> = sessions
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .filter(|s| !s.starting && s.local_listener)
+                    .map(|s| (s.id, s.local_port, s.address_family))
+                    .collect();
+                // Sampled before the latency probes, whose connections
+                // would otherwise count
+                let ports: Vec<u16> = running.iter().map(|(_, port, _)| *port).collect();
+                let connections = activity::established_connections(&ports);
+                {
+                    let mut activity = activity.lock().unwrap();
+                    activity.retain(|id, _| running.iter().any(|(r, _, _)| r == id));
+                    for (id, port, _) in &running {
+                        let count = connections.get(port).copied().unwrap_or(0);
+                        let entry = activity.entry(*id).or_insert(PortActivity {
+                            connections: 0,
+                            last_active: None,
+                        });
+                        entry.connections = count;
+                        if count > 0 {
+                            entry.last_active = Some(Utc::now());
+                        }
+                    }
+                }
+                let measured: HashMap<Uuid, Duration> = running
+                    .into_iter()
+                    .filter_map(|(id, port, family)| {
+                        Self::connect_time(port, family).map(|t| (id, t))
+                    })
+                    .collect();
+                *latencies.lock().unwrap() = measured;
+
+                // Each probe waits a moment for the far end, so they run on
+                // their own threads like the health commands
+                if verify_tunnels.load(Ordering::Relaxed) {
+                    let probed: Vec<(Uuid, u16, AddressFamily)> = sessions
+                        .lock()
+                        .unwrap()
+                        .iter()
+                        .filter(|s| !s.starting && s.stopping_since.is_none() && s.probe_remote)
+                        .map(|s| (s.id, s.local_port, s.address_family))
+                        .collect();
+                    for (id, port, family) in probed {
+                        let tunnels = Arc::clone(&tunnels);
+                        let sessions = Arc::clone(&sessions);
+                        thread::spawn(move || {
+                            let result = health::probe_tunnel(port, family).err();
+                            if sessions.lock().unwrap().iter().any(|s| s.id == id) {
+                                tunnels.lock().unwrap().insert(id, result);
+                            }
+                        });
+                    }
+                }
+            }
+
+            // Report forwards whose end-to-end probe changed its verdict
+            {
+                let results = tunnels.lock().unwrap().clone();
+                let mut monitored = sessions.lock().unwrap();
+                tunnels
+                    .lock()
+                    .unwrap()
+                    .retain(|id, _| monitored.iter().any(|s| s.id == *id));
+                for session in monitored
+                    .iter_mut()
+                    .filter(|s| !s.starting && s.stopping_since.is_none())
+                {
+                    let (Some(pid), Some(result)) = (session.pid, results.get(&session.id)) else {
+                        continue;
+                    };
+                    if result.is_some() == session.degraded {
+                        continue;
+                    }
+                    session.degraded = result.is_some();
+                    let (status, note) = match result {
+                        Some(reason) => (
+                            SessionStatus::Degraded(reason.clone()),
+                            format!("Process up but tunnel broken: {}\n", reason),
+                        ),
+                        None => (SessionStatus::Running, "Tunnel works again\n".to_string()),
+                    };
+                    let _ = storage.append_log(&session.id, &note);
+                    let _ = update_sender.send(StatusUpdate {
+                        session_id: session.id,
+                        status,
+                        pid: Some(pid),
+                    });
+                }
+            }
+
+            // First check as soon as the session is up, then every interval.
+            // Each gets its own thread and times out well before the next one
+            // is due, so runs never pile up.
+            let checked: Vec<(Uuid, String)> = sessions
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|s| !s.starting)
+                .filter_map(|s| Some((s.id, s.health_command.clone()?)))
+                .collect();
+            health_checked.retain(|id, _| checked.iter().any(|(c, _)| c == id));
+            health
+                .lock()
+                .unwrap()
+                .retain(|id, _| checked.iter().any(|(c, _)| c == id));
+            for (id, command) in checked {
+                let due = health_checked
+                    .get(&id)
+                    .is_none_or(|at| at.elapsed() >= HEALTH_INTERVAL);
+                if due {
+                    health_checked.insert(id, Instant::now());
+                    let health = Arc::clone(&health);
+                    let sessions = Arc::clone(&sessions);
+                    thread::spawn(move || {
+                        let result = health::check(&command);
+                        // Skip the result if the session stopped meanwhile
+                        if sessions.lock().unwrap().iter().any(|s| s.id == id) {
+                            health.lock().unwrap().insert(id, result);
+                        }
+                    });
+                }
+            }
+
+            let status_path = status_file.lock().unwrap().clone();
+            if let Some(path) = status_path
+                && let Ok(all_sessions) = storage.load_sessions()
+            {
+                let report = StatusReport::new(&all_sessions, &latencies.lock().unwrap())
+                    .with_health(&health.lock().unwrap());
+                let _ = report.write(&path);
             }
 
-            // Wait for 2 seconds or until shutdown signal (whichever comes first)
-            match shutdown_receiver.recv_timeout(Duration::from_secs(2)) {
+            // Probe more often while a session is starting so Running shows up promptly
+            let any_starting = sessions.lock().unwrap().iter().any(|s| s.starting);
+            let interval = if any_starting {
+                Duration::from_millis(500)
+            } else {
+                Duration::from_secs(2)
+            };
+
+            // Wait for the interval or until shutdown signal (whichever comes first)
+            match shutdown_receiver.recv_timeout(interval) {
                 Ok(_) | Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => break,
                 Err(std::sync::mpsc::RecvTimeoutError::Timeout) => continue,
             }
         }
     }
 
+    /// Bring the monitor's process table up to date for `pids`, dropping
+    /// those that exited. Only the monitored processes are read, so a busy
+    /// machine doesn't cost a scan of every process on each tick.
+    pub(crate) fn refresh_processes(sys: &mut System, pids: &[Pid]) {
+        sys.refresh_processes_specifics(
+            ProcessesToUpdate::Some(pids),
+            true,
+            ProcessRefreshKind::new(),
+        );
+    }
+
+    fn connect_time(port: u16, family: AddressFamily) -> Option<Duration> {
+        let started = Instant::now();
+        health::connect_loopback(port, family, LATENCY_TIMEOUT).ok()?;
+        Some(started.elapsed())
+    }
+
+    fn is_listening(port: u16, family: AddressFamily) -> bool {
+        health::connect_loopback(port, family, Duration::from_millis(300)).is_ok()
+    }
+
     fn get_last_log_lines(storage: &Storage, session_id: &Uuid, lines: usize) -> Result<String> {
-        let log_content = storage.read_logs(session_id)?;
+        // What it wrote since the collector last ran
+        capture::collect(&storage.log_file(session_id), false);
+        let log_content = storage.read_log_tail(session_id, lines)?;
         if log_content.is_empty() {
             return Ok("Process exited without output".to_string());
         }
 
-        let last_lines: Vec<&str> = log_content
+        let result = log_content
             .lines()
-            .rev()
-            .take(lines)
+            .filter(|line| !line.trim().is_empty() && !line.contains(LIFECYCLE_MARKER))
+            .map(|line| line.replacen(STDERR_MARKER, "", 1))
             .collect::<Vec<_>>()
-            .into_iter()
-            .rev()
-            .collect();
-
-        let result = last_lines.join(" ");
+            .join(" ");
         if result.len() > 100 {
             Ok(format!("...{}", &result[result.len() - 100..]))
         } else {
@@ -192,130 +643,566 @@ impl Drop for StatusMonitor {
 pub struct ProcessManager {
     storage: Storage,
     monitored_sessions: Arc<Mutex<Vec<MonitoredSession>>>,
-    update_receiver: Receiver<StatusUpdate>,
+    /// Status updates from the monitor, until an event loop takes them
+    update_receiver: Mutex<Option<UnboundedReceiver<StatusUpdate>>>,
+    /// For the pre-start commands, which report through the same channel
+    update_sender: UnboundedSender<StatusUpdate>,
+    /// Sessions whose pre-start command is running, spawned once it is done
+    pre_starts: Mutex<HashMap<Uuid, PendingStart>>,
     _status_monitor: StatusMonitor,
+    /// Spawn simulated listeners instead of real ssh/kubectl (demo mode)
+    simulate: bool,
+    policy: Policy,
+    /// Tag started sessions with this terminal (terminal scoping)
+    scope: Option<TerminalScope>,
+    /// Global options for every ssh command, after the session's own
+    ssh_options: Vec<String>,
+    /// Add `-o ExitOnForwardFailure=yes` so a failed bind ends ssh
+    exit_on_forward_failure: bool,
+    shared: MonitorShared,
+    /// Re-forwards since each kubectl session last stayed up
+    reforward_streaks: Mutex<HashMap<Uuid, u32>>,
+    /// Plugins running the sessions of other types
+    providers: ProviderRegistry,
 }
 
 impl ProcessManager {
     pub fn new(storage: Storage) -> Self {
         let monitored_sessions = Arc::new(Mutex::new(Vec::new()));
-        let (update_sender, update_receiver) = mpsc::channel();
+        let (update_sender, update_receiver) = unbounded_channel();
+        let shared = MonitorShared::default();
 
         let status_monitor = StatusMonitor::new(
             Arc::clone(&monitored_sessions),
             storage.clone(),
-            update_sender,
+            update_sender.clone(),
+            shared.clone(),
         );
+        spawn_output_collector(Arc::downgrade(&monitored_sessions), storage.clone());
 
         Self {
             storage,
             monitored_sessions,
-            update_receiver,
+            update_receiver: Mutex::new(Some(update_receiver)),
+            update_sender,
+            pre_starts: Mutex::new(HashMap::new()),
             _status_monitor: status_monitor,
+            simulate: false,
+            policy: Policy::default(),
+            scope: None,
+            ssh_options: Vec::new(),
+            exit_on_forward_failure: true,
+            shared,
+            reforward_streaks: Mutex::new(HashMap::new()),
+            providers: ProviderRegistry::default(),
         }
     }
 
+    pub fn with_scope(mut self, scope: Option<TerminalScope>) -> Self {
+        self.scope = scope;
+        self
+    }
+
+    pub fn scope(&self) -> Option<&TerminalScope> {
+        self.scope.as_ref()
+    }
+
+    /// Options added to every SSH/SOCKS5 command. Bare `Key=value` entries
+    /// become `-o Key=value`; a session setting the same key wins.
+    pub fn with_ssh_options(mut self, options: Vec<String>) -> Self {
+        self.ssh_options = options;
+        self
+    }
+
+    /// Session providers for the plugin session types
+    pub fn with_providers(mut self, providers: ProviderRegistry) -> Self {
+        self.providers = providers;
+        self
+    }
+
+    pub fn providers(&self) -> &ProviderRegistry {
+        &self.providers
+    }
+
+    /// Make ssh exit when a forward can't be set up (on by default), so a
+    /// busy local port shows up as an error instead of a dead tunnel
+    pub fn with_exit_on_forward_failure(mut self, enabled: bool) -> Self {
+        self.exit_on_forward_failure = enabled;
+        self
+    }
+
+    /// Probe running forwards end to end, reporting those that don't get
+    /// through to the remote side as Degraded
+    pub fn with_tunnel_verification(self, enabled: bool) -> Self {
+        self.shared.verify_tunnels.store(enabled, Ordering::Relaxed);
+        self
+    }
+
+    /// Have the monitor keep a JSON status report at this path
+    pub fn with_status_file(self, path: Option<PathBuf>) -> Self {
+        *self.shared.status_file.lock().unwrap() = path;
+        self
+    }
+
+    /// Refuse to start sessions the policy denies
+    pub fn with_policy(mut self, policy: Policy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    pub fn policy(&self) -> &Policy {
+        &self.policy
+    }
+
+    /// TCP connect time to each running session's local port, refreshed
+    /// every few seconds by the status monitor
+    pub fn latencies(&self) -> HashMap<Uuid, Duration> {
+        self.shared.latencies.lock().unwrap().clone()
+    }
+
+    /// Connections through each running session's local port at the last
+    /// sample
+    pub fn activity(&self) -> HashMap<Uuid, PortActivity> {
+        self.shared.activity.lock().unwrap().clone()
+    }
+
+    /// Latest health command result of each running session that has one
+    pub fn health_checks(&self) -> HashMap<Uuid, HealthCheck> {
+        self.shared.health.lock().unwrap().clone()
+    }
+
+    /// Sessions run as the current executable started with
+    /// [`crate::demo::LISTENER_FLAG`], which its `main` has to hand to
+    /// [`crate::demo::run_listener`]
+    pub fn simulated(storage: Storage) -> Self {
+        Self {
+            simulate: true,
+            ..Self::new(storage)
+        }
+    }
+
+    pub fn is_simulated(&self) -> bool {
+        self.simulate
+    }
+
     pub fn sync_monitored_sessions(&self, sessions: &[Session]) {
         let mut monitored = self.monitored_sessions.lock().unwrap();
         monitored.clear();
         for session in sessions {
-            monitored.push(MonitoredSession {
-                id: session.id,
-                pid: session.pid,
-                started_at: session.last_started,
-            });
+            monitored.push(MonitoredSession::from_session(session));
+        }
+    }
+
+    /// Check the PIDs saved with the sessions against the process table.
+    /// A process still running the session's forward (same program, target
+    /// and local port) is adopted as Running, whatever state was saved, e.g.
+    /// when an earlier pfman quit and left it up; one that was stopping is
+    /// left to the monitor to see out. A PID that is gone or now
+    /// belongs to another program is dropped and the session marked Stopped.
+    /// Returns whether any session changed.
+    pub fn adopt_sessions(&self, sessions: &mut [Session]) -> bool {
+        let pids: Vec<u32> = sessions.iter().filter_map(|s| s.pid).collect();
+        let sys = processes(&pids);
+        let mut changed = false;
+        for session in sessions.iter_mut() {
+            let Some(pid) = session.pid else {
+                // pfman quit while its pre-start command ran
+                if session.status == SessionStatus::Starting {
+                    session.status = SessionStatus::Stopped;
+                    changed = true;
+                }
+                continue;
+            };
+            if !self.runs_session(&sys, pid, session) {
+                // What it wrote after the last pfman quit
+                capture::collect(&self.storage.log_file(&session.id), true);
+                session.status = SessionStatus::Stopped;
+                session.pid = None;
+                session.scope = None;
+                changed = true;
+            } else if !session.status.is_running() && session.status != SessionStatus::Stopping {
+                session.status = SessionStatus::Running;
+                changed = true;
+            }
+        }
+        if changed {
+            self.sync_monitored_sessions(sessions);
         }
+        changed
     }
 
+    /// Whether process `pid` is this session's forward. Only the program,
+    /// target and local port are compared: global options may have changed
+    /// since it was started.
+    fn runs_session(&self, sys: &System, pid: u32, session: &Session) -> bool {
+        let Some(process) = sys.process(Pid::from_u32(pid)) else {
+            return false;
+        };
+        let Ok(session) = &vars::expand_session(session) else {
+            return false;
+        };
+        let Ok(expected) = self.build_unexpanded_command(session) else {
+            return false;
+        };
+        let program_name = |program: &std::ffi::OsStr| {
+            std::path::Path::new(program)
+                .file_name()
+                .map(|name| name.to_os_string())
+        };
+        let args = process.cmd();
+        let Some(program) = args.first() else {
+            return false;
+        };
+        let port = session.listen_port().to_string();
+        let destination = session.ssh_destination();
+        program_name(program) == program_name(expected.get_program())
+            && (self.simulate
+                || args
+                    .iter()
+                    .any(|arg| *arg == *session.target || *arg == *destination)
+                || matches!(session.session_type, SessionType::Custom(_))
+                    && args
+                        .iter()
+                        .any(|arg| arg.to_string_lossy().contains(&session.target)))
+            && args.iter().any(|arg| arg.to_string_lossy().contains(&port))
+    }
+
+    /// Apply the status updates the monitor sent since the last call;
+    /// returns whether any session changed. Nothing arrives here once an
+    /// event loop took the updates with [`Self::take_status_updates`].
     pub fn poll_status_updates(&self, sessions: &mut [Session]) -> bool {
         let mut updated = false;
-        while let Ok(update) = self.update_receiver.try_recv() {
-            if let Some(session) = sessions.iter_mut().find(|s| s.id == update.session_id) {
+        let mut receiver = self.update_receiver.lock().unwrap();
+        let Some(receiver) = receiver.as_mut() else {
+            return false;
+        };
+        while let Ok(update) = receiver.try_recv() {
+            updated |= self.apply_status_update(sessions, update);
+        }
+        updated
+    }
+
+    /// Wait for the pre-start commands of `sessions` that are still running
+    /// and spawn or fail their sessions, for callers without an event loop
+    pub fn finish_pre_starts(&self, sessions: &mut [Session]) {
+        loop {
+            let waiting = {
+                let pre_starts = self.pre_starts.lock().unwrap();
+                sessions.iter().any(|s| pre_starts.contains_key(&s.id))
+            };
+            if !waiting || self.update_receiver.lock().unwrap().is_none() {
+                return;
+            }
+            self.poll_status_updates(sessions);
+            thread::sleep(Duration::from_millis(100));
+        }
+    }
+
+    /// Receive the monitor's status updates as they are sent, to be
+    /// applied with [`Self::apply_status_update`]
+    pub fn take_status_updates(&self) -> Option<UnboundedReceiver<StatusUpdate>> {
+        self.update_receiver.lock().unwrap().take()
+    }
+
+    /// Apply one status update from the monitor; returns whether the
+    /// session changed
+    pub fn apply_status_update(&self, sessions: &mut [Session], update: StatusUpdate) -> bool {
+        let Some(session) = sessions.iter_mut().find(|s| s.id == update.session_id) else {
+            return false;
+        };
+        // Sent about a process that has been stopped or replaced since
+        if update.pid.is_some() && update.pid != session.pid {
+            return false;
+        }
+        // A pre-start command is done; the session may have been stopped since
+        if update.pid.is_none() && session.pid.is_none() {
+            let pending = self.pre_starts.lock().unwrap().remove(&session.id);
+            let Some(pending) = pending else {
+                return false;
+            };
+            if update.status != SessionStatus::Starting {
                 session.status = update.status;
-                session.pid = update.pid;
-                updated = true;
+            } else if let Err(err) = self.spawn_session(session, pending) {
+                session.status = SessionStatus::Error(SessionError::new(err.to_string()));
             }
+            return true;
         }
-        updated
+        // The run is over: reported without a PID while one was up
+        let ended = update.pid.is_none() && session.pid.is_some();
+        if ended {
+            session.count_run_end(Utc::now(), false);
+        }
+        if let SessionStatus::Error(err) = &update.status
+            && session.session_type == SessionType::Kubectl
+            && lost_pod(&err.message)
+            && self.reforward(session)
+        {
+            return true;
+        }
+        if ended && matches!(update.status, SessionStatus::Error(_)) {
+            session.stats.crashes += 1;
+        }
+        session.status = update.status;
+        session.pid = update.pid;
+        true
     }
 
-    pub fn start_session(&self, session: &mut Session) -> Result<()> {
-        let started_at = Utc::now();
+    /// Start a kubectl session again after its pod went away, so it
+    /// forwards to whichever pod backs the target now. It stays Running,
+    /// on the same local port, with one more reconnect counted.
+    fn reforward(&self, session: &mut Session) -> bool {
+        let stable = session
+            .last_started
+            .is_some_and(|t| (Utc::now() - t).num_seconds() >= STABLE_AFTER_SECS);
+        {
+            let mut streaks = self.reforward_streaks.lock().unwrap();
+            let streak = streaks.entry(session.id).or_insert(0);
+            if stable {
+                *streak = 0;
+            }
+            if *streak >= REFORWARD_LIMIT {
+                streaks.remove(&session.id);
+                return false;
+            }
+            *streak += 1;
+        }
 
-        let mut cmd = match session.session_type {
-            SessionType::SSH => self.build_ssh_command(session),
-            SessionType::Kubectl => self.build_kubectl_command(session),
-            SessionType::Socks5 => self.build_socks5_command(session),
+        let _ = self.storage.append_log(
+            &session.id,
+            "Lost the pod; forwarding to the target again\n",
+        );
+        let reconnects = session.reconnects;
+        let auto_local_port = session.auto_local_port;
+        session.auto_local_port = false;
+        // The lost pod took the old process down with it
+        session.pid = None;
+        let started = self.start_session(session);
+        session.auto_local_port = auto_local_port;
+        if started.is_err() {
+            return false;
+        }
+        session.reconnects = reconnects + 1;
+        // Left Starting while its pre-start command runs
+        if session.pid.is_some() {
+            session.status = SessionStatus::Running;
+            self.sync_monitored_session(session);
+        }
+        true
+    }
+
+    fn sync_monitored_session(&self, session: &Session) {
+        // A probe of the previous process mustn't judge this one
+        self.shared.tunnels.lock().unwrap().remove(&session.id);
+        let mut monitored = self.monitored_sessions.lock().unwrap();
+        monitored.retain(|s| s.id != session.id);
+        monitored.push(MonitoredSession::from_session(session));
+    }
+
+    pub fn start_session(&self, session: &mut Session) -> Result<()> {
+        // The old process may still hold the local port
+        if session.status == SessionStatus::Stopping {
+            return Err(eyre!("{} is still stopping", session.name));
+        }
+        // A second process would fight the first over the local port
+        if session.pid.is_some()
+            && (session.status.is_running() || session.status == SessionStatus::Starting)
+        {
+            return Err(eyre!("{} is already running", session.name));
+        }
+        if self.pre_starts.lock().unwrap().contains_key(&session.id) {
+            return Err(eyre!("{} is already starting", session.name));
+        }
+        let expanded = match vars::expand_session(session) {
+            Ok(expanded) => expanded,
+            Err(message) => {
+                session.status = SessionStatus::Error(SessionError::new(message.clone()));
+                return Err(eyre!(message));
+            }
         };
+        if let Err(reason) = self.policy.check(&expanded) {
+            let message = format!("Blocked by policy: {}", reason);
+            session.status = SessionStatus::Error(SessionError::new(message.clone()));
+            return Err(eyre!(message));
+        }
+        if !self.simulate
+            && let Err(message) =
+                preflight::check(&expanded).and_then(|()| self.providers.check(&expanded))
+        {
+            session.status = SessionStatus::Error(SessionError::new(message.clone()));
+            return Err(eyre!(message));
+        }
 
-        let log_file = std::fs::OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(self.storage.log_file(&session.id))?;
+        let started_at = Utc::now();
 
-        cmd.stdout(Stdio::from(log_file.try_clone()?))
-            .stderr(Stdio::from(log_file));
+        if !session.session_type.listens_locally() {
+            // Nothing to take here; the port is opened on the target
+        } else if session.auto_local_port {
+            session.local_port = allocate_free_port(session.address_family)?;
+        } else if port_in_use(session.local_port, session.address_family) {
+            let message = format!("Local port {} is already in use", session.local_port);
+            session.status = SessionStatus::Error(SessionError::new(message.clone()));
+            return Err(eyre!(message));
+        }
 
-        let child = cmd.spawn()?;
-        let pid = child.id();
+        let cmd = self.build_command(session)?;
+        let log = self.storage.new_run_log(&session.id, started_at)?;
+        let pending = PendingStart { cmd, log };
+        session.reconnects = 0;
+        if let Some(command) = &session.pre_start {
+            let command = health::fill_placeholders(command, session);
+            let log_file = self.storage.run_log_file(&session.id, &pending.log);
+            self.pre_starts.lock().unwrap().insert(session.id, pending);
+            // Spawned when the command reports back, see apply_status_update
+            session.status = SessionStatus::Starting;
+            spawn_pre_start(self.update_sender.clone(), session.id, command, log_file);
+            return Ok(());
+        }
+        self.spawn_session(session, pending)
+    }
+
+    /// Spawn the forward of a start that passed its checks and pre-start
+    /// command, logging to the run log created for it
+    fn spawn_session(&self, session: &mut Session, pending: PendingStart) -> Result<()> {
+        let PendingStart { cmd, log } = pending;
+        let started_at = Utc::now();
+        let command = command_line(&cmd);
+        let log_file = self.storage.run_log_file(&session.id, &log);
+        let spawned = if session.login_prompts && !self.simulate {
+            self.spawn_with_terminal(session, &cmd, &log_file)
+        } else {
+            self.spawn_with_captured_output(cmd, &log_file)
+        };
+        let pid = match spawned {
+            Ok(pid) => pid,
+            Err(err) => {
+                self.storage.discard_run_log(&session.id, &log);
+                return Err(err);
+            }
+        };
         session.pid = Some(pid);
-        session.status = SessionStatus::Running;
+        // Promoted to Running by the monitor once the local port accepts connections
+        session.status = SessionStatus::Starting;
         session.last_started = Some(started_at);
+        session.scope = self.scope.clone();
+        session.paused = false;
+        session.stats.starts += 1;
 
-        // Write separator with timestamp and PID
-        let separator = format!(
-            "\n{}\nSession Started: {} | PID: {}\n{}\n",
-            "=".repeat(80),
-            started_at.format("%Y-%m-%d %H:%M:%S"),
-            pid,
-            "=".repeat(80)
+        runs::record(
+            &self.storage,
+            &session.id,
+            RunRecord::new(RunEvent::Started, started_at, pid)
+                .with_log(log)
+                .with_command(command),
         );
-        let _ = self.storage.append_log(&session.id, &separator);
+        audit::record(&self.storage, AuditAction::Start, session);
 
         // Update monitored sessions immediately
-        let mut monitored = self.monitored_sessions.lock().unwrap();
-        if let Some(existing) = monitored.iter_mut().find(|s| s.id == session.id) {
-            existing.pid = Some(pid);
-            existing.started_at = session.last_started;
-        } else {
-            monitored.push(MonitoredSession {
-                id: session.id,
-                pid: Some(pid),
-                started_at: session.last_started,
-            });
-        }
+        self.sync_monitored_session(session);
 
         Ok(())
     }
 
-    pub fn stop_session(&self, session: &mut Session) -> Result<()> {
-        if let Some(pid) = session.pid {
-            #[cfg(unix)]
+    /// Run the session with its output going into files that are copied
+    /// into its log with timestamps (see [`crate::capture`]), so it keeps
+    /// running and writing after pfman quits
+    fn spawn_with_captured_output(&self, mut cmd: Command, log_file: &Path) -> Result<u32> {
+        let (stdout, stderr) = capture::open(log_file)?;
+        cmd.stdout(Stdio::from(stdout)).stderr(Stdio::from(stderr));
+        // Own process group, so helpers the tool spawns are stopped with it
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(&mut cmd, 0);
+
+        let mut child = cmd.spawn()?;
+        let pid = child.id();
+        // Reap the session so the monitor sees it exit, keeping its exit code
+        let exit_codes = Arc::clone(&self.shared.exit_codes);
+        thread::spawn(move || {
+            if let Ok(status) = child.wait()
+                && let Some(code) = status.code()
             {
-                use std::process::Command;
-                Command::new("kill").arg(pid.to_string()).output()?;
+                exit_codes.lock().unwrap().insert(pid, code);
             }
-            #[cfg(windows)]
-            {
-                use std::process::Command;
-                Command::new("taskkill")
-                    .args(["/PID", &pid.to_string(), "/F"])
-                    .output()?;
+        });
+        Ok(pid)
+    }
+
+    /// Run the session under a terminal so it can ask for a password or
+    /// one-time code. The terminal makes it a session leader, and with that
+    /// the leader of its own process group.
+    fn spawn_with_terminal(
+        &self,
+        session: &Session,
+        cmd: &Command,
+        log_file: &Path,
+    ) -> Result<u32> {
+        let log = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(log_file)?;
+        let mut child = pty::spawn(&self.shared.terminals, session.id, cmd, log)?;
+        let pid = child
+            .process_id()
+            .ok_or_else(|| eyre!("session process has no PID"))?;
+        let exit_codes = Arc::clone(&self.shared.exit_codes);
+        thread::spawn(move || {
+            if let Ok(status) = child.wait() {
+                exit_codes
+                    .lock()
+                    .unwrap()
+                    .insert(pid, status.exit_code() as i32);
+            }
+        });
+        Ok(pid)
+    }
+
+    /// A session waiting on a password, passphrase or one-time code, with
+    /// the question ssh asked
+    pub fn login_prompt(&self, sessions: &[Session]) -> Option<(Uuid, String)> {
+        sessions
+            .iter()
+            .filter(|s| s.pid.is_some())
+            .find_map(|s| Some((s.id, pty::prompt(&self.shared.terminals, &s.id)?)))
+    }
+
+    pub fn answer_login_prompt(&self, session_id: &Uuid, answer: &str) -> Result<()> {
+        pty::answer(&self.shared.terminals, session_id, answer)
+    }
+
+    pub fn stop_session(&self, session: &mut Session) -> Result<()> {
+        // Its pre-start command runs out, but the forward isn't spawned
+        self.pre_starts.lock().unwrap().remove(&session.id);
+        // A reused PID must not take an unrelated process down with it
+        if let Some(pid) = session.pid
+            && self.runs_session(&single_process(pid), pid, session)
+        {
+            kill_process(pid)?;
+            audit::record(&self.storage, AuditAction::Stop, session);
+
+            // Most forwards exit right away; the monitor sees the rest out
+            if !wait_for_exit(pid, STOP_WAIT) {
+                pty::close(&self.shared.terminals, &session.id);
+                session.status = SessionStatus::Stopping;
+                self.sync_monitored_session(session);
+                return Ok(());
             }
 
-            // Write separator for manual stop
-            let stopped_at = Utc::now();
-            let separator = format!(
-                "\n{}\nSession Stopped: {} | PID: {}\n{}\n\n",
-                "=".repeat(80),
-                stopped_at.format("%Y-%m-%d %H:%M:%S"),
-                pid,
-                "=".repeat(80)
+            runs::record(
+                &self.storage,
+                &session.id,
+                RunRecord::new(RunEvent::Stopped, Utc::now(), pid),
             );
-            let _ = self.storage.append_log(&session.id, &separator);
+        }
+        pty::close(&self.shared.terminals, &session.id);
+        if session.pid.is_some() {
+            session.count_run_end(Utc::now(), false);
+            if let Some(command) = &session.post_stop {
+                let command = health::fill_placeholders(command, session);
+                spawn_post_stop(&self.storage, session.id, command);
+            }
         }
         session.status = SessionStatus::Stopped;
         session.pid = None;
+        session.scope = None;
 
         // Remove from monitored sessions (no need to monitor stopped sessions)
         let mut monitored = self.monitored_sessions.lock().unwrap();
@@ -324,27 +1211,117 @@ impl ProcessManager {
         Ok(())
     }
 
+    /// Kill a session that is still stopping outright, for when nothing
+    /// will be left to see it out (e.g. on quit)
+    pub fn finish_stopping(&self, session: &mut Session) {
+        if session.status != SessionStatus::Stopping {
+            return;
+        }
+        if let Some(pid) = session.pid
+            && self.runs_session(&single_process(pid), pid, session)
+        {
+            let _ = force_kill_process(pid);
+            wait_for_exit(pid, STOP_WAIT);
+        }
+        if session.pid.is_some() {
+            session.count_run_end(Utc::now(), false);
+            // Waited for, as the monitor that would run it is going away
+            if let Some(command) = &session.post_stop {
+                let command = health::fill_placeholders(command, session);
+                let log_file = self.storage.log_file(&session.id);
+                let _ = run_session_command("post-stop", &command, &log_file);
+            }
+        }
+        session.status = SessionStatus::Stopped;
+        session.pid = None;
+        session.scope = None;
+        let mut monitored = self.monitored_sessions.lock().unwrap();
+        monitored.retain(|s| s.id != session.id);
+    }
+
+    /// Configured global options, then the built-in defaults
+    fn global_ssh_options(&self) -> Vec<String> {
+        let mut options = self.ssh_options.clone();
+        if self.exit_on_forward_failure {
+            options.push("ExitOnForwardFailure=yes".to_string());
+        }
+        options
+    }
+
+    /// The command for `session` with its `${VAR}` references expanded
+    fn build_command(&self, session: &Session) -> Result<Command> {
+        let session = vars::expand_session(session).map_err(|e| eyre!(e))?;
+        self.build_unexpanded_command(&session)
+    }
+
+    fn build_unexpanded_command(&self, session: &Session) -> Result<Command> {
+        Ok(match session.session_type {
+            _ if self.simulate => self.build_simulated_command(session)?,
+            SessionType::SSH => self.build_ssh_command(session),
+            SessionType::Kubectl => self.build_kubectl_command(session),
+            SessionType::Socks5 => self.build_socks5_command(session),
+            SessionType::ReverseSocks5 => self.build_reverse_socks5_command(session),
+            SessionType::Custom(_) => self.providers.build_command(session)?,
+        })
+    }
+
+    /// The command line a start would run, with global options merged in.
+    /// References to unset variables are shown as written.
+    pub fn command_preview(&self, session: &Session) -> String {
+        let cmd = self
+            .build_command(session)
+            .or_else(|_| self.build_unexpanded_command(session));
+        let Ok(cmd) = cmd else {
+            return String::new();
+        };
+        command_line(&cmd)
+    }
+
+    fn build_simulated_command(&self, session: &Session) -> Result<Command> {
+        let mut cmd = Command::new(std::env::current_exe()?);
+        cmd.arg(crate::demo::LISTENER_FLAG)
+            .arg(session.local_port.to_string());
+
+        // Demo sessions carry their scripted behaviour in the options
+        for opt in &session.additional_options {
+            cmd.arg(opt);
+        }
+
+        Ok(cmd)
+    }
+
     fn build_ssh_command(&self, session: &Session) -> Command {
         let mut cmd = Command::new("ssh");
         cmd.arg("-L")
             .arg(format!(
-                "{}:localhost:{}",
+                "{}{}:{}:{}",
+                local_bind_prefix(session.address_family),
                 session.local_port,
+                forward_spec_host(session.forward_host()),
                 session.remote_port.unwrap_or(0)
             ))
-            .arg(&session.target)
-            .arg("-N");
-
-        for opt in &session.additional_options {
-            cmd.arg(opt);
+            .arg(session.ssh_destination())
+            .arg("-N")
+            .args(ssh_family_flag(session.address_family));
+        if let Some(identity_file) = &session.identity_file {
+            cmd.arg("-i").arg(identity_file);
         }
 
+        cmd.args(merge_ssh_options(
+            &session.additional_options,
+            &self.global_ssh_options(),
+        ));
+
         cmd
     }
 
     fn build_kubectl_command(&self, session: &Session) -> Command {
         let mut cmd = Command::new("kubectl");
 
+        if let Some(path) = &session.kubeconfig_path {
+            cmd.arg("--kubeconfig").arg(expand_home(path));
+        }
+
         // Add context if specified
         if let Some(ctx) = &session.kube_context {
             cmd.arg("--context").arg(ctx);
@@ -361,6 +1338,16 @@ impl ProcessManager {
             session.remote_port.unwrap_or(0)
         ));
 
+        // kubectl listens on both loopbacks unless given an address
+        let address_set = session
+            .additional_options
+            .iter()
+            .any(|opt| opt.starts_with("--address"));
+        if session.address_family != AddressFamily::Any && !address_set {
+            let loopback = session.address_family.loopbacks()[0];
+            cmd.arg("--address").arg(loopback.to_string());
+        }
+
         for opt in &session.additional_options {
             cmd.arg(opt);
         }
@@ -371,14 +1358,398 @@ impl ProcessManager {
     fn build_socks5_command(&self, session: &Session) -> Command {
         let mut cmd = Command::new("ssh");
         cmd.arg("-D")
-            .arg(session.local_port.to_string())
-            .arg(&session.target)
-            .arg("-N");
+            .arg(format!(
+                "{}{}",
+                local_bind_prefix(session.address_family),
+                session.local_port
+            ))
+            .arg(session.ssh_destination())
+            .arg("-N")
+            .args(ssh_family_flag(session.address_family));
+        if let Some(identity_file) = &session.identity_file {
+            cmd.arg("-i").arg(identity_file);
+        }
 
-        for opt in &session.additional_options {
-            cmd.arg(opt);
+        cmd.args(merge_ssh_options(
+            &session.additional_options,
+            &self.global_ssh_options(),
+        ));
+
+        cmd
+    }
+
+    /// `ssh -R <port>` without a destination makes ssh a SOCKS proxy for
+    /// connections to that port on the target (OpenSSH 7.6 and later)
+    fn build_reverse_socks5_command(&self, session: &Session) -> Command {
+        let mut cmd = Command::new("ssh");
+        cmd.arg("-R")
+            .arg(session.remote_port.unwrap_or(0).to_string())
+            .arg(session.ssh_destination())
+            .arg("-N")
+            .args(ssh_family_flag(session.address_family));
+        if let Some(identity_file) = &session.identity_file {
+            cmd.arg("-i").arg(identity_file);
         }
 
+        let mut global = self.global_ssh_options();
+        global.extend(REVERSE_KEEPALIVE_OPTIONS.map(str::to_string));
+        cmd.args(merge_ssh_options(&session.additional_options, &global));
+
         cmd
     }
 }
+
+/// `-4` or `-6`, making ssh use one IP version for the connection and
+/// its listeners
+fn ssh_family_flag(family: AddressFamily) -> Option<&'static str> {
+    match family {
+        AddressFamily::Any => None,
+        AddressFamily::Ipv4 => Some("-4"),
+        AddressFamily::Ipv6 => Some("-6"),
+    }
+}
+
+/// Listen address in front of a local forward's port, so only the chosen
+/// family's loopback is bound
+fn local_bind_prefix(family: AddressFamily) -> &'static str {
+    match family {
+        AddressFamily::Any => "",
+        AddressFamily::Ipv4 => "127.0.0.1:",
+        AddressFamily::Ipv6 => "[::1]:",
+    }
+}
+
+/// Session options followed by the global ones, minus global `-o` options
+/// whose key the session already sets (ssh uses the first value it sees)
+fn merge_ssh_options(session: &[String], global: &[String]) -> Vec<String> {
+    let mut merged = session.to_vec();
+    let session_keys: Vec<String> = ssh_option_keys(session).collect();
+
+    let mut global_args = Vec::new();
+    for opt in global {
+        if opt.starts_with('-') {
+            global_args.push(opt.clone());
+        } else {
+            global_args.push("-o".to_string());
+            global_args.push(opt.clone());
+        }
+    }
+
+    let mut args = global_args.into_iter().peekable();
+    while let Some(arg) = args.next() {
+        if arg == "-o"
+            && let Some(value) = args.next_if(|v| !v.starts_with('-'))
+        {
+            if !session_keys.contains(&option_key(&value)) {
+                merged.push(arg);
+                merged.push(value);
+            }
+        } else if let Some(value) = arg.strip_prefix("-o")
+            && session_keys.contains(&option_key(value))
+        {
+            continue;
+        } else {
+            merged.push(arg);
+        }
+    }
+    merged
+}
+
+/// Lowercased keys of the `-o Key=value` / `-oKey=value` options in `args`
+fn ssh_option_keys(args: &[String]) -> impl Iterator<Item = String> + '_ {
+    args.iter().enumerate().filter_map(|(i, arg)| {
+        if arg == "-o" {
+            args.get(i + 1).map(|v| option_key(v))
+        } else {
+            arg.strip_prefix("-o").map(option_key)
+        }
+    })
+}
+
+fn option_key(option: &str) -> String {
+    option
+        .split(['=', ' '])
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_lowercase()
+}
+
+/// `cmd` as one line, quoting arguments that are empty or hold whitespace
+fn command_line(cmd: &Command) -> String {
+    std::iter::once(cmd.get_program())
+        .chain(cmd.get_args())
+        .map(|arg| {
+            let arg = arg.to_string_lossy();
+            if arg.is_empty() || arg.contains(char::is_whitespace) {
+                format!("'{}'", arg)
+            } else {
+                arg.into_owned()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Copy the output of the monitored sessions into their logs until the
+/// process manager is dropped
+fn spawn_output_collector(sessions: Weak<Mutex<Vec<MonitoredSession>>>, storage: Storage) {
+    thread::spawn(move || {
+        while let Some(sessions) = sessions.upgrade() {
+            let ids: Vec<Uuid> = sessions.lock().unwrap().iter().map(|s| s.id).collect();
+            drop(sessions);
+            for id in ids {
+                capture::collect(&storage.log_file(&id), false);
+            }
+            thread::sleep(capture::COLLECT_INTERVAL);
+        }
+    });
+}
+
+/// Run a session's pre-start command on a thread of its own. It reports
+/// back as a status update without a PID: Starting to spawn the session,
+/// an error if the command failed.
+fn spawn_pre_start(
+    sender: UnboundedSender<StatusUpdate>,
+    session_id: Uuid,
+    command: String,
+    log_file: PathBuf,
+) {
+    thread::spawn(move || {
+        let status = match run_session_command("pre-start", &command, &log_file) {
+            Ok(()) => SessionStatus::Starting,
+            Err(reason) => {
+                SessionStatus::Error(SessionError::new(format!("Pre-start command {}", reason)))
+            }
+        };
+        let _ = sender.send(StatusUpdate {
+            session_id,
+            status,
+            pid: None,
+        });
+    });
+}
+
+/// Run a session's post-stop command on a thread of its own
+fn spawn_post_stop(storage: &Storage, session_id: Uuid, command: String) {
+    let log_file = storage.log_file(&session_id);
+    thread::spawn(move || {
+        let _ = run_session_command("post-stop", &command, &log_file);
+    });
+}
+
+/// Run a session's pre-start or post-stop command through `sh -c`, its
+/// output going into the session's log like the session's own. The error
+/// says how it failed, e.g. `exited with code 1`.
+fn run_session_command(label: &str, command: &str, log_file: &Path) -> Result<(), String> {
+    let open_log = || {
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(log_file)
+            .map_err(|e| e.to_string())
+    };
+    let mut log = open_log()?;
+    let lifecycle = |log: &mut std::fs::File, text: String| {
+        let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S");
+        let _ = writeln!(log, "[{}] {}{}", timestamp, LIFECYCLE_MARKER, text);
+    };
+    lifecycle(&mut log, format!("{}: {}", label, command));
+
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("could not be run: {}", e))?;
+    let mut pumps = Vec::new();
+    if let Some(stdout) = child.stdout.take() {
+        pumps.push(spawn_line_pump(stdout, open_log()?, ""));
+    }
+    if let Some(stderr) = child.stderr.take() {
+        pumps.push(spawn_line_pump(stderr, open_log()?, STDERR_MARKER));
+    }
+
+    let started = Instant::now();
+    let failure = loop {
+        match child.try_wait() {
+            Ok(Some(status)) if status.success() => break None,
+            Ok(Some(status)) => {
+                break Some(match status.code() {
+                    Some(code) => format!("exited with code {}", code),
+                    None => "was killed by a signal".to_string(),
+                });
+            }
+            Ok(None) if started.elapsed() < SESSION_COMMAND_TIMEOUT => {
+                thread::sleep(Duration::from_millis(100))
+            }
+            _ => {
+                let _ = child.kill();
+                let _ = child.wait();
+                break Some(format!(
+                    "timed out after {}s",
+                    SESSION_COMMAND_TIMEOUT.as_secs()
+                ));
+            }
+        }
+    };
+    // Let the output land before the outcome, but don't join: something
+    // the command left running may hold the pipes
+    let drained = Instant::now();
+    while !pumps.iter().all(|pump| pump.is_finished())
+        && drained.elapsed() < Duration::from_millis(500)
+    {
+        thread::sleep(Duration::from_millis(10));
+    }
+    match failure {
+        None => Ok(()),
+        Some(reason) => {
+            lifecycle(&mut log, format!("{} command {}", label, reason));
+            Err(reason)
+        }
+    }
+}
+
+/// Copy lines from `reader` to `writer` on a reader thread, prefixing each
+/// with the time it was read so drops can be correlated with incidents, and
+/// with `marker`
+fn spawn_line_pump<R, W>(reader: R, mut writer: W, marker: &'static str) -> thread::JoinHandle<()>
+where
+    R: Read + Send + 'static,
+    W: Write + Send + 'static,
+{
+    thread::spawn(move || {
+        let mut reader = BufReader::new(reader);
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            match reader.read_until(b'\n', &mut line) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {
+                    let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S");
+                    let mut entry = format!("[{}] {}", timestamp, marker).into_bytes();
+                    entry.extend_from_slice(&line);
+                    if !line.ends_with(b"\n") {
+                        entry.push(b'\n');
+                    }
+                    // One write per line, so the stdout and stderr pumps
+                    // appending to the same file don't split each other's lines
+                    let _ = writer.write_all(&entry);
+                    let _ = writer.flush();
+                }
+            }
+        }
+    })
+}
+
+/// kubectl output when the forwarded pod is gone, e.g. deleted during a
+/// rollout or restarted
+fn lost_pod(output: &str) -> bool {
+    let output = output.to_lowercase();
+    ["lost connection to pod", "failed to find sandbox"]
+        .iter()
+        .any(|p| output.contains(p))
+}
+
+/// Whether something holds `port` on one of the family's loopbacks. A
+/// loopback the machine doesn't have, like `::1` with IPv6 disabled,
+/// doesn't count.
+fn port_in_use(port: u16, family: AddressFamily) -> bool {
+    family.loopbacks().into_iter().any(|ip| {
+        TcpListener::bind((ip, port)).is_err_and(|e| {
+            !matches!(
+                e.kind(),
+                std::io::ErrorKind::AddrNotAvailable | std::io::ErrorKind::Unsupported
+            )
+        })
+    })
+}
+
+/// Ask the OS for a free port by binding port 0, one free on all of the
+/// family's loopbacks. The listener is dropped right away so the
+/// forwarding process can bind the port itself.
+fn allocate_free_port(family: AddressFamily) -> Result<u16> {
+    let first = family.loopbacks()[0];
+    for _ in 0..10 {
+        let port = TcpListener::bind((first, 0))?.local_addr()?.port();
+        if !port_in_use(port, family) {
+            return Ok(port);
+        }
+    }
+    Err(eyre!("no local port is free on every loopback address"))
+}
+
+/// Process table holding just `pid` with its command line
+fn single_process(pid: u32) -> System {
+    processes(&[pid])
+}
+
+/// Process table holding just `pids` with their command lines
+fn processes(pids: &[u32]) -> System {
+    let pids: Vec<Pid> = pids.iter().copied().map(Pid::from_u32).collect();
+    let mut sys = System::new();
+    sys.refresh_processes_specifics(
+        ProcessesToUpdate::Some(&pids),
+        true,
+        ProcessRefreshKind::new().with_cmd(sysinfo::UpdateKind::Always),
+    );
+    sys
+}
+
+/// Whether `pid` is still running; reaped and zombie processes aren't
+fn is_alive(sys: &System, pid: u32) -> bool {
+    sys.process(Pid::from_u32(pid))
+        .is_some_and(|p| p.status() != sysinfo::ProcessStatus::Zombie)
+}
+
+/// Poll until `pid` has exited, for at most `timeout`
+fn wait_for_exit(pid: u32, timeout: Duration) -> bool {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if !is_alive(&single_process(pid), pid) {
+            return true;
+        }
+        if Instant::now() >= deadline {
+            return false;
+        }
+        thread::sleep(Duration::from_millis(25));
+    }
+}
+
+/// Stop a session process and whatever it spawned
+fn kill_process(pid: u32) -> Result<()> {
+    signal_process(pid, "TERM")
+}
+
+/// Kill a session process that ignored [`kill_process`]
+fn force_kill_process(pid: u32) -> Result<()> {
+    signal_process(pid, "KILL")
+}
+
+#[cfg_attr(windows, allow(unused_variables))]
+fn signal_process(pid: u32, signal: &str) -> Result<()> {
+    #[cfg(unix)]
+    {
+        // Sessions lead their own process group. Processes started by older
+        // versions don't, and only the process itself is signalled.
+        let group = Command::new("kill")
+            .args(["-s", signal, "--"])
+            .arg(format!("-{}", pid))
+            .output()?;
+        if !group.status.success() {
+            Command::new("kill")
+                .args(["-s", signal, "--"])
+                .arg(pid.to_string())
+                .output()?;
+        }
+    }
+    #[cfg(windows)]
+    {
+        Command::new("taskkill")
+            .args(["/PID", &pid.to_string(), "/T", "/F"])
+            .output()?;
+    }
+    Ok(())
+}
@@ -0,0 +1,227 @@
+//! Session providers: plugins adding session types beyond SSH, kubectl
+//! and the SOCKS5 proxies, e.g. a company's own tunnel client. A provider
+//! builds the command a session runs, offers targets in the session form
+//! and checks sessions before they are saved or started. Providers come
+//! from `session_providers` in the config ([`CommandProvider`]), or are
+//! registered in code by apps using pfman as a library.
+
+use crate::config::ProviderConfig;
+use crate::models::{Session, SessionType};
+use crate::preflight;
+use color_eyre::Result;
+use color_eyre::eyre::eyre;
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+
+pub trait SessionProvider: Send + Sync {
+    /// Session type name, saved with the sessions. Must differ from the
+    /// built-in types (`SSH`, `Kubectl`, `Socks5`, `ReverseSocks5`).
+    fn name(&self) -> &str;
+
+    /// One line shown when choosing a session type
+    fn description(&self) -> &str {
+        ""
+    }
+
+    /// Command running the forward. It keeps running while the forward is
+    /// up, and its output goes to the session's log.
+    fn build_command(&self, session: &Session) -> Result<Command>;
+
+    /// Targets offered in the session form. Called off the UI thread, so
+    /// it may block.
+    fn list_targets(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Why `session` can't be saved or started, if anything
+    fn validate(&self, _session: &Session) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// The session providers known to this instance, by name
+#[derive(Clone, Default)]
+pub struct ProviderRegistry {
+    providers: Vec<Arc<dyn SessionProvider>>,
+}
+
+impl ProviderRegistry {
+    /// Providers configured in `session_providers`
+    pub fn from_config(configs: &[ProviderConfig]) -> Self {
+        let mut registry = Self::default();
+        for config in configs {
+            registry.register(CommandProvider::new(config.clone()));
+        }
+        registry
+    }
+
+    /// Add a provider, replacing the one of the same name
+    pub fn register(&mut self, provider: impl SessionProvider + 'static) {
+        self.providers.retain(|p| p.name() != provider.name());
+        self.providers.push(Arc::new(provider));
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Arc<dyn SessionProvider>> {
+        self.providers.iter().find(|p| p.name() == name)
+    }
+
+    /// In the order they were registered
+    pub fn providers(&self) -> &[Arc<dyn SessionProvider>] {
+        &self.providers
+    }
+
+    /// Provider of a plugin session; built-in types have none
+    fn provider_for(&self, session: &Session) -> Result<Option<&Arc<dyn SessionProvider>>, String> {
+        match &session.session_type {
+            SessionType::Custom(name) => self.get(name).map(Some).ok_or_else(|| {
+                format!(
+                    "No session provider named {} (see session_providers in config.yaml)",
+                    name
+                )
+            }),
+            _ => Ok(None),
+        }
+    }
+
+    /// Whether the provider of a plugin session knows it and finds it fine;
+    /// sessions of the built-in types always pass
+    pub fn validate(&self, session: &Session) -> Result<(), String> {
+        match self.provider_for(session)? {
+            Some(provider) => provider.validate(session),
+            None => Ok(()),
+        }
+    }
+
+    /// [`Self::validate`], and the program the provider runs is on PATH
+    pub fn check(&self, session: &Session) -> Result<(), String> {
+        let Some(provider) = self.provider_for(session)? else {
+            return Ok(());
+        };
+        provider.validate(session)?;
+        let cmd = provider.build_command(session).map_err(|e| e.to_string())?;
+        let program = cmd.get_program().to_string_lossy();
+        if !program.contains(std::path::is_separator) && preflight::find_program(&program).is_none()
+        {
+            return Err(format!(
+                "{} not found on PATH: install it to run {} sessions",
+                program,
+                provider.name()
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn build_command(&self, session: &Session) -> Result<Command> {
+        match self.provider_for(session).map_err(|e| eyre!(e))? {
+            Some(provider) => provider.build_command(session),
+            None => Err(eyre!("{} is not a plugin session", session.name)),
+        }
+    }
+}
+
+/// Provider made of the command lines in a [`ProviderConfig`]
+pub struct CommandProvider {
+    config: ProviderConfig,
+}
+
+impl CommandProvider {
+    pub fn new(config: ProviderConfig) -> Self {
+        Self { config }
+    }
+}
+
+impl SessionProvider for CommandProvider {
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    fn description(&self) -> &str {
+        &self.config.description
+    }
+
+    fn build_command(&self, session: &Session) -> Result<Command> {
+        // Filled in word by word, so a value with spaces stays one argument
+        let mut words = self
+            .config
+            .command
+            .split_whitespace()
+            .map(|word| fill_in(word, session));
+        let program = words
+            .next()
+            .ok_or_else(|| eyre!("Session provider {} has no command", self.config.name))?;
+        let mut cmd = Command::new(program);
+        cmd.args(words);
+        Ok(cmd)
+    }
+
+    fn list_targets(&self) -> Vec<String> {
+        let Some(command) = &self.config.list_targets else {
+            return Vec::new();
+        };
+        let output = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output();
+        match output {
+            Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(str::to_string)
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn validate(&self, session: &Session) -> Result<(), String> {
+        let Some(command) = &self.config.validate else {
+            return Ok(());
+        };
+        let output = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .env("PFMAN_TARGET", &session.target)
+            .env("PFMAN_LOCAL_PORT", session.local_port.to_string())
+            .env(
+                "PFMAN_REMOTE_PORT",
+                session
+                    .remote_port
+                    .map(|p| p.to_string())
+                    .unwrap_or_default(),
+            )
+            .stdin(Stdio::null())
+            .output()
+            .map_err(|e| format!("Failed to run {} validation: {}", self.config.name, e))?;
+        if output.status.success() {
+            return Ok(());
+        }
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let reason = [stderr.trim(), stdout.trim()]
+            .into_iter()
+            .find(|text| !text.is_empty())
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("validation failed ({})", output.status));
+        Err(format!("{}: {}", self.config.name, reason))
+    }
+}
+
+/// `word` with the session's `{target}`, `{local_port}`, `{local_host}`
+/// and `{remote_port}` filled in
+fn fill_in(word: &str, session: &Session) -> String {
+    word.replace("{target}", &session.target)
+        .replace("{local_port}", &session.local_port.to_string())
+        .replace(
+            "{local_host}",
+            &session.address_family.loopbacks()[0].to_string(),
+        )
+        .replace(
+            "{remote_port}",
+            &session
+                .remote_port
+                .map(|p| p.to_string())
+                .unwrap_or_default(),
+        )
+}
@@ -0,0 +1,154 @@
+//! Sessions with login prompts run under a pseudo-terminal instead of
+//! writing to files, so ssh can ask for a password, key passphrase or one-time code.
+//! Output still ends up in the session log; a question ssh is waiting on is
+//! held back and shown by pfman, which types the answer in.
+
+use chrono::{DateTime, Utc};
+use color_eyre::Result;
+use color_eyre::eyre::eyre;
+use portable_pty::{CommandBuilder, MasterPty, PtySize, native_pty_system};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use uuid::Uuid;
+
+/// Terminal of every session running under one, by session id
+pub type Terminals = Arc<Mutex<HashMap<Uuid, Terminal>>>;
+
+pub struct Terminal {
+    pid: u32,
+    // Dropping the master hangs up the session's terminal
+    _master: Box<dyn MasterPty + Send>,
+    writer: Box<dyn Write + Send>,
+    /// Output after the last newline, e.g. a prompt waiting for input
+    pending: String,
+    answered_at: Option<DateTime<Utc>>,
+}
+
+/// A session process started under a terminal
+pub type Child = Box<dyn portable_pty::Child + Send + Sync>;
+
+/// Start `cmd` under a new terminal registered for `session_id`, copying
+/// its output to `log` line by line
+pub fn spawn(terminals: &Terminals, session_id: Uuid, cmd: &Command, log: File) -> Result<Child> {
+    let pair = native_pty_system()
+        .openpty(PtySize {
+            rows: 24,
+            cols: 200,
+            pixel_width: 0,
+            pixel_height: 0,
+        })
+        .map_err(|e| eyre!("{}", e))?;
+
+    let mut builder = wrap(cmd);
+    builder.cwd(std::env::current_dir()?);
+    let child = pair
+        .slave
+        .spawn_command(builder)
+        .map_err(|e| eyre!("{}", e))?;
+    // Only the session holds the terminal open, so its exit ends the reader
+    drop(pair.slave);
+
+    let pid = child.process_id().unwrap_or_default();
+    let reader = pair.master.try_clone_reader().map_err(|e| eyre!("{}", e))?;
+    let writer = pair.master.take_writer().map_err(|e| eyre!("{}", e))?;
+    terminals.lock().unwrap().insert(
+        session_id,
+        Terminal {
+            pid,
+            _master: pair.master,
+            writer,
+            pending: String::new(),
+            answered_at: None,
+        },
+    );
+    spawn_reader(Arc::clone(terminals), session_id, pid, reader, log);
+    Ok(child)
+}
+
+/// The terminal goes away with pfman, which would hang up the session. On
+/// Unix it runs through a shell that ignores that first, so the tunnel stays
+/// up like one whose output goes to files.
+fn wrap(cmd: &Command) -> CommandBuilder {
+    #[cfg(unix)]
+    let mut builder = {
+        let mut builder = CommandBuilder::new("sh");
+        builder.args(["-c", "trap '' HUP; exec \"$0\" \"$@\""]);
+        builder.arg(cmd.get_program());
+        builder
+    };
+    #[cfg(not(unix))]
+    let mut builder = CommandBuilder::new(cmd.get_program());
+    builder.args(cmd.get_args());
+    builder
+}
+
+/// Log complete lines with timestamps, like [`crate::capture`] does, and
+/// keep the unfinished last line as the session's pending output
+fn spawn_reader(
+    terminals: Terminals,
+    session_id: Uuid,
+    pid: u32,
+    mut reader: Box<dyn Read + Send>,
+    mut log: File,
+) {
+    thread::spawn(move || {
+        let mut buffer = [0u8; 1024];
+        let mut partial: Vec<u8> = Vec::new();
+        loop {
+            match reader.read(&mut buffer) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => partial.extend_from_slice(&buffer[..n]),
+            }
+            while let Some(end) = partial.iter().position(|b| *b == b'\n') {
+                let line: Vec<u8> = partial.drain(..=end).collect();
+                let line = String::from_utf8_lossy(&line);
+                let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S");
+                let _ = writeln!(log, "[{}] {}", timestamp, line.trim_end());
+            }
+            if let Some(terminal) = terminals.lock().unwrap().get_mut(&session_id) {
+                terminal.pending = String::from_utf8_lossy(&partial).trim().to_string();
+            }
+        }
+
+        let mut terminals = terminals.lock().unwrap();
+        // A restart may have registered a new terminal meanwhile
+        if terminals.get(&session_id).is_some_and(|t| t.pid == pid) {
+            terminals.remove(&session_id);
+        }
+    });
+}
+
+/// The question the session is waiting on, if any. Only output ending like
+/// a prompt counts, not a line that is still being written.
+pub fn prompt(terminals: &Terminals, session_id: &Uuid) -> Option<String> {
+    let terminals = terminals.lock().unwrap();
+    let pending = &terminals.get(session_id)?.pending;
+    (pending.ends_with(':') || pending.ends_with('?')).then(|| pending.clone())
+}
+
+/// Type `answer` into the session's terminal
+pub fn answer(terminals: &Terminals, session_id: &Uuid, answer: &str) -> Result<()> {
+    let mut terminals = terminals.lock().unwrap();
+    let terminal = terminals
+        .get_mut(session_id)
+        .ok_or_else(|| eyre!("session is not waiting for input"))?;
+    terminal.writer.write_all(answer.as_bytes())?;
+    terminal.writer.write_all(b"\r")?;
+    terminal.writer.flush()?;
+    terminal.pending.clear();
+    terminal.answered_at = Some(Utc::now());
+    Ok(())
+}
+
+/// When the last prompt was answered; the startup timeout counts from then
+pub fn answered_at(terminals: &Terminals, session_id: &Uuid) -> Option<DateTime<Utc>> {
+    terminals.lock().unwrap().get(session_id)?.answered_at
+}
+
+pub fn close(terminals: &Terminals, session_id: &Uuid) {
+    terminals.lock().unwrap().remove(session_id);
+}
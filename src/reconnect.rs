@@ -0,0 +1,102 @@
+//! Automatic restarts of failed sessions that have auto-restart on. Each
+//! failure in a row doubles the pause before the next attempt, up to a cap,
+//! and the pause is jittered so sessions that failed together don't retry
+//! in lockstep.
+
+use crate::config::ReconnectBackoff;
+use crate::models::{PendingRestart, Session, SessionStatus};
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// A restarted session up this long counts as recovered, so its next
+/// failure starts the backoff over
+const STABLE_AFTER_SECS: i64 = 30;
+/// Pauses vary by up to this many percent either way
+const JITTER_PERCENT: i64 = 20;
+
+/// Schedules the restarts and keeps count of each session's failures in a
+/// row
+#[derive(Debug)]
+pub struct Reconnector {
+    backoff: ReconnectBackoff,
+    /// Restarts made since the session last ran stably
+    attempts: HashMap<Uuid, u32>,
+}
+
+impl Reconnector {
+    pub fn new(backoff: ReconnectBackoff) -> Self {
+        Self {
+            backoff,
+            attempts: HashMap::new(),
+        }
+    }
+
+    /// Schedule a restart for each newly failed session, and return the
+    /// sessions whose restart is due, with its attempt number. Sessions in
+    /// `held` wait for the retry coordinator instead.
+    pub fn observe(
+        &mut self,
+        sessions: &mut [Session],
+        held: &[Uuid],
+        now: DateTime<Utc>,
+    ) -> Vec<(Uuid, u32)> {
+        let mut due = Vec::new();
+        for session in sessions.iter_mut() {
+            let id = session.id;
+            match &session.status {
+                SessionStatus::Error(_) if session.auto_restart && !held.contains(&id) => {
+                    match &session.pending_restart {
+                        None => {
+                            let attempt = self.attempts.get(&id).copied().unwrap_or(0) + 1;
+                            session.pending_restart = Some(PendingRestart {
+                                attempt,
+                                at: now + self.delay(attempt),
+                            });
+                        }
+                        Some(pending) if now >= pending.at => {
+                            let attempt = pending.attempt;
+                            self.attempts.insert(id, attempt);
+                            session.pending_restart = None;
+                            due.push((id, attempt));
+                        }
+                        Some(_) => {}
+                    }
+                }
+                SessionStatus::Error(_) | SessionStatus::Starting | SessionStatus::Stopping => {
+                    session.pending_restart = None;
+                }
+                SessionStatus::Running | SessionStatus::Degraded(_)
+                    if session
+                        .last_started
+                        .is_some_and(|t| (now - t).num_seconds() < STABLE_AFTER_SECS) =>
+                {
+                    session.pending_restart = None;
+                }
+                SessionStatus::Running | SessionStatus::Degraded(_) | SessionStatus::Stopped => {
+                    session.pending_restart = None;
+                    self.attempts.remove(&id);
+                }
+            }
+            if !session.auto_restart {
+                self.attempts.remove(&id);
+            }
+        }
+        self.attempts
+            .retain(|id, _| sessions.iter().any(|s| s.id == *id));
+        due
+    }
+
+    /// Pause before restart `attempt`: doubled per attempt, jittered, capped
+    fn delay(&self, attempt: u32) -> Duration {
+        let initial = self.backoff.initial_delay_secs.max(1) * 1000;
+        let max = self.backoff.max_delay_secs.max(1) * 1000;
+        let base = initial
+            .saturating_mul(1 << attempt.saturating_sub(1).min(20))
+            .min(max) as i64;
+        // No RNG crate here; v4 UUIDs are random enough for spreading retries
+        let jitter =
+            (Uuid::new_v4().as_u128() % (2 * JITTER_PERCENT as u128 + 1)) as i64 - JITTER_PERCENT;
+        Duration::milliseconds((base + base * jitter / 100).min(max as i64))
+    }
+}
@@ -0,0 +1,107 @@
+//! `pfman restore`: roll the sessions back to one of the backups kept by
+//! every save that changes session definitions, e.g. after an accidental
+//! bulk delete.
+
+use crate::instance;
+use crate::models::{Session, SessionStatus};
+use crate::process::ProcessManager;
+use crate::provider::ProviderRegistry;
+use crate::storage::{SessionBackup, Storage};
+use color_eyre::Result;
+use color_eyre::eyre::eyre;
+
+/// Entry point for `pfman restore [<number>]`; without a number the
+/// backups are listed
+pub fn run(args: &[String]) -> Result<()> {
+    let storage = Storage::new()?;
+    let backups = storage.session_backups();
+    let Some(number) = args.iter().find(|a| !a.starts_with("--")) else {
+        if backups.is_empty() {
+            println!("No backups yet");
+            return Ok(());
+        }
+        for backup in &backups {
+            println!("{}", describe(backup));
+        }
+        println!("Restore one with: pfman restore <number>");
+        return Ok(());
+    };
+
+    if let Some(pid) = instance::running_instance(&storage) {
+        return Err(eyre!(
+            "pfman (PID {}) is managing these sessions; restore from its backups screen (b) or quit it first",
+            pid
+        ));
+    }
+    let backup = number
+        .parse::<usize>()
+        .ok()
+        .and_then(|n| backups.into_iter().find(|b| b.number == n))
+        .ok_or_else(|| eyre!("no backup {}; run `pfman restore` to list them", number))?;
+
+    let mut sessions = storage.load_sessions()?;
+    let config = storage.load_config()?;
+    let process_manager = ProcessManager::new(storage.clone())
+        .with_providers(ProviderRegistry::from_config(&config.session_providers));
+    let count = backup.sessions.len();
+    let restored = restore(backup.sessions, &mut sessions, &process_manager);
+    storage.save_sessions(&restored)?;
+    println!(
+        "Restored {} session(s) saved {}; the replaced list is now backup 1",
+        count,
+        backup
+            .saved_at
+            .with_timezone(&chrono::Local)
+            .format("%Y-%m-%d %H:%M:%S")
+    );
+    Ok(())
+}
+
+/// One line per backup, as listed by `pfman restore` and the backups screen
+pub fn describe(backup: &SessionBackup) -> String {
+    format!(
+        "{}  {}  {} session(s)",
+        backup.number,
+        backup
+            .saved_at
+            .with_timezone(&chrono::Local)
+            .format("%Y-%m-%d %H:%M:%S"),
+        backup.sessions.len()
+    )
+}
+
+/// The backed-up sessions with the runtime state they have now. Running
+/// sessions the backup doesn't have are stopped, as nothing would track
+/// them afterwards; sessions only in the backup come back stopped.
+pub fn restore(
+    backup: Vec<Session>,
+    current: &mut [Session],
+    process_manager: &ProcessManager,
+) -> Vec<Session> {
+    for session in current.iter_mut() {
+        if session.pid.is_some() && !backup.iter().any(|b| b.id == session.id) {
+            let _ = process_manager.stop_session(session);
+        }
+    }
+
+    backup
+        .into_iter()
+        .map(|mut session| {
+            match current.iter().find(|c| c.id == session.id) {
+                Some(now) => {
+                    session.status = now.status.clone();
+                    session.pid = now.pid;
+                    session.last_started = now.last_started;
+                    session.stats = now.stats.clone();
+                    session.scope = now.scope.clone();
+                }
+                None => {
+                    session.status = SessionStatus::Stopped;
+                    session.pid = None;
+                    session.scope = None;
+                }
+            }
+            session
+        })
+        .collect()
+}
@@ -0,0 +1,141 @@
+use crate::models::{ErrorCategory, Session, SessionStatus};
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// Network failures of this many sessions within STORM_WINDOW are treated
+/// as one outage. One session failing again and again (e.g. restarted
+/// automatically) isn't one.
+const STORM_THRESHOLD: usize = 3;
+const STORM_WINDOW: Duration = Duration::from_secs(30);
+/// Pause between canary attempts while an outage lasts
+const CANARY_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Correlated network failure: the affected sessions are held back until
+/// the configured canary passes, or without one, until a single canary
+/// session comes up again
+#[derive(Debug)]
+pub struct Storm {
+    pub held: Vec<Uuid>,
+    pub canary: Option<Uuid>,
+    next_probe: Instant,
+}
+
+/// Detects retry storms and restarts the held sessions as one wave once the
+/// canary succeeds, instead of every session hammering an unreachable host
+#[derive(Debug, Default)]
+pub struct RetryCoordinator {
+    /// Sessions with auto-restart seen in a network error on the previous
+    /// observation
+    failing: HashSet<Uuid>,
+    recent_failures: Vec<(Instant, Uuid)>,
+    storm: Option<Storm>,
+}
+
+impl RetryCoordinator {
+    pub fn storm(&self) -> Option<&Storm> {
+        self.storm.as_ref()
+    }
+
+    /// Feed the current session list and the configured canary's last
+    /// result; returns the sessions to start now
+    pub fn observe(
+        &mut self,
+        sessions: &[Session],
+        network_up: Option<bool>,
+        now: Instant,
+    ) -> Vec<Uuid> {
+        // Only sessions that would be restarted at all can storm; the rest
+        // stay in Error for the user, as with the reconnector
+        let failing: HashSet<Uuid> = sessions
+            .iter()
+            .filter(|s| s.auto_restart && s.error_category() == Some(ErrorCategory::Network))
+            .map(|s| s.id)
+            .collect();
+        for id in failing.difference(&self.failing) {
+            self.recent_failures.push((now, *id));
+        }
+        self.failing = failing;
+        self.recent_failures
+            .retain(|(t, _)| now.duration_since(*t) < STORM_WINDOW);
+        let failed_sessions: HashSet<&Uuid> =
+            self.recent_failures.iter().map(|(_, id)| id).collect();
+
+        // A failing canary holds every network failure, however few
+        let degraded = network_up == Some(false) && !self.failing.is_empty();
+        if self.storm.is_none() && (degraded || failed_sessions.len() >= STORM_THRESHOLD) {
+            self.storm = Some(Storm {
+                held: self.failing.iter().copied().collect(),
+                canary: None,
+                next_probe: now + CANARY_INTERVAL,
+            });
+        }
+
+        // Still failed, and still to be restarted
+        let is_retried = |id: &Uuid| {
+            sessions.iter().any(|s| {
+                s.id == *id && s.auto_restart && matches!(s.status, SessionStatus::Error(_))
+            })
+        };
+        let status_of = |id: Uuid| sessions.iter().find(|s| s.id == id).map(|s| &s.status);
+
+        let Some(storm) = &mut self.storm else {
+            return Vec::new();
+        };
+        // Sessions failing while the outage lasts join the wave
+        for id in &self.failing {
+            if !storm.held.contains(id) {
+                storm.held.push(*id);
+            }
+        }
+
+        match storm.canary.map(|id| (id, status_of(id))) {
+            Some((canary, Some(SessionStatus::Running))) => {
+                let wave = storm
+                    .held
+                    .iter()
+                    .copied()
+                    .filter(|id| *id != canary && is_retried(id))
+                    .collect();
+                self.storm = None;
+                self.recent_failures.clear();
+                wave
+            }
+            Some((_, Some(SessionStatus::Starting))) => Vec::new(),
+            Some(_) => {
+                storm.canary = None;
+                storm.next_probe = now + CANARY_INTERVAL;
+                Vec::new()
+            }
+            None if now >= storm.next_probe => {
+                // Sessions restarted or deleted by the user meanwhile, or
+                // with auto-restart turned off, drop out
+                storm.held.retain(is_retried);
+                match network_up {
+                    Some(false) => {
+                        storm.next_probe = now + CANARY_INTERVAL;
+                        return Vec::new();
+                    }
+                    Some(true) => {
+                        let wave = std::mem::take(&mut storm.held);
+                        self.storm = None;
+                        self.recent_failures.clear();
+                        return wave;
+                    }
+                    None => {}
+                }
+                match storm.held.first().copied() {
+                    Some(canary) => {
+                        storm.canary = Some(canary);
+                        vec![canary]
+                    }
+                    None => {
+                        self.storm = None;
+                        Vec::new()
+                    }
+                }
+            }
+            None => Vec::new(),
+        }
+    }
+}
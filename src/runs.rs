@@ -0,0 +1,270 @@
+//! Run history: every start of a session's process and how it ended, one
+//! JSON record per line in `runs/<id>.jsonl` next to the logs, which is
+//! also the index of each run's own log in `logs/<id>/`. Usage
+//! statistics, the log sink and the logs viewer's run list read these
+//! instead of parsing the log, which stays free-form output.
+
+use crate::storage::{self, Storage};
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+/// Start of a line the process manager writes into the log itself, after
+/// the timestamp, so it can be told apart from the session's own output
+pub const LIFECYCLE_MARKER: &str = "[pfman] ";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunEvent {
+    Started,
+    /// Ended by a stop, including one for reaching the max lifetime
+    Stopped,
+    /// Exited on its own
+    Crashed,
+    /// Exited within the verification window after starting
+    FailedEarly,
+    /// Never accepted connections on the local port
+    FailedHealthCheck,
+}
+
+impl RunEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RunEvent::Started => "started",
+            RunEvent::Stopped => "stopped",
+            RunEvent::Crashed => "crashed",
+            RunEvent::FailedEarly => "failed early",
+            RunEvent::FailedHealthCheck => "failed health check",
+        }
+    }
+
+    /// Whether the run ended without being asked to
+    pub fn is_failure(&self) -> bool {
+        matches!(
+            self,
+            RunEvent::Crashed | RunEvent::FailedEarly | RunEvent::FailedHealthCheck
+        )
+    }
+}
+
+/// One line of a session's run history
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunRecord {
+    pub event: RunEvent,
+    pub at: DateTime<Utc>,
+    pub pid: u32,
+    /// Why the run ended, when pfman knows more than the event says
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    /// On starts, the run's own log, see [`Storage::new_run_log`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log: Option<String>,
+    /// On starts, the command line run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+}
+
+impl RunRecord {
+    pub fn new(event: RunEvent, at: DateTime<Utc>, pid: u32) -> Self {
+        Self {
+            event,
+            at,
+            pid,
+            reason: None,
+            exit_code: None,
+            log: None,
+            command: None,
+        }
+    }
+
+    pub fn with_reason(mut self, reason: impl Into<String>) -> Self {
+        self.reason = Some(reason.into());
+        self
+    }
+
+    pub fn with_exit_code(mut self, exit_code: Option<i32>) -> Self {
+        self.exit_code = exit_code;
+        self
+    }
+
+    pub fn with_log(mut self, log: String) -> Self {
+        self.log = Some(log);
+        self
+    }
+
+    pub fn with_command(mut self, command: String) -> Self {
+        self.command = Some(command);
+        self
+    }
+
+    /// The line noting the event in the session log, e.g.
+    /// `[2024-05-01 10:00:00] [pfman] crashed (PID 123, exit code 255)`
+    pub fn log_line(&self) -> String {
+        let mut details = format!("PID {}", self.pid);
+        if let Some(code) = self.exit_code {
+            details.push_str(&format!(", exit code {}", code));
+        }
+        let mut line = format!(
+            "[{}] {}{} ({})",
+            self.at.format("%Y-%m-%d %H:%M:%S"),
+            LIFECYCLE_MARKER,
+            self.event.as_str(),
+            details
+        );
+        if let Some(reason) = &self.reason {
+            line.push_str(&format!(": {}", reason));
+        }
+        line.push('\n');
+        line
+    }
+}
+
+/// Record `record` in the session's run history and note it in its log,
+/// after the last of the output of a run that ended
+pub fn record(storage: &Storage, session_id: &Uuid, record: RunRecord) {
+    if record.event != RunEvent::Started {
+        crate::capture::collect(&storage.log_file(session_id), true);
+    }
+    // Carry over the runs older versions only noted in the log
+    if !storage.runs_file(session_id).exists() {
+        for legacy in legacy_records(storage, session_id) {
+            let _ = storage.append_run(session_id, &legacy);
+        }
+    }
+    let _ = storage.append_run(session_id, &record);
+    let _ = storage.append_log(session_id, &record.log_line());
+}
+
+/// A start of the session's process and, once over, how it ended
+#[derive(Debug, Clone)]
+pub struct Run {
+    pub pid: u32,
+    pub started_at: DateTime<Utc>,
+    /// `None` while still up, or when pfman never saw the end
+    pub end: Option<RunRecord>,
+    /// File name of the run's log; runs of earlier versions share the
+    /// session's single log instead
+    pub log: Option<String>,
+    /// Command line run; not recorded by earlier versions
+    pub command: Option<String>,
+}
+
+impl Run {
+    /// How long it was up; until `now` for a run that hasn't ended
+    pub fn duration(&self, now: DateTime<Utc>) -> Duration {
+        self.end.as_ref().map_or(now, |end| end.at) - self.started_at
+    }
+
+    pub fn failed(&self) -> bool {
+        self.end.as_ref().is_some_and(|end| end.event.is_failure())
+    }
+
+    /// Where the run's output went
+    pub fn log_file(&self, storage: &Storage, session_id: &Uuid) -> PathBuf {
+        match &self.log {
+            Some(name) => storage.run_log_file(session_id, name),
+            None => storage.legacy_log_file(session_id),
+        }
+    }
+}
+
+/// The session's runs, oldest first. Sessions last run by versions that
+/// only wrote separators into the log get them parsed from there.
+pub fn history(storage: &Storage, session_id: &Uuid) -> Vec<Run> {
+    let records = match storage.read_runs(session_id) {
+        Some(records) => records,
+        None => legacy_records(storage, session_id),
+    };
+    pair(records)
+}
+
+/// Match every start with the end that follows it. A start followed by
+/// another start lost its end, e.g. to pfman being killed.
+fn pair(records: Vec<RunRecord>) -> Vec<Run> {
+    let mut runs: Vec<Run> = Vec::new();
+    for record in records {
+        if record.event == RunEvent::Started {
+            runs.push(Run {
+                pid: record.pid,
+                started_at: record.at,
+                end: None,
+                log: record.log,
+                command: record.command,
+            });
+        } else if let Some(run) = runs.last_mut()
+            && run.end.is_none()
+            && run.pid == record.pid
+        {
+            run.end = Some(record);
+        }
+    }
+    runs
+}
+
+/// Move the single logs of earlier versions into the per-run layout, each
+/// as the log of the oldest run it holds, and index those runs. A session
+/// whose log can't be moved keeps it, and is tried again next time.
+pub fn migrate_legacy_logs(storage: &Storage) {
+    for session_id in storage.legacy_logs() {
+        let legacy = storage.legacy_log_file(&session_id);
+        let mut records = storage
+            .read_runs(&session_id)
+            .unwrap_or_else(|| legacy_records(storage, &session_id));
+        let modified = fs::metadata(&legacy)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .map(DateTime::<Utc>::from);
+        // Before the runs that already have logs of their own
+        let Some(oldest) = records.iter().map(|r| r.at).chain(modified).min() else {
+            continue;
+        };
+        let name = storage::run_log_name(oldest - Duration::milliseconds(1));
+        let migrated = storage.run_log_file(&session_id, &name);
+        if migrated.exists()
+            || migrated
+                .parent()
+                .is_some_and(|dir| fs::create_dir_all(dir).is_err())
+            || fs::rename(&legacy, &migrated).is_err()
+        {
+            continue;
+        }
+        for record in &mut records {
+            if record.event == RunEvent::Started && record.log.is_none() {
+                record.log = Some(name.clone());
+            }
+        }
+        if storage.write_runs(&session_id, &records).is_err() {
+            let _ = fs::rename(&migrated, &legacy);
+        }
+    }
+}
+
+/// Records from the `Session Started: <time> | PID: <pid>` separators
+/// earlier versions wrote between runs into the session's single log
+fn legacy_records(storage: &Storage, session_id: &Uuid) -> Vec<RunRecord> {
+    let log = fs::read_to_string(storage.legacy_log_file(session_id)).unwrap_or_default();
+    log.lines()
+        .filter_map(|line| {
+            let (label, rest) = line.split_once(": ")?;
+            let event = match label {
+                "Session Started" => RunEvent::Started,
+                "Session Stopped" => RunEvent::Stopped,
+                "Session Crashed/Exited" => RunEvent::Crashed,
+                "Session Failed Early" => RunEvent::FailedEarly,
+                "Session Failed Health Check" => RunEvent::FailedHealthCheck,
+                _ => return None,
+            };
+            let (timestamp, pid) = rest.split_once(" | PID: ")?;
+            let at = NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%d %H:%M:%S").ok()?;
+            Some(RunRecord::new(
+                event,
+                at.and_utc(),
+                pid.trim().parse().ok()?,
+            ))
+        })
+        .collect()
+}
@@ -0,0 +1,105 @@
+//! Writing `sessions.yaml` off the UI thread. Status changes seen by the
+//! monitor only mark the sessions dirty; a background thread writes them
+//! once [`SAVE_DELAY`] has passed, so a long session list doesn't stall
+//! frames on disk I/O at every poll. Saves for user actions still happen
+//! right away, and a background write never replaces a newer one.
+
+use crate::models::Session;
+use crate::storage::Storage;
+use color_eyre::Result;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long status changes may wait to be written, collecting the ones
+/// that follow in the meantime
+pub const SAVE_DELAY: Duration = Duration::from_secs(1);
+
+/// A numbered copy of the sessions; higher numbers are newer
+type Snapshot = (u64, Vec<Session>);
+
+pub struct SessionSaver {
+    storage: Storage,
+    sender: Sender<Snapshot>,
+    /// Number of the newest snapshot taken
+    taken: u64,
+    /// Number of the snapshot on disk, also held while writing
+    written: Arc<Mutex<u64>>,
+    /// First unsaved change since the last snapshot
+    dirty_since: Option<Instant>,
+}
+
+impl SessionSaver {
+    pub fn spawn(storage: Storage) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let written = Arc::new(Mutex::new(0));
+        let shared = Arc::clone(&written);
+        let background = storage.clone();
+        thread::spawn(move || save_loop(background, receiver, shared));
+        Self {
+            storage,
+            sender,
+            taken: 0,
+            written,
+            dirty_since: None,
+        }
+    }
+
+    /// Note a change that can wait to be written
+    pub fn mark_dirty(&mut self) {
+        self.dirty_since.get_or_insert_with(Instant::now);
+    }
+
+    /// Hand the sessions to the background thread once the oldest unsaved
+    /// change has waited [`SAVE_DELAY`]
+    pub fn save_if_due(&mut self, sessions: &[Session]) {
+        if self
+            .dirty_since
+            .is_some_and(|since| since.elapsed() >= SAVE_DELAY)
+        {
+            self.dirty_since = None;
+            self.taken += 1;
+            let _ = self.sender.send((self.taken, sessions.to_vec()));
+        }
+    }
+
+    /// Write the sessions now, superseding any pending background write
+    pub fn save_now(&mut self, sessions: &[Session]) -> Result<()> {
+        self.dirty_since = None;
+        self.taken += 1;
+        write(&self.storage, &self.written, self.taken, sessions)
+    }
+
+    /// Write whatever hasn't reached the disk yet, e.g. before exiting,
+    /// waiting for a background write in progress
+    pub fn flush(&mut self, sessions: &[Session]) -> Result<()> {
+        let behind = *self.written.lock().unwrap() < self.taken;
+        if self.dirty_since.is_some() || behind {
+            self.save_now(sessions)?;
+        }
+        Ok(())
+    }
+}
+
+fn save_loop(storage: Storage, receiver: Receiver<Snapshot>, written: Arc<Mutex<u64>>) {
+    while let Ok(mut snapshot) = receiver.recv() {
+        // Only the newest of the snapshots queued up is worth writing
+        while let Ok(newer) = receiver.try_recv() {
+            snapshot = newer;
+        }
+        let (number, sessions) = snapshot;
+        let _ = write(&storage, &written, number, &sessions);
+    }
+}
+
+/// Write snapshot `number` unless a newer one is already on disk
+fn write(storage: &Storage, written: &Mutex<u64>, number: u64, sessions: &[Session]) -> Result<()> {
+    let mut written = written.lock().unwrap();
+    if *written > number {
+        return Ok(());
+    }
+    storage.save_sessions(sessions)?;
+    *written = number;
+    Ok(())
+}
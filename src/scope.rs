@@ -0,0 +1,33 @@
+use crate::models::TerminalScope;
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, RefreshKind, System};
+
+/// The terminal pfman runs in: the tmux pane or TTY for display, and the
+/// shell that launched pfman as the process whose exit ends the scope
+pub fn current_scope() -> Option<TerminalScope> {
+    let mut sys =
+        System::new_with_specifics(RefreshKind::new().with_processes(ProcessRefreshKind::new()));
+    let own_pid = sysinfo::get_current_pid().ok()?;
+    sys.refresh_processes(ProcessesToUpdate::Some(&[own_pid]), true);
+    let owner_pid = sys.process(own_pid)?.parent()?.as_u32();
+
+    let label = match std::env::var("TMUX_PANE") {
+        Ok(pane) => format!("tmux {}", pane),
+        Err(_) => tty_name().unwrap_or_else(|| format!("shell {}", owner_pid)),
+    };
+    Some(TerminalScope { label, owner_pid })
+}
+
+fn tty_name() -> Option<String> {
+    let path = std::fs::read_link("/proc/self/fd/0").ok()?;
+    let path = path.to_string_lossy();
+    path.starts_with("/dev/").then(|| path.to_string())
+}
+
+/// Whether the shell that owns the scope is still running
+pub fn is_alive(scope: &TerminalScope) -> bool {
+    let pid = Pid::from_u32(scope.owner_pid);
+    let mut sys =
+        System::new_with_specifics(RefreshKind::new().with_processes(ProcessRefreshKind::new()));
+    sys.refresh_processes(ProcessesToUpdate::Some(&[pid]), true);
+    sys.process(pid).is_some()
+}
@@ -0,0 +1,402 @@
+//! `pfman export` / `pfman import`: share session definitions as files.
+//!
+//! Only the definition of a session is written out; pid, status, start
+//! times and terminal scope stay local. Every format holds the same
+//! document: a schema `version` and a `sessions` list in the import format,
+//! so exports can also be pasted into the import screen. `-` reads from
+//! stdin or writes to stdout, for sharing through a chat paste or curl.
+
+use crate::import::{default_session_type, resolve_name_conflicts, session_key};
+use crate::models::{AddressFamily, Lifetime, MAX_IDLE_TIMEOUT_MINUTES, Session, SessionType};
+use crate::storage::Storage;
+use color_eyre::Result;
+use color_eyre::eyre::eyre;
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::path::Path;
+use uuid::Uuid;
+
+/// Version of the export document. Bumped only for changes older pfman
+/// versions can't read; new optional fields keep it.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// File formats for `pfman export --format`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Yaml,
+    Json,
+    Toml,
+}
+
+impl Format {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "yaml" | "yml" => Some(Format::Yaml),
+            "json" => Some(Format::Json),
+            "toml" => Some(Format::Toml),
+            _ => None,
+        }
+    }
+
+    /// Guessed from a file extension
+    fn of_path(path: &Path) -> Option<Self> {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(Format::parse)
+    }
+}
+
+/// Portable part of a session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SharedSession {
+    /// Kept so re-importing an export recognizes its own sessions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<Uuid>,
+    pub name: String,
+    #[serde(default = "default_session_type")]
+    pub session_type: SessionType,
+    pub target: String,
+    pub local_port: u16,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub auto_local_port: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remote_port: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remote_host: Option<String>,
+    #[serde(default, skip_serializing_if = "is_any_family")]
+    pub address_family: AddressFamily,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub additional_options: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kube_context: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kube_namespace: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub health_command: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pre_start: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_stop: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub login_prompts: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub identity_file: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kubeconfig_path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idle_timeout_minutes: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_lifetime: Option<Lifetime>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub auto_restart: bool,
+}
+
+impl From<&Session> for SharedSession {
+    fn from(session: &Session) -> Self {
+        Self {
+            id: Some(session.id),
+            name: session.name.clone(),
+            session_type: session.session_type.clone(),
+            target: session.target.clone(),
+            local_port: session.local_port,
+            auto_local_port: session.auto_local_port,
+            remote_port: session.remote_port,
+            remote_host: session.remote_host.clone(),
+            address_family: session.address_family,
+            additional_options: session.additional_options.clone(),
+            kube_context: session.kube_context.clone(),
+            kube_namespace: session.kube_namespace.clone(),
+            icon: session.icon.clone(),
+            color: session.color.clone(),
+            tags: session.tags.clone(),
+            health_command: session.health_command.clone(),
+            pre_start: session.pre_start.clone(),
+            post_stop: session.post_stop.clone(),
+            login_prompts: session.login_prompts,
+            user: session.user.clone(),
+            identity_file: session.identity_file.clone(),
+            kubeconfig_path: session.kubeconfig_path.clone(),
+            idle_timeout_minutes: session.idle_timeout_minutes,
+            max_lifetime: session.max_lifetime,
+            auto_restart: session.auto_restart,
+        }
+    }
+}
+
+impl SharedSession {
+    /// A stopped session; the id is a fresh one when the file had none
+    pub fn into_session(self) -> Session {
+        let mut session = Session::new(
+            self.name,
+            self.session_type,
+            self.target,
+            self.local_port,
+            self.remote_port,
+        );
+        if let Some(id) = self.id {
+            session.id = id;
+        }
+        session.auto_local_port = self.auto_local_port;
+        session.remote_host = self.remote_host;
+        session.address_family = self.address_family;
+        session.additional_options = self.additional_options;
+        session.kube_context = self.kube_context;
+        session.kube_namespace = self.kube_namespace;
+        session.icon = self.icon;
+        session.color = self.color;
+        session.tags = self.tags;
+        session.health_command = self.health_command;
+        session.pre_start = self.pre_start;
+        session.post_stop = self.post_stop;
+        session.login_prompts = self.login_prompts;
+        session.user = self.user;
+        session.identity_file = self.identity_file;
+        session.kubeconfig_path = self.kubeconfig_path;
+        session.idle_timeout_minutes = self.idle_timeout_minutes;
+        session.max_lifetime = self.max_lifetime;
+        session.auto_restart = self.auto_restart;
+        session
+    }
+}
+
+/// Export document. Hand-written files may leave out the version.
+#[derive(Serialize, Deserialize)]
+struct SharedFile {
+    #[serde(default = "default_version")]
+    version: u32,
+    sessions: Vec<SharedSession>,
+}
+
+fn is_any_family(family: &AddressFamily) -> bool {
+    *family == AddressFamily::Any
+}
+
+fn default_version() -> u32 {
+    SCHEMA_VERSION
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SharedDocument {
+    List(Vec<SharedSession>),
+    Table(SharedFile),
+}
+
+impl SharedDocument {
+    fn into_sessions(self) -> Result<Vec<SharedSession>, String> {
+        let sessions = match self {
+            SharedDocument::List(sessions) => sessions,
+            SharedDocument::Table(file) if file.version > SCHEMA_VERSION => {
+                return Err(format!(
+                    "export version {} is newer than this pfman supports ({}), upgrade pfman",
+                    file.version, SCHEMA_VERSION
+                ));
+            }
+            SharedDocument::Table(file) => file.sessions,
+        };
+        let too_long = |s: &&SharedSession| {
+            s.idle_timeout_minutes
+                .is_some_and(|minutes| minutes > MAX_IDLE_TIMEOUT_MINUTES)
+        };
+        if let Some(session) = sessions.iter().find(too_long) {
+            return Err(format!(
+                "session {} has an idle stop over {} minutes",
+                session.name, MAX_IDLE_TIMEOUT_MINUTES
+            ));
+        }
+        Ok(sessions)
+    }
+}
+
+/// Serialize session definitions in the given format
+pub fn export(sessions: &[Session], format: Format) -> Result<String> {
+    let document = SharedFile {
+        version: SCHEMA_VERSION,
+        sessions: sessions.iter().map(SharedSession::from).collect(),
+    };
+    Ok(match format {
+        Format::Yaml => serde_yaml::to_string(&document)?,
+        Format::Json => serde_json::to_string_pretty(&document)? + "\n",
+        Format::Toml => toml::to_string(&document)?,
+    })
+}
+
+/// Parse an export. Without a known format every one is tried in turn.
+pub fn parse(text: &str, format: Option<Format>) -> Result<Vec<SharedSession>, String> {
+    let parse_as = |format: Format| -> Result<SharedDocument, String> {
+        match format {
+            Format::Yaml => serde_yaml::from_str(text).map_err(|e| e.to_string()),
+            Format::Json => serde_json::from_str(text).map_err(|e| e.to_string()),
+            Format::Toml => toml::from_str(text).map_err(|e| e.to_string()),
+        }
+    };
+    let document = match format {
+        Some(format) => parse_as(format)?,
+        // YAML last, it accepts JSON too but gives worse errors for TOML
+        None => parse_as(Format::Json)
+            .or_else(|_| parse_as(Format::Toml))
+            .or_else(|_| parse_as(Format::Yaml))?,
+    };
+    document.into_sessions()
+}
+
+/// What happened to each imported session
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    pub added: Vec<String>,
+    /// Already present with the same definition (and id, if the file had one)
+    pub skipped: Vec<String>,
+    /// Given a new id because the one in the file belongs to another session
+    pub reassigned: Vec<String>,
+    /// `(old, new)` names
+    pub renamed: Vec<(String, String)>,
+}
+
+/// Add imported sessions to `existing`. Re-imports of the same session are
+/// skipped, colliding ids replaced and taken names made unique.
+pub fn merge(existing: &mut Vec<Session>, shared: Vec<SharedSession>) -> ImportReport {
+    let mut report = ImportReport::default();
+    let mut incoming: Vec<Session> = Vec::new();
+
+    for entry in shared {
+        let had_id = entry.id.is_some();
+        let mut session = entry.into_session();
+        // Hand-written entries have no id, they are matched by definition
+        let duplicate = !had_id
+            && existing
+                .iter()
+                .chain(incoming.iter())
+                .any(|s| session_key(s) == session_key(&session));
+        if duplicate {
+            report.skipped.push(session.name);
+            continue;
+        }
+
+        let owner = existing
+            .iter()
+            .chain(incoming.iter())
+            .find(|s| s.id == session.id);
+        match owner {
+            Some(owner) if session_key(owner) == session_key(&session) => {
+                report.skipped.push(session.name);
+                continue;
+            }
+            Some(_) => {
+                session.id = Uuid::new_v4();
+                report.reassigned.push(session.name.clone());
+            }
+            None => {}
+        }
+        incoming.push(session);
+    }
+
+    report.renamed = resolve_name_conflicts(&mut incoming, existing);
+    report.added = incoming.iter().map(|s| s.name.clone()).collect();
+    existing.extend(incoming);
+    report
+}
+
+const EXPORT_USAGE: &str =
+    "usage: pfman export [<file>|-] [--format yaml|json|toml] [--tag <tag>]...";
+
+/// Entry point for `pfman export [<file>|-] [--format yaml|json|toml] [--tag <tag>]...`.
+/// Without a file (or with `-`) the export goes to stdout.
+pub fn run_export(args: &[String]) -> Result<()> {
+    let mut format = None;
+    let mut destination = None;
+    let mut tags = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => {
+                let name = args.next().map(String::as_str).unwrap_or_default();
+                format = Some(
+                    Format::parse(name)
+                        .ok_or_else(|| eyre!("unknown format '{}' (yaml, json or toml)", name))?,
+                );
+            }
+            "--tag" => tags.extend(args.next().cloned()),
+            path if destination.is_none() && (path == "-" || !path.starts_with('-')) => {
+                destination = Some(path.to_string());
+            }
+            other => return Err(eyre!("unexpected argument '{}'\n{}", other, EXPORT_USAGE)),
+        }
+    }
+    let destination = destination.filter(|path| path != "-");
+    let format = format
+        .or_else(|| {
+            destination
+                .as_deref()
+                .and_then(|p| Format::of_path(Path::new(p)))
+        })
+        .unwrap_or(Format::Yaml);
+
+    let storage = Storage::new()?;
+    let sessions: Vec<Session> = storage
+        .load_sessions()?
+        .into_iter()
+        .filter(|s| tags.is_empty() || s.tags.iter().any(|t| tags.contains(t)))
+        .collect();
+    let text = export(&sessions, format)?;
+    match destination {
+        Some(path) => {
+            std::fs::write(&path, text)?;
+            eprintln!("{} session(s) exported to {}", sessions.len(), path);
+        }
+        None => print!("{}", text),
+    }
+    Ok(())
+}
+
+/// Entry point for `pfman import <file>|-`; `-` reads stdin
+pub fn run_import(args: &[String]) -> Result<()> {
+    let path = args
+        .iter()
+        .find(|a| !a.starts_with("--"))
+        .ok_or_else(|| eyre!("usage: pfman import <file>|-"))?;
+    let (content, format) = if path == "-" {
+        let mut content = String::new();
+        std::io::stdin().read_to_string(&mut content)?;
+        (content, None)
+    } else {
+        (
+            std::fs::read_to_string(path)?,
+            Format::of_path(Path::new(path)),
+        )
+    };
+    let source = if path == "-" { "from stdin" } else { path };
+    let shared = parse(&content, format).map_err(|e| eyre!("invalid export {}: {}", source, e))?;
+
+    let storage = Storage::new()?;
+    let mut sessions = storage.load_sessions()?;
+    let report = merge(&mut sessions, shared);
+
+    for name in &report.skipped {
+        println!("= skip {} (already imported)", name);
+    }
+    for name in &report.reassigned {
+        println!("! new id for {} (id taken by another session)", name);
+    }
+    for (old, new) in &report.renamed {
+        println!("~ rename {} -> {} (name taken)", old, new);
+    }
+    for name in &report.added {
+        println!("+ import {}", name);
+    }
+
+    if report.added.is_empty() {
+        println!("Nothing to import");
+    } else {
+        storage.save_sessions(&sessions)?;
+        println!("{} session(s) imported", report.added.len());
+    }
+    Ok(())
+}
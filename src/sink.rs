@@ -0,0 +1,276 @@
+use crate::config::LogSink;
+use crate::models::Session;
+use crate::runs::{LIFECYCLE_MARKER, RunEvent, RunRecord};
+use crate::storage::Storage;
+use chrono::Utc;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use uuid::Uuid;
+
+const SHIP_INTERVAL: Duration = Duration::from_secs(2);
+/// Records kept for retry while the endpoint is unreachable
+const MAX_PENDING: usize = 1000;
+
+/// One shipped line: a lifecycle event or a line of session output
+#[derive(Debug, Clone, Serialize)]
+struct Record {
+    timestamp: String,
+    user: String,
+    host: String,
+    session_id: Uuid,
+    session: String,
+    target: String,
+    /// `started`, `stopped`, `crashed`, `failed` or `log`
+    event: &'static str,
+    message: String,
+}
+
+#[derive(Clone)]
+struct Tracked {
+    name: String,
+    target: String,
+}
+
+/// Follows the session logs and run histories and forwards new lines and
+/// lifecycle events to the configured sink
+pub struct LogShipper {
+    sessions: Arc<Mutex<HashMap<Uuid, Tracked>>>,
+}
+
+impl LogShipper {
+    pub fn spawn(sink: LogSink, storage: Storage) -> Self {
+        let sessions = Arc::new(Mutex::new(HashMap::new()));
+        let shared = Arc::clone(&sessions);
+        thread::spawn(move || ship_loop(sink, storage, shared));
+        Self { sessions }
+    }
+
+    /// Keep the name/target shown in shipped records up to date
+    pub fn track(&self, sessions: &[Session]) {
+        let mut tracked = self.sessions.lock().unwrap();
+        tracked.clear();
+        for session in sessions {
+            tracked.insert(
+                session.id,
+                Tracked {
+                    name: session.name.clone(),
+                    target: session.target.clone(),
+                },
+            );
+        }
+    }
+}
+
+fn ship_loop(sink: LogSink, storage: Storage, sessions: Arc<Mutex<HashMap<Uuid, Tracked>>>) {
+    let user = std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_default();
+    let host = sysinfo::System::host_name().unwrap_or_default();
+    // Only output and runs written after startup are shipped
+    let mut offsets: HashMap<PathBuf, u64> = HashMap::new();
+    // Logs of runs started later are shipped from their first line
+    let mut first_pass = true;
+    let mut pending: Vec<Record> = Vec::new();
+
+    // Exits once the shipper handle is dropped
+    while Arc::strong_count(&sessions) > 1 {
+        let tracked = sessions.lock().unwrap().clone();
+        for (id, session) in &tracked {
+            let record = |event, timestamp: String, message: String| Record {
+                timestamp,
+                user: user.clone(),
+                host: host.clone(),
+                session_id: *id,
+                session: session.name.clone(),
+                target: session.target.clone(),
+                event,
+                message,
+            };
+            if let Some(new_text) = read_new(&mut offsets, &storage.log_file(id), first_pass) {
+                for line in new_text.lines().filter(|l| !l.trim().is_empty()) {
+                    // Shipped as events from the run history instead
+                    if line.contains(LIFECYCLE_MARKER) {
+                        continue;
+                    }
+                    pending.push(record("log", Utc::now().to_rfc3339(), line.to_string()));
+                }
+            }
+            if let Some(new_text) = read_new(&mut offsets, &storage.runs_file(id), first_pass) {
+                for run in new_text
+                    .lines()
+                    .filter_map(|l| serde_json::from_str::<RunRecord>(l).ok())
+                {
+                    pending.push(record(
+                        event_name(run.event),
+                        run.at.to_rfc3339(),
+                        run.log_line().trim_end().to_string(),
+                    ));
+                }
+            }
+        }
+
+        first_pass = false;
+
+        if !pending.is_empty() && send(&sink, &pending) {
+            pending.clear();
+        }
+        if pending.len() > MAX_PENDING {
+            pending.drain(..pending.len() - MAX_PENDING);
+        }
+        thread::sleep(SHIP_INTERVAL);
+    }
+}
+
+/// What was appended to `path` since the last call; everything again when
+/// it was cleared or replaced. A file seen for the first time counts as
+/// read up to its end when `skip_existing`, otherwise as unread.
+fn read_new(
+    offsets: &mut HashMap<PathBuf, u64>,
+    path: &Path,
+    skip_existing: bool,
+) -> Option<String> {
+    let len = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    let offset = *offsets
+        .entry(path.to_path_buf())
+        .or_insert(if skip_existing { len } else { 0 });
+    let offset = if len < offset { 0 } else { offset };
+    if len == offset {
+        return None;
+    }
+    let text = read_from(path, offset).ok()?;
+    offsets.insert(path.to_path_buf(), len);
+    Some(text)
+}
+
+fn read_from(path: &Path, offset: u64) -> std::io::Result<String> {
+    let mut file = fs::File::open(path)?;
+    file.seek(SeekFrom::Start(offset))?;
+    let mut buf = Vec::new();
+    file.read_to_end(&mut buf)?;
+    Ok(String::from_utf8_lossy(&buf).to_string())
+}
+
+fn event_name(event: RunEvent) -> &'static str {
+    match event {
+        RunEvent::Started => "started",
+        RunEvent::Stopped => "stopped",
+        RunEvent::Crashed => "crashed",
+        RunEvent::FailedEarly | RunEvent::FailedHealthCheck => "failed",
+    }
+}
+
+fn send(sink: &LogSink, records: &[Record]) -> bool {
+    match sink {
+        LogSink::Http(url) => send_http(url, records),
+        LogSink::Syslog(addr) => send_syslog(addr, records),
+    }
+}
+
+/// POST the batch as a JSON array. curl handles TLS and proxies for us.
+fn send_http(url: &str, records: &[Record]) -> bool {
+    let Ok(body) = serde_json::to_vec(records) else {
+        return false;
+    };
+    pipe_to(
+        Command::new("curl").args([
+            "-sS",
+            "-f",
+            "-m",
+            "10",
+            "-X",
+            "POST",
+            "-H",
+            "Content-Type: application/json",
+            "--data-binary",
+            "@-",
+            url,
+        ]),
+        &body,
+    )
+}
+
+/// RFC 5425 syslog over TLS (octet-counted RFC 5424 messages) through
+/// `openssl s_client`
+fn send_syslog(addr: &str, records: &[Record]) -> bool {
+    let mut payload = Vec::new();
+    for record in records {
+        // facility user (1); err for crashes/failures, info otherwise
+        let severity = match record.event {
+            "crashed" | "failed" => 3,
+            _ => 6,
+        };
+        let message = format!(
+            "<{}>1 {} {} pfman - {} [pfman@32473 user=\"{}\" session=\"{}\" target=\"{}\"] {}",
+            8 + severity,
+            record.timestamp,
+            if record.host.is_empty() {
+                "-"
+            } else {
+                &record.host
+            },
+            record.event,
+            sd_escape(&record.user),
+            sd_escape(&record.session),
+            sd_escape(&record.target),
+            record.message
+        );
+        payload.extend_from_slice(format!("{} {}", message.len(), message).as_bytes());
+    }
+    // The server's certificate must name it, and the connection closes
+    // once everything has been written (-quiet alone waits for the server)
+    let host = addr
+        .rsplit_once(':')
+        .map_or(addr, |(host, _)| host)
+        .trim_start_matches('[')
+        .trim_end_matches(']');
+    let verify = if host.parse::<IpAddr>().is_ok() {
+        "-verify_ip"
+    } else {
+        "-verify_hostname"
+    };
+    pipe_to(
+        Command::new("openssl").args([
+            "s_client",
+            "-quiet",
+            "-no_ign_eof",
+            "-nocommands",
+            "-verify_return_error",
+            verify,
+            host,
+            "-connect",
+            addr,
+        ]),
+        &payload,
+    )
+}
+
+/// `value` as an RFC 5424 structured data parameter value
+fn sd_escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace(']', "\\]")
+}
+
+fn pipe_to(cmd: &mut Command, input: &[u8]) -> bool {
+    let Ok(mut child) = cmd
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+    else {
+        return false;
+    };
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = stdin.write_all(input);
+    }
+    child.wait().is_ok_and(|status| status.success())
+}
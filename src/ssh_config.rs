@@ -1,5 +1,6 @@
+use crate::policy::glob_match;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Clone)]
 pub struct SshHost {
@@ -7,10 +8,28 @@ pub struct SshHost {
     pub hostname: Option<String>,
     pub user: Option<String>,
     pub port: Option<u16>,
+    pub proxy_jump: Option<String>,
+    /// First `IdentityFile` given for the host
+    pub identity_file: Option<String>,
 }
 
 impl SshHost {
+    /// Host with its port, jump host and key, when the config sets them
     pub fn display_name(&self) -> String {
+        let mut name = self.address();
+        if let Some(port) = self.port {
+            name.push_str(&format!(" port {}", port));
+        }
+        if let Some(jump) = &self.proxy_jump {
+            name.push_str(&format!(" via {}", jump));
+        }
+        if let Some(identity_file) = &self.identity_file {
+            name.push_str(&format!(" key {}", identity_file));
+        }
+        name
+    }
+
+    fn address(&self) -> String {
         if let Some(user) = &self.user {
             if let Some(hostname) = &self.hostname {
                 format!("{}@{} ({})", user, hostname, self.name)
@@ -33,19 +52,32 @@ impl SshHost {
     }
 }
 
+/// Includes nested deeper than this are ignored, like ssh does
+const MAX_INCLUDE_DEPTH: usize = 16;
+
 pub fn parse_ssh_config() -> Vec<SshHost> {
-    let config_path = get_ssh_config_path();
-    if !config_path.exists() {
-        return Vec::new();
+    let mut hosts = Vec::new();
+    let mut current_host: Option<SshHost> = None;
+    read_config(&get_ssh_config_path(), 0, &mut hosts, &mut current_host);
+
+    if let Some(host) = current_host {
+        hosts.push(host);
     }
 
-    let content = match fs::read_to_string(&config_path) {
-        Ok(c) => c,
-        Err(_) => return Vec::new(),
-    };
+    hosts
+}
 
-    let mut hosts = Vec::new();
-    let mut current_host: Option<SshHost> = None;
+/// Collect the hosts of one file. `Include`d files are read in place, as if
+/// their lines were part of this one.
+fn read_config(
+    path: &Path,
+    depth: usize,
+    hosts: &mut Vec<SshHost>,
+    current_host: &mut Option<SshHost>,
+) {
+    let Ok(content) = fs::read_to_string(path) else {
+        return;
+    };
 
     for line in content.lines() {
         let line = line.trim();
@@ -62,6 +94,16 @@ pub fn parse_ssh_config() -> Vec<SshHost> {
         let key = parts[0].to_lowercase();
 
         match key.as_str() {
+            "include" => {
+                if depth >= MAX_INCLUDE_DEPTH {
+                    continue;
+                }
+                for pattern in &parts[1..] {
+                    for included in expand_include(pattern) {
+                        read_config(&included, depth + 1, hosts, current_host);
+                    }
+                }
+            }
             "host" => {
                 if let Some(host) = current_host.take() {
                     hosts.push(host);
@@ -71,50 +113,108 @@ pub fn parse_ssh_config() -> Vec<SshHost> {
                     let host_name = parts[1..].join(" ");
                     // Skip wildcard hosts
                     if !host_name.contains('*') && !host_name.contains('?') {
-                        current_host = Some(SshHost {
+                        *current_host = Some(SshHost {
                             name: host_name,
                             hostname: None,
                             user: None,
                             port: None,
+                            proxy_jump: None,
+                            identity_file: None,
                         });
                     }
                 }
             }
             "hostname" => {
-                if let Some(ref mut host) = current_host {
-                    if parts.len() > 1 {
-                        host.hostname = Some(parts[1].to_string());
-                    }
+                if let Some(host) = current_host
+                    && parts.len() > 1
+                {
+                    host.hostname = Some(parts[1].to_string());
                 }
             }
             "user" => {
-                if let Some(ref mut host) = current_host {
-                    if parts.len() > 1 {
-                        host.user = Some(parts[1].to_string());
-                    }
+                if let Some(host) = current_host
+                    && parts.len() > 1
+                {
+                    host.user = Some(parts[1].to_string());
                 }
             }
             "port" => {
-                if let Some(ref mut host) = current_host {
-                    if parts.len() > 1 {
-                        host.port = parts[1].parse().ok();
-                    }
+                if let Some(host) = current_host
+                    && parts.len() > 1
+                {
+                    host.port = parts[1].parse().ok();
+                }
+            }
+            "proxyjump" => {
+                if let Some(host) = current_host
+                    && parts.len() > 1
+                {
+                    host.proxy_jump = Some(unquote(parts[1]));
+                }
+            }
+            "identityfile" => {
+                if let Some(host) = current_host
+                    && parts.len() > 1
+                    && host.identity_file.is_none()
+                {
+                    host.identity_file = Some(unquote(&parts[1..].join(" ")));
                 }
             }
             _ => {}
         }
     }
+}
 
-    if let Some(host) = current_host {
-        hosts.push(host);
+fn unquote(value: &str) -> String {
+    value.trim_matches('"').to_string()
+}
+
+/// Files an `Include` argument names, sorted. Relative paths are in
+/// `~/.ssh`, and `*` in any path component matches file names not
+/// starting with a dot.
+fn expand_include(pattern: &str) -> Vec<PathBuf> {
+    let ssh_dir = get_ssh_config_path()
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+    let path = match pattern.strip_prefix("~/") {
+        Some(rest) => home_dir().join(rest),
+        None => ssh_dir.join(pattern),
+    };
+
+    let mut matches = vec![PathBuf::new()];
+    for component in path.components() {
+        let name = component.as_os_str().to_string_lossy();
+        if !name.contains('*') {
+            for m in &mut matches {
+                m.push(component);
+            }
+            continue;
+        }
+        matches = matches
+            .iter()
+            .filter_map(|dir| fs::read_dir(dir).ok())
+            .flatten()
+            .flatten()
+            .filter(|entry| {
+                let file_name = entry.file_name().to_string_lossy().to_string();
+                !file_name.starts_with('.') && glob_match(&name, &file_name)
+            })
+            .map(|entry| entry.path())
+            .collect();
     }
 
-    hosts
+    matches.retain(|m| m.is_file());
+    matches.sort();
+    matches
+}
+
+fn home_dir() -> PathBuf {
+    PathBuf::from(std::env::var("HOME").unwrap_or_else(|_| ".".to_string()))
 }
 
 fn get_ssh_config_path() -> PathBuf {
-    let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
-    PathBuf::from(home).join(".ssh").join("config")
+    home_dir().join(".ssh").join("config")
 }
 
 pub fn filter_hosts(hosts: &[SshHost], query: &str) -> Vec<SshHost> {
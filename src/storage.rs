@@ -1,44 +1,207 @@
-use crate::models::Session;
-use color_eyre::Result;
-use std::fs;
-use std::path::PathBuf;
+use crate::audit::{self, AuditEntry};
+use crate::config::Config;
+use crate::models::{FormDraft, Session};
+use crate::runs::RunRecord;
+use chrono::{DateTime, Utc};
+use color_eyre::{Result, eyre::eyre};
+use directories::{BaseDirs, ProjectDirs};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
 use uuid::Uuid;
 
+/// Chunk size when scanning a log backwards for line breaks
+const TAIL_CHUNK_BYTES: u64 = 64 * 1024;
+/// Upper bound on what a tail read loads, however long the lines are
+const MAX_TAIL_BYTES: u64 = 1024 * 1024;
+
+/// Earlier versions of sessions.yaml kept around, newest first
+const SESSION_BACKUPS: usize = 5;
+
+/// Set from `--config-dir`, replaces the platform directories
+static CONFIG_DIR_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
+/// Use `dir` instead of the platform config directory, and `dir/data`
+/// instead of the data directory, for every storage opened afterwards.
+/// Only the first call has an effect.
+pub fn set_config_dir(dir: PathBuf) {
+    let _ = CONFIG_DIR_OVERRIDE.set(dir);
+}
+
+/// An earlier version of sessions.yaml; number 1 is the most recent
+#[derive(Debug, Clone)]
+pub struct SessionBackup {
+    pub number: usize,
+    /// When this version was last saved
+    pub saved_at: DateTime<Utc>,
+    pub sessions: Vec<Session>,
+}
+
 #[derive(Clone)]
 pub struct Storage {
     data_dir: PathBuf,
+    config_dir: PathBuf,
+    /// Name of each session's latest run log, shared between clones, so
+    /// its directory is only listed the first time it is asked for
+    latest_logs: Arc<Mutex<HashMap<Uuid, String>>>,
 }
 
 impl Storage {
+    /// Storage in the platform directories: `$XDG_CONFIG_HOME/pfman` and
+    /// `$XDG_DATA_HOME/pfman` on Linux, `~/Library/Application Support/pfman`
+    /// on macOS and `%APPDATA%`/`%LOCALAPPDATA%` on Windows. Until they
+    /// exist, the `~/.config/pfman` and `~/.local/share/pfman` of earlier
+    /// versions are used if those do.
     pub fn new() -> Result<Self> {
-        let data_dir = Self::get_data_dir()?;
+        if let Some(config_dir) = CONFIG_DIR_OVERRIDE.get() {
+            // Logs and the rest belong to that profile's sessions
+            return Self::with_dirs(config_dir.join("data"), config_dir.clone());
+        }
+        let (Some(dirs), Some(base)) = (ProjectDirs::from("", "", "pfman"), BaseDirs::new()) else {
+            return Err(eyre!("no home directory to store sessions in"));
+        };
+        let home = base.home_dir();
+        Self::with_dirs(
+            current_or_legacy(dirs.data_local_dir(), home.join(".local/share/pfman")),
+            current_or_legacy(dirs.config_dir(), home.join(".config/pfman")),
+        )
+    }
+
+    /// Storage rooted in a scratch directory, used by demo mode so the
+    /// user's real sessions and logs are never touched
+    pub fn in_dir(root: PathBuf) -> Result<Self> {
+        Self::with_dirs(root.join("data"), root.join("config"))
+    }
+
+    fn with_dirs(data_dir: PathBuf, config_dir: PathBuf) -> Result<Self> {
         fs::create_dir_all(&data_dir)?;
         fs::create_dir_all(data_dir.join("logs"))?;
+        fs::create_dir_all(data_dir.join("runs"))?;
+        fs::create_dir_all(&config_dir)?;
 
-        // Also create config directory for sessions file
-        let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
-        let config_dir = PathBuf::from(home).join(".config/pfman");
-        fs::create_dir_all(config_dir)?;
+        Ok(Self {
+            data_dir,
+            config_dir,
+            latest_logs: Arc::default(),
+        })
+    }
 
-        Ok(Self { data_dir })
+    fn sessions_file(&self) -> PathBuf {
+        self.config_dir.join("sessions.yaml")
     }
 
-    fn get_data_dir() -> Result<PathBuf> {
-        let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
-        Ok(PathBuf::from(home).join(".local/share/pfman"))
+    /// Locked by the instance supervising this profile's sessions
+    pub fn lock_file(&self) -> PathBuf {
+        self.config_dir.join("pfman.lock")
     }
 
-    fn sessions_file(&self) -> PathBuf {
-        let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
-        PathBuf::from(home).join(".config/pfman/sessions.yaml")
+    /// PID of the instance supervising this profile's sessions, kept apart
+    /// from the lock file, which can't be read while locked on Windows
+    pub fn pid_file(&self) -> PathBuf {
+        self.config_dir.join("pfman.pid")
+    }
+
+    fn config_file(&self) -> PathBuf {
+        self.config_dir.join("config.yaml")
+    }
+
+    pub fn load_config(&self) -> Result<Config> {
+        let file = self.config_file();
+        if !file.exists() {
+            return Ok(Config::default());
+        }
+        let content = fs::read_to_string(file)?;
+        Ok(serde_yaml::from_str(&content)?)
     }
 
+    /// Log of the session's latest run, where its output and notes go.
+    /// Sessions never started have none yet and write to the single log of
+    /// earlier versions, moved into place on the next start of pfman.
     pub fn log_file(&self, session_id: &Uuid) -> PathBuf {
+        let mut latest_logs = self.latest_logs.lock().unwrap();
+        if let Some(name) = latest_logs.get(session_id) {
+            return self.run_log_file(session_id, name);
+        }
+        let latest = fs::read_dir(self.run_logs_dir(session_id))
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .filter(|name| name.ends_with(".log"))
+            // Named by start time, so they sort in start order
+            .max();
+        match latest {
+            Some(name) => {
+                let file = self.run_log_file(session_id, &name);
+                latest_logs.insert(*session_id, name);
+                file
+            }
+            None => self.legacy_log_file(session_id),
+        }
+    }
+
+    /// The one log earlier versions kept for all runs of a session
+    pub fn legacy_log_file(&self, session_id: &Uuid) -> PathBuf {
         self.data_dir
             .join("logs")
             .join(format!("{}.log", session_id))
     }
 
+    /// Sessions that still have a log in [`Storage::legacy_log_file`]
+    pub fn legacy_logs(&self) -> Vec<Uuid> {
+        fs::read_dir(self.data_dir.join("logs"))
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_ok_and(|t| t.is_file()))
+            .filter_map(|entry| {
+                let name = entry.file_name().to_string_lossy().to_string();
+                name.strip_suffix(".log")?.parse().ok()
+            })
+            .collect()
+    }
+
+    fn run_logs_dir(&self, session_id: &Uuid) -> PathBuf {
+        self.data_dir.join("logs").join(session_id.to_string())
+    }
+
+    /// Log of one run, `name` as returned by [`Storage::new_run_log`]
+    pub fn run_log_file(&self, session_id: &Uuid, name: &str) -> PathBuf {
+        self.run_logs_dir(session_id).join(name)
+    }
+
+    /// Create the log of a run starting at `started_at`, which becomes the
+    /// session's latest log. Returns its file name.
+    pub fn new_run_log(&self, session_id: &Uuid, started_at: DateTime<Utc>) -> Result<String> {
+        fs::create_dir_all(self.run_logs_dir(session_id))?;
+        let name = run_log_name(started_at);
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.run_log_file(session_id, &name))?;
+        self.latest_logs
+            .lock()
+            .unwrap()
+            .insert(*session_id, name.clone());
+        Ok(name)
+    }
+
+    /// Remove the log of a run that failed to start
+    pub fn discard_run_log(&self, session_id: &Uuid, name: &str) {
+        let _ = fs::remove_file(self.run_log_file(session_id, name));
+        // The run before it is the latest again
+        self.latest_logs.lock().unwrap().remove(session_id);
+    }
+
+    /// Run history of a session, see [`crate::runs`]
+    pub fn runs_file(&self, session_id: &Uuid) -> PathBuf {
+        self.data_dir
+            .join("runs")
+            .join(format!("{}.jsonl", session_id))
+    }
+
     pub fn load_sessions(&self) -> Result<Vec<Session>> {
         let file = self.sessions_file();
         if !file.exists() {
@@ -49,25 +212,241 @@ impl Storage {
         Ok(sessions)
     }
 
+    /// Save the sessions. When definitions changed (not just status or
+    /// pids), the previous file is kept as `sessions.yaml.bak.1` and older
+    /// backups move up, dropping the oldest.
     pub fn save_sessions(&self, sessions: &[Session]) -> Result<()> {
         let content = serde_yaml::to_string(sessions)?;
-        fs::write(self.sessions_file(), content)?;
+        let file = self.sessions_file();
+        let saved = self.load_sessions();
+        if let Ok(saved) = &saved {
+            for entry in audit::definition_changes(saved, sessions) {
+                let _ = self.append_audit(&entry);
+            }
+        }
+        if file.exists() && definitions_changed(saved.ok().as_deref(), sessions) {
+            for number in (1..SESSION_BACKUPS).rev() {
+                let backup = self.backup_file(number);
+                if backup.exists() {
+                    fs::rename(backup, self.backup_file(number + 1))?;
+                }
+            }
+            // Renaming keeps the modification time, i.e. when it was saved
+            fs::rename(&file, self.backup_file(1))?;
+        }
+        fs::write(file, content)?;
+        Ok(())
+    }
+
+    /// Who did what to which session, see [`crate::audit`]
+    pub fn audit_file(&self) -> PathBuf {
+        self.data_dir.join("audit.jsonl")
+    }
+
+    pub fn append_audit(&self, entry: &AuditEntry) -> Result<()> {
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.audit_file())?;
+        file.write_all(line.as_bytes())?;
         Ok(())
     }
 
-    pub fn read_logs(&self, session_id: &Uuid) -> Result<String> {
-        let log_file = self.log_file(session_id);
+    /// Every audit entry, oldest first, skipping unreadable lines
+    pub fn read_audit(&self) -> Vec<AuditEntry> {
+        fs::read_to_string(self.audit_file())
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect()
+    }
+
+    fn backup_file(&self, number: usize) -> PathBuf {
+        self.config_dir
+            .join(format!("sessions.yaml.bak.{}", number))
+    }
+
+    /// Readable backups, newest first
+    pub fn session_backups(&self) -> Vec<SessionBackup> {
+        (1..=SESSION_BACKUPS)
+            .filter_map(|number| {
+                let file = self.backup_file(number);
+                let saved_at = fs::metadata(&file).ok()?.modified().ok()?.into();
+                let sessions = serde_yaml::from_str(&fs::read_to_string(&file).ok()?).ok()?;
+                Some(SessionBackup {
+                    number,
+                    saved_at,
+                    sessions,
+                })
+            })
+            .collect()
+    }
+
+    pub fn debug_log_file(&self) -> PathBuf {
+        self.data_dir.join("debug.log")
+    }
+
+    /// Left behind by the panic hook, removed once the user was notified
+    pub fn crash_marker_file(&self) -> PathBuf {
+        self.data_dir.join("crashed")
+    }
+
+    /// JSON status report for external watchdogs (`status_file` config)
+    pub fn status_file(&self) -> PathBuf {
+        self.data_dir.join("status.json")
+    }
+
+    /// Last catalog fetched, so it is available offline
+    pub fn catalog_file(&self) -> PathBuf {
+        self.data_dir.join("catalog.yaml")
+    }
+
+    /// Checkout of a git catalog source
+    pub fn catalog_repo_dir(&self) -> PathBuf {
+        self.data_dir.join("catalog-repo")
+    }
+
+    fn draft_file(&self) -> PathBuf {
+        self.data_dir.join("form_draft.yaml")
+    }
+
+    pub fn save_draft(&self, draft: &FormDraft) -> Result<()> {
+        fs::write(self.draft_file(), serde_yaml::to_string(draft)?)?;
+        Ok(())
+    }
+
+    /// Draft left behind by a previous run, if any
+    pub fn load_draft(&self) -> Option<FormDraft> {
+        let content = fs::read_to_string(self.draft_file()).ok()?;
+        serde_yaml::from_str(&content).ok()
+    }
+
+    pub fn clear_draft(&self) -> Result<()> {
+        let file = self.draft_file();
+        if file.exists() {
+            fs::remove_file(file)?;
+        }
+        Ok(())
+    }
+
+    /// Last `lines` lines of the session's latest log
+    pub fn read_log_tail(&self, session_id: &Uuid, lines: usize) -> Result<String> {
+        self.read_tail(&self.log_file(session_id), lines)
+    }
+
+    /// Last `lines` lines of a log, read by seeking from the end so large
+    /// logs cost the same as small ones
+    pub fn read_tail(&self, log_file: &Path, lines: usize) -> Result<String> {
         if !log_file.exists() {
             return Ok(String::new());
         }
-        Ok(fs::read_to_string(log_file)?)
+
+        let mut file = fs::File::open(log_file)?;
+        let len = file.metadata()?.len();
+        let mut start = len;
+        let mut buf = Vec::new();
+        // One extra line break: the file usually ends with one
+        while start > 0
+            && len - start < MAX_TAIL_BYTES
+            && buf.iter().filter(|&&b| b == b'\n').count() <= lines
+        {
+            let chunk = TAIL_CHUNK_BYTES.min(start);
+            start -= chunk;
+            file.seek(SeekFrom::Start(start))?;
+            let mut chunk_buf = vec![0; chunk as usize];
+            file.read_exact(&mut chunk_buf)?;
+            chunk_buf.extend_from_slice(&buf);
+            buf = chunk_buf;
+        }
+
+        let text = String::from_utf8_lossy(&buf);
+        let all: Vec<&str> = text.lines().collect();
+        Ok(all[all.len().saturating_sub(lines)..].join("\n"))
+    }
+
+    /// Truncate a log. Running processes keep appending to the same file
+    /// since it is opened in append mode.
+    pub fn clear_log(&self, log_file: &Path) -> Result<()> {
+        if log_file.exists() {
+            OpenOptions::new()
+                .write(true)
+                .truncate(true)
+                .open(log_file)?;
+        }
+        Ok(())
+    }
+
+    pub fn append_run(&self, session_id: &Uuid, record: &RunRecord) -> Result<()> {
+        let mut line = serde_json::to_string(record)?;
+        line.push('\n');
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.runs_file(session_id))?;
+        // One write per record, so concurrent writers don't interleave
+        file.write_all(line.as_bytes())?;
+        Ok(())
+    }
+
+    /// Replace a session's run records
+    pub fn write_runs(&self, session_id: &Uuid, records: &[RunRecord]) -> Result<()> {
+        let mut content = String::new();
+        for record in records {
+            content.push_str(&serde_json::to_string(record)?);
+            content.push('\n');
+        }
+        fs::write(self.runs_file(session_id), content)?;
+        Ok(())
+    }
+
+    /// A session's run records, oldest first, skipping unreadable lines;
+    /// `None` when it has no run history yet
+    pub fn read_runs(&self, session_id: &Uuid) -> Option<Vec<RunRecord>> {
+        let content = fs::read_to_string(self.runs_file(session_id)).ok()?;
+        Some(
+            content
+                .lines()
+                .filter_map(|line| serde_json::from_str(line).ok())
+                .collect(),
+        )
     }
 
     pub fn append_log(&self, session_id: &Uuid, content: &str) -> Result<()> {
-        let log_file = self.log_file(session_id);
-        let mut existing = self.read_logs(session_id)?;
-        existing.push_str(content);
-        fs::write(log_file, existing)?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.log_file(session_id))?;
+        file.write_all(content.as_bytes())?;
         Ok(())
     }
 }
+
+/// Whether `sessions` differ from the `saved` ones in more than runtime
+/// state. An unreadable file counts as changed, so it gets backed up.
+fn definitions_changed(saved: Option<&[Session]>, sessions: &[Session]) -> bool {
+    let Some(saved) = saved else {
+        return true;
+    };
+    saved.len() != sessions.len()
+        || saved.iter().zip(sessions).any(|(old, new)| {
+            old.id != new.id || old.name != new.name || crate::apply::differs(old, new)
+        })
+}
+
+/// File name of the log of a run started at `started_at`; they sort in
+/// start order
+pub fn run_log_name(started_at: DateTime<Utc>) -> String {
+    format!("{}.log", started_at.format("%Y%m%d-%H%M%S%.3f"))
+}
+
+/// `dir`, or `legacy` where earlier versions kept the same files if only
+/// that exists
+fn current_or_legacy(dir: &Path, legacy: PathBuf) -> PathBuf {
+    if !dir.exists() && legacy.exists() {
+        legacy
+    } else {
+        dir.to_path_buf()
+    }
+}
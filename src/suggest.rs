@@ -0,0 +1,219 @@
+use crate::kube_config::{KubeTarget, get_targets};
+use crate::models::{Session, SessionType};
+use crate::ssh_config::{SshHost, parse_ssh_config};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+
+/// Source of autocomplete entries for the session form. Implement this to
+/// feed hosts or kubectl targets from an inventory service or CMDB.
+pub trait SuggestionProvider: Send + Sync {
+    /// SSH/SOCKS5 targets
+    fn ssh_hosts(&self) -> Vec<SshHost> {
+        Vec::new()
+    }
+
+    /// kubectl targets for a context/namespace (None means the defaults).
+    /// Called off the UI thread, so it may block.
+    fn kube_targets(&self, _context: Option<&str>, _namespace: Option<&str>) -> Vec<KubeTarget> {
+        Vec::new()
+    }
+}
+
+pub type Providers = Vec<Arc<dyn SuggestionProvider>>;
+
+/// Hosts from `~/.ssh/config`
+pub struct SshConfigProvider;
+
+impl SuggestionProvider for SshConfigProvider {
+    fn ssh_hosts(&self) -> Vec<SshHost> {
+        parse_ssh_config()
+    }
+}
+
+/// Pods and services from the cluster via kubectl
+pub struct KubeProvider;
+
+impl SuggestionProvider for KubeProvider {
+    fn kube_targets(&self, context: Option<&str>, namespace: Option<&str>) -> Vec<KubeTarget> {
+        get_targets(None, context, namespace)
+    }
+}
+
+/// Targets of existing sessions, so they are offered even when no other
+/// source knows them
+pub struct HistoryProvider {
+    sessions: Vec<Session>,
+}
+
+impl HistoryProvider {
+    pub fn new(sessions: &[Session]) -> Self {
+        Self {
+            sessions: sessions.to_vec(),
+        }
+    }
+}
+
+impl SuggestionProvider for HistoryProvider {
+    fn ssh_hosts(&self) -> Vec<SshHost> {
+        self.sessions
+            .iter()
+            .filter(|s| s.session_type != SessionType::Kubectl)
+            .map(|s| {
+                let (user, name) = match s.target.split_once('@') {
+                    Some((user, host)) => (Some(user.to_string()), host.to_string()),
+                    None => (None, s.target.clone()),
+                };
+                SshHost {
+                    name,
+                    hostname: None,
+                    user,
+                    port: None,
+                    proxy_jump: None,
+                    identity_file: s.identity_file.clone(),
+                }
+            })
+            .collect()
+    }
+
+    fn kube_targets(&self, context: Option<&str>, namespace: Option<&str>) -> Vec<KubeTarget> {
+        self.sessions
+            .iter()
+            .filter(|s| s.session_type == SessionType::Kubectl)
+            .filter(|s| context.is_none() || s.kube_context.as_deref() == context)
+            .filter(|s| namespace.is_none() || s.kube_namespace.as_deref() == namespace)
+            .filter_map(|s| {
+                let (kind, name) = s.target.split_once('/')?;
+                Some(KubeTarget {
+                    kind: kind.to_string(),
+                    name: name.to_string(),
+                    namespace: s.kube_namespace.clone().unwrap_or_default(),
+                    ports: s.remote_port.into_iter().collect(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// External command printing suggestions as a JSON array. It is run as
+/// `<command> ssh` or `<command> kubectl <context> <namespace>` (empty
+/// strings for the defaults).
+pub struct ScriptProvider {
+    command: String,
+}
+
+#[derive(Deserialize)]
+struct ScriptHost {
+    name: String,
+    hostname: Option<String>,
+    user: Option<String>,
+    port: Option<u16>,
+    proxy_jump: Option<String>,
+    identity_file: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ScriptKubeTarget {
+    kind: String,
+    name: String,
+    #[serde(default)]
+    namespace: String,
+    #[serde(default)]
+    ports: Vec<u16>,
+}
+
+impl ScriptProvider {
+    pub fn new(command: String) -> Self {
+        Self { command }
+    }
+
+    fn run<T: for<'de> Deserialize<'de>>(&self, args: &[&str]) -> Vec<T> {
+        let script = format!("{} \"$@\"", self.command);
+        let output = Command::new("sh")
+            .arg("-c")
+            .arg(script)
+            .arg("sh")
+            .args(args)
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output();
+        match output {
+            Ok(output) if output.status.success() => {
+                serde_json::from_slice(&output.stdout).unwrap_or_default()
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl SuggestionProvider for ScriptProvider {
+    fn ssh_hosts(&self) -> Vec<SshHost> {
+        self.run::<ScriptHost>(&["ssh"])
+            .into_iter()
+            .map(|h| SshHost {
+                name: h.name,
+                hostname: h.hostname,
+                user: h.user,
+                port: h.port,
+                proxy_jump: h.proxy_jump,
+                identity_file: h.identity_file,
+            })
+            .collect()
+    }
+
+    fn kube_targets(&self, context: Option<&str>, namespace: Option<&str>) -> Vec<KubeTarget> {
+        self.run::<ScriptKubeTarget>(&[
+            "kubectl",
+            context.unwrap_or_default(),
+            namespace.unwrap_or_default(),
+        ])
+        .into_iter()
+        .map(|t| KubeTarget {
+            kind: t.kind,
+            name: t.name,
+            namespace: t.namespace,
+            ports: t.ports,
+        })
+        .collect()
+    }
+}
+
+/// ssh config and kubectl, the sources used when nothing else is configured
+pub fn default_providers() -> Providers {
+    vec![Arc::new(SshConfigProvider), Arc::new(KubeProvider)]
+}
+
+/// Built-in sources, then the configured scripts, then existing sessions
+pub fn providers(scripts: &[String], sessions: &[Session]) -> Providers {
+    let mut providers = default_providers();
+    for script in scripts {
+        providers.push(Arc::new(ScriptProvider::new(script.clone())));
+    }
+    providers.push(Arc::new(HistoryProvider::new(sessions)));
+    providers
+}
+
+/// Hosts from all providers; the first provider to list a host wins
+pub fn collect_ssh_hosts(providers: &[Arc<dyn SuggestionProvider>]) -> Vec<SshHost> {
+    let mut seen = HashSet::new();
+    providers
+        .iter()
+        .flat_map(|p| p.ssh_hosts())
+        .filter(|h| seen.insert(h.connection_string()))
+        .collect()
+}
+
+/// kubectl targets from all providers; the first provider to list a target wins
+pub fn collect_kube_targets(
+    providers: &[Arc<dyn SuggestionProvider>],
+    context: Option<&str>,
+    namespace: Option<&str>,
+) -> Vec<KubeTarget> {
+    let mut seen = HashSet::new();
+    providers
+        .iter()
+        .flat_map(|p| p.kube_targets(context, namespace))
+        .filter(|t| seen.insert(t.target_string()))
+        .collect()
+}
@@ -0,0 +1,101 @@
+use crate::restore::describe;
+use crate::ui::AppState;
+use crate::ui::dashboard::render_prompt;
+use ratatui::{
+    Frame,
+    layout::{Constraint, Layout, Rect},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph},
+};
+
+pub fn render(frame: &mut Frame, state: &AppState, area: Rect) {
+    let chunks = Layout::vertical([
+        Constraint::Length(3),
+        Constraint::Min(0),
+        Constraint::Length(3),
+    ])
+    .split(area);
+
+    let title = Paragraph::new("Session Backups")
+        .style(
+            Style::default()
+                .fg(state.theme.title)
+                .add_modifier(Modifier::BOLD),
+        )
+        .block(Block::default().borders(Borders::ALL));
+    frame.render_widget(title, chunks[0]);
+
+    let mut lines = Vec::new();
+    if state.backups.is_empty() {
+        lines.push(Line::from(Span::styled(
+            "No backups yet. One is kept every time sessions are added, edited or deleted.",
+            Style::default().fg(state.theme.muted),
+        )));
+    }
+    for (idx, backup) in state.backups.iter().enumerate() {
+        let selected = idx == state.backup_selected;
+        let marker = if selected { "> " } else { "  " };
+        let mut style = Style::default();
+        if selected {
+            style = style.add_modifier(Modifier::REVERSED);
+        }
+        lines.push(Line::from(Span::styled(
+            format!("{}{}", marker, describe(backup)),
+            style,
+        )));
+        if selected {
+            let names: Vec<&str> = backup.sessions.iter().map(|s| s.name.as_str()).collect();
+            lines.push(Line::from(Span::styled(
+                format!("    {}", names.join(", ")),
+                Style::default().fg(state.theme.muted),
+            )));
+        }
+    }
+
+    let list = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title("Earlier versions, newest first"),
+    );
+    frame.render_widget(list, chunks[1]);
+
+    render_help(frame, state, chunks[2]);
+
+    if state.restore_confirmation
+        && let Some(backup) = state.backups.get(state.backup_selected)
+    {
+        render_prompt(
+            frame,
+            "Restore This Backup?",
+            format!("Replaces sessions with {}", describe(backup)),
+            state.theme.warning,
+            &state.theme,
+            area,
+        );
+    }
+}
+
+fn render_help(frame: &mut Frame, state: &AppState, area: Rect) {
+    let theme = &state.theme;
+    let help_text = if state.restore_confirmation {
+        Line::from(vec![
+            Span::styled("y", Style::default().fg(theme.key)),
+            Span::raw(" restore | "),
+            Span::styled("n", Style::default().fg(theme.key)),
+            Span::raw(" cancel"),
+        ])
+    } else {
+        Line::from(vec![
+            Span::styled("↑/↓", Style::default().fg(theme.key)),
+            Span::raw(" select | "),
+            Span::styled("Enter", Style::default().fg(theme.key)),
+            Span::raw(" restore | "),
+            Span::styled("Esc", Style::default().fg(theme.key)),
+            Span::raw(" back"),
+        ])
+    };
+
+    let help = Paragraph::new(help_text).block(Block::default().borders(Borders::ALL));
+    frame.render_widget(help, area);
+}
@@ -0,0 +1,160 @@
+use crate::apply::differs;
+use crate::catalog::with_local_commands;
+use crate::models::Session;
+use crate::ui::AppState;
+use crate::ui::theme::Theme;
+use ratatui::{
+    Frame,
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Cell, Paragraph, Row, Table, Wrap},
+};
+
+/// How a catalog entry relates to the local sessions, matched by name
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EntryState {
+    New,
+    Added,
+    /// A local session has the name but different settings
+    Outdated,
+}
+
+impl EntryState {
+    pub fn of(entry: &Session, sessions: &[Session]) -> Self {
+        match sessions.iter().find(|s| s.name == entry.name) {
+            None => EntryState::New,
+            Some(local) if differs(local, &with_local_commands(entry.clone(), local)) => {
+                EntryState::Outdated
+            }
+            Some(_) => EntryState::Added,
+        }
+    }
+
+    fn label(&self, theme: &Theme) -> (&'static str, Color) {
+        match self {
+            EntryState::New => ("", theme.muted),
+            EntryState::Added => ("added", theme.success),
+            EntryState::Outdated => ("outdated", theme.warning),
+        }
+    }
+}
+
+pub fn render(frame: &mut Frame, state: &AppState, area: Rect) {
+    let theme = &state.theme;
+    let chunks = Layout::vertical([
+        Constraint::Length(3),
+        Constraint::Min(0),
+        Constraint::Length(3),
+    ])
+    .split(area);
+
+    let Some(catalog) = &state.catalog else {
+        render_title(frame, None, theme, chunks[0]);
+        let message = Paragraph::new("No catalog configured. Set `catalog` in config.yaml.")
+            .style(Style::default().fg(theme.muted))
+            .wrap(Wrap { trim: false })
+            .block(Block::default().borders(Borders::ALL));
+        frame.render_widget(message, chunks[1]);
+        render_help(frame, state, chunks[2]);
+        return;
+    };
+
+    let snapshot = catalog.snapshot();
+    let status = match (&snapshot.error, snapshot.synced_at) {
+        (Some(err), _) => Span::styled(
+            format!("Sync failed: {}", err),
+            Style::default().fg(theme.error),
+        ),
+        (None, Some(at)) => Span::styled(
+            format!(
+                "Synced {}",
+                at.with_timezone(&chrono::Local).format("%H:%M")
+            ),
+            Style::default().fg(theme.success),
+        ),
+        (None, None) => Span::styled("Syncing...", Style::default().fg(theme.warning)),
+    };
+    render_title(frame, Some(status), theme, chunks[0]);
+
+    let header = Row::new(vec![
+        Cell::from("Name").style(Style::default().add_modifier(Modifier::BOLD)),
+        Cell::from("Type").style(Style::default().add_modifier(Modifier::BOLD)),
+        Cell::from("Target").style(Style::default().add_modifier(Modifier::BOLD)),
+        Cell::from("Ports").style(Style::default().add_modifier(Modifier::BOLD)),
+        Cell::from("Local").style(Style::default().add_modifier(Modifier::BOLD)),
+    ]);
+    let rows: Vec<Row> = snapshot
+        .entries
+        .iter()
+        .enumerate()
+        .map(|(idx, entry)| {
+            let (label, color) = EntryState::of(entry, &state.sessions).label(theme);
+            let row = Row::new(vec![
+                Cell::from(entry.name.clone()),
+                Cell::from(entry.session_type.as_str().to_string()),
+                Cell::from(entry.target.clone()),
+                Cell::from(entry.port_mapping()),
+                Cell::from(label).style(Style::default().fg(color)),
+            ]);
+            if idx == state.catalog_selected {
+                row.style(Style::default().add_modifier(Modifier::REVERSED))
+            } else {
+                row
+            }
+        })
+        .collect();
+    let widths = [
+        Constraint::Fill(2),
+        Constraint::Length(7),
+        Constraint::Fill(3),
+        Constraint::Length(13),
+        Constraint::Length(9),
+    ];
+    let table = Table::new(rows, widths).header(header).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(format!("Catalog ({} sessions)", snapshot.entries.len())),
+    );
+    frame.render_widget(table, chunks[1]);
+
+    render_help(frame, state, chunks[2]);
+}
+
+fn render_title(frame: &mut Frame, status: Option<Span>, theme: &Theme, area: Rect) {
+    let mut spans = vec![Span::styled(
+        "Session Catalog",
+        Style::default()
+            .fg(theme.title)
+            .add_modifier(Modifier::BOLD),
+    )];
+    if let Some(status) = status {
+        spans.push(Span::raw("  "));
+        spans.push(status);
+    }
+    let title = Paragraph::new(Line::from(spans)).block(Block::default().borders(Borders::ALL));
+    frame.render_widget(title, area);
+}
+
+fn render_help(frame: &mut Frame, state: &AppState, area: Rect) {
+    let theme = &state.theme;
+    let help_text = match &state.catalog_message {
+        Some(message) => Line::from(Span::styled(
+            message.clone(),
+            Style::default().fg(theme.warning),
+        )),
+        None => Line::from(vec![
+            Span::styled("↑/↓", Style::default().fg(theme.key)),
+            Span::raw(" select | "),
+            Span::styled("Enter", Style::default().fg(theme.key)),
+            Span::raw(" add/update local session | "),
+            Span::styled("r", Style::default().fg(theme.key)),
+            Span::raw(" sync now | "),
+            Span::styled("Esc", Style::default().fg(theme.key)),
+            Span::raw(" back"),
+        ]),
+    };
+
+    let help = Paragraph::new(help_text).block(Block::default().borders(Borders::ALL));
+    frame.render_widget(help, area);
+}
@@ -1,13 +1,50 @@
-use crate::models::SessionStatus;
-use crate::ui::AppState;
+use crate::activity::PortActivity;
+use crate::health::HealthCheck;
+use crate::models::{Session, SessionStatus, SessionType};
+use crate::ui::session_table::{
+    SessionTable, format_latency, health_label, name_cell, port_mapping, selected_row_style,
+    status_color, status_glyph, status_label,
+};
+use crate::ui::theme::Theme;
+use crate::ui::{AppState, spinner_frame};
+use crate::usage::format_duration;
 use ratatui::{
     Frame,
     layout::{Alignment, Constraint, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, Cell, Clear, Paragraph, Row, Table},
+    widgets::{Block, Borders, Cell, Clear, Paragraph, Row, Table, Wrap},
 };
 use std::time::{SystemTime, UNIX_EPOCH};
+use uuid::Uuid;
+
+/// Below this width the dashboard switches to the condensed list view
+pub(crate) const COMPACT_MAX_WIDTH: u16 = 80;
+/// From this width on a details pane is shown next to the sessions table
+const DETAILS_MIN_WIDTH: u16 = 140;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum LayoutMode {
+    Compact,
+    Table,
+    TableWithDetails,
+    /// Details plus a live tail of the selected session's log
+    TableWithPreview,
+}
+
+impl LayoutMode {
+    fn for_width(width: u16, preview: bool) -> Self {
+        if width < COMPACT_MAX_WIDTH {
+            LayoutMode::Compact
+        } else if preview {
+            LayoutMode::TableWithPreview
+        } else if width < DETAILS_MIN_WIDTH {
+            LayoutMode::Table
+        } else {
+            LayoutMode::TableWithDetails
+        }
+    }
+}
 
 pub fn render(frame: &mut Frame, state: &AppState, area: Rect) {
     let chunks = Layout::vertical([
@@ -17,151 +54,645 @@ pub fn render(frame: &mut Frame, state: &AppState, area: Rect) {
     ])
     .split(area);
 
-    render_title(frame, chunks[0]);
-    render_table(frame, state, chunks[1]);
-    render_help(frame, state, chunks[2]);
+    let mode = LayoutMode::for_width(area.width, state.preview_pane);
+
+    render_title(frame, state, chunks[0]);
+    match mode {
+        LayoutMode::Compact => render_compact_list(frame, state, chunks[1]),
+        LayoutMode::Table => render_table(frame, state, chunks[1]),
+        LayoutMode::TableWithDetails => {
+            let columns =
+                Layout::horizontal([Constraint::Min(0), Constraint::Length(48)]).split(chunks[1]);
+            render_table(frame, state, columns[0]);
+            render_details(frame, state, columns[1]);
+        }
+        LayoutMode::TableWithPreview => {
+            let columns = Layout::horizontal([Constraint::Min(0), Constraint::Percentage(45)])
+                .split(chunks[1]);
+            let rows = Layout::vertical([Constraint::Percentage(40), Constraint::Min(0)])
+                .split(columns[1]);
+            render_table(frame, state, columns[0]);
+            render_details(frame, state, rows[0]);
+            render_log_preview(frame, state, rows[1]);
+        }
+    }
+    render_help(frame, state, mode, chunks[2]);
 
+    let theme = &state.theme;
     // Render confirmation dialog on top if active
-    if state.delete_confirmation.is_some() {
-        render_delete_confirmation(frame, state, area);
+    if let Some(log_path) = &state.crash_notice {
+        render_prompt(
+            frame,
+            "pfman crashed last time",
+            format!("Details: {}", log_path.display()),
+            theme.error,
+            theme,
+            area,
+        );
+    } else if state.quit_prompt {
+        let running = state.sessions.iter().filter(|s| s.pid.is_some()).count();
+        render_prompt(
+            frame,
+            "Quit pfman?",
+            format!("{} session(s) still running", running),
+            theme.warning,
+            theme,
+            area,
+        );
+    } else if let Some(id) = state.value_view
+        && let Some(session) = state.sessions.iter().find(|s| s.id == id)
+    {
+        render_value_view(frame, session, theme, area);
+    } else if let Some(id) = state.delete_confirmation {
+        render_confirmation(frame, state, id, "Delete Session?", theme.error, area);
+    } else if let Some(id) = state.stop_confirmation {
+        render_confirmation(frame, state, id, "Stop Session?", theme.warning, area);
+    } else if let Some(id) = state.restart_confirmation {
+        render_confirmation(frame, state, id, "Restart Session?", theme.warning, area);
+    } else if let Some(id) = state.start_confirmation {
+        render_confirmation(frame, state, id, "Start Session Now?", theme.success, area);
+    } else if let Some(id) = state.expiry_prompt
+        && let Some(session) = state.sessions.iter().find(|s| s.id == id)
+    {
+        render_prompt(
+            frame,
+            "Keep This Tunnel Up?",
+            format!(
+                "{} has been up for {}",
+                session.name,
+                session.uptime_string()
+            ),
+            theme.accent,
+            theme,
+            area,
+        );
+    } else if let Some(draft) = &state.pending_draft {
+        let name = if draft.name.is_empty() {
+            "(unnamed)"
+        } else {
+            draft.name.as_str()
+        };
+        render_prompt(
+            frame,
+            "Restore Unsaved Form?",
+            format!("Session: {}", name),
+            theme.warning,
+            theme,
+            area,
+        );
     }
 }
 
-fn render_title(frame: &mut Frame, area: Rect) {
-    let title = Paragraph::new("Port-Forwarding Manager")
-        .style(
+fn render_title(frame: &mut Frame, state: &AppState, area: Rect) {
+    let text = if state.process_manager.is_simulated() {
+        "Port-Forwarding Manager [DEMO]"
+    } else {
+        "Port-Forwarding Manager"
+    };
+    let mut spans = vec![Span::styled(
+        text,
+        Style::default()
+            .fg(state.theme.title)
+            .add_modifier(Modifier::BOLD),
+    )];
+    if state.network_up() == Some(false) {
+        spans.push(Span::styled(
+            "  Network degraded",
             Style::default()
-                .fg(Color::Cyan)
+                .fg(state.theme.error)
                 .add_modifier(Modifier::BOLD),
-        )
-        .block(Block::default().borders(Borders::ALL));
+        ));
+    }
+    if let Some(err) = &state.api_error {
+        spans.push(Span::styled(
+            format!("  API off: {}", err),
+            Style::default().fg(state.theme.error),
+        ));
+    }
+    if let Some(err) = &state.ipc_error {
+        spans.push(Span::styled(
+            format!("  IPC off: {}", err),
+            Style::default().fg(state.theme.error),
+        ));
+    }
+    if let Some(storm) = state.retry.storm() {
+        let probing = storm
+            .canary
+            .and_then(|id| state.sessions.iter().find(|s| s.id == id))
+            .map(|s| format!(", probing {}", s.name))
+            .unwrap_or_default();
+        spans.push(Span::styled(
+            format!(
+                "  Network outage: {} sessions held{}",
+                storm.held.len(),
+                probing
+            ),
+            Style::default()
+                .fg(state.theme.error)
+                .add_modifier(Modifier::BOLD),
+        ));
+    }
+    let title = Paragraph::new(Line::from(spans)).block(Block::default().borders(Borders::ALL));
     frame.render_widget(title, area);
 }
 
+fn sessions_title(state: &AppState, count: usize) -> String {
+    // Add blinking cursor to search query in title
+    if state.search_mode {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis();
+        let show_cursor = (now / 500).is_multiple_of(2);
+
+        let cursor_pos = state.search_cursor_pos.min(state.search_query.len());
+        let cursor = if state.config.accessible { "_" } else { "█" };
+        let search_display = if show_cursor {
+            format!(
+                "{}{}{}",
+                &state.search_query[..cursor_pos],
+                cursor,
+                &state.search_query[cursor_pos..]
+            )
+        } else {
+            state.search_query.clone()
+        };
+        format!("Sessions (Search: {})", search_display)
+    } else {
+        format!("Sessions ({})", count)
+    }
+}
+
 fn render_table(frame: &mut Frame, state: &AppState, area: Rect) {
     let filtered = state.filtered_sessions();
 
-    let header = Row::new(vec![
-        Cell::from("Name").style(Style::default().add_modifier(Modifier::BOLD)),
-        Cell::from("Type").style(Style::default().add_modifier(Modifier::BOLD)),
-        Cell::from("Target").style(Style::default().add_modifier(Modifier::BOLD)),
-        Cell::from("Ports").style(Style::default().add_modifier(Modifier::BOLD)),
-        Cell::from("Status").style(Style::default().add_modifier(Modifier::BOLD)),
-        Cell::from("Uptime").style(Style::default().add_modifier(Modifier::BOLD)),
-    ])
-    .height(1);
+    let table = SessionTable::new(filtered.iter().map(|(_, s)| *s))
+        .selected(state.selected_row_id())
+        .accessible(state.config.accessible)
+        .remind_after(state.config.remind_after())
+        .latencies(state.process_manager.latencies())
+        .health(state.process_manager.health_checks())
+        .show_ids(state.config.show_ids)
+        .theme(state.theme)
+        .truncation(state.config.truncation)
+        .column_widths(state.config.column_widths)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(sessions_title(state, filtered.len())),
+        );
+
+    frame.render_widget(table, area);
+}
+
+fn render_compact_list(frame: &mut Frame, state: &AppState, area: Rect) {
+    let filtered = state.filtered_sessions();
+    let sessions: Vec<&Session> = filtered.iter().map(|(_, s)| *s).collect();
+
+    let table = compact_table(
+        &sessions,
+        state.selected_index(),
+        state.config.accessible,
+        &state.theme,
+    )
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(sessions_title(state, filtered.len())),
+    );
+
+    frame.render_widget(table, area);
+}
 
-    let rows: Vec<Row> = filtered
+/// Condensed list of status glyph, name and port for narrow terminals
+pub(crate) fn compact_table<'a>(
+    sessions: &[&'a Session],
+    selected: Option<usize>,
+    accessible: bool,
+    theme: &Theme,
+) -> Table<'a> {
+    let rows: Vec<Row> = sessions
         .iter()
         .enumerate()
-        .map(|(idx, (_, session))| {
-            let (status_text, status_color) = match &session.status {
-                SessionStatus::Running => (session.status.as_str().to_string(), Color::Green),
-                SessionStatus::Stopped => (session.status.as_str().to_string(), Color::Gray),
-                SessionStatus::Error(msg) => {
-                    let short_msg = if msg.len() > 30 {
-                        format!("{}...", &msg[..27])
-                    } else {
-                        msg.clone()
-                    };
-                    (format!("Error: {}", short_msg), Color::Red)
-                }
-            };
-
-            let style = if idx == state.selected_index {
-                Style::default()
-                    .bg(Color::DarkGray)
-                    .fg(Color::White)
-                    .add_modifier(Modifier::BOLD)
+        .map(|(idx, session)| {
+            let style = if Some(idx) == selected {
+                selected_row_style(theme)
             } else {
                 Style::default()
             };
 
             Row::new(vec![
-                Cell::from(session.name.clone()),
-                Cell::from(session.session_type.as_str()),
-                Cell::from(session.target.clone()),
-                Cell::from(session.port_mapping()),
-                Cell::from(status_text).style(Style::default().fg(status_color)),
-                Cell::from(session.uptime_string()),
+                Cell::from(if session.is_transitional() {
+                    spinner_frame(accessible)
+                } else {
+                    status_glyph(&session.status, accessible)
+                })
+                .style(Style::default().fg(status_color(session, theme))),
+                name_cell(session, theme),
+                // A reverse proxy's port is on the target
+                Cell::from(if session.session_type.listens_locally() {
+                    session.local_port_label()
+                } else {
+                    session.listen_port().to_string()
+                }),
             ])
             .style(style)
         })
         .collect();
 
+    let glyph_width = if accessible { 5 } else { 1 };
     let widths = [
-        Constraint::Percentage(20),
-        Constraint::Percentage(10),
-        Constraint::Percentage(25),
-        Constraint::Percentage(15),
-        Constraint::Percentage(15),
-        Constraint::Percentage(15),
+        Constraint::Length(glyph_width),
+        Constraint::Fill(1),
+        Constraint::Length(5),
     ];
 
-    // Add blinking cursor to search query in title
-    let title = if state.search_mode {
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_millis();
-        let show_cursor = (now / 500) % 2 == 0;
+    Table::new(rows, widths)
+}
 
-        let cursor_pos = state.search_cursor_pos.min(state.search_query.len());
-        let search_display = if show_cursor {
-            format!(
-                "{}█{}",
-                &state.search_query[..cursor_pos],
-                &state.search_query[cursor_pos..]
-            )
-        } else {
-            state.search_query.clone()
-        };
-        format!("Sessions (Search: {})", search_display)
+fn render_details(frame: &mut Frame, state: &AppState, area: Rect) {
+    let block = Block::default().borders(Borders::ALL).title("Details");
+
+    let Some((_, session)) = state.selected_session() else {
+        let empty = Paragraph::new("No session selected")
+            .style(Style::default().fg(state.theme.muted))
+            .block(block);
+        frame.render_widget(empty, area);
+        return;
+    };
+
+    let command = state.process_manager.command_preview(session);
+    let latency = state.process_manager.latencies().get(&session.id).copied();
+    let health = state
+        .process_manager
+        .health_checks()
+        .get(&session.id)
+        .cloned();
+    let activity = state.process_manager.activity().get(&session.id).cloned();
+    let details = Paragraph::new(details_lines(
+        session,
+        command,
+        latency,
+        health,
+        activity,
+        state.config.accessible,
+        &state.theme,
+    ))
+    .block(block)
+    .wrap(Wrap { trim: false });
+    frame.render_widget(details, area);
+}
+
+/// Last lines of the selected session's log, re-read on every frame
+fn render_log_preview(frame: &mut Frame, state: &AppState, area: Rect) {
+    let block = Block::default().borders(Borders::ALL).title("Logs");
+    let Some((_, session)) = state.selected_session() else {
+        frame.render_widget(block, area);
+        return;
+    };
+
+    let lines = area.height.saturating_sub(2) as usize;
+    let logs = state
+        .storage
+        .read_log_tail(&session.id, lines)
+        .unwrap_or_else(|_| "Failed to read logs".to_string());
+    let preview = if logs.is_empty() {
+        Paragraph::new("No logs yet").style(Style::default().fg(state.theme.muted))
     } else {
-        format!("Sessions ({})", filtered.len())
+        Paragraph::new(logs)
     };
+    frame.render_widget(preview.block(block), area);
+}
 
-    let table = Table::new(rows, widths)
-        .header(header)
-        .block(Block::default().borders(Borders::ALL).title(title))
-        .row_highlight_style(Style::default().add_modifier(Modifier::BOLD));
+fn details_lines<'a>(
+    session: &'a Session,
+    command: String,
+    latency: Option<std::time::Duration>,
+    health: Option<HealthCheck>,
+    activity: Option<PortActivity>,
+    accessible: bool,
+    theme: &Theme,
+) -> Vec<Line<'a>> {
+    let label =
+        |text: &'static str| Span::styled(text, Style::default().add_modifier(Modifier::BOLD));
 
-    frame.render_widget(table, area);
+    let mut lines = vec![
+        Line::from(vec![label("Name: "), Span::raw(session.display_name())]),
+        Line::from(vec![label("ID: "), Span::raw(session.short_id())]),
+        Line::from(vec![
+            label("Type: "),
+            Span::raw(session.session_type.as_str()),
+        ]),
+        Line::from(vec![label("Target: "), Span::raw(&session.target)]),
+    ];
+    if !session.tags.is_empty() {
+        lines.push(Line::from(vec![
+            label("Tags: "),
+            Span::raw(session.tags.join(", ")),
+        ]));
+    }
+
+    if session.session_type == SessionType::Kubectl {
+        if let Some(ctx) = &session.kube_context {
+            lines.push(Line::from(vec![label("Context: "), Span::raw(ctx)]));
+        }
+        if let Some(ns) = &session.kube_namespace {
+            lines.push(Line::from(vec![label("Namespace: "), Span::raw(ns)]));
+        }
+    }
+
+    let mut ports = vec![
+        label("Ports: "),
+        Span::raw(port_mapping(session, accessible)),
+    ];
+    if session.auto_local_port {
+        ports.push(Span::styled(" (auto)", Style::default().fg(theme.muted)));
+    }
+    lines.push(Line::from(ports));
+    lines.push(Line::from(vec![
+        label("Status: "),
+        Span::styled(
+            if session.is_transitional() {
+                status_label(session, accessible)
+            } else {
+                session.status.as_str().to_string()
+            },
+            Style::default().fg(status_color(session, theme)),
+        ),
+    ]));
+    if let Some(pid) = session.pid {
+        lines.push(Line::from(vec![label("PID: "), Span::raw(pid.to_string())]));
+    }
+    if let Some(scope) = &session.scope {
+        lines.push(Line::from(vec![
+            label("Terminal: "),
+            Span::raw(&scope.label),
+        ]));
+    }
+    lines.push(Line::from(vec![
+        label("Uptime: "),
+        Span::raw(session.uptime_string()),
+    ]));
+    if session.stats.starts > 0 {
+        lines.push(Line::from(vec![
+            label("All Time: "),
+            Span::raw(format!(
+                "{} starts, {} crashes, {} up",
+                session.stats.starts,
+                session.stats.crashes,
+                format_duration(chrono::Duration::seconds(session.stats.uptime_secs as i64))
+            )),
+        ]));
+    }
+    if session.reconnects > 0 {
+        lines.push(Line::from(vec![
+            label("Reconnects: "),
+            Span::raw(format!("{} (pod replaced)", session.reconnects)),
+        ]));
+    }
+    if let Some(latency) = latency {
+        lines.push(Line::from(vec![
+            label("Latency: "),
+            Span::raw(format_latency(latency)),
+        ]));
+    }
+    if let Some(health_command) = &session.health_command {
+        let mut spans = vec![label("Health: ")];
+        match health {
+            Some(check) => {
+                let color = if check.healthy {
+                    theme.success
+                } else {
+                    theme.error
+                };
+                spans.push(Span::styled(
+                    health_label(&check),
+                    Style::default().fg(color),
+                ));
+                spans.push(Span::styled(
+                    format!(
+                        " at {}: {}",
+                        check
+                            .checked_at
+                            .with_timezone(&chrono::Local)
+                            .format("%H:%M:%S"),
+                        check.detail
+                    ),
+                    Style::default().fg(theme.muted),
+                ));
+            }
+            None if session.status == SessionStatus::Running => spans.push(Span::styled(
+                "Not checked yet",
+                Style::default().fg(theme.muted),
+            )),
+            None => {}
+        }
+        if spans.len() > 1 {
+            lines.push(Line::from(spans));
+        }
+        lines.push(Line::from(vec![
+            label("Health Check: "),
+            Span::styled(health_command, Style::default().fg(theme.muted)),
+        ]));
+    }
+    for (name, command) in [
+        ("Pre-start: ", &session.pre_start),
+        ("Post-stop: ", &session.post_stop),
+    ] {
+        if let Some(command) = command {
+            lines.push(Line::from(vec![
+                label(name),
+                Span::styled(command, Style::default().fg(theme.muted)),
+            ]));
+        }
+    }
+    if let Some(minutes) = session.idle_timeout_minutes {
+        let mut spans = vec![
+            label("Idle Stop: "),
+            Span::raw(format!("after {} min without connections", minutes)),
+        ];
+        let running = session.status.is_running();
+        match activity.filter(|_| running) {
+            Some(activity) if activity.connections > 0 => spans.push(Span::styled(
+                format!(" ({} open now)", activity.connections),
+                Style::default().fg(theme.muted),
+            )),
+            Some(activity) => {
+                if let Some(since) = activity.last_active.max(session.last_started) {
+                    let idle = (chrono::Utc::now() - since).num_minutes();
+                    spans.push(Span::styled(
+                        format!(" (idle {} min)", idle),
+                        Style::default().fg(theme.muted),
+                    ));
+                }
+            }
+            None => {}
+        }
+        lines.push(Line::from(spans));
+    }
+    if let Some(lifetime) = session.max_lifetime {
+        let mut spans = vec![label("Max Lifetime: "), Span::raw(lifetime.to_string())];
+        if matches!(
+            session.status,
+            SessionStatus::Running | SessionStatus::Degraded(_) | SessionStatus::Starting
+        ) && let Some(started) = session.last_started
+        {
+            let ends = (started + lifetime.duration()).with_timezone(&chrono::Local);
+            spans.push(Span::styled(
+                format!(" (stops at {})", ends.format("%H:%M")),
+                Style::default().fg(theme.muted),
+            ));
+        }
+        lines.push(Line::from(spans));
+    }
+    if session.login_prompts {
+        lines.push(Line::from(vec![
+            label("Login prompts: "),
+            Span::raw("answered in pfman"),
+        ]));
+    }
+    if session.auto_restart {
+        lines.push(Line::from(vec![
+            label("Auto-restart: "),
+            Span::raw("on failure, with backoff"),
+        ]));
+    }
+    lines.push(Line::from(vec![
+        label("Command: "),
+        Span::styled(command, Style::default().fg(theme.muted)),
+    ]));
+
+    if let SessionStatus::Error(err) = &session.status {
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(
+            err.message.as_str(),
+            Style::default().fg(theme.error),
+        )));
+        let mut failed = format!(
+            "{} at {}",
+            err.kind.as_str(),
+            err.at
+                .with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M:%S")
+        );
+        if let Some(code) = err.exit_code {
+            failed.push_str(&format!(", exit code {}", code));
+        }
+        lines.push(Line::from(Span::styled(
+            failed,
+            Style::default().fg(theme.muted),
+        )));
+    }
+    if let SessionStatus::Degraded(reason) = &session.status {
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(
+            format!("Process up but tunnel broken: {}", reason),
+            Style::default().fg(theme.warning),
+        )));
+    }
+
+    lines
 }
 
-fn render_help(frame: &mut Frame, state: &AppState, area: Rect) {
-    let help_text = if state.delete_confirmation.is_some() {
+fn render_help(frame: &mut Frame, state: &AppState, mode: LayoutMode, area: Rect) {
+    let theme = &state.theme;
+    let help_text = if state.crash_notice.is_some() || state.value_view.is_some() {
+        Line::from(vec![
+            Span::styled("any key", Style::default().fg(theme.key)),
+            Span::raw(" dismiss"),
+        ])
+    } else if state.quit_prompt {
+        Line::from(vec![
+            Span::styled("k", Style::default().fg(theme.key)),
+            Span::raw(" keep them running | "),
+            Span::styled("s", Style::default().fg(theme.key)),
+            Span::raw(" stop them | "),
+            Span::styled("Esc", Style::default().fg(theme.key)),
+            Span::raw(" cancel"),
+        ])
+    } else if state.expiry_prompt.is_some() {
+        Line::from(vec![
+            Span::styled("y", Style::default().fg(theme.key)),
+            Span::raw(" keep running | "),
+            Span::styled("n", Style::default().fg(theme.key)),
+            Span::raw(" stop it"),
+        ])
+    } else if state.delete_confirmation.is_some()
+        || state.stop_confirmation.is_some()
+        || state.restart_confirmation.is_some()
+        || state.start_confirmation.is_some()
+        || state.pending_draft.is_some()
+    {
         Line::from(vec![
-            Span::styled("y", Style::default().fg(Color::Yellow)),
+            Span::styled("y", Style::default().fg(theme.key)),
             Span::raw(" confirm | "),
-            Span::styled("n", Style::default().fg(Color::Yellow)),
+            Span::styled("n", Style::default().fg(theme.key)),
             Span::raw(" cancel | "),
-            Span::styled("Esc", Style::default().fg(Color::Yellow)),
+            Span::styled("Esc", Style::default().fg(theme.key)),
             Span::raw(" cancel"),
         ])
     } else if state.search_mode {
         Line::from(vec![
             Span::raw("Type to search | "),
-            Span::styled("Esc", Style::default().fg(Color::Yellow)),
+            Span::styled("Esc", Style::default().fg(theme.key)),
             Span::raw(" cancel | "),
-            Span::styled("Enter", Style::default().fg(Color::Yellow)),
+            Span::styled("Enter", Style::default().fg(theme.key)),
             Span::raw(" apply"),
         ])
+    } else if mode == LayoutMode::Compact {
+        Line::from(vec![
+            Span::styled("c", Style::default().fg(theme.key)),
+            Span::raw(" new "),
+            Span::styled("e", Style::default().fg(theme.key)),
+            Span::raw(" edit "),
+            Span::styled("d", Style::default().fg(theme.key)),
+            Span::raw(" del "),
+            Span::styled("s", Style::default().fg(theme.key)),
+            Span::raw(" run "),
+            Span::styled("l", Style::default().fg(theme.key)),
+            Span::raw(" logs "),
+            Span::styled("q", Style::default().fg(theme.key)),
+            Span::raw(" quit"),
+        ])
     } else {
         Line::from(vec![
-            Span::styled("c", Style::default().fg(Color::Yellow)),
+            Span::styled("c", Style::default().fg(theme.key)),
             Span::raw(" create | "),
-            Span::styled("e", Style::default().fg(Color::Yellow)),
+            Span::styled("e", Style::default().fg(theme.key)),
             Span::raw(" edit | "),
-            Span::styled("d", Style::default().fg(Color::Yellow)),
+            Span::styled("d", Style::default().fg(theme.key)),
             Span::raw(" delete | "),
-            Span::styled("s", Style::default().fg(Color::Yellow)),
+            Span::styled("s", Style::default().fg(theme.key)),
             Span::raw(" start/stop | "),
-            Span::styled("l", Style::default().fg(Color::Yellow)),
+            Span::styled("S", Style::default().fg(theme.key)),
+            Span::raw(" login prompts | "),
+            Span::styled("R", Style::default().fg(theme.key)),
+            Span::raw(" auto-restart | "),
+            Span::styled("P", Style::default().fg(theme.key)),
+            Span::raw("/"),
+            Span::styled("r", Style::default().fg(theme.key)),
+            Span::raw(" pause/resume all | "),
+            Span::styled("l", Style::default().fg(theme.key)),
             Span::raw(" view logs | "),
-            Span::styled("/", Style::default().fg(Color::Yellow)),
+            Span::styled("Enter", Style::default().fg(theme.key)),
+            Span::raw(" error details | "),
+            Span::styled("i", Style::default().fg(theme.key)),
+            Span::raw(" import | "),
+            Span::styled("t", Style::default().fg(theme.key)),
+            Span::raw(" triage | "),
+            Span::styled("u", Style::default().fg(theme.key)),
+            Span::raw(" usage | "),
+            Span::styled("C", Style::default().fg(theme.key)),
+            Span::raw(" catalog | "),
+            Span::styled("p", Style::default().fg(theme.key)),
+            Span::raw(" preview | "),
+            Span::styled("v", Style::default().fg(theme.key)),
+            Span::raw(" full values | "),
+            Span::styled("/", Style::default().fg(theme.key)),
             Span::raw(" search | "),
-            Span::styled("q", Style::default().fg(Color::Yellow)),
+            Span::styled("Ctrl+P", Style::default().fg(theme.key)),
+            Span::raw(" actions | "),
+            Span::styled("q", Style::default().fg(theme.key)),
             Span::raw(" quit"),
         ])
     };
@@ -170,46 +701,121 @@ fn render_help(frame: &mut Frame, state: &AppState, area: Rect) {
     frame.render_widget(help, area);
 }
 
-fn render_delete_confirmation(frame: &mut Frame, state: &AppState, area: Rect) {
-    if let Some(idx) = state.delete_confirmation {
-        let session_name = state
-            .sessions
-            .get(idx)
-            .map(|s| s.name.as_str())
-            .unwrap_or("Unknown");
-
-        // Create centered popup
-        let popup_width = 50.min(area.width - 4);
-        let popup_height = 7;
-        let popup_x = (area.width.saturating_sub(popup_width)) / 2;
-        let popup_y = (area.height.saturating_sub(popup_height)) / 2;
-
-        let popup_area = Rect {
-            x: area.x + popup_x,
-            y: area.y + popup_y,
-            width: popup_width,
-            height: popup_height,
-        };
+fn render_confirmation(
+    frame: &mut Frame,
+    state: &AppState,
+    id: Uuid,
+    question: &str,
+    color: Color,
+    area: Rect,
+) {
+    let session_name = state
+        .sessions
+        .iter()
+        .find(|s| s.id == id)
+        .map(|s| s.name.as_str())
+        .unwrap_or("Unknown");
 
-        let text = vec![
-            Line::from(""),
-            Line::from(Span::styled(
-                "Delete Session?",
-                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
-            )),
-            Line::from(""),
-            Line::from(Span::raw(format!("Session: {}", session_name))),
-            Line::from(""),
-        ];
+    render_prompt(
+        frame,
+        question,
+        format!("Session: {}", session_name),
+        color,
+        &state.theme,
+        area,
+    );
+}
 
-        let paragraph = Paragraph::new(text).alignment(Alignment::Center).block(
-            Block::default()
-                .borders(Borders::ALL)
-                .border_style(Style::default().fg(Color::Red))
-                .style(Style::default().bg(Color::Black)),
-        );
+/// Popup with the values the sessions table may have cut short
+fn render_value_view(frame: &mut Frame, session: &Session, theme: &Theme, area: Rect) {
+    let popup_width = (area.width * 4 / 5).max(50).min(area.width);
+    let status = match &session.status {
+        SessionStatus::Error(err) => format!("Error: {}", err.message),
+        status => status.as_str().to_string(),
+    };
+    let fields = [
+        ("Name", session.name.clone()),
+        ("Target", session.target.clone()),
+        ("Ports", session.port_mapping()),
+        ("Status", status),
+    ];
+    let text: Vec<Line> = fields
+        .into_iter()
+        .map(|(label, value)| {
+            Line::from(vec![
+                Span::styled(
+                    format!("{}: ", label),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(value),
+            ])
+        })
+        .collect();
 
-        frame.render_widget(Clear, popup_area);
-        frame.render_widget(paragraph, popup_area);
-    }
+    // Room for wrapped lines plus the borders
+    let inner_width = popup_width.saturating_sub(2).max(1) as usize;
+    let lines: usize = text
+        .iter()
+        .map(|l| l.width().div_ceil(inner_width).max(1))
+        .sum();
+    let popup_height = (lines as u16 + 2).min(area.height);
+    let popup_area = Rect {
+        x: area.x + (area.width - popup_width) / 2,
+        y: area.y + (area.height - popup_height) / 2,
+        width: popup_width,
+        height: popup_height,
+    };
+
+    let paragraph = Paragraph::new(text).wrap(Wrap { trim: false }).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(session.name.clone())
+            .border_style(Style::default().fg(theme.accent))
+            .style(Style::default().bg(theme.popup_bg)),
+    );
+    frame.render_widget(Clear, popup_area);
+    frame.render_widget(paragraph, popup_area);
+}
+
+pub(crate) fn render_prompt(
+    frame: &mut Frame,
+    question: &str,
+    detail: String,
+    color: Color,
+    theme: &Theme,
+    area: Rect,
+) {
+    // Create centered popup
+    let popup_width = 50.min(area.width.saturating_sub(4));
+    let popup_height = 7.min(area.height);
+    let popup_x = (area.width.saturating_sub(popup_width)) / 2;
+    let popup_y = (area.height.saturating_sub(popup_height)) / 2;
+
+    let popup_area = Rect {
+        x: area.x + popup_x,
+        y: area.y + popup_y,
+        width: popup_width,
+        height: popup_height,
+    };
+
+    let text = vec![
+        Line::from(""),
+        Line::from(Span::styled(
+            question,
+            Style::default().fg(color).add_modifier(Modifier::BOLD),
+        )),
+        Line::from(""),
+        Line::from(Span::raw(detail)),
+        Line::from(""),
+    ];
+
+    let paragraph = Paragraph::new(text).alignment(Alignment::Center).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(color))
+            .style(Style::default().bg(theme.popup_bg)),
+    );
+
+    frame.render_widget(Clear, popup_area);
+    frame.render_widget(paragraph, popup_area);
 }
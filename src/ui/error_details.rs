@@ -0,0 +1,229 @@
+use crate::models::{ErrorCategory, Session, SessionError, SessionStatus, SessionType};
+use crate::runs;
+use crate::ui::AppState;
+use crate::ui::theme::Theme;
+use ratatui::{
+    Frame,
+    layout::{Constraint, Layout, Rect},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph, Wrap},
+};
+
+pub fn render(frame: &mut Frame, state: &AppState, session_idx: usize, area: Rect) {
+    let Some(session) = state.sessions.get(session_idx) else {
+        return;
+    };
+    let chunks = Layout::vertical([
+        Constraint::Length(3),
+        Constraint::Min(0),
+        Constraint::Length(3),
+    ])
+    .split(area);
+
+    let title = Paragraph::new(format!("Error - {}", session.display_name()))
+        .style(
+            Style::default()
+                .fg(state.theme.title)
+                .add_modifier(Modifier::BOLD),
+        )
+        .block(Block::default().borders(Borders::ALL));
+    frame.render_widget(title, chunks[0]);
+
+    // The error takes the room it needs, up to two thirds; the log the rest
+    let lines = error_lines(state, session);
+    let inner_width = chunks[1].width.saturating_sub(2).max(1) as usize;
+    let wrapped: usize = lines
+        .iter()
+        .map(|l| l.width().div_ceil(inner_width).max(1))
+        .sum();
+    let height = (wrapped as u16 + 2).min(chunks[1].height * 2 / 3);
+    let body = Layout::vertical([Constraint::Length(height), Constraint::Min(0)]).split(chunks[1]);
+
+    let error = Paragraph::new(lines).wrap(Wrap { trim: false }).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title("What happened"),
+    );
+    frame.render_widget(error, body[0]);
+
+    render_log_tail(frame, state, session, body[1]);
+    render_help(frame, &state.theme, chunks[2]);
+}
+
+/// The whole message, what was run and what to try next
+fn error_lines<'a>(state: &AppState, session: &'a Session) -> Vec<Line<'a>> {
+    let theme = &state.theme;
+    let label =
+        |text: &'static str| Span::styled(text, Style::default().add_modifier(Modifier::BOLD));
+
+    let SessionStatus::Error(err) = &session.status else {
+        return vec![Line::from(Span::styled(
+            format!(
+                "{} is no longer in error ({})",
+                session.display_name(),
+                session.status.as_str()
+            ),
+            Style::default().fg(theme.success),
+        ))];
+    };
+
+    let mut lines = vec![
+        Line::from(Span::styled(
+            err.message.clone(),
+            Style::default().fg(theme.error),
+        )),
+        Line::from(""),
+        Line::from(vec![
+            label("When: "),
+            Span::raw(
+                err.at
+                    .with_timezone(&chrono::Local)
+                    .format("%Y-%m-%d %H:%M:%S")
+                    .to_string(),
+            ),
+        ]),
+        Line::from(vec![label("Likely cause: "), Span::raw(err.kind.as_str())]),
+    ];
+    if let Some(code) = err.exit_code {
+        lines.push(Line::from(vec![
+            label("Exit code: "),
+            Span::raw(code.to_string()),
+        ]));
+    }
+
+    // Errors before a start, e.g. the pre-flight check, leave the previous
+    // run as the last one
+    let last_command = runs::history(&state.storage, &session.id)
+        .pop()
+        .and_then(|run| run.command);
+    match last_command {
+        Some(command) => lines.push(Line::from(vec![label("Last run: "), Span::raw(command)])),
+        None => lines.push(Line::from(vec![
+            label("Would run: "),
+            Span::raw(state.process_manager.command_preview(session)),
+        ])),
+    }
+
+    lines.push(Line::from(""));
+    lines.push(Line::from(label("Next steps")));
+    for step in next_steps(session, err) {
+        lines.push(Line::from(vec![Span::raw("  • "), Span::raw(step)]));
+    }
+    lines
+}
+
+/// What to try for the likely cause of `err`
+fn next_steps(session: &Session, err: &SessionError) -> Vec<String> {
+    let ssh = matches!(
+        session.session_type,
+        SessionType::SSH | SessionType::Socks5 | SessionType::ReverseSocks5
+    );
+    let mut steps = Vec::new();
+    match err.kind {
+        ErrorCategory::MissingTool => {
+            steps.push("Install or upgrade the tool named above".to_string());
+            steps.push("Make sure it is on the PATH of the shell pfman runs in".to_string());
+        }
+        ErrorCategory::PortConflict if session.session_type == SessionType::ReverseSocks5 => {
+            steps.push(format!(
+                "Port {} is taken on {}: pick another remote port (e)",
+                session.remote_port.unwrap_or(0),
+                session.target
+            ));
+        }
+        ErrorCategory::PortConflict => {
+            steps.push(format!(
+                "See what holds the port with `lsof -i :{}` and stop it",
+                session.local_port
+            ));
+            steps.push("Or pick another local port, or let pfman pick one (e)".to_string());
+        }
+        ErrorCategory::Auth if ssh => {
+            steps.push(format!(
+                "Check that `ssh {}` logs in from a terminal",
+                session.target
+            ));
+            steps.push("If it asks for a password or passphrase, turn on login prompts (S on the dashboard)".to_string());
+        }
+        ErrorCategory::Auth if session.session_type == SessionType::Kubectl => {
+            steps.push(
+                "Refresh the cluster credentials, e.g. log in to your cloud CLI again".to_string(),
+            );
+            steps.push(format!(
+                "Check access with `kubectl auth can-i get pods{}`",
+                session
+                    .kube_namespace
+                    .as_ref()
+                    .map(|ns| format!(" -n {}", ns))
+                    .unwrap_or_default()
+            ));
+        }
+        ErrorCategory::Auth => {
+            steps.push("Log in again with the tool the session runs".to_string());
+        }
+        ErrorCategory::TargetMissing if session.session_type == SessionType::Kubectl => {
+            steps.push(format!(
+                "Check that {} exists in the context and namespace the session uses (e)",
+                session.target
+            ));
+        }
+        ErrorCategory::TargetMissing => {
+            steps.push(format!(
+                "Check the spelling of {}, and that it resolves or is in ~/.ssh/config",
+                session.target
+            ));
+        }
+        ErrorCategory::Network => {
+            steps.push(format!(
+                "Check that {} is reachable, e.g. that the VPN is up",
+                session.target
+            ));
+            steps.push(
+                "Turn on auto-restart (R on the dashboard) to reconnect after drops".to_string(),
+            );
+        }
+        ErrorCategory::Other => {
+            steps.push("Read the log below for what the tool reported".to_string());
+        }
+    }
+    if session.pre_start.is_some() && err.message.starts_with("Pre-start command") {
+        steps.push(
+            "Run the pre-start command in a terminal to see it fail (e to change it)".to_string(),
+        );
+    }
+    steps.push("Press s to start it again once fixed".to_string());
+    steps
+}
+
+/// As many of the latest log lines as fit
+fn render_log_tail(frame: &mut Frame, state: &AppState, session: &Session, area: Rect) {
+    let block = Block::default().borders(Borders::ALL).title("Log");
+    let lines = area.height.saturating_sub(2) as usize;
+    let logs = state
+        .storage
+        .read_log_tail(&session.id, lines)
+        .unwrap_or_else(|_| "Failed to read logs".to_string());
+    let tail = if logs.is_empty() {
+        Paragraph::new("No logs yet").style(Style::default().fg(state.theme.muted))
+    } else {
+        Paragraph::new(logs)
+    };
+    frame.render_widget(tail.block(block), area);
+}
+
+fn render_help(frame: &mut Frame, theme: &Theme, area: Rect) {
+    let help_text = Line::from(vec![
+        Span::styled("s", Style::default().fg(theme.key)),
+        Span::raw(" start again | "),
+        Span::styled("e", Style::default().fg(theme.key)),
+        Span::raw(" edit | "),
+        Span::styled("l", Style::default().fg(theme.key)),
+        Span::raw(" full log | "),
+        Span::styled("Esc", Style::default().fg(theme.key)),
+        Span::raw(" back"),
+    ]);
+
+    let help = Paragraph::new(help_text).block(Block::default().borders(Borders::ALL));
+    frame.render_widget(help, area);
+}
@@ -0,0 +1,182 @@
+use crate::models::Session;
+use crate::ui::AppState;
+use crate::ui::theme::Theme;
+use ratatui::{
+    Frame,
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Cell, Paragraph, Row, Table, Wrap},
+};
+
+/// Sessions parsed from pasted text, waiting for the user to confirm
+pub struct ImportPreview {
+    pub parsed: Result<Vec<Session>, String>,
+    /// Sessions renamed to avoid duplicate names, as `(old, new)`
+    pub renames: Vec<(String, String)>,
+}
+
+impl ImportPreview {
+    pub fn from_text(text: &str, existing: &[Session]) -> Self {
+        Self::resolved(crate::import::parse_sessions(text), existing)
+    }
+
+    /// Import failure shown in place of the preview
+    pub fn error(message: String) -> Self {
+        Self {
+            parsed: Err(message),
+            renames: Vec::new(),
+        }
+    }
+
+    fn resolved(mut parsed: Result<Vec<Session>, String>, existing: &[Session]) -> Self {
+        let renames = match &mut parsed {
+            Ok(sessions) => crate::import::resolve_name_conflicts(sessions, existing),
+            Err(_) => Vec::new(),
+        };
+        Self { parsed, renames }
+    }
+
+    /// Candidates from shell history, minus sessions that already exist
+    pub fn from_history(existing: &[Session]) -> Self {
+        let known: Vec<String> = existing.iter().map(crate::import::session_key).collect();
+        let parsed = crate::import::scan_shell_history().and_then(|sessions| {
+            let new: Vec<Session> = sessions
+                .into_iter()
+                .filter(|s| !known.contains(&crate::import::session_key(s)))
+                .collect();
+            if new.is_empty() {
+                Err("Every port-forward in shell history is already a session".to_string())
+            } else {
+                Ok(new)
+            }
+        });
+        Self::resolved(parsed, existing)
+    }
+}
+
+pub fn render(frame: &mut Frame, state: &AppState, area: Rect) {
+    let chunks = Layout::vertical([
+        Constraint::Length(3),
+        Constraint::Min(0),
+        Constraint::Length(3),
+    ])
+    .split(area);
+
+    let title = Paragraph::new("Import Sessions")
+        .style(
+            Style::default()
+                .fg(state.theme.title)
+                .add_modifier(Modifier::BOLD),
+        )
+        .block(Block::default().borders(Borders::ALL));
+    frame.render_widget(title, chunks[0]);
+
+    match state.import_preview.as_ref() {
+        Some(ImportPreview {
+            parsed: Ok(sessions),
+            renames,
+        }) if !renames.is_empty() => {
+            let rows = Layout::vertical([
+                Constraint::Min(0),
+                Constraint::Length(renames.len().min(5) as u16 + 2),
+            ])
+            .split(chunks[1]);
+            render_preview(frame, sessions, rows[0]);
+            render_renames(frame, renames, &state.theme, rows[1]);
+        }
+        Some(ImportPreview {
+            parsed: Ok(sessions),
+            ..
+        }) => render_preview(frame, sessions, chunks[1]),
+        Some(ImportPreview {
+            parsed: Err(err), ..
+        }) => render_message(frame, err, state.theme.error, chunks[1]),
+        None => render_message(
+            frame,
+            "Paste YAML/JSON sessions or `host local:remote` lines",
+            state.theme.muted,
+            chunks[1],
+        ),
+    }
+
+    render_help(frame, &state.theme, chunks[2]);
+}
+
+fn render_preview(frame: &mut Frame, sessions: &[Session], area: Rect) {
+    let header = Row::new(vec![
+        Cell::from("Name").style(Style::default().add_modifier(Modifier::BOLD)),
+        Cell::from("Type").style(Style::default().add_modifier(Modifier::BOLD)),
+        Cell::from("Target").style(Style::default().add_modifier(Modifier::BOLD)),
+        Cell::from("Ports").style(Style::default().add_modifier(Modifier::BOLD)),
+    ]);
+
+    let rows: Vec<Row> = sessions
+        .iter()
+        .map(|session| {
+            Row::new(vec![
+                Cell::from(session.name.clone()),
+                Cell::from(session.session_type.as_str()),
+                Cell::from(session.target.clone()),
+                Cell::from(session.port_mapping()),
+            ])
+        })
+        .collect();
+
+    let widths = [
+        Constraint::Fill(2),
+        Constraint::Length(7),
+        Constraint::Fill(3),
+        Constraint::Length(13),
+    ];
+
+    let table = Table::new(rows, widths).header(header).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(format!("Preview ({} sessions)", sessions.len())),
+    );
+    frame.render_widget(table, area);
+}
+
+fn render_renames(frame: &mut Frame, renames: &[(String, String)], theme: &Theme, area: Rect) {
+    let lines: Vec<Line> = renames
+        .iter()
+        .map(|(old, new)| {
+            Line::from(vec![
+                Span::raw(old.clone()),
+                Span::raw(" → "),
+                Span::styled(new.clone(), Style::default().fg(theme.warning)),
+            ])
+        })
+        .collect();
+    let paragraph = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(format!("Renamed ({}, names already taken)", renames.len())),
+    );
+    frame.render_widget(paragraph, area);
+}
+
+fn render_message(frame: &mut Frame, message: &str, color: Color, area: Rect) {
+    let paragraph = Paragraph::new(message)
+        .style(Style::default().fg(color))
+        .wrap(Wrap { trim: false })
+        .block(Block::default().borders(Borders::ALL).title("Preview"));
+    frame.render_widget(paragraph, area);
+}
+
+fn render_help(frame: &mut Frame, theme: &Theme, area: Rect) {
+    let help_text = Line::from(vec![
+        Span::styled("Enter", Style::default().fg(theme.key)),
+        Span::raw(" import | "),
+        Span::styled("p", Style::default().fg(theme.key)),
+        Span::raw(" re-read clipboard | "),
+        Span::styled("h", Style::default().fg(theme.key)),
+        Span::raw(" shell history | "),
+        Span::styled("Esc", Style::default().fg(theme.key)),
+        Span::raw(" cancel"),
+    ]);
+
+    let help = Paragraph::new(help_text).block(Block::default().borders(Borders::ALL));
+    frame.render_widget(help, area);
+}
@@ -0,0 +1,66 @@
+//! `--inline`: a compact live view drawn below the shell prompt instead of
+//! on the alternate screen. Status changes are printed above it, so they
+//! stay in the scrollback with the rest of the shell output.
+
+use crate::models::{Session, SessionStatus};
+use crate::ui::AppState;
+use crate::ui::dashboard::{COMPACT_MAX_WIDTH, compact_table};
+use crate::ui::session_table::SessionTable;
+use ratatui::{
+    Frame,
+    widgets::{Block, Borders},
+};
+
+/// Tallest the inline view gets, however many sessions there are
+pub const MAX_HEIGHT: u16 = 15;
+
+/// Rows for the session list plus its borders and header
+pub fn height(sessions: &[Session]) -> u16 {
+    (sessions.len() as u16 + 3).clamp(4, MAX_HEIGHT)
+}
+
+pub fn render(frame: &mut Frame, state: &AppState) {
+    let area = frame.area();
+    let up = state
+        .sessions
+        .iter()
+        .filter(|s| s.status.is_running())
+        .count();
+    let block = Block::default().borders(Borders::ALL).title(format!(
+        "pfman {}/{} up (q quit)",
+        up,
+        state.sessions.len()
+    ));
+
+    if area.width < COMPACT_MAX_WIDTH {
+        let sessions: Vec<&Session> = state.sessions.iter().collect();
+        let table = compact_table(&sessions, None, state.config.accessible, &state.theme);
+        frame.render_widget(table.block(block), area);
+    } else {
+        let table = SessionTable::new(&state.sessions)
+            .accessible(state.config.accessible)
+            .remind_after(state.config.remind_after())
+            .latencies(state.process_manager.latencies())
+            .health(state.process_manager.health_checks())
+            .theme(state.theme)
+            .truncation(state.config.truncation)
+            .column_widths(state.config.column_widths)
+            .block(block);
+        frame.render_widget(table, area);
+    }
+}
+
+/// One scrollback line for a session whose status changed
+pub fn transition_line(session: &Session) -> String {
+    let status = match &session.status {
+        SessionStatus::Error(err) => format!("Error: {}", err.message),
+        status => status.as_str().to_string(),
+    };
+    format!(
+        "{} {} {} ({})",
+        chrono::Local::now().format("%H:%M:%S"),
+        session.name,
+        status,
+        session.port_mapping()
+    )
+}
@@ -0,0 +1,72 @@
+use crate::ui::AppState;
+use ratatui::{
+    Frame,
+    layout::{Alignment, Rect},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+};
+use uuid::Uuid;
+
+/// Popup for a question a session's ssh is waiting on, drawn over any screen
+pub fn render(frame: &mut Frame, state: &AppState, id: Uuid, prompt: &str, area: Rect) {
+    let theme = &state.theme;
+    let name = state
+        .sessions
+        .iter()
+        .find(|s| s.id == id)
+        .map(|s| s.display_name())
+        .unwrap_or_default();
+    // Host key questions are answered with yes/no, which is fine to show
+    let answer = if prompt.contains("yes/no") {
+        state.login_answer.clone()
+    } else {
+        "*".repeat(state.login_answer.chars().count())
+    };
+
+    let popup_width = 60.min(area.width.saturating_sub(4));
+    let popup_height = 9.min(area.height);
+    let popup_area = Rect {
+        x: area.x + (area.width.saturating_sub(popup_width)) / 2,
+        y: area.y + (area.height.saturating_sub(popup_height)) / 2,
+        width: popup_width,
+        height: popup_height,
+    };
+
+    let text = vec![
+        Line::from(Span::styled(
+            format!("{} is asking", name),
+            Style::default()
+                .fg(theme.warning)
+                .add_modifier(Modifier::BOLD),
+        )),
+        Line::from(""),
+        Line::from(prompt.to_string()),
+        Line::from(vec![
+            Span::raw("> "),
+            Span::raw(answer),
+            Span::styled("_", Style::default().fg(theme.key)),
+        ]),
+        Line::from(""),
+        Line::from(vec![
+            Span::styled("Enter", Style::default().fg(theme.key)),
+            Span::raw(" send | "),
+            Span::styled("Esc", Style::default().fg(theme.key)),
+            Span::raw(" stop the session"),
+        ]),
+    ];
+
+    let paragraph = Paragraph::new(text)
+        .alignment(Alignment::Center)
+        .wrap(Wrap { trim: true })
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Login")
+                .border_style(Style::default().fg(theme.warning))
+                .style(Style::default().bg(theme.popup_bg)),
+        );
+
+    frame.render_widget(Clear, popup_area);
+    frame.render_widget(paragraph, popup_area);
+}
@@ -1,139 +1,385 @@
-use crate::models::Session;
+use crate::models::{Session, SessionStatus};
+use crate::process::STDERR_MARKER;
+use crate::runs::LIFECYCLE_MARKER;
 use crate::ui::AppState;
+use crate::ui::dashboard::render_prompt;
+use crate::ui::session_table::{
+    format_latency, health_label, port_mapping, status_color, status_glyph, status_label,
+};
 use ratatui::{
     Frame,
     layout::{Constraint, Layout, Rect},
-    style::{Color, Modifier, Style},
+    style::{Modifier, Style},
     text::{Line, Span},
     widgets::{Block, Borders, Paragraph, Wrap},
 };
+use std::path::PathBuf;
+
+/// Header height for the lines it shows, with the command wrapped to fit
+fn header_height(lines: &[Line], width: u16) -> u16 {
+    let inner_width = width.saturating_sub(2).max(1) as usize;
+    let rows: usize = lines
+        .iter()
+        .map(|l| l.width().div_ceil(inner_width).max(1))
+        .sum();
+    rows as u16 + 2
+}
+
+/// Log lines that fit on a screen of this size
+pub fn visible_lines(state: &AppState, session: &Session, area: Rect) -> usize {
+    let header = header_height(&header_lines(session, state), area.width);
+    // Header, help bar and the log pane's borders
+    area.height.saturating_sub(header + 3 + 2) as usize
+}
+
+/// Furthest the unwrapped logs can be scrolled right: until the longest
+/// line on screen ends at the pane's right edge
+pub fn max_hscroll(state: &AppState, session: &Session, area: Rect) -> usize {
+    let lines = visible_lines(state, session, area) + state.log_scroll;
+    let logs = read_shown_log(state, session, lines).unwrap_or_default();
+    let longest = logs
+        .lines()
+        .map(|line| Span::raw(line).width())
+        .max()
+        .unwrap_or(0);
+    longest.saturating_sub(area.width.saturating_sub(2) as usize)
+}
 
 pub fn render(frame: &mut Frame, state: &AppState, session_idx: usize, area: Rect) {
     if let Some(session) = state.sessions.get(session_idx) {
-        // Adjust header height based on whether there's an error
-        let header_height = if matches!(session.status, crate::models::SessionStatus::Error(_)) {
-            6
-        } else {
-            5
-        };
-
+        let header = header_lines(session, state);
         let chunks = Layout::vertical([
-            Constraint::Length(header_height),
+            Constraint::Length(header_height(&header, area.width)),
             Constraint::Min(0),
             Constraint::Length(3),
         ])
         .split(area);
 
-        render_header(frame, session, chunks[0]);
+        let header = Paragraph::new(header)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title("Session Details"),
+            )
+            .wrap(Wrap { trim: false });
+        frame.render_widget(header, chunks[0]);
         render_logs(frame, state, session, chunks[1]);
-        render_help(frame, chunks[2]);
+        render_help(frame, state, chunks[2]);
+
+        let prompt = if state.clear_logs_confirmation == Some(session.id) {
+            Some(("Clear Logs?", state.theme.error))
+        } else if state.stop_confirmation == Some(session.id) {
+            Some(("Stop Session?", state.theme.warning))
+        } else if state.restart_confirmation == Some(session.id) {
+            Some(("Restart Session?", state.theme.warning))
+        } else {
+            None
+        };
+        if let Some((question, color)) = prompt {
+            render_prompt(
+                frame,
+                question,
+                format!("Session: {}", session.name),
+                color,
+                &state.theme,
+                area,
+            );
+        }
     }
 }
 
-fn render_header(frame: &mut Frame, session: &Session, area: Rect) {
-    let mut header_text = vec![
-        Line::from(vec![
-            Span::styled("Name: ", Style::default().add_modifier(Modifier::BOLD)),
-            Span::raw(&session.name),
-        ]),
+/// Everything the dashboard knows about the session, with the same glyphs
+/// and colors, so this screen works as its status page
+fn header_lines<'a>(session: &'a Session, state: &AppState) -> Vec<Line<'a>> {
+    let theme = &state.theme;
+    let accessible = state.config.accessible;
+    let label =
+        |text: &'static str| Span::styled(text, Style::default().add_modifier(Modifier::BOLD));
+    // Transitional labels already lead with the spinner
+    let status_text = if session.is_transitional() {
+        status_label(session, accessible)
+    } else {
+        format!(
+            "{} {}",
+            status_glyph(&session.status, accessible),
+            session.status.as_str()
+        )
+    };
+
+    let mut ports = vec![
+        label("Ports: "),
+        Span::raw(port_mapping(session, accessible)),
+    ];
+    if session.auto_local_port {
+        ports.push(Span::styled(" (auto)", Style::default().fg(theme.muted)));
+    }
+    ports.push(Span::raw("  "));
+    ports.push(label("Bind: "));
+    ports.push(Span::raw(session.bind_address()));
+
+    let mut status = vec![
+        label("Status: "),
+        Span::styled(
+            status_text,
+            Style::default().fg(status_color(session, theme)),
+        ),
+        Span::raw("  "),
+        label("Uptime: "),
+        Span::raw(session.uptime_string()),
+    ];
+    if let Some(pid) = session.pid {
+        status.push(Span::raw("  "));
+        status.push(label("PID: "));
+        status.push(Span::raw(pid.to_string()));
+    }
+    if session.reconnects > 0 {
+        status.push(Span::raw("  "));
+        status.push(label("Reconnects: "));
+        status.push(Span::raw(session.reconnects.to_string()));
+    }
+    if let Some(latency) = state.process_manager.latencies().get(&session.id) {
+        status.push(Span::raw("  "));
+        status.push(label("Latency: "));
+        status.push(Span::raw(format_latency(*latency)));
+    }
+    if session.health_command.is_some() {
+        status.push(Span::raw("  "));
+        status.push(label("Health: "));
+        status.push(
+            match state.process_manager.health_checks().get(&session.id) {
+                Some(check) => Span::styled(
+                    health_label(check),
+                    Style::default().fg(if check.healthy {
+                        theme.success
+                    } else {
+                        theme.error
+                    }),
+                ),
+                None => Span::styled("Not checked yet", Style::default().fg(theme.muted)),
+            },
+        );
+    }
+
+    let mut lines = vec![
         Line::from(vec![
-            Span::styled("Type: ", Style::default().add_modifier(Modifier::BOLD)),
-            Span::raw(session.session_type.as_str()),
+            label("Name: "),
+            Span::raw(session.display_name()),
             Span::raw("  "),
-            Span::styled("Target: ", Style::default().add_modifier(Modifier::BOLD)),
-            Span::raw(&session.target),
+            label("ID: "),
+            Span::raw(session.short_id()),
+            Span::raw("  "),
+            label("Type: "),
+            Span::raw(session.session_type.as_str()),
         ]),
+        Line::from(vec![label("Target: "), Span::raw(&session.target)]),
+        Line::from(ports),
+        Line::from(status),
         Line::from(vec![
-            Span::styled("Ports: ", Style::default().add_modifier(Modifier::BOLD)),
-            Span::raw(session.port_mapping()),
-            Span::raw("  "),
-            Span::styled("Status: ", Style::default().add_modifier(Modifier::BOLD)),
+            label("Command: "),
             Span::styled(
-                session.status.as_str(),
-                Style::default().fg(match &session.status {
-                    crate::models::SessionStatus::Running => Color::Green,
-                    crate::models::SessionStatus::Stopped => Color::Gray,
-                    crate::models::SessionStatus::Error(_) => Color::Red,
-                }),
+                state.process_manager.command_preview(session),
+                Style::default().fg(theme.muted),
             ),
         ]),
     ];
 
-    // Add error message line if status is Error
-    if let crate::models::SessionStatus::Error(msg) = &session.status {
-        header_text.push(Line::from(vec![
+    if let SessionStatus::Error(err) = &session.status {
+        lines.push(Line::from(vec![
             Span::styled(
                 "Error: ",
-                Style::default().add_modifier(Modifier::BOLD).fg(Color::Red),
+                Style::default()
+                    .add_modifier(Modifier::BOLD)
+                    .fg(theme.error),
             ),
-            Span::styled(msg, Style::default().fg(Color::Red)),
+            Span::styled(err.message.as_str(), Style::default().fg(theme.error)),
         ]));
     }
 
-    let header = Paragraph::new(header_text).block(
-        Block::default()
-            .borders(Borders::ALL)
-            .title("Session Details"),
-    );
-    frame.render_widget(header, area);
+    lines
+}
+
+/// The log on screen: that of the past run picked on the run history
+/// screen, otherwise the latest one
+pub fn shown_log_file(state: &AppState, session: &Session) -> PathBuf {
+    match &state.log_run {
+        Some(run) => run.log_file(&state.storage, &session.id),
+        None => state.storage.log_file(&session.id),
+    }
+}
+
+/// The last `lines` lines of the shown log, or of what the session wrote
+/// to stderr while that filter is on
+pub fn read_shown_log(
+    state: &AppState,
+    session: &Session,
+    lines: usize,
+) -> color_eyre::Result<String> {
+    let log_file = shown_log_file(state, session);
+    if !state.log_stderr_only {
+        return state.storage.read_tail(&log_file, lines);
+    }
+    // Stderr lines can be anywhere in the log
+    let logs = state.storage.read_tail(&log_file, usize::MAX)?;
+    let stderr: Vec<&str> = logs.lines().filter(|line| is_stderr(line)).collect();
+    Ok(stderr[stderr.len().saturating_sub(lines)..].join("\n"))
+}
+
+/// Whether capture marked the line as written to stderr
+fn is_stderr(line: &str) -> bool {
+    line.split_once("] ")
+        .is_some_and(|(_, rest)| rest.starts_with(STDERR_MARKER))
 }
 
 fn render_logs(frame: &mut Frame, state: &AppState, session: &Session, area: Rect) {
-    let logs = state
-        .storage
-        .read_logs(&session.id)
+    // Only what fits on screen is read, so huge logs don't stall rendering
+    let visible_height = area.height.saturating_sub(2); // Subtract borders
+    let logs = read_shown_log(state, session, visible_height as usize + state.log_scroll)
         .unwrap_or_else(|_| "Failed to read logs".to_string());
 
     let log_text = if logs.is_empty() {
         match &session.status {
-            crate::models::SessionStatus::Stopped => {
+            _ if state.log_stderr_only => "Nothing written to stderr.".to_string(),
+            SessionStatus::Stopped => {
                 if session.last_started.is_some() {
                     "Session was stopped. No logs were generated.".to_string()
                 } else {
                     "Session has never been started. No logs available.".to_string()
                 }
             }
-            crate::models::SessionStatus::Error(_) => {
+            SessionStatus::Error(_) => {
                 "Session failed. Check error message above or logs may be empty.".to_string()
             }
-            crate::models::SessionStatus::Running => {
+            SessionStatus::Running | SessionStatus::Degraded(_) => {
                 "Session is running but no output yet...".to_string()
             }
+            SessionStatus::Starting => {
+                "Session is starting, waiting for the local port...".to_string()
+            }
+            SessionStatus::Stopping => {
+                "Session is stopping, waiting for the process to exit...".to_string()
+            }
         }
     } else {
         logs
     };
 
-    let title = match &session.status {
-        crate::models::SessionStatus::Running => "Logs (Live)",
-        crate::models::SessionStatus::Stopped => "Logs (Historical)",
-        crate::models::SessionStatus::Error(_) => "Logs (Error)",
+    let status_title = match &session.status {
+        _ if state.log_scroll > 0 => "Logs (Scrolled, G to follow)",
+        SessionStatus::Running
+        | SessionStatus::Starting
+        | SessionStatus::Degraded(_)
+        | SessionStatus::Stopping => "Logs (Live)",
+        SessionStatus::Stopped => "Logs (Historical)",
+        SessionStatus::Error(_) => "Logs (Error)",
     };
+    let mut title = match &state.log_run {
+        Some(run) => format!(
+            "Logs (Run started {}, Esc back to the runs)",
+            run.started_at.format("%Y-%m-%d %H:%M:%S")
+        ),
+        None => status_title.to_string(),
+    };
+    if state.log_stderr_only {
+        title.push_str(" - stderr only");
+    }
+    if !state.log_wrap {
+        title.push_str(&format!(
+            " - unwrapped from column {}",
+            state.log_hscroll + 1
+        ));
+    }
 
-    // Calculate scroll position to show bottom (tail behavior)
-    let total_lines = log_text.lines().count() as u16;
-    let visible_height = area.height.saturating_sub(2); // Subtract borders
-    let scroll_offset = total_lines.saturating_sub(visible_height);
+    // Show the bottom (tail behavior), minus however far the user scrolled up.
+    // Wrapped lines take a row per pane width.
+    let inner_width = area.width.saturating_sub(2).max(1) as usize;
+    let total_rows: usize = if state.log_wrap {
+        log_text
+            .lines()
+            .map(|line| Span::raw(line).width().div_ceil(inner_width).max(1))
+            .sum()
+    } else {
+        log_text.lines().count()
+    };
+    let scroll_offset = (total_rows as u16)
+        .saturating_sub(visible_height)
+        .saturating_sub(state.log_scroll as u16);
 
-    let logs_widget = Paragraph::new(log_text)
-        .block(Block::default().borders(Borders::ALL).title(title))
-        .wrap(Wrap { trim: false })
-        .scroll((scroll_offset, 0));
+    let lines: Vec<Line> = log_text
+        .lines()
+        .map(|line| {
+            if is_stderr(line) {
+                Line::styled(line.to_string(), Style::default().fg(state.theme.warning))
+            } else if line.contains(LIFECYCLE_MARKER) {
+                Line::styled(line.to_string(), Style::default().fg(state.theme.accent))
+            } else {
+                Line::raw(line.to_string())
+            }
+        })
+        .collect();
+    let mut logs_widget =
+        Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(title));
+    logs_widget = if state.log_wrap {
+        logs_widget
+            .wrap(Wrap { trim: false })
+            .scroll((scroll_offset, 0))
+    } else {
+        logs_widget.scroll((scroll_offset, state.log_hscroll as u16))
+    };
 
     frame.render_widget(logs_widget, area);
 }
 
-fn render_help(frame: &mut Frame, area: Rect) {
-    let help_text = Line::from(vec![
-        Span::styled("s", Style::default().fg(Color::Yellow)),
-        Span::raw(" start/stop | "),
-        Span::styled("r", Style::default().fg(Color::Yellow)),
-        Span::raw(" restart | "),
-        Span::styled("e", Style::default().fg(Color::Yellow)),
-        Span::raw(" edit | "),
-        Span::styled("Esc", Style::default().fg(Color::Yellow)),
-        Span::raw(" back"),
-    ]);
+fn render_help(frame: &mut Frame, state: &AppState, area: Rect) {
+    let theme = &state.theme;
+    let help_text = if state.clear_logs_confirmation.is_some()
+        || state.stop_confirmation.is_some()
+        || state.restart_confirmation.is_some()
+    {
+        Line::from(vec![
+            Span::styled("y", Style::default().fg(theme.key)),
+            Span::raw(" confirm | "),
+            Span::styled("n", Style::default().fg(theme.key)),
+            Span::raw(" cancel | "),
+            Span::styled("Esc", Style::default().fg(theme.key)),
+            Span::raw(" cancel"),
+        ])
+    } else {
+        let mut spans = vec![
+            Span::styled("j/k", Style::default().fg(theme.key)),
+            Span::raw(" scroll | "),
+            Span::styled("w", Style::default().fg(theme.key)),
+            Span::raw(if state.log_wrap {
+                " unwrap | "
+            } else {
+                " wrap | "
+            }),
+        ];
+        if !state.log_wrap {
+            spans.push(Span::styled("h/l", Style::default().fg(theme.key)));
+            spans.push(Span::raw(" scroll sideways | "));
+        }
+        spans.extend([
+            Span::styled("f", Style::default().fg(theme.key)),
+            Span::raw(if state.log_stderr_only {
+                " all output | "
+            } else {
+                " stderr only | "
+            }),
+            Span::styled("H", Style::default().fg(theme.key)),
+            Span::raw(" run history | "),
+            Span::styled("s", Style::default().fg(theme.key)),
+            Span::raw(" start/stop | "),
+            Span::styled("r", Style::default().fg(theme.key)),
+            Span::raw(" restart | "),
+            Span::styled("e", Style::default().fg(theme.key)),
+            Span::raw(" edit | "),
+            Span::styled("c", Style::default().fg(theme.key)),
+            Span::raw(" clear logs | "),
+            Span::styled("Esc", Style::default().fg(theme.key)),
+            Span::raw(" back"),
+        ]);
+        Line::from(spans)
+    };
 
     let help = Paragraph::new(help_text).block(Block::default().borders(Borders::ALL));
     frame.render_widget(help, area);
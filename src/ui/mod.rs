@@ -1,16 +1,60 @@
+pub mod backups;
+pub mod catalog;
 pub mod dashboard;
+pub mod error_details;
+pub mod import;
+pub mod inline;
+pub mod login_prompt;
 pub mod logs_viewer;
+pub mod palette;
+pub mod run_history;
 pub mod session_form;
+pub mod session_table;
+pub mod theme;
+pub mod triage;
+pub mod usage;
 
-use crate::models::Session;
+use crate::api::ApiServer;
+use crate::canary::CanaryMonitor;
+use crate::catalog::CatalogSync;
+use crate::config::Config;
+use crate::hooks::HookRunner;
+use crate::ipc::IpcServer;
+use crate::kube_config::KubeCache;
+use crate::models::{ErrorCategory, FormDraft, Session, SessionStatus};
+use crate::policy::Policy;
 use crate::process::ProcessManager;
-use crate::storage::Storage;
+use crate::provider::ProviderRegistry;
+use crate::reconnect::Reconnector;
+use crate::retry::RetryCoordinator;
+use crate::runs::Run;
+use crate::saver::SessionSaver;
+use crate::sink::LogShipper;
+use crate::storage::{SessionBackup, Storage};
+use crate::suggest::{self, Providers};
+use crate::usage::{SummaryPeriod, UsageSummary};
+use ratatui::Frame;
+use ratatui::layout::{Alignment, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::widgets::{Paragraph, Wrap};
+use std::collections::{HashMap, HashSet};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use theme::Theme;
+use uuid::Uuid;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Screen {
     Dashboard,
     LogsViewer(usize),
     SessionForm(FormMode),
+    Import,
+    Triage,
+    Usage,
+    Catalog,
+    Backups,
+    RunHistory(usize),
+    /// Everything known about a session's error
+    ErrorDetails(usize),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -22,61 +66,561 @@ pub enum FormMode {
 pub struct AppState {
     pub sessions: Vec<Session>,
     pub current_screen: Screen,
-    pub selected_index: usize,
+    /// Selection is tracked by id so list changes never retarget the next
+    /// action. While the search hides it, the first visible row stands in
+    /// (see [`AppState::selected_index`]), and it comes back once cleared.
+    pub selected_id: Option<Uuid>,
     pub search_query: String,
     pub search_mode: bool,
     pub search_cursor_pos: usize,
     pub storage: Storage,
+    /// Writes the sessions, right away or once status changes settle
+    pub saver: SessionSaver,
     pub process_manager: ProcessManager,
-    pub delete_confirmation: Option<usize>,
+    pub delete_confirmation: Option<Uuid>,
+    /// Running session about to be stopped, with `confirm_stop` on
+    pub stop_confirmation: Option<Uuid>,
+    /// Running session about to be restarted, with `confirm_stop` on
+    pub restart_confirmation: Option<Uuid>,
+    /// Restarted sessions still stopping, started once they have stopped
+    pub restarts_after_stop: HashSet<Uuid>,
+    /// Newly created session awaiting a "start now?" answer
+    pub start_confirmation: Option<Uuid>,
+    /// Session whose log is about to be wiped from the logs viewer
+    pub clear_logs_confirmation: Option<Uuid>,
+    /// Long-running session the user is asked about
+    pub expiry_prompt: Option<Uuid>,
+    /// Quitting with sessions up: leave them running or stop them?
+    pub quit_prompt: bool,
+    /// When each session was last asked about, so "keep" snoozes the nag
+    pub reminded_at: HashMap<Uuid, Instant>,
+    pub config: Config,
+    pub import_preview: Option<import::ImportPreview>,
+    /// Unsaved form from a previous run, offered for restore at startup
+    pub pending_draft: Option<FormDraft>,
+    /// Debug log path shown when the previous run ended in a panic
+    pub crash_notice: Option<std::path::PathBuf>,
+    /// Session whose untruncated values are shown in a popup
+    pub value_view: Option<Uuid>,
+    /// Highlighted group on the triage screen
+    pub triage_selected: usize,
+    pub retry: RetryCoordinator,
+    pub reconnector: Reconnector,
+    /// Running only when a canary is configured
+    pub canary: Option<CanaryMonitor>,
+    /// Computed when the usage screen is opened
+    pub usage_summary: Option<UsageSummary>,
+    /// Running only when a log sink is configured
+    pub log_shipper: Option<LogShipper>,
+    /// Running only when a catalog source is configured
+    pub catalog: Option<CatalogSync>,
+    /// Highlighted entry on the catalog screen
+    pub catalog_selected: usize,
+    /// Outcome of the last catalog action, shown in the help bar
+    pub catalog_message: Option<String>,
+    /// Read when the backups screen is opened
+    pub backups: Vec<SessionBackup>,
+    /// Highlighted backup on the backups screen
+    pub backup_selected: usize,
+    /// The highlighted backup is about to replace the sessions
+    pub restore_confirmation: bool,
+    /// What has been typed for the login prompt shown
+    pub login_answer: String,
+    /// Running only when the API is configured and could listen
+    pub api: Option<ApiServer>,
+    /// Why the configured API isn't listening
+    pub api_error: Option<String>,
+    /// Running only when the IPC socket is configured and could listen
+    pub ipc: Option<IpcServer>,
+    /// Why the configured IPC socket isn't listening
+    pub ipc_error: Option<String>,
+    /// Runs the configured status change hooks; `None` without hooks
+    pub hooks: Option<HookRunner>,
+    /// Namespaces and targets looked up by session forms, reused for a while
+    pub kube_cache: KubeCache,
+    /// Show the selected session's details and log tail next to the table
+    pub preview_pane: bool,
+    pub theme: Theme,
+    /// Lines the logs viewer is scrolled up from the tail; 0 follows new output
+    pub log_scroll: usize,
+    /// Wrap long log lines; off, they are cut at the pane's edge and
+    /// scrolled sideways
+    pub log_wrap: bool,
+    /// Columns the unwrapped logs are scrolled to the right
+    pub log_hscroll: usize,
+    /// Show only the log lines the session wrote to stderr
+    pub log_stderr_only: bool,
+    /// Past run whose log the logs viewer shows instead of the latest one
+    pub log_run: Option<Run>,
+    /// Highlighted run on the run history screen, 0 being the newest
+    pub run_selected: usize,
+    /// Open command palette (Ctrl+P)
+    pub palette: Option<palette::Palette>,
+    /// Demo mode's scratch directory, removed with the state
+    demo_dir: Option<crate::demo::DemoDir>,
 }
 
 impl AppState {
     pub fn new() -> color_eyre::Result<Self> {
         let storage = Storage::new()?;
-        let sessions = storage.load_sessions()?;
-        let process_manager = ProcessManager::new(Storage::new()?);
+        let process_manager = ProcessManager::new(storage.clone()).with_policy(Policy::load()?);
+        Self::with_storage(storage, process_manager)
+    }
+
+    /// Demo mode: scratch storage seeded with fake sessions that are backed
+    /// by simulated listener processes instead of ssh/kubectl
+    pub fn demo() -> color_eyre::Result<Self> {
+        let dir = crate::demo::DemoDir::new();
+        let storage = Storage::in_dir(dir.path().to_path_buf())?;
+        storage.save_sessions(&crate::demo::demo_sessions())?;
+        let process_manager = ProcessManager::simulated(storage.clone());
+        let mut state = Self::with_storage(storage, process_manager)?;
+        state.demo_dir = Some(dir);
+        Ok(state)
+    }
+
+    pub(crate) fn with_storage(
+        storage: Storage,
+        process_manager: ProcessManager,
+    ) -> color_eyre::Result<Self> {
+        crate::runs::migrate_legacy_logs(&storage);
+        let mut sessions = storage.load_sessions()?;
+        let config = storage.load_config()?;
+        let scope = config
+            .terminal_scoping
+            .then(crate::scope::current_scope)
+            .flatten();
+        let process_manager = process_manager
+            .with_scope(scope)
+            .with_ssh_options(config.ssh_options.clone())
+            .with_exit_on_forward_failure(config.exit_on_forward_failure)
+            .with_status_file(config.status_file.then(|| storage.status_file()))
+            .with_tunnel_verification(config.verify_tunnels)
+            .with_providers(ProviderRegistry::from_config(&config.session_providers));
+        let pending_draft = storage.load_draft();
 
         // Sync monitored sessions with loaded sessions
         process_manager.sync_monitored_sessions(&sessions);
+        if process_manager.adopt_sessions(&mut sessions) {
+            storage.save_sessions(&sessions)?;
+        }
+
+        let selected_id = sessions.first().map(|s| s.id);
+        let canary = config.canary.clone().map(CanaryMonitor::spawn);
+        let log_shipper = config
+            .log_sink
+            .clone()
+            .map(|sink| LogShipper::spawn(sink, storage.clone()));
+        let catalog = config
+            .catalog
+            .clone()
+            .map(|source| CatalogSync::spawn(source, config.catalog_interval(), storage.clone()));
+        // Demo mode stays off the API, which a real instance may be serving
+        let (api, api_error) = match &config.api {
+            Some(listen) if !process_manager.is_simulated() => match ApiServer::spawn(listen) {
+                Ok(api) => (Some(api), None),
+                Err(err) => (None, Some(err)),
+            },
+            _ => (None, None),
+        };
+        let (ipc, ipc_error) = match &config.ipc_socket {
+            Some(path) if !process_manager.is_simulated() => match IpcServer::spawn(path) {
+                Ok(ipc) => (Some(ipc), None),
+                Err(err) => (None, Some(err)),
+            },
+            _ => (None, None),
+        };
+        // Demo sessions shouldn't ping anyone's webhooks
+        let reconnector = Reconnector::new(config.reconnect);
+        let hooks = (!config.hooks.is_empty() && !process_manager.is_simulated())
+            .then(|| HookRunner::new(config.hooks.clone(), storage.clone()));
+        let usage_summary = config
+            .startup_summary
+            .map(|period| UsageSummary::collect(&storage, &sessions, period));
+        let current_screen = if usage_summary.is_some() {
+            Screen::Usage
+        } else {
+            Screen::Dashboard
+        };
 
         Ok(Self {
             sessions,
-            current_screen: Screen::Dashboard,
-            selected_index: 0,
+            current_screen,
+            selected_id,
             search_query: String::new(),
             search_mode: false,
             search_cursor_pos: 0,
+            saver: SessionSaver::spawn(storage.clone()),
             storage,
             process_manager,
             delete_confirmation: None,
+            stop_confirmation: None,
+            restart_confirmation: None,
+            restarts_after_stop: HashSet::new(),
+            start_confirmation: None,
+            clear_logs_confirmation: None,
+            expiry_prompt: None,
+            quit_prompt: false,
+            reminded_at: HashMap::new(),
+            preview_pane: config.preview_pane,
+            theme: Theme::named(config.theme),
+            log_scroll: 0,
+            log_wrap: true,
+            log_hscroll: 0,
+            log_stderr_only: false,
+            log_run: None,
+            run_selected: 0,
+            palette: None,
+            config,
+            import_preview: None,
+            pending_draft,
+            crash_notice: None,
+            value_view: None,
+            triage_selected: 0,
+            retry: RetryCoordinator::default(),
+            reconnector,
+            canary,
+            usage_summary,
+            log_shipper,
+            catalog,
+            catalog_selected: 0,
+            catalog_message: None,
+            backups: Vec::new(),
+            backup_selected: 0,
+            restore_confirmation: false,
+            login_answer: String::new(),
+            api,
+            api_error,
+            ipc,
+            ipc_error,
+            hooks,
+            kube_cache: KubeCache::default(),
+            demo_dir: None,
         })
     }
 
-    pub fn save(&self) -> color_eyre::Result<()> {
-        self.storage.save_sessions(&self.sessions)
+    pub fn save(&mut self) -> color_eyre::Result<()> {
+        self.saver.save_now(&self.sessions)
     }
 
-    pub fn filtered_sessions(&self) -> Vec<(usize, &Session)> {
-        if self.search_query.is_empty() {
-            self.sessions.iter().enumerate().collect()
-        } else {
+    /// Save in the background once changes stop coming in, for frequent
+    /// ones such as status updates
+    pub fn save_later(&mut self) {
+        self.saver.mark_dirty();
+    }
+
+    /// Hand changes saved with [`AppState::save_later`] to the background
+    /// writer once they're due; called every tick
+    pub fn save_if_due(&mut self) {
+        self.saver.save_if_due(&self.sessions);
+    }
+
+    /// Write anything not on disk yet, before exiting
+    pub fn flush_saves(&mut self) -> color_eyre::Result<()> {
+        self.saver.flush(&self.sessions)
+    }
+
+    /// Move the dashboard selection to the session with the given id,
+    /// clearing the search if it currently hides that session
+    pub fn select_session(&mut self, id: Uuid) {
+        if !self.filtered_sessions().iter().any(|(_, s)| s.id == id) {
+            self.search_query.clear();
+            self.search_cursor_pos = 0;
+        }
+        self.selected_id = Some(id);
+    }
+
+    /// Position of the highlighted row within the filtered list: the
+    /// selected session, or the first row while the search hides it
+    pub fn selected_index(&self) -> Option<usize> {
+        let filtered = self.filtered_sessions();
+        if filtered.is_empty() {
+            return None;
+        }
+        let id = self.selected_id;
+        Some(
+            filtered
+                .iter()
+                .position(|(_, s)| Some(s.id) == id)
+                .unwrap_or(0),
+        )
+    }
+
+    /// Id of the highlighted row's session, the one actions apply to
+    pub fn selected_row_id(&self) -> Option<Uuid> {
+        self.selected_session().map(|(_, s)| s.id)
+    }
+
+    /// The highlighted session and its index in `sessions`
+    pub fn selected_session(&self) -> Option<(usize, &Session)> {
+        let idx = self.selected_index()?;
+        self.filtered_sessions().get(idx).copied()
+    }
+
+    pub fn session_index(&self, id: Uuid) -> Option<usize> {
+        self.sessions.iter().position(|s| s.id == id)
+    }
+
+    /// Move the selection off a deleted session, to the first visible one.
+    /// One the search merely hides stays selected, to be highlighted again
+    /// when the search is cleared.
+    pub fn sync_selection(&mut self) {
+        if self
+            .selected_id
+            .is_none_or(|id| self.session_index(id).is_none())
+        {
+            self.selected_id = self.filtered_sessions().first().map(|(_, s)| s.id);
+        }
+    }
+
+    /// Pull list selections back in range, e.g. after a resize or when the
+    /// lists changed underneath them
+    pub fn clamp_selections(&mut self) {
+        self.sync_selection();
+        let groups = self.error_groups().len();
+        self.triage_selected = self.triage_selected.min(groups.saturating_sub(1));
+        let entries = self
+            .catalog
+            .as_ref()
+            .map_or(0, |catalog| catalog.snapshot().entries.len());
+        self.catalog_selected = self.catalog_selected.min(entries.saturating_sub(1));
+    }
+
+    pub fn show_usage(&mut self, period: SummaryPeriod) {
+        self.usage_summary = Some(UsageSummary::collect(&self.storage, &self.sessions, period));
+        self.current_screen = Screen::Usage;
+    }
+
+    /// Suggestion sources for the session form, including configured scripts
+    pub fn suggestion_providers(&self) -> Providers {
+        suggest::providers(&self.config.suggestion_scripts, &self.sessions)
+    }
+
+    /// Start the stopped sessions with the short ID or name `selector`, or
+    /// tagged with it, and select the first one. Fails if nothing matches.
+    pub fn start_matching(&mut self, selector: &str) -> color_eyre::Result<()> {
+        // A name that happens to look like an ID prefix means the name
+        let named = self
+            .sessions
+            .iter()
+            .any(|s| s.name.eq_ignore_ascii_case(selector));
+        let by_id: Vec<Uuid> = self
+            .sessions
+            .iter()
+            .filter(|s| !named && s.has_id(selector))
+            .map(|s| s.id)
+            .collect();
+        if by_id.len() > 1 {
+            return Err(color_eyre::eyre::eyre!(
+                "{} matches {} sessions, use more of the ID",
+                selector,
+                by_id.len()
+            ));
+        }
+        let ids: Vec<Uuid> = if by_id.is_empty() {
             self.sessions
                 .iter()
-                .enumerate()
-                .filter(|(_, s)| {
-                    s.name
-                        .to_lowercase()
-                        .contains(&self.search_query.to_lowercase())
-                        || s.target
-                            .to_lowercase()
-                            .contains(&self.search_query.to_lowercase())
-                        || s.local_port.to_string().contains(&self.search_query)
-                        || s.remote_port
-                            .map(|p| p.to_string().contains(&self.search_query))
-                            .unwrap_or(false)
-                })
+                .filter(|s| s.name.eq_ignore_ascii_case(selector) || s.has_tag(selector))
+                .map(|s| s.id)
                 .collect()
+        } else {
+            by_id
+        };
+        let Some(&first) = ids.first() else {
+            return Err(color_eyre::eyre::eyre!(
+                "no session or tag named {}",
+                selector
+            ));
+        };
+
+        for session in self.sessions.iter_mut().filter(|s| ids.contains(&s.id)) {
+            if !matches!(
+                session.status,
+                SessionStatus::Running | SessionStatus::Degraded(_) | SessionStatus::Starting
+            ) {
+                let _ = self.process_manager.start_session(session);
+            }
+        }
+        self.save()?;
+        self.select_session(first);
+        Ok(())
+    }
+
+    /// Stop the session with `id` and start it again, once the monitor has
+    /// seen the old process out if it doesn't exit right away
+    pub fn restart_session(&mut self, id: Uuid) -> color_eyre::Result<()> {
+        let Some(session) = self.sessions.iter_mut().find(|s| s.id == id) else {
+            return Ok(());
+        };
+        self.process_manager.stop_session(session)?;
+        self.start_after_stop(id)
+    }
+
+    /// Start the session with `id` after it was stopped, or note it for
+    /// [`Self::start_stopped_restarts`] while it is still stopping
+    pub fn start_after_stop(&mut self, id: Uuid) -> color_eyre::Result<()> {
+        let Some(session) = self.sessions.iter_mut().find(|s| s.id == id) else {
+            return Ok(());
+        };
+        if session.status == SessionStatus::Stopping {
+            self.restarts_after_stop.insert(id);
+            return Ok(());
+        }
+        self.process_manager.start_session(session)
+    }
+
+    /// Start the restarted sessions that have finished stopping; returns
+    /// whether any was
+    pub fn start_stopped_restarts(&mut self) -> bool {
+        let mut started = false;
+        for id in std::mem::take(&mut self.restarts_after_stop) {
+            // Gone if deleted while stopping
+            let Some(session) = self.sessions.iter_mut().find(|s| s.id == id) else {
+                continue;
+            };
+            match session.status {
+                SessionStatus::Stopping => {
+                    self.restarts_after_stop.insert(id);
+                }
+                SessionStatus::Stopped => {
+                    let _ = self.process_manager.start_session(session);
+                    started = true;
+                }
+                _ => {}
+            }
+        }
+        started
+    }
+
+    /// Running sessions started from this terminal
+    pub fn scoped_here(&self) -> Vec<Uuid> {
+        let Some(scope) = self.process_manager.scope() else {
+            return Vec::new();
+        };
+        self.sessions
+            .iter()
+            .filter(|s| s.scope.as_ref() == Some(scope) && s.pid.is_some())
+            .map(|s| s.id)
+            .collect()
+    }
+
+    /// Last canary result; `None` without a canary or before the first probe
+    pub fn network_up(&self) -> Option<bool> {
+        self.canary.as_ref().and_then(CanaryMonitor::healthy)
+    }
+
+    /// Errored sessions grouped by likely cause, skipping empty groups.
+    /// Indices point into `sessions`.
+    pub fn error_groups(&self) -> Vec<(ErrorCategory, Vec<usize>)> {
+        ErrorCategory::ALL
+            .iter()
+            .map(|&category| {
+                let indices = self
+                    .sessions
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, s)| s.error_category() == Some(category))
+                    .map(|(idx, _)| idx)
+                    .collect::<Vec<_>>();
+                (category, indices)
+            })
+            .filter(|(_, indices)| !indices.is_empty())
+            .collect()
+    }
+
+    /// Sessions matching the search. `tag:<name>` terms must all match a
+    /// tag; the remaining text matches name, target, ports, tags or short ID.
+    pub fn filtered_sessions(&self) -> Vec<(usize, &Session)> {
+        if self.search_query.is_empty() {
+            return self.sessions.iter().enumerate().collect();
+        }
+
+        let (tag_terms, text_terms): (Vec<&str>, Vec<&str>) = self
+            .search_query
+            .split_whitespace()
+            .partition(|term| term.starts_with("tag:"));
+        let tags: Vec<&str> = tag_terms
+            .iter()
+            .map(|t| &t[4..])
+            .filter(|t| !t.is_empty())
+            .collect();
+        let query = text_terms.join(" ").to_lowercase();
+
+        self.sessions
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| tags.iter().all(|tag| s.has_tag(tag)))
+            .filter(|(_, s)| {
+                query.is_empty()
+                    || s.name.to_lowercase().contains(&query)
+                    || s.target.to_lowercase().contains(&query)
+                    || s.local_port.to_string().contains(&query)
+                    || s.remote_port
+                        .map(|p| p.to_string().contains(&query))
+                        .unwrap_or(false)
+                    || s.tags.iter().any(|t| t.to_lowercase().contains(&query))
+                    || s.short_id().contains(&query)
+            })
+            .collect()
+    }
+}
+
+const SPINNER_FRAMES: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+const ASCII_SPINNER_FRAMES: [&str; 4] = ["|", "/", "-", "\\"];
+
+/// Current frame of the activity indicator shown on transitional sessions
+pub fn spinner_frame(accessible: bool) -> &'static str {
+    let tick = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis()
+        / 250;
+    if accessible {
+        ASCII_SPINNER_FRAMES[tick as usize % ASCII_SPINNER_FRAMES.len()]
+    } else {
+        SPINNER_FRAMES[tick as usize % SPINNER_FRAMES.len()]
+    }
+}
+
+/// Smallest terminal the screens are laid out for; anything smaller only
+/// gets a notice until it is resized
+pub const MIN_WIDTH: u16 = 40;
+pub const MIN_HEIGHT: u16 = 10;
+
+pub fn is_too_small(area: Rect) -> bool {
+    area.width < MIN_WIDTH || area.height < MIN_HEIGHT
+}
+
+pub fn render_too_small(frame: &mut Frame, theme: &Theme) {
+    let area = frame.area();
+    let text = format!(
+        "Terminal too small ({}x{}). Resize to at least {}x{}, or use `pfman watch`.",
+        area.width, area.height, MIN_WIDTH, MIN_HEIGHT
+    );
+    // Vertically centered when there's room, top-aligned otherwise
+    let top = area.height.saturating_sub(2) / 2;
+    let message = Paragraph::new(text)
+        .style(Style::default().fg(theme.warning))
+        .alignment(Alignment::Center)
+        .wrap(Wrap { trim: true });
+    frame.render_widget(
+        message,
+        Rect {
+            y: area.y + top,
+            height: area.height - top,
+            ..area
+        },
+    );
+}
+
+/// Drop all colors from the rendered frame. Cells that had a background
+/// (selections, highlights) are reversed instead so they stay distinguishable.
+pub fn strip_colors(frame: &mut Frame, theme: &Theme) {
+    for cell in frame.buffer_mut().content.iter_mut() {
+        if cell.bg != Color::Reset && cell.bg != theme.popup_bg {
+            cell.modifier.insert(Modifier::REVERSED);
         }
+        cell.fg = Color::Reset;
+        cell.bg = Color::Reset;
     }
 }
@@ -0,0 +1,218 @@
+use crate::models::Session;
+use crate::ui::AppState;
+use ratatui::{
+    Frame,
+    layout::Rect,
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+};
+use uuid::Uuid;
+
+/// Matches listed below the query; the rest is reached by typing more
+const MAX_VISIBLE: usize = 12;
+
+/// Ctrl+P overlay: type to fuzzy-find an action or a session action,
+/// `Enter` runs the highlighted one
+#[derive(Debug, Default)]
+pub struct Palette {
+    pub query: String,
+    /// Index into [`Palette::matches`]
+    pub selected: usize,
+}
+
+/// What a palette entry does when picked
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PaletteAction {
+    Start(Uuid),
+    Stop(Uuid),
+    Restart(Uuid),
+    Logs(Uuid),
+    Runs(Uuid),
+    Edit(Uuid),
+    Delete(Uuid),
+    ToggleAutoRestart(Uuid),
+    /// Select the session in the table
+    Select(Uuid),
+    Create,
+    Import,
+    Triage,
+    Usage,
+    Catalog,
+    Backups,
+    PauseAll,
+    ResumePaused,
+    TogglePreview,
+    Quit,
+}
+
+#[derive(Debug, Clone)]
+pub struct PaletteEntry {
+    pub label: String,
+    pub action: PaletteAction,
+}
+
+impl Palette {
+    /// Entries matching the query, best first; all of them, in order,
+    /// while the query is empty
+    pub fn matches(&self, state: &AppState) -> Vec<PaletteEntry> {
+        let mut scored: Vec<(i64, PaletteEntry)> = entries(state)
+            .into_iter()
+            .filter_map(|entry| Some((fuzzy_score(&self.query, &entry.label)?, entry)))
+            .collect();
+        // Stable, so equal scores keep the listing order
+        scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        scored.into_iter().map(|(_, entry)| entry).collect()
+    }
+
+    /// The highlighted entry, if anything matches
+    pub fn selected_entry(&self, state: &AppState) -> Option<PaletteEntry> {
+        self.matches(state).into_iter().nth(self.selected)
+    }
+}
+
+/// Every action on offer: the general ones, then those of each session
+/// that make sense in its current status
+fn entries(state: &AppState) -> Vec<PaletteEntry> {
+    let entry = |label: &str, action| PaletteEntry {
+        label: label.to_string(),
+        action,
+    };
+    let mut entries = vec![
+        entry("New session", PaletteAction::Create),
+        entry("Import sessions", PaletteAction::Import),
+        entry("Pause all running sessions", PaletteAction::PauseAll),
+    ];
+    if state.sessions.iter().any(|s| s.paused) {
+        entries.push(entry("Resume paused sessions", PaletteAction::ResumePaused));
+    }
+    entries.extend([
+        entry("Triage failed sessions", PaletteAction::Triage),
+        entry("Usage summary", PaletteAction::Usage),
+    ]);
+    if state.catalog.is_some() {
+        entries.push(entry("Team catalog", PaletteAction::Catalog));
+    }
+    entries.extend([
+        entry("Backups", PaletteAction::Backups),
+        entry("Toggle preview pane", PaletteAction::TogglePreview),
+        entry("Quit", PaletteAction::Quit),
+    ]);
+
+    for session in &state.sessions {
+        entries.extend(session_entries(session));
+    }
+    entries
+}
+
+fn session_entries(session: &Session) -> Vec<PaletteEntry> {
+    let name = &session.name;
+    let entry = |verb: &str, action| PaletteEntry {
+        label: format!("{} {}", verb, name),
+        action,
+    };
+    let id = session.id;
+    let mut entries = Vec::new();
+    if session.pid.is_some() {
+        entries.push(entry("stop", PaletteAction::Stop(id)));
+        entries.push(entry("restart", PaletteAction::Restart(id)));
+    } else {
+        entries.push(entry("start", PaletteAction::Start(id)));
+    }
+    entries.extend([
+        entry("logs", PaletteAction::Logs(id)),
+        entry("run history", PaletteAction::Runs(id)),
+        entry("edit", PaletteAction::Edit(id)),
+        entry("go to", PaletteAction::Select(id)),
+        entry(
+            if session.auto_restart {
+                "disable auto-restart"
+            } else {
+                "enable auto-restart"
+            },
+            PaletteAction::ToggleAutoRestart(id),
+        ),
+        entry("delete", PaletteAction::Delete(id)),
+    ]);
+    entries
+}
+
+/// How well `query` matches `text`: its characters must all appear in
+/// order, ignoring case and spaces. Runs of consecutive characters and
+/// matches at word starts score higher, so "st pg" ranks "start postgres"
+/// above "restart api-pg". `None` when it doesn't match at all.
+fn fuzzy_score(query: &str, text: &str) -> Option<i64> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut pos = 0;
+    let mut previous: Option<usize> = None;
+    for wanted in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let found = pos + text[pos..].iter().position(|&c| c == wanted)?;
+        let word_start = found == 0 || !text[found - 1].is_alphanumeric();
+        score += match previous {
+            Some(p) if p + 1 == found => 8,
+            _ if word_start => 6,
+            _ => 1,
+        };
+        // Gaps cost a little, so tighter matches win among the rest
+        score -= (found - pos).min(5) as i64;
+        previous = Some(found);
+        pos = found + 1;
+    }
+    Some(score)
+}
+
+/// Query line and matches, drawn over the top of any screen
+pub fn render(frame: &mut Frame, state: &AppState, palette: &Palette, area: Rect) {
+    let theme = &state.theme;
+    let matches = palette.matches(state);
+
+    let popup_width = 70.min(area.width.saturating_sub(4));
+    let list_height = matches.len().clamp(1, MAX_VISIBLE) as u16;
+    let popup_height = (list_height + 4).min(area.height);
+    let popup_area = Rect {
+        x: area.x + (area.width.saturating_sub(popup_width)) / 2,
+        y: area.y + (area.height / 6).min(area.height.saturating_sub(popup_height)),
+        width: popup_width,
+        height: popup_height,
+    };
+
+    let mut lines = vec![
+        Line::from(vec![
+            Span::styled("> ", Style::default().fg(theme.key)),
+            Span::raw(palette.query.clone()),
+            Span::styled("_", Style::default().fg(theme.key)),
+        ]),
+        Line::from(""),
+    ];
+    if matches.is_empty() {
+        lines.push(Line::from(Span::styled(
+            "No matching action",
+            Style::default().fg(theme.muted),
+        )));
+    }
+    // Keep the highlighted entry in view
+    let first = palette.selected.saturating_sub(MAX_VISIBLE - 1);
+    for (idx, entry) in matches.iter().enumerate().skip(first).take(MAX_VISIBLE) {
+        let style = if idx == palette.selected {
+            Style::default()
+                .fg(theme.selection_fg)
+                .bg(theme.selection_bg)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default()
+        };
+        lines.push(Line::from(Span::styled(entry.label.clone(), style)));
+    }
+
+    let paragraph = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title("Actions (Enter run, Esc close)")
+            .border_style(Style::default().fg(theme.accent))
+            .style(Style::default().bg(theme.popup_bg)),
+    );
+
+    frame.render_widget(Clear, popup_area);
+    frame.render_widget(paragraph, popup_area);
+}
@@ -0,0 +1,153 @@
+use crate::models::Session;
+use crate::runs::{self, Run};
+use crate::ui::AppState;
+use crate::usage::format_duration;
+use chrono::{DateTime, Utc};
+use ratatui::{
+    Frame,
+    layout::{Constraint, Layout, Rect},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Cell, Paragraph, Row, Table, TableState},
+};
+
+/// The session's runs, newest first, as listed on the screen
+pub fn runs_newest_first(state: &AppState, session: &Session) -> Vec<Run> {
+    let mut runs = runs::history(&state.storage, &session.id);
+    runs.reverse();
+    runs
+}
+
+pub fn render(frame: &mut Frame, state: &AppState, session_idx: usize, area: Rect) {
+    let Some(session) = state.sessions.get(session_idx) else {
+        return;
+    };
+    let chunks = Layout::vertical([
+        Constraint::Length(3),
+        Constraint::Min(0),
+        Constraint::Length(3),
+    ])
+    .split(area);
+
+    let title = Paragraph::new(format!("Run History - {}", session.display_name()))
+        .style(
+            Style::default()
+                .fg(state.theme.title)
+                .add_modifier(Modifier::BOLD),
+        )
+        .block(Block::default().borders(Borders::ALL));
+    frame.render_widget(title, chunks[0]);
+
+    render_runs(frame, state, session, chunks[1]);
+    render_help(frame, state, chunks[2]);
+}
+
+/// Every run with when and how it ended
+fn render_runs(frame: &mut Frame, state: &AppState, session: &Session, area: Rect) {
+    let theme = &state.theme;
+    let now = Utc::now();
+    let runs = runs_newest_first(state, session);
+    let still_up = session.pid.is_some();
+    let time = |at: DateTime<Utc>| at.format("%Y-%m-%d %H:%M:%S").to_string();
+
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let header = Row::new(
+        ["Started", "Ended", "Up", "Outcome", "PID", "Details"]
+            .map(|title| Cell::from(title).style(bold)),
+    );
+    let mut uptime = chrono::Duration::zero();
+    let rows: Vec<Row> = runs
+        .iter()
+        .enumerate()
+        .map(|(idx, run)| {
+            let up = run.end.is_some() || (idx == 0 && still_up);
+            if up {
+                uptime += run.duration(now);
+            }
+            let (ended, outcome, details, style) = match &run.end {
+                Some(end) => {
+                    let mut details = Vec::new();
+                    if let Some(code) = end.exit_code {
+                        details.push(format!("exit code {}", code));
+                    }
+                    details.extend(end.reason.clone());
+                    let style = if end.event.is_failure() {
+                        Style::default().fg(theme.error)
+                    } else {
+                        Style::default()
+                    };
+                    (time(end.at), end.event.as_str(), details.join(", "), style)
+                }
+                None if up => (
+                    String::new(),
+                    "running",
+                    String::new(),
+                    Style::default().fg(theme.success),
+                ),
+                None => (
+                    String::new(),
+                    "unknown",
+                    "pfman didn't see it end".to_string(),
+                    Style::default().fg(theme.muted),
+                ),
+            };
+            Row::new(vec![
+                Cell::from(time(run.started_at)),
+                Cell::from(ended),
+                Cell::from(if up {
+                    format_duration(run.duration(now))
+                } else {
+                    String::new()
+                }),
+                Cell::from(outcome).style(style),
+                Cell::from(run.pid.to_string()),
+                Cell::from(details),
+            ])
+        })
+        .collect();
+
+    let failed = runs.iter().filter(|run| run.failed()).count();
+    let title = format!(
+        "Runs ({}, {} failed, up {} in total)",
+        runs.len(),
+        failed,
+        format_duration(uptime)
+    );
+    let block = Block::default().borders(Borders::ALL).title(title);
+    if runs.is_empty() {
+        let empty = Paragraph::new("No runs recorded yet.")
+            .style(Style::default().fg(theme.muted))
+            .block(block);
+        frame.render_widget(empty, area);
+        return;
+    }
+    let widths = [
+        Constraint::Length(19),
+        Constraint::Length(19),
+        Constraint::Length(8),
+        Constraint::Length(19),
+        Constraint::Length(8),
+        Constraint::Fill(1),
+    ];
+    let table = Table::new(rows, widths)
+        .header(header)
+        .block(block)
+        .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    let mut table_state = TableState::default().with_selected(Some(state.run_selected));
+    frame.render_stateful_widget(table, area, &mut table_state);
+}
+
+fn render_help(frame: &mut Frame, state: &AppState, area: Rect) {
+    let theme = &state.theme;
+    let help_text = Line::from(vec![
+        Span::styled("↑/↓", Style::default().fg(theme.key)),
+        Span::raw(" select | "),
+        Span::styled("Enter", Style::default().fg(theme.key)),
+        Span::raw(" open its log | "),
+        Span::styled("Esc", Style::default().fg(theme.key)),
+        Span::raw(" back"),
+    ]);
+
+    let help = Paragraph::new(help_text).block(Block::default().borders(Borders::ALL));
+    frame.render_widget(help, area);
+}
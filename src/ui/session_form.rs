@@ -1,19 +1,27 @@
+use crate::events::{AppEvent, EventSender, Suggestions};
 use crate::kube_config::{
-    KubeContext, KubeTarget, filter_targets, get_current_context, get_namespaces, get_targets,
-    parse_kube_config,
+    KubeCache, KubeContext, KubeScope, KubeTarget, filter_targets, get_current_context,
+    get_namespaces, get_targets, parse_kube_config,
 };
-use crate::models::{Session, SessionType};
-use crate::ssh_config::{SshHost, filter_hosts, parse_ssh_config};
+use crate::models::{
+    AUTO_PORT, AddressFamily, FieldChange, FormDraft, Lifetime, MAX_IDLE_TIMEOUT_MINUTES, Session,
+    SessionType,
+};
+use crate::provider::ProviderRegistry;
+use crate::ssh_config::{SshHost, filter_hosts};
+use crate::suggest::{self, Providers};
 use crate::ui::FormMode;
+use crate::ui::theme::Theme;
 use ratatui::{
     Frame,
     layout::{Constraint, Layout, Rect},
-    style::{Color, Modifier, Style},
+    style::{Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, List, ListItem, Paragraph},
+    widgets::{Block, Borders, Clear, List, ListItem, Paragraph},
 };
-use std::sync::mpsc::{self, Receiver};
-use std::thread;
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use std::path::PathBuf;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 #[derive(Debug, Clone, PartialEq)]
@@ -32,6 +40,28 @@ pub struct FormState {
     pub target: String,
     pub local_port: String,
     pub remote_port: String,
+    /// Host behind an SSH target the forward reaches, empty for the target
+    pub remote_host: String,
+    pub icon: String,
+    pub color: String,
+    /// Comma-separated tags
+    pub tags: String,
+    pub health_command: String,
+    /// Shell commands run before each start and after the process ends
+    pub pre_start: String,
+    pub post_stop: String,
+    /// SSH/SOCKS5 login user override
+    pub user: String,
+    pub identity_file: String,
+    /// kubeconfig file of a kubectl session, empty for the default ones
+    pub kubeconfig: String,
+    /// Minutes without connections before the session is stopped, empty
+    /// for never
+    pub idle_timeout: String,
+    /// e.g. `8h`; empty for no limit
+    pub max_lifetime: String,
+    /// `ipv4` or `ipv6`; empty for either
+    pub address_family: String,
     pub focused_field: usize,
     pub cursor_pos: usize,
     pub ssh_hosts: Vec<SshHost>,
@@ -45,20 +75,55 @@ pub struct FormState {
     pub kube_targets: Vec<KubeTarget>,
     pub filtered_kube_targets: Vec<KubeTarget>,
     pub available_ports: Vec<u16>,
+    /// Remote ports of existing sessions, most used first
+    pub recent_ports: Vec<u16>,
+    /// kubectl targets of existing sessions, most used first
+    pub recent_kube_targets: Vec<String>,
+    /// SSH targets of existing sessions, most used first
+    pub recent_ssh_targets: Vec<String>,
+    /// Ports offered in the port fields: the kube target's ports, then recent ones
+    pub suggested_ports: Vec<u16>,
+    /// Completions of the path typed in the identity file or kubeconfig field
+    pub filtered_paths: Vec<String>,
     pub scroll_offset: usize,
     pub loading_targets: bool,
-    target_receiver: Option<Receiver<Vec<KubeTarget>>>,
+    /// Scope the namespaces and the targets were looked up for
+    namespaces_scope: Option<KubeScope>,
+    targets_scope: Option<KubeScope>,
+    /// Why the last save was rejected, shown until the next key press
+    pub error: Option<String>,
+    /// Fields whose errors are shown: the ones left with Tab, and all of
+    /// them once saving was tried
+    checked_fields: HashSet<usize>,
+    /// Warning the user already saw once; saving again goes ahead anyway
+    pub acknowledged_warning: Option<String>,
+    /// Name of the edited session and the changes about to be saved,
+    /// waiting for confirmation
+    pub pending_changes: Option<(String, Vec<FieldChange>)>,
+    /// Sources of host and kubectl target suggestions
+    providers: Providers,
+    /// Plugin session types offered after the built-in ones
+    session_providers: ProviderRegistry,
+    kube_cache: KubeCache,
+    /// Scopes of the namespaces and targets lookups in progress
+    namespaces_requested: Option<KubeScope>,
+    targets_requested: Option<KubeScope>,
+    /// Where lookups deliver their results; without it they block
+    events: Option<EventSender>,
+}
+
+impl Default for FormState {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl FormState {
     pub fn new() -> Self {
-        let ssh_hosts = parse_ssh_config();
-        let filtered_hosts = ssh_hosts.clone();
+        // Hosts are looked up once the providers are known, see `with_providers`
+        let providers = suggest::default_providers();
 
-        let (kube_context, kube_contexts) = parse_kube_config().unwrap_or_else(|| {
-            let ctx = get_current_context().unwrap_or_default();
-            (ctx, Vec::new())
-        });
+        let (kube_context, kube_contexts) = load_contexts(None);
 
         let filtered_kube_contexts = kube_contexts.clone();
         // Don't load namespaces immediately - will be lazy loaded when namespace field is focused
@@ -75,10 +140,23 @@ impl FormState {
             target: String::new(),
             local_port: String::new(),
             remote_port: String::new(),
+            remote_host: String::new(),
+            icon: String::new(),
+            color: String::new(),
+            tags: String::new(),
+            health_command: String::new(),
+            pre_start: String::new(),
+            post_stop: String::new(),
+            user: String::new(),
+            identity_file: String::new(),
+            kubeconfig: String::new(),
+            idle_timeout: String::new(),
+            max_lifetime: String::new(),
+            address_family: String::new(),
             focused_field: 0,
             cursor_pos: 0,
-            ssh_hosts,
-            filtered_hosts,
+            ssh_hosts: Vec::new(),
+            filtered_hosts: Vec::new(),
             selected_suggestion: 0,
             show_suggestions: false,
             kube_contexts,
@@ -88,21 +166,34 @@ impl FormState {
             kube_targets: Vec::new(),
             filtered_kube_targets: Vec::new(),
             available_ports: Vec::new(),
+            recent_ports: Vec::new(),
+            recent_kube_targets: Vec::new(),
+            recent_ssh_targets: Vec::new(),
+            suggested_ports: Vec::new(),
+            filtered_paths: Vec::new(),
             scroll_offset: 0,
             loading_targets: false,
-            target_receiver: None,
+            namespaces_scope: None,
+            targets_scope: None,
+            error: None,
+            checked_fields: HashSet::new(),
+            acknowledged_warning: None,
+            pending_changes: None,
+            providers,
+            session_providers: ProviderRegistry::default(),
+            kube_cache: KubeCache::default(),
+            namespaces_requested: None,
+            targets_requested: None,
+            events: None,
         }
     }
 
     pub fn from_session(session: &Session) -> Self {
-        let ssh_hosts = parse_ssh_config();
-        let filtered_hosts = ssh_hosts.clone();
+        // Hosts are looked up once the providers are known, see `with_providers`
+        let providers = suggest::default_providers();
         let name_len = session.name.len();
 
-        let (default_context, kube_contexts) = parse_kube_config().unwrap_or_else(|| {
-            let ctx = get_current_context().unwrap_or_default();
-            (ctx, Vec::new())
-        });
+        let (default_context, kube_contexts) = load_contexts(session.kubeconfig_path.as_deref());
 
         let filtered_kube_contexts = kube_contexts.clone();
         let context_field = session.kube_context.clone().unwrap_or(default_context);
@@ -127,15 +218,41 @@ impl FormState {
             context_field,
             namespace_field,
             target: session.target.clone(),
-            local_port: session.local_port.to_string(),
+            local_port: if session.auto_local_port {
+                AUTO_PORT.to_string()
+            } else {
+                session.local_port.to_string()
+            },
             remote_port: session
                 .remote_port
                 .map(|p| p.to_string())
                 .unwrap_or_default(),
+            remote_host: session.remote_host.clone().unwrap_or_default(),
+            icon: session.icon.clone().unwrap_or_default(),
+            color: session.color.clone().unwrap_or_default(),
+            tags: session.tags.join(", "),
+            health_command: session.health_command.clone().unwrap_or_default(),
+            pre_start: session.pre_start.clone().unwrap_or_default(),
+            post_stop: session.post_stop.clone().unwrap_or_default(),
+            user: session.user.clone().unwrap_or_default(),
+            identity_file: session.identity_file.clone().unwrap_or_default(),
+            kubeconfig: session.kubeconfig_path.clone().unwrap_or_default(),
+            idle_timeout: session
+                .idle_timeout_minutes
+                .map(|m| m.to_string())
+                .unwrap_or_default(),
+            max_lifetime: session
+                .max_lifetime
+                .map(|l| l.to_string())
+                .unwrap_or_default(),
+            address_family: match session.address_family {
+                AddressFamily::Any => String::new(),
+                family => family.to_string(),
+            },
             focused_field,
             cursor_pos: name_len,
-            ssh_hosts,
-            filtered_hosts,
+            ssh_hosts: Vec::new(),
+            filtered_hosts: Vec::new(),
             selected_suggestion: 0,
             show_suggestions: false,
             kube_contexts,
@@ -145,18 +262,108 @@ impl FormState {
             kube_targets: Vec::new(),
             filtered_kube_targets: Vec::new(),
             available_ports: Vec::new(),
+            recent_ports: Vec::new(),
+            recent_kube_targets: Vec::new(),
+            recent_ssh_targets: Vec::new(),
+            suggested_ports: Vec::new(),
+            filtered_paths: Vec::new(),
             scroll_offset: 0,
             loading_targets: false,
-            target_receiver: None,
+            namespaces_scope: None,
+            targets_scope: None,
+            error: None,
+            checked_fields: HashSet::new(),
+            acknowledged_warning: None,
+            pending_changes: None,
+            providers,
+            session_providers: ProviderRegistry::default(),
+            kube_cache: KubeCache::default(),
+            namespaces_requested: None,
+            targets_requested: None,
+            events: None,
+        }
+    }
+
+    pub fn from_draft(draft: &FormDraft) -> Self {
+        let mut state = Self::new();
+        state.step = FormStep::FillFields;
+        state.session_type = draft.session_type.clone();
+        state.name = draft.name.clone();
+        state.context_field = draft.context.clone();
+        state.namespace_field = draft.namespace.clone();
+        state.target = draft.target.clone();
+        state.local_port = draft.local_port.clone();
+        state.remote_port = draft.remote_port.clone();
+        state.remote_host = draft.remote_host.clone();
+        state.icon = draft.icon.clone();
+        state.color = draft.color.clone();
+        state.tags = draft.tags.clone();
+        state.health_command = draft.health_command.clone();
+        state.pre_start = draft.pre_start.clone();
+        state.post_stop = draft.post_stop.clone();
+        state.user = draft.user.clone();
+        state.identity_file = draft.identity_file.clone();
+        state.kubeconfig = draft.kubeconfig.clone();
+        state.idle_timeout = draft.idle_timeout.clone();
+        state.max_lifetime = draft.max_lifetime.clone();
+        state.address_family = draft.address_family.clone();
+        if !state.kubeconfig.is_empty() {
+            state.reload_contexts();
+        }
+        state.focused_field = if state.session_type == SessionType::Kubectl {
+            1
+        } else {
+            0
+        };
+        state.cursor_pos = state.name.len();
+        state
+    }
+
+    /// Draft of the current field values; None until a type is chosen
+    pub fn to_draft(&self, editing: Option<uuid::Uuid>) -> Option<FormDraft> {
+        if self.step != FormStep::FillFields {
+            return None;
         }
+        Some(FormDraft {
+            editing,
+            session_type: self.session_type.clone(),
+            name: self.name.clone(),
+            context: self.context_field.clone(),
+            namespace: self.namespace_field.clone(),
+            target: self.target.clone(),
+            local_port: self.local_port.clone(),
+            remote_port: self.remote_port.clone(),
+            icon: self.icon.clone(),
+            color: self.color.clone(),
+            tags: self.tags.clone(),
+            health_command: self.health_command.clone(),
+            pre_start: self.pre_start.clone(),
+            post_stop: self.post_stop.clone(),
+            user: self.user.clone(),
+            identity_file: self.identity_file.clone(),
+            remote_host: self.remote_host.clone(),
+            kubeconfig: self.kubeconfig.clone(),
+            idle_timeout: self.idle_timeout.clone(),
+            max_lifetime: self.max_lifetime.clone(),
+            address_family: self.address_family.clone(),
+        })
     }
 
     pub fn to_session(&self) -> Option<Session> {
-        let local_port = self.local_port.parse::<u16>().ok()?;
-        let remote_port = if self.session_type == SessionType::Socks5 {
-            None
+        let listens_locally = self.session_type.listens_locally();
+        let auto_local_port =
+            listens_locally && self.local_port.trim().eq_ignore_ascii_case(AUTO_PORT);
+        // A reverse proxy's only port is the one opened on the target
+        let local_port = if auto_local_port || !listens_locally {
+            0
         } else {
-            Some(self.remote_port.parse::<u16>().ok()?)
+            self.local_port.trim().parse::<u16>().ok()?
+        };
+        let remote_port = match self.session_type {
+            SessionType::Socks5 => None,
+            // Plugin tools may not forward to one particular port
+            SessionType::Custom(_) if self.remote_port.trim().is_empty() => None,
+            _ => Some(self.remote_port.trim().parse::<u16>().ok()?),
         };
 
         let mut session = Session::new(
@@ -167,6 +374,53 @@ impl FormState {
             remote_port,
         );
 
+        session.auto_local_port = auto_local_port;
+
+        if !self.icon.trim().is_empty() {
+            session.icon = Some(self.icon.trim().to_string());
+        }
+        if !self.color.trim().is_empty() {
+            session.color = Some(self.color.trim().to_string());
+        }
+        session.tags = self
+            .tags
+            .split([',', ' '])
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+            .map(str::to_string)
+            .collect();
+        if !self.health_command.trim().is_empty() {
+            session.health_command = Some(self.health_command.trim().to_string());
+        }
+        if !self.pre_start.trim().is_empty() {
+            session.pre_start = Some(self.pre_start.trim().to_string());
+        }
+        if !self.post_stop.trim().is_empty() {
+            session.post_stop = Some(self.post_stop.trim().to_string());
+        }
+        if !self.idle_timeout.trim().is_empty() {
+            let minutes = self.idle_timeout.trim().parse::<u64>().ok();
+            session.idle_timeout_minutes =
+                Some(minutes.filter(|m| (1..=MAX_IDLE_TIMEOUT_MINUTES).contains(m))?);
+        }
+        if !self.max_lifetime.trim().is_empty() {
+            session.max_lifetime = Some(self.max_lifetime.parse().ok()?);
+        }
+        session.address_family = self.address_family.parse().ok()?;
+
+        if self.session_type != SessionType::Kubectl {
+            if !self.user.trim().is_empty() {
+                session.user = Some(self.user.trim().to_string());
+            }
+            if !self.identity_file.trim().is_empty() {
+                session.identity_file = Some(self.identity_file.trim().to_string());
+            }
+        }
+
+        if self.session_type == SessionType::SSH && !self.remote_host.trim().is_empty() {
+            session.remote_host = Some(self.remote_host.trim().to_string());
+        }
+
         if self.session_type == SessionType::Kubectl {
             session.kube_context = if self.context_field.is_empty() {
                 None
@@ -178,17 +432,124 @@ impl FormState {
             } else {
                 Some(self.namespace_field.clone())
             };
+            session.kubeconfig_path = self.kubeconfig_path().map(str::to_string);
         }
 
         Some(session)
     }
 
-    pub fn toggle_type(&mut self) {
-        self.session_type = match self.session_type {
-            SessionType::SSH => SessionType::Kubectl,
-            SessionType::Kubectl => SessionType::Socks5,
-            SessionType::Socks5 => SessionType::SSH,
+    /// Index of the Name field
+    fn name_field(&self) -> usize {
+        if self.session_type == SessionType::Kubectl {
+            1
+        } else {
+            0
+        }
+    }
+
+    /// Index of the Target field
+    fn target_field(&self) -> usize {
+        if self.session_type == SessionType::Kubectl {
+            3
+        } else {
+            1
+        }
+    }
+
+    /// Index of the Local Port field; a reverse proxy has none
+    fn local_port_field(&self) -> Option<usize> {
+        match self.session_type {
+            SessionType::Kubectl => Some(4),
+            SessionType::ReverseSocks5 => None,
+            _ => Some(2),
+        }
+    }
+
+    /// Index of the Remote Port field; a SOCKS5 proxy has none
+    fn remote_port_field(&self) -> Option<usize> {
+        match self.session_type {
+            SessionType::Kubectl => Some(5),
+            SessionType::Socks5 => None,
+            SessionType::ReverseSocks5 => Some(2),
+            SessionType::SSH | SessionType::Custom(_) => Some(3),
+        }
+    }
+
+    /// Why the value of field `idx` can't be saved, if it can't
+    pub fn field_error(&self, idx: usize) -> Option<String> {
+        let port_error = |value: &str| match value.trim().parse::<u16>() {
+            Ok(port) if port > 0 => None,
+            _ if value.trim().is_empty() => Some("Enter a port".to_string()),
+            _ => Some(format!("Invalid port '{}' (1 to 65535)", value.trim())),
         };
+        if idx == self.name_field() {
+            return self
+                .name
+                .trim()
+                .is_empty()
+                .then(|| "Enter a name".to_string());
+        }
+        if idx == self.target_field() {
+            return self
+                .target
+                .trim()
+                .is_empty()
+                .then(|| "Enter a target".to_string());
+        }
+        if self.local_port_field() == Some(idx) {
+            if self.local_port.trim().eq_ignore_ascii_case(AUTO_PORT) {
+                return None;
+            }
+            return port_error(&self.local_port)
+                .map(|error| format!("{}, or {} to pick a free one", error, AUTO_PORT));
+        }
+        if self.remote_port_field() == Some(idx) {
+            // Plugin tools may not forward to one particular port
+            if matches!(self.session_type, SessionType::Custom(_))
+                && self.remote_port.trim().is_empty()
+            {
+                return None;
+            }
+            return port_error(&self.remote_port);
+        }
+        match idx.checked_sub(self.common_fields_start()) {
+            Some(6) if !self.idle_timeout.trim().is_empty() => {
+                match self.idle_timeout.trim().parse::<u64>() {
+                    Ok(minutes) if (1..=MAX_IDLE_TIMEOUT_MINUTES).contains(&minutes) => None,
+                    _ => Some(format!(
+                        "Invalid idle stop '{}' (minutes up to {}, empty for never)",
+                        self.idle_timeout.trim(),
+                        MAX_IDLE_TIMEOUT_MINUTES
+                    )),
+                }
+            }
+            Some(7) if !self.max_lifetime.trim().is_empty() => {
+                self.max_lifetime.parse::<Lifetime>().err()
+            }
+            Some(8) => self.address_family.parse::<AddressFamily>().err(),
+            _ => None,
+        }
+    }
+
+    /// Error shown under field `idx`, once it has been checked
+    pub fn shown_field_error(&self, idx: usize) -> Option<String> {
+        if !self.checked_fields.contains(&idx) {
+            return None;
+        }
+        self.field_error(idx)
+    }
+
+    /// Show the error of field `idx` from now on, e.g. when it is left
+    pub fn check_field(&mut self, idx: usize) {
+        self.checked_fields.insert(idx);
+    }
+
+    /// Show the errors of all fields; the number of fields with one
+    pub fn check_all_fields(&mut self) -> usize {
+        self.checked_fields.extend(0..self.field_count());
+        (0..self.field_count())
+            .filter(|&idx| self.field_error(idx).is_some())
+            .count()
     }
 
     pub fn confirm_type_selection(&mut self) {
@@ -196,24 +557,301 @@ impl FormState {
             0 => SessionType::SSH,
             1 => SessionType::Kubectl,
             2 => SessionType::Socks5,
-            _ => SessionType::SSH,
+            3 => SessionType::ReverseSocks5,
+            idx => self
+                .session_providers
+                .providers()
+                .get(idx - 4)
+                .map_or(SessionType::SSH, |p| {
+                    SessionType::Custom(p.name().to_string())
+                }),
         };
         self.step = FormStep::FillFields;
+        self.start_loading_provider_targets();
     }
 
     pub fn move_type_selection(&mut self, delta: i32) {
-        let new_idx = (self.type_selection as i32 + delta).rem_euclid(3);
+        let count = 4 + self.session_providers.providers().len() as i32;
+        let new_idx = (self.type_selection as i32 + delta).rem_euclid(count);
         self.type_selection = new_idx as usize;
     }
 
     pub fn field_count(&self) -> usize {
         match self.session_type {
-            SessionType::Socks5 => 3,  // Name, Target, Local Port
-            SessionType::Kubectl => 6, // Name, Context, Namespace, Target, Local Port, Remote Port
-            SessionType::SSH => 4,     // Name, Target, Local Port, Remote Port
+            SessionType::Socks5 => 14, // Name, Target, Local Port, User, Identity, Icon, Color, Tags, Health Check, Pre-start, Post-stop, Idle Stop, Max Lifetime, IP Version
+            SessionType::ReverseSocks5 => 14, // Name, Target, Remote Port, User, Identity, Icon, Color, Tags, Health Check, Pre-start, Post-stop, Idle Stop, Max Lifetime, IP Version
+            SessionType::Kubectl => 16, // Context, Name, Namespace, Target, Local Port, Remote Port, Kubeconfig, Icon, Color, Tags, Health Check, Pre-start, Post-stop, Idle Stop, Max Lifetime, IP Version
+            SessionType::SSH => 16, // Name, Target, Local Port, Remote Port, Remote Host, User, Identity, Icon, Color, Tags, Health Check, Pre-start, Post-stop, Idle Stop, Max Lifetime, IP Version
+            SessionType::Custom(_) => 13, // Name, Target, Local Port, Remote Port, Icon, Color, Tags, Health Check, Pre-start, Post-stop, Idle Stop, Max Lifetime, IP Version
+        }
+    }
+
+    /// Index of the Remote Host field; SSH only
+    pub fn remote_host_field(&self) -> Option<usize> {
+        (self.session_type == SessionType::SSH).then_some(4)
+    }
+
+    /// Index of the User field, followed by Identity; the SSH based types
+    pub fn ssh_fields_start(&self) -> Option<usize> {
+        match self.session_type {
+            SessionType::SSH => Some(5),
+            SessionType::Socks5 | SessionType::ReverseSocks5 => Some(3),
+            SessionType::Kubectl | SessionType::Custom(_) => None,
+        }
+    }
+
+    /// Index of the Kubeconfig field; kubectl only
+    pub fn kubeconfig_field(&self) -> Option<usize> {
+        (self.session_type == SessionType::Kubectl).then_some(6)
+    }
+
+    /// Index of the first field shared by all session types (Icon, Color,
+    /// Tags, Health Check, Pre-start, Post-stop, Idle Stop, Max Lifetime,
+    /// IP Version)
+    pub fn common_fields_start(&self) -> usize {
+        self.field_count() - 9
+    }
+
+    pub fn common_field_mut(&mut self, idx: usize) -> Option<&mut String> {
+        match idx.checked_sub(self.common_fields_start()) {
+            Some(0) => Some(&mut self.icon),
+            Some(1) => Some(&mut self.color),
+            Some(2) => Some(&mut self.tags),
+            Some(3) => Some(&mut self.health_command),
+            Some(4) => Some(&mut self.pre_start),
+            Some(5) => Some(&mut self.post_stop),
+            Some(6) => Some(&mut self.idle_timeout),
+            Some(7) => Some(&mut self.max_lifetime),
+            Some(8) => Some(&mut self.address_family),
+            _ => None,
+        }
+    }
+
+    /// Plain text fields after the connection fields: Remote Host, User and
+    /// Identity or Kubeconfig, then the common ones
+    pub fn text_field_mut(&mut self, idx: usize) -> Option<&mut String> {
+        if self.kubeconfig_field() == Some(idx) {
+            return Some(&mut self.kubeconfig);
+        }
+        if self.remote_host_field() == Some(idx) {
+            return Some(&mut self.remote_host);
+        }
+        // In place of the Local Port field, which a reverse proxy hasn't
+        if self.session_type == SessionType::ReverseSocks5 && idx == 2 {
+            return Some(&mut self.remote_port);
+        }
+        match self
+            .ssh_fields_start()
+            .and_then(|start| idx.checked_sub(start))
+        {
+            Some(0) => Some(&mut self.user),
+            Some(1) => Some(&mut self.identity_file),
+            _ => self.common_field_mut(idx),
+        }
+    }
+
+    pub fn is_identity_field(&self) -> bool {
+        self.ssh_fields_start()
+            .is_some_and(|start| self.focused_field == start + 1)
+    }
+
+    fn is_kubeconfig_field(&self) -> bool {
+        self.kubeconfig_field() == Some(self.focused_field)
+    }
+
+    /// Identity or Kubeconfig, the fields completing file paths
+    pub fn is_path_field(&self) -> bool {
+        self.is_identity_field() || self.is_kubeconfig_field()
+    }
+
+    /// The kubeconfig file typed in, if any
+    fn kubeconfig_path(&self) -> Option<&str> {
+        Some(self.kubeconfig.trim()).filter(|k| !k.is_empty())
+    }
+
+    /// Offer paths completing what's typed in the identity or kubeconfig
+    /// field. A changed kubeconfig also brings its own contexts.
+    pub fn update_path_suggestions(&mut self) {
+        if self.is_identity_field() {
+            self.filtered_paths = complete_path(&self.identity_file, "~/.ssh/", is_ssh_key);
+        } else if self.is_kubeconfig_field() {
+            self.filtered_paths = complete_path(&self.kubeconfig, "~/.kube/", |_| true);
+            self.reload_contexts();
+        } else {
+            return;
+        }
+        self.show_suggestions = !self.filtered_paths.is_empty();
+        self.selected_suggestion = 0;
+        self.scroll_offset = 0;
+    }
+
+    /// Run host and target lookups in the background, sending their
+    /// results to the app's event loop, which hands them to
+    /// [`FormState::on_suggestions`]. Needs a tokio runtime.
+    pub fn with_events(mut self, events: EventSender) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Replace the suggestion sources and reload the host list
+    pub fn with_providers(mut self, providers: Providers) -> Self {
+        self.providers = providers;
+        self.start_loading_hosts();
+        self
+    }
+
+    /// Offer the plugin session types of these providers, and their
+    /// targets when editing a plugin session
+    pub fn with_session_providers(mut self, session_providers: ProviderRegistry) -> Self {
+        self.session_providers = session_providers;
+        self.start_loading_provider_targets();
+        self
+    }
+
+    /// Reuse namespaces and targets other forms looked up recently
+    pub fn with_kube_cache(mut self, kube_cache: KubeCache) -> Self {
+        self.kube_cache = kube_cache;
+        self
+    }
+
+    /// Rank values used by existing sessions so they are suggested first:
+    /// SSH targets ahead of the other hosts, frequent remote ports in the
+    /// port fields and frequent kubectl targets ahead of the others
+    pub fn with_history(mut self, sessions: &[Session]) -> Self {
+        self.recent_ports = rank_by_frequency(sessions.iter().filter_map(|s| s.remote_port));
+        self.recent_kube_targets = rank_by_frequency(
+            sessions
+                .iter()
+                .filter(|s| s.session_type == SessionType::Kubectl)
+                .map(|s| s.target.clone()),
+        );
+
+        self.recent_ssh_targets = rank_by_frequency(
+            sessions
+                .iter()
+                .filter(|s| s.session_type != SessionType::Kubectl)
+                .map(|s| s.target.clone()),
+        );
+        let hosts = std::mem::take(&mut self.ssh_hosts);
+        self.set_ssh_hosts(hosts);
+        self
+    }
+
+    fn start_loading_hosts(&mut self) {
+        let providers = self.providers.clone();
+        self.load(move || Suggestions::SshHosts(suggest::collect_ssh_hosts(&providers)));
+    }
+
+    /// Targets of a plugin session type, in place of the SSH hosts
+    fn start_loading_provider_targets(&mut self) {
+        let SessionType::Custom(name) = &self.session_type else {
+            return;
+        };
+        let Some(provider) = self.session_providers.get(name).cloned() else {
+            return;
+        };
+        let name = name.clone();
+        self.load(move || Suggestions::ProviderTargets(name, provider.list_targets()));
+    }
+
+    /// SSH targets used by other sessions first, most used first
+    fn set_ssh_hosts(&mut self, mut hosts: Vec<SshHost>) {
+        hosts.sort_by_key(|h| {
+            self.recent_ssh_targets
+                .iter()
+                .position(|t| *t == h.connection_string())
+                .unwrap_or(usize::MAX)
+        });
+        self.ssh_hosts = hosts;
+        self.filtered_hosts = filter_hosts(&self.ssh_hosts, &self.target);
+    }
+
+    /// Run a lookup off the UI thread when there is an event loop to send
+    /// the result to, otherwise right away
+    fn load(&mut self, lookup: impl FnOnce() -> Suggestions + Send + 'static) {
+        match &self.events {
+            Some(events) => {
+                let events = events.clone();
+                tokio::task::spawn_blocking(move || {
+                    let _ = events.send(AppEvent::Suggestions(lookup()));
+                });
+            }
+            None => self.on_suggestions(lookup()),
+        }
+    }
+
+    /// Take in the result of a lookup started by this form, or an earlier
+    /// one; a targets lookup that has been superseded is ignored
+    pub fn on_suggestions(&mut self, suggestions: Suggestions) {
+        match suggestions {
+            // A plugin session type offers its provider's targets instead
+            Suggestions::SshHosts(hosts) => {
+                if !matches!(self.session_type, SessionType::Custom(_)) {
+                    self.set_ssh_hosts(hosts);
+                }
+            }
+            Suggestions::ProviderTargets(name, targets) => {
+                if self.session_type == SessionType::Custom(name) {
+                    let hosts = targets
+                        .into_iter()
+                        .map(|name| SshHost {
+                            name,
+                            hostname: None,
+                            user: None,
+                            port: None,
+                            proxy_jump: None,
+                            identity_file: None,
+                        })
+                        .collect();
+                    self.set_ssh_hosts(hosts);
+                }
+            }
+            Suggestions::Namespaces(scope, namespaces) => {
+                if self.namespaces_requested.as_ref() == Some(&scope) {
+                    self.namespaces_requested = None;
+                    self.set_namespaces(scope, namespaces);
+                }
+            }
+            Suggestions::KubeTargets(scope, targets) => {
+                if self.targets_requested.as_ref() == Some(&scope) {
+                    self.targets_requested = None;
+                    self.loading_targets = false;
+                    self.set_kube_targets(scope, targets);
+                }
+            }
+        }
+    }
+
+    fn is_local_port_field(&self) -> bool {
+        match self.session_type {
+            SessionType::Kubectl => self.focused_field == 4,
+            SessionType::ReverseSocks5 => false,
+            _ => self.focused_field == 2,
+        }
+    }
+
+    pub fn is_port_field(&self) -> bool {
+        match self.session_type {
+            SessionType::Kubectl => matches!(self.focused_field, 4 | 5),
+            SessionType::SSH | SessionType::Custom(_) => matches!(self.focused_field, 2 | 3),
+            SessionType::Socks5 | SessionType::ReverseSocks5 => self.focused_field == 2,
         }
     }
 
+    /// Suggested ports starting with what's typed in the focused port field
+    pub fn filtered_ports(&self) -> Vec<u16> {
+        let typed = if self.is_local_port_field() {
+            &self.local_port
+        } else {
+            &self.remote_port
+        };
+        self.suggested_ports
+            .iter()
+            .copied()
+            .filter(|p| p.to_string().starts_with(typed.as_str()))
+            .collect()
+    }
+
     pub fn update_target(&mut self, target: String) {
         self.target = target;
         let target_field_idx = if self.session_type == SessionType::Kubectl {
@@ -223,15 +861,14 @@ impl FormState {
         };
 
         if self.focused_field == target_field_idx {
-            if self.session_type == SessionType::SSH || self.session_type == SessionType::Socks5 {
+            if self.session_type != SessionType::Kubectl {
                 self.filtered_hosts = filter_hosts(&self.ssh_hosts, &self.target);
                 self.show_suggestions = !self.filtered_hosts.is_empty();
                 self.selected_suggestion = 0;
                 self.scroll_offset = 0;
             } else if self.session_type == SessionType::Kubectl {
                 // Load targets asynchronously if not already loaded
-                if self.kube_targets.is_empty() && !self.target.is_empty() && !self.loading_targets
-                {
+                if !self.targets_loaded() && !self.target.is_empty() && !self.loading_targets {
                     self.start_loading_targets();
                 } else {
                     // Filter existing targets
@@ -269,7 +906,7 @@ impl FormState {
                         || ctx
                             .namespace
                             .as_ref()
-                            .map_or(false, |ns| ns.to_lowercase().contains(&query_lower))
+                            .is_some_and(|ns| ns.to_lowercase().contains(&query_lower))
                 })
                 .cloned()
                 .collect();
@@ -281,7 +918,7 @@ impl FormState {
 
         if self.session_type == SessionType::Kubectl && self.focused_field == 2 {
             // Lazy load namespaces if not already loaded
-            if self.namespaces.is_empty() {
+            if !self.namespaces_loaded() {
                 self.reload_namespaces();
             }
             self.filter_namespaces();
@@ -305,15 +942,89 @@ impl FormState {
         }
     }
 
+    /// Contexts of the kubeconfig in the form
+    pub fn reload_contexts(&mut self) {
+        let (_, contexts) = load_contexts(self.kubeconfig_path());
+        self.kube_contexts = contexts;
+        self.filtered_kube_contexts = self.kube_contexts.clone();
+    }
+
+    /// Where namespaces and targets are looked up: the kubeconfig, context
+    /// and namespace in the form
+    fn kube_scope(&self) -> KubeScope {
+        let non_empty = |value: &str| Some(value.trim().to_string()).filter(|v| !v.is_empty());
+        KubeScope {
+            kubeconfig: non_empty(&self.kubeconfig),
+            context: non_empty(&self.context_field),
+            namespace: non_empty(&self.namespace_field),
+        }
+    }
+
+    /// Namespaces are per cluster, so the namespace typed doesn't matter
+    fn namespaces_scope(&self) -> KubeScope {
+        KubeScope {
+            namespace: None,
+            ..self.kube_scope()
+        }
+    }
+
+    fn namespaces_loaded(&self) -> bool {
+        self.namespaces_scope.as_ref() == Some(&self.namespaces_scope())
+    }
+
+    fn targets_loaded(&self) -> bool {
+        self.targets_scope.as_ref() == Some(&self.kube_scope())
+    }
+
     pub fn reload_namespaces(&mut self) {
-        if self.session_type == SessionType::Kubectl {
-            let context = if self.context_field.is_empty() {
-                None
-            } else {
-                Some(self.context_field.as_str())
-            };
-            self.namespaces = get_namespaces(context);
-            self.filtered_namespaces = self.namespaces.clone();
+        if self.session_type != SessionType::Kubectl {
+            return;
+        }
+        let scope = self.namespaces_scope();
+        if let Some(namespaces) = self.kube_cache.namespaces(&scope) {
+            self.namespaces_requested = None;
+            self.set_namespaces(scope, namespaces);
+            return;
+        }
+        if self.namespaces_requested.as_ref() == Some(&scope) {
+            // Already on its way
+            return;
+        }
+        self.namespaces_requested = Some(scope.clone());
+        let kube_cache = self.kube_cache.clone();
+        self.load(move || {
+            let namespaces = get_namespaces(scope.kubeconfig.as_deref(), scope.context.as_deref());
+            kube_cache.set_namespaces(scope.clone(), namespaces.clone());
+            Suggestions::Namespaces(scope, namespaces)
+        });
+    }
+
+    fn set_namespaces(&mut self, scope: KubeScope, namespaces: Vec<String>) {
+        self.namespaces = namespaces;
+        self.namespaces_scope = Some(scope);
+        self.filter_namespaces();
+        if self.focused_field == 2 {
+            self.show_suggestions = !self.filtered_namespaces.is_empty();
+            self.selected_suggestion = 0;
+            self.scroll_offset = 0;
+        }
+    }
+
+    /// Look the contexts, namespaces and targets up again instead of using
+    /// the cached ones
+    pub fn refresh_kube(&mut self) {
+        if self.session_type != SessionType::Kubectl {
+            return;
+        }
+        self.kube_cache.invalidate(&self.kube_scope());
+        self.reload_contexts();
+        self.namespaces_scope = None;
+        self.targets_scope = None;
+        if self.focused_field == 2 {
+            self.update_namespace(self.namespace_field.clone());
+        }
+        if !self.loading_targets {
+            self.start_loading_targets();
         }
     }
 
@@ -340,19 +1051,15 @@ impl FormState {
                 self.namespace_field = namespace.clone();
                 self.cursor_pos = self.namespace_field.len();
                 self.show_suggestions = false;
-                // Reload targets when namespace changes
-                self.kube_targets.clear();
             }
             return;
         }
 
-        // Handle port field suggestions for kubectl
-        if self.session_type == SessionType::Kubectl
-            && (self.focused_field == 4 || self.focused_field == 5)
-        {
-            if let Some(&port) = self.available_ports.get(self.selected_suggestion) {
+        // Handle port field suggestions
+        if self.is_port_field() {
+            if let Some(&port) = self.filtered_ports().get(self.selected_suggestion) {
                 let port_str = port.to_string();
-                if self.focused_field == 4 {
+                if self.is_local_port_field() {
                     self.local_port = port_str;
                     self.cursor_pos = self.local_port.len();
                 } else {
@@ -364,33 +1071,59 @@ impl FormState {
             return;
         }
 
+        // Handle file path suggestions; a directory is completed further
+        if self.is_path_field() {
+            if let Some(path) = self.filtered_paths.get(self.selected_suggestion).cloned() {
+                let field = if self.is_identity_field() {
+                    &mut self.identity_file
+                } else {
+                    &mut self.kubeconfig
+                };
+                *field = path.clone();
+                self.cursor_pos = field.len();
+                self.show_suggestions = false;
+                if path.ends_with('/') {
+                    self.update_path_suggestions();
+                } else if self.is_kubeconfig_field() {
+                    self.reload_contexts();
+                }
+            }
+            return;
+        }
+
         // Handle target field suggestions
-        if self.session_type == SessionType::SSH || self.session_type == SessionType::Socks5 {
+        if self.session_type != SessionType::Kubectl {
             if let Some(host) = self.filtered_hosts.get(self.selected_suggestion) {
                 self.target = host.connection_string();
+                // Port and ProxyJump are applied by ssh from its config
+                if self.identity_file.is_empty()
+                    && let Some(identity_file) = &host.identity_file
+                {
+                    self.identity_file = identity_file.clone();
+                }
                 self.show_suggestions = false;
             }
-        } else if self.session_type == SessionType::Kubectl {
-            if let Some(kube_target) = self.filtered_kube_targets.get(self.selected_suggestion) {
-                self.target = kube_target.target_string();
-                // Auto-fill namespace
-                if self.namespace_field.is_empty() {
-                    self.namespace_field = kube_target.namespace.clone();
+        } else if self.session_type == SessionType::Kubectl
+            && let Some(kube_target) = self.filtered_kube_targets.get(self.selected_suggestion)
+        {
+            self.target = kube_target.target_string();
+            // Auto-fill namespace
+            if self.namespace_field.is_empty() {
+                self.namespace_field = kube_target.namespace.clone();
+            }
+            // Store available ports for later suggestion
+            self.available_ports = kube_target.ports.clone();
+            // Auto-fill port if we have exactly one port
+            if kube_target.ports.len() == 1 {
+                let port_str = kube_target.ports[0].to_string();
+                if self.local_port.is_empty() {
+                    self.local_port = port_str.clone();
                 }
-                // Store available ports for later suggestion
-                self.available_ports = kube_target.ports.clone();
-                // Auto-fill port if we have exactly one port
-                if kube_target.ports.len() == 1 {
-                    let port_str = kube_target.ports[0].to_string();
-                    if self.local_port.is_empty() {
-                        self.local_port = port_str.clone();
-                    }
-                    if self.remote_port.is_empty() {
-                        self.remote_port = port_str;
-                    }
+                if self.remote_port.is_empty() {
+                    self.remote_port = port_str;
                 }
-                self.show_suggestions = false;
             }
+            self.show_suggestions = false;
         }
     }
 
@@ -411,14 +1144,19 @@ impl FormState {
                 return;
             }
             self.filtered_namespaces.len()
-        } else if self.session_type == SessionType::Kubectl
-            && (self.focused_field == 4 || self.focused_field == 5)
-        {
+        } else if self.is_port_field() {
             // Port field suggestions
-            if self.available_ports.is_empty() {
+            let count = self.filtered_ports().len();
+            if count == 0 {
                 return;
             }
-            self.available_ports.len()
+            count
+        } else if self.is_path_field() {
+            // Identity or kubeconfig file suggestions
+            if self.filtered_paths.is_empty() {
+                return;
+            }
+            self.filtered_paths.len()
         } else if self.session_type == SessionType::Kubectl {
             // Target field suggestions
             if self.filtered_kube_targets.is_empty() {
@@ -450,10 +1188,18 @@ impl FormState {
     }
 
     pub fn show_port_suggestions(&mut self) {
-        if self.session_type == SessionType::Kubectl
-            && !self.available_ports.is_empty()
-            && (self.focused_field == 4 || self.focused_field == 5)
-        {
+        if !self.is_port_field() {
+            return;
+        }
+        let mut ports = self.available_ports.clone();
+        for port in &self.recent_ports {
+            if !ports.contains(port) {
+                ports.push(*port);
+            }
+        }
+        self.suggested_ports = ports;
+
+        if !self.filtered_ports().is_empty() {
             self.show_suggestions = true;
             self.selected_suggestion = 0;
             self.scroll_offset = 0;
@@ -464,10 +1210,48 @@ impl FormState {
         // Lazy load namespaces when namespace field gets focus
         if self.session_type == SessionType::Kubectl
             && self.focused_field == 2
-            && self.namespaces.is_empty()
+            && !self.namespaces_loaded()
         {
             self.reload_namespaces();
         }
+        // Load the targets' ports so the remote port can be checked on save
+        if self.session_type == SessionType::Kubectl
+            && self.focused_field == 5
+            && !self.targets_loaded()
+            && !self.loading_targets
+        {
+            self.start_loading_targets();
+        }
+        self.update_path_suggestions();
+    }
+
+    /// Warning for a kubectl remote port the selected target doesn't
+    /// declare. Only known targets are checked; nothing is fetched here.
+    pub fn remote_port_warning(&self) -> Option<String> {
+        if self.session_type != SessionType::Kubectl || !self.targets_loaded() {
+            return None;
+        }
+        let port: u16 = self.remote_port.trim().parse().ok()?;
+        let namespace = self.namespace_field.trim();
+        let mut ports: Vec<u16> = self
+            .kube_targets
+            .iter()
+            .filter(|t| t.matches(self.target.trim()))
+            .filter(|t| namespace.is_empty() || t.namespace.is_empty() || t.namespace == namespace)
+            .flat_map(|t| t.ports.iter().copied())
+            .collect();
+        if ports.is_empty() || ports.contains(&port) {
+            return None;
+        }
+        ports.sort_unstable();
+        ports.dedup();
+        let ports: Vec<String> = ports.iter().map(|p| p.to_string()).collect();
+        Some(format!(
+            "Warning: {} has no port {} (ports: {}). Ctrl+S again to save anyway",
+            self.target.trim(),
+            port,
+            ports.join(", ")
+        ))
     }
 
     pub fn start_loading_targets(&mut self) {
@@ -475,48 +1259,125 @@ impl FormState {
             return;
         }
 
-        let context = if self.context_field.is_empty() {
-            None
-        } else {
-            Some(self.context_field.clone())
-        };
-        let namespace = if self.namespace_field.is_empty() {
-            None
-        } else {
-            Some(self.namespace_field.clone())
-        };
-        let (tx, rx) = mpsc::channel();
+        let scope = self.kube_scope();
+        if let Some(targets) = self.kube_cache.targets(&scope) {
+            self.targets_requested = None;
+            self.loading_targets = false;
+            self.set_kube_targets(scope, targets);
+            return;
+        }
 
-        self.target_receiver = Some(rx);
+        let providers = self.providers.clone();
+        let kube_cache = self.kube_cache.clone();
+        self.targets_requested = Some(scope.clone());
         self.loading_targets = true;
 
-        thread::spawn(move || {
-            let targets = get_targets(context.as_deref(), namespace.as_deref());
-            let _ = tx.send(targets);
+        self.load(move || {
+            let context = scope.context.as_deref();
+            let namespace = scope.namespace.as_deref();
+            // Providers know the default clusters; one in its own kubeconfig
+            // file is asked directly
+            let targets = match &scope.kubeconfig {
+                Some(path) => get_targets(Some(path), context, namespace),
+                None => suggest::collect_kube_targets(&providers, context, namespace),
+            };
+            kube_cache.set_targets(scope.clone(), targets.clone());
+            Suggestions::KubeTargets(scope, targets)
         });
     }
 
-    pub fn poll_target_updates(&mut self) -> bool {
-        if let Some(ref rx) = self.target_receiver {
-            if let Ok(targets) = rx.try_recv() {
-                self.kube_targets = targets;
-                self.filtered_kube_targets = filter_targets(&self.kube_targets, &self.target);
-                self.show_suggestions =
-                    !self.filtered_kube_targets.is_empty() && self.focused_field == 3;
-                self.selected_suggestion = 0;
-                self.scroll_offset = 0;
-                self.loading_targets = false;
-                self.target_receiver = None;
-                return true;
+    fn set_kube_targets(&mut self, scope: KubeScope, targets: Vec<KubeTarget>) {
+        self.kube_targets = targets;
+        self.targets_scope = Some(scope);
+        // Targets used by other sessions first, most used first
+        let rank = |target: &KubeTarget| {
+            self.recent_kube_targets
+                .iter()
+                .position(|t| *t == target.target_string())
+                .unwrap_or(usize::MAX)
+        };
+        self.kube_targets.sort_by_key(rank);
+        self.filtered_kube_targets = filter_targets(&self.kube_targets, &self.target);
+        self.show_suggestions = !self.filtered_kube_targets.is_empty() && self.focused_field == 3;
+        self.selected_suggestion = 0;
+        self.scroll_offset = 0;
+    }
+}
+
+/// Files accepted by `keep` and directories completing `typed`, with `~/`
+/// kept as typed; with nothing typed the files in `default_dir` are offered
+fn complete_path(typed: &str, default_dir: &str, keep: fn(&str) -> bool) -> Vec<String> {
+    let typed = if typed.is_empty() { default_dir } else { typed };
+    let (dir, prefix) = match typed.rfind('/') {
+        Some(idx) => typed.split_at(idx + 1),
+        None => ("", typed),
+    };
+    let dir_path = match (dir.strip_prefix("~/"), std::env::var_os("HOME")) {
+        (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ if dir.is_empty() => PathBuf::from("."),
+        _ => PathBuf::from(dir),
+    };
+    let Ok(entries) = std::fs::read_dir(dir_path) else {
+        return Vec::new();
+    };
+
+    let mut paths: Vec<String> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+                return None;
             }
+            if entry.file_type().ok()?.is_dir() {
+                return Some(format!("{}{}/", dir, name));
+            }
+            keep(&name).then(|| format!("{}{}", dir, name))
+        })
+        .collect();
+    paths.sort();
+    paths
+}
+
+/// Public keys and ssh's other files aren't offered as identity files
+fn is_ssh_key(name: &str) -> bool {
+    !(name.ends_with(".pub")
+        || name.starts_with("known_hosts")
+        || name.starts_with("authorized_keys")
+        || name == "config")
+}
+
+/// Current context and contexts of `kubeconfig`, or of the default files
+fn load_contexts(kubeconfig: Option<&str>) -> (String, Vec<KubeContext>) {
+    parse_kube_config(kubeconfig).unwrap_or_else(|| {
+        let ctx = get_current_context(kubeconfig).unwrap_or_default();
+        (ctx, Vec::new())
+    })
+}
+
+/// Distinct values, most frequent first (ties keep first-seen order)
+fn rank_by_frequency<T: Eq + Hash + Clone>(values: impl Iterator<Item = T>) -> Vec<T> {
+    let mut counts: HashMap<T, usize> = HashMap::new();
+    let mut order = Vec::new();
+    for value in values {
+        let count = counts.entry(value.clone()).or_insert(0);
+        if *count == 0 {
+            order.push(value);
         }
-        false
+        *count += 1;
     }
+    order.sort_by_key(|v| std::cmp::Reverse(counts[v]));
+    order
 }
 
-pub fn render(frame: &mut Frame, form_state: &FormState, mode: &FormMode, area: Rect) {
+pub fn render(
+    frame: &mut Frame,
+    form_state: &FormState,
+    mode: &FormMode,
+    theme: &Theme,
+    area: Rect,
+) {
     if form_state.step == FormStep::SelectType {
-        render_type_selection(frame, form_state, area);
+        render_type_selection(frame, form_state, theme, area);
     } else {
         let chunks = if form_state.show_suggestions {
             Layout::vertical([
@@ -540,30 +1401,93 @@ pub fn render(frame: &mut Frame, form_state: &FormState, mode: &FormMode, area:
             FormMode::Edit(_) => "Edit Session",
         };
 
-        render_title(frame, title, chunks[0]);
-        render_form(frame, form_state, chunks[1]);
+        render_title(frame, title, theme, chunks[0]);
+        render_form(frame, form_state, theme, chunks[1]);
 
         if form_state.show_suggestions {
-            render_suggestions(frame, form_state, chunks[2]);
-            render_help(frame, chunks[3]);
+            render_suggestions(frame, form_state, theme, chunks[2]);
+            render_help(frame, form_state, theme, chunks[3]);
         } else {
-            render_help(frame, chunks[2]);
+            render_help(frame, form_state, theme, chunks[2]);
         }
+
+        if let Some((name, changes)) = &form_state.pending_changes {
+            render_changes(frame, name, changes, theme, area);
+        }
+    }
+}
+
+/// Field-level diff of an edit, old values in red and new ones in green
+fn render_changes(
+    frame: &mut Frame,
+    name: &str,
+    changes: &[FieldChange],
+    theme: &Theme,
+    area: Rect,
+) {
+    let label_width = changes.iter().map(|c| c.field.len()).max().unwrap_or(0);
+    let mut lines = vec![Line::from("")];
+    for change in changes {
+        let old = if change.old.is_empty() {
+            "(empty)"
+        } else {
+            change.old.as_str()
+        };
+        let new = if change.new.is_empty() {
+            "(empty)"
+        } else {
+            change.new.as_str()
+        };
+        lines.push(Line::from(vec![
+            Span::styled(
+                format!(" {:>width$}: ", change.field, width = label_width),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Span::styled(old.to_string(), Style::default().fg(theme.error)),
+            Span::raw(" → "),
+            Span::styled(new.to_string(), Style::default().fg(theme.success)),
+        ]));
     }
+    lines.push(Line::from(""));
+    lines.push(Line::from(vec![
+        Span::styled(" y/Enter", Style::default().fg(theme.key)),
+        Span::raw(" save | "),
+        Span::styled("n/Esc", Style::default().fg(theme.key)),
+        Span::raw(" keep editing"),
+    ]));
+
+    let popup_width = 70.min(area.width.saturating_sub(4));
+    let popup_height = (lines.len() as u16 + 2).min(area.height);
+    let popup_area = Rect {
+        x: area.x + area.width.saturating_sub(popup_width) / 2,
+        y: area.y + area.height.saturating_sub(popup_height) / 2,
+        width: popup_width,
+        height: popup_height,
+    };
+
+    let paragraph = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(format!("Save Changes to {}?", name))
+            .border_style(Style::default().fg(theme.warning))
+            .style(Style::default().bg(theme.popup_bg)),
+    );
+    frame.render_widget(Clear, popup_area);
+    frame.render_widget(paragraph, popup_area);
 }
 
-fn render_title(frame: &mut Frame, title: &str, area: Rect) {
+fn render_title(frame: &mut Frame, title: &str, theme: &Theme, area: Rect) {
     let title_widget = Paragraph::new(title)
         .style(
             Style::default()
-                .fg(Color::Cyan)
+                .fg(theme.title)
                 .add_modifier(Modifier::BOLD),
         )
         .block(Block::default().borders(Borders::ALL));
     frame.render_widget(title_widget, area);
 }
 
-fn render_type_selection(frame: &mut Frame, form_state: &FormState, area: Rect) {
+fn render_type_selection(frame: &mut Frame, form_state: &FormState, theme: &Theme, area: Rect) {
     let chunks = Layout::vertical([
         Constraint::Length(3),
         Constraint::Min(0),
@@ -571,20 +1495,25 @@ fn render_type_selection(frame: &mut Frame, form_state: &FormState, area: Rect)
     ])
     .split(area);
 
-    render_title(frame, "Select Session Type", chunks[0]);
+    render_title(frame, "Select Session Type", theme, chunks[0]);
 
-    let types = vec![
+    let mut types = vec![
         ("SSH", "Standard SSH port forwarding"),
         ("kubectl", "Kubernetes port forwarding"),
         ("SOCKS5", "SOCKS5 proxy via SSH"),
+        (
+            "Reverse SOCKS5",
+            "SOCKS5 proxy on the remote host, out through this machine",
+        ),
     ];
+    for provider in form_state.session_providers.providers() {
+        types.push((provider.name(), provider.description()));
+    }
 
     let mut lines = vec![Line::from("")];
     for (idx, (name, desc)) in types.iter().enumerate() {
         let style = if idx == form_state.type_selection {
-            Style::default()
-                .fg(Color::Yellow)
-                .add_modifier(Modifier::BOLD)
+            Style::default().fg(theme.key).add_modifier(Modifier::BOLD)
         } else {
             Style::default()
         };
@@ -597,9 +1526,9 @@ fn render_type_selection(frame: &mut Frame, form_state: &FormState, area: Rect)
 
         lines.push(Line::from(vec![
             Span::raw(prefix),
-            Span::styled(format!("{:10}", name), style.add_modifier(Modifier::BOLD)),
+            Span::styled(format!("{:14}", name), style.add_modifier(Modifier::BOLD)),
             Span::raw("  "),
-            Span::styled(*desc, Style::default().fg(Color::Gray)),
+            Span::styled(*desc, Style::default().fg(theme.muted)),
         ]));
         lines.push(Line::from(""));
     }
@@ -612,18 +1541,18 @@ fn render_type_selection(frame: &mut Frame, form_state: &FormState, area: Rect)
     frame.render_widget(type_list, chunks[1]);
 
     let help_text = Line::from(vec![
-        Span::styled("↑↓", Style::default().fg(Color::Yellow)),
+        Span::styled("↑↓", Style::default().fg(theme.key)),
         Span::raw(" navigate | "),
-        Span::styled("Enter", Style::default().fg(Color::Yellow)),
+        Span::styled("Enter", Style::default().fg(theme.key)),
         Span::raw(" confirm | "),
-        Span::styled("Esc", Style::default().fg(Color::Yellow)),
+        Span::styled("Esc", Style::default().fg(theme.key)),
         Span::raw(" cancel"),
     ]);
     let help = Paragraph::new(help_text).block(Block::default().borders(Borders::ALL));
     frame.render_widget(help, chunks[2]);
 }
 
-fn render_form(frame: &mut Frame, form_state: &FormState, area: Rect) {
+fn render_form(frame: &mut Frame, form_state: &FormState, theme: &Theme, area: Rect) {
     let type_str = form_state.session_type.as_str().to_string();
     let mut fields = vec![];
 
@@ -638,18 +1567,46 @@ fn render_form(frame: &mut Frame, form_state: &FormState, area: Rect) {
     } else {
         fields.push(("Name", &form_state.name, 0));
         fields.push(("Target", &form_state.target, 1));
-        fields.push(("Local Port", &form_state.local_port, 2));
-        if form_state.session_type != SessionType::Socks5 {
-            fields.push(("Remote Port", &form_state.remote_port, 3));
+        match form_state.session_type {
+            SessionType::ReverseSocks5 => {
+                fields.push(("Remote Port", &form_state.remote_port, 2));
+            }
+            SessionType::Socks5 => {
+                fields.push(("Local Port", &form_state.local_port, 2));
+            }
+            _ => {
+                fields.push(("Local Port", &form_state.local_port, 2));
+                fields.push(("Remote Port", &form_state.remote_port, 3));
+            }
         }
     }
+    if let Some(idx) = form_state.remote_host_field() {
+        fields.push(("Remote Host", &form_state.remote_host, idx));
+    }
+    if let Some(ssh_start) = form_state.ssh_fields_start() {
+        fields.push(("User", &form_state.user, ssh_start));
+        fields.push(("Identity", &form_state.identity_file, ssh_start + 1));
+    }
+    if let Some(idx) = form_state.kubeconfig_field() {
+        fields.push(("Kubeconfig", &form_state.kubeconfig, idx));
+    }
+    let common_start = form_state.common_fields_start();
+    fields.push(("Icon", &form_state.icon, common_start));
+    fields.push(("Color", &form_state.color, common_start + 1));
+    fields.push(("Tags", &form_state.tags, common_start + 2));
+    fields.push(("Health Check", &form_state.health_command, common_start + 3));
+    fields.push(("Pre-start", &form_state.pre_start, common_start + 4));
+    fields.push(("Post-stop", &form_state.post_stop, common_start + 5));
+    fields.push(("Idle Stop", &form_state.idle_timeout, common_start + 6));
+    fields.push(("Max Lifetime", &form_state.max_lifetime, common_start + 7));
+    fields.push(("IP Version", &form_state.address_family, common_start + 8));
 
     // Calculate blinking cursor visibility (500ms on, 500ms off)
     let now = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap()
         .as_millis();
-    let show_cursor = (now / 500) % 2 == 0;
+    let show_cursor = (now / 500).is_multiple_of(2);
 
     let mut lines = vec![
         Line::from(vec![
@@ -657,7 +1614,7 @@ fn render_form(frame: &mut Frame, form_state: &FormState, area: Rect) {
             Span::styled(
                 type_str,
                 Style::default()
-                    .fg(Color::Cyan)
+                    .fg(theme.title)
                     .add_modifier(Modifier::BOLD),
             ),
         ]),
@@ -668,16 +1625,15 @@ fn render_form(frame: &mut Frame, form_state: &FormState, area: Rect) {
     if form_state.session_type == SessionType::Kubectl && form_state.loading_targets {
         lines.push(Line::from(vec![
             Span::styled("Status: ", Style::default().add_modifier(Modifier::BOLD)),
-            Span::styled("Loading targets...", Style::default().fg(Color::Yellow)),
+            Span::styled("Loading targets...", Style::default().fg(theme.warning)),
         ]));
         lines.push(Line::from(""));
     }
 
     for (label, value, idx) in fields {
+        let error = form_state.shown_field_error(idx);
         let style = if idx == form_state.focused_field {
-            Style::default()
-                .fg(Color::Yellow)
-                .add_modifier(Modifier::BOLD)
+            Style::default().fg(theme.key).add_modifier(Modifier::BOLD)
         } else {
             Style::default()
         };
@@ -695,24 +1651,36 @@ fn render_form(frame: &mut Frame, form_state: &FormState, area: Rect) {
             value.to_string()
         };
 
+        let mut label_style = Style::default().add_modifier(Modifier::BOLD);
+        if error.is_some() {
+            label_style = label_style.fg(theme.error);
+        }
         lines.push(Line::from(vec![
             Span::raw(prefix),
-            Span::styled(
-                format!("{:12}: ", label),
-                Style::default().add_modifier(Modifier::BOLD),
-            ),
+            Span::styled(format!("{:12}: ", label), label_style),
             Span::styled(display_value, style),
         ]));
-        lines.push(Line::from(""));
+        // In the gap below the field, lined up with the value
+        lines.push(match error {
+            Some(error) => Line::from(vec![
+                Span::raw(" ".repeat(16)),
+                Span::styled(error, Style::default().fg(theme.error)),
+            ]),
+            None => Line::from(""),
+        });
     }
 
     let form = Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title("Form"));
     frame.render_widget(form, area);
 }
 
-fn render_suggestions(frame: &mut Frame, form_state: &FormState, area: Rect) {
+fn render_suggestions(frame: &mut Frame, form_state: &FormState, theme: &Theme, area: Rect) {
     // Calculate visible items based on area height (subtract 2 for borders)
-    let scroll_offset = form_state.scroll_offset;
+    let visible = (area.height.saturating_sub(2) as usize).max(1);
+    let scroll_offset = form_state
+        .scroll_offset
+        .min(form_state.selected_suggestion)
+        .max((form_state.selected_suggestion + 1).saturating_sub(visible));
 
     let (items, title): (Vec<ListItem>, &str) =
         // Context suggestions for kubectl
@@ -725,8 +1693,8 @@ fn render_suggestions(frame: &mut Frame, form_state: &FormState, area: Rect) {
                 .map(|(idx, context)| {
                     let style = if idx == form_state.selected_suggestion {
                         Style::default()
-                            .bg(Color::DarkGray)
-                            .fg(Color::Yellow)
+                            .bg(theme.selection_bg)
+                            .fg(theme.key)
                             .add_modifier(Modifier::BOLD)
                     } else {
                         Style::default()
@@ -746,8 +1714,8 @@ fn render_suggestions(frame: &mut Frame, form_state: &FormState, area: Rect) {
                 .map(|(idx, namespace)| {
                     let style = if idx == form_state.selected_suggestion {
                         Style::default()
-                            .bg(Color::DarkGray)
-                            .fg(Color::Yellow)
+                            .bg(theme.selection_bg)
+                            .fg(theme.key)
                             .add_modifier(Modifier::BOLD)
                     } else {
                         Style::default()
@@ -757,18 +1725,18 @@ fn render_suggestions(frame: &mut Frame, form_state: &FormState, area: Rect) {
                 .collect();
             (items, "Kubernetes Namespaces (↑↓ navigate, Enter select, Esc close)")
         }
-        // Port suggestions for kubectl
-        else if form_state.session_type == SessionType::Kubectl && (form_state.focused_field == 4 || form_state.focused_field == 5) {
+        // Port suggestions: the kube target's ports, then ports used by other sessions
+        else if form_state.is_port_field() {
             let items = form_state
-                .available_ports
+                .filtered_ports()
                 .iter()
                 .enumerate()
                 .skip(scroll_offset)
                 .map(|(idx, &port)| {
                     let style = if idx == form_state.selected_suggestion {
                         Style::default()
-                            .bg(Color::DarkGray)
-                            .fg(Color::Yellow)
+                            .bg(theme.selection_bg)
+                            .fg(theme.key)
                             .add_modifier(Modifier::BOLD)
                     } else {
                         Style::default()
@@ -776,7 +1744,33 @@ fn render_suggestions(frame: &mut Frame, form_state: &FormState, area: Rect) {
                     ListItem::new(port.to_string()).style(style)
                 })
                 .collect();
-            (items, "Available Ports (↑↓ navigate, Enter select, Esc close)")
+            (items, "Ports (↑↓ navigate, Enter select, Esc close)")
+        }
+        // Identity and kubeconfig file completions
+        else if form_state.is_path_field() {
+            let items = form_state
+                .filtered_paths
+                .iter()
+                .enumerate()
+                .skip(scroll_offset)
+                .map(|(idx, path)| {
+                    let style = if idx == form_state.selected_suggestion {
+                        Style::default()
+                            .bg(theme.selection_bg)
+                            .fg(theme.key)
+                            .add_modifier(Modifier::BOLD)
+                    } else {
+                        Style::default()
+                    };
+                    ListItem::new(path.clone()).style(style)
+                })
+                .collect();
+            let title = if form_state.is_identity_field() {
+                "Key Files (↑↓ navigate, Enter select, Esc close)"
+            } else {
+                "Kubeconfig Files (↑↓ navigate, Enter select, Esc close)"
+            };
+            (items, title)
         }
         // Target suggestions for kubectl
         else if form_state.session_type == SessionType::Kubectl {
@@ -788,8 +1782,8 @@ fn render_suggestions(frame: &mut Frame, form_state: &FormState, area: Rect) {
                 .map(|(idx, target)| {
                     let style = if idx == form_state.selected_suggestion {
                         Style::default()
-                            .bg(Color::DarkGray)
-                            .fg(Color::Yellow)
+                            .bg(theme.selection_bg)
+                            .fg(theme.key)
                             .add_modifier(Modifier::BOLD)
                     } else {
                         Style::default()
@@ -809,8 +1803,8 @@ fn render_suggestions(frame: &mut Frame, form_state: &FormState, area: Rect) {
                 .map(|(idx, host)| {
                     let style = if idx == form_state.selected_suggestion {
                         Style::default()
-                            .bg(Color::DarkGray)
-                            .fg(Color::Yellow)
+                            .bg(theme.selection_bg)
+                            .fg(theme.key)
                             .add_modifier(Modifier::BOLD)
                     } else {
                         Style::default()
@@ -818,7 +1812,12 @@ fn render_suggestions(frame: &mut Frame, form_state: &FormState, area: Rect) {
                     ListItem::new(host.display_name()).style(style)
                 })
                 .collect();
-            (items, "SSH Hosts (↑↓ navigate, Enter select, Esc close)")
+            let title = if matches!(form_state.session_type, SessionType::Custom(_)) {
+                "Targets (↑↓ navigate, Enter select, Esc close)"
+            } else {
+                "SSH Hosts (↑↓ navigate, Enter select, Esc close)"
+            };
+            (items, title)
         };
 
     let list = List::new(items).block(Block::default().borders(Borders::ALL).title(title));
@@ -826,15 +1825,28 @@ fn render_suggestions(frame: &mut Frame, form_state: &FormState, area: Rect) {
     frame.render_widget(list, area);
 }
 
-fn render_help(frame: &mut Frame, area: Rect) {
-    let help_text = Line::from(vec![
-        Span::styled("Tab", Style::default().fg(Color::Yellow)),
+fn render_help(frame: &mut Frame, form_state: &FormState, theme: &Theme, area: Rect) {
+    if let Some(error) = &form_state.error {
+        let error = Paragraph::new(error.as_str())
+            .style(Style::default().fg(theme.error))
+            .block(Block::default().borders(Borders::ALL));
+        frame.render_widget(error, area);
+        return;
+    }
+
+    let mut help_text = vec![
+        Span::styled("Tab", Style::default().fg(theme.key)),
         Span::raw(" next field | "),
-        Span::styled("Ctrl+S", Style::default().fg(Color::Yellow)),
+        Span::styled("Ctrl+S", Style::default().fg(theme.key)),
         Span::raw(" save | "),
-        Span::styled("Esc", Style::default().fg(Color::Yellow)),
-        Span::raw(" cancel"),
-    ]);
+    ];
+    if form_state.session_type == SessionType::Kubectl {
+        help_text.push(Span::styled("Ctrl+R", Style::default().fg(theme.key)));
+        help_text.push(Span::raw(" refresh | "));
+    }
+    help_text.push(Span::styled("Esc", Style::default().fg(theme.key)));
+    help_text.push(Span::raw(" cancel"));
+    let help_text = Line::from(help_text);
 
     let help = Paragraph::new(help_text).block(Block::default().borders(Borders::ALL));
     frame.render_widget(help, area);
@@ -0,0 +1,429 @@
+use crate::config::{ColumnWidths, Truncation, WidthLimits};
+use crate::health::HealthCheck;
+use crate::models::{Session, SessionStatus};
+use crate::ui::spinner_frame;
+use crate::ui::theme::Theme;
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Flex, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Cell, Row, Table, Widget},
+};
+use std::collections::HashMap;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Local port connect times from here on are highlighted
+const SLOW_LATENCY: Duration = Duration::from_millis(100);
+
+/// Table of sessions with name, type, target, ports, status and uptime.
+///
+/// This is the widget the dashboard renders, and it's exported through
+/// [`crate::embed`] so other ratatui apps can show the same list.
+pub struct SessionTable<'a> {
+    sessions: Vec<&'a Session>,
+    selected: Option<Uuid>,
+    accessible: bool,
+    remind_after: Option<chrono::Duration>,
+    latencies: HashMap<Uuid, Duration>,
+    health: HashMap<Uuid, HealthCheck>,
+    show_ids: bool,
+    theme: Theme,
+    truncation: Truncation,
+    column_widths: ColumnWidths,
+    block: Option<Block<'a>>,
+}
+
+impl<'a> SessionTable<'a> {
+    pub fn new(sessions: impl IntoIterator<Item = &'a Session>) -> Self {
+        Self {
+            sessions: sessions.into_iter().collect(),
+            selected: None,
+            accessible: false,
+            remind_after: None,
+            latencies: HashMap::new(),
+            health: HashMap::new(),
+            show_ids: false,
+            theme: Theme::default(),
+            truncation: Truncation::Middle,
+            column_widths: ColumnWidths::default(),
+            block: None,
+        }
+    }
+
+    /// Highlight the row of the session with this id
+    pub fn selected(mut self, id: Option<Uuid>) -> Self {
+        self.selected = id;
+        self
+    }
+
+    /// Use text status markers and ASCII glyphs (see `Config::accessible`)
+    pub fn accessible(mut self, accessible: bool) -> Self {
+        self.accessible = accessible;
+        self
+    }
+
+    /// Badge sessions that have been running at least this long
+    pub fn remind_after(mut self, after: Option<chrono::Duration>) -> Self {
+        self.remind_after = after;
+        self
+    }
+
+    /// Show measured local port latency next to the status of running sessions
+    pub fn latencies(mut self, latencies: HashMap<Uuid, Duration>) -> Self {
+        self.latencies = latencies;
+        self
+    }
+
+    /// Show Healthy/Unhealthy instead of Running for sessions with a health
+    /// command
+    pub fn health(mut self, health: HashMap<Uuid, HealthCheck>) -> Self {
+        self.health = health;
+        self
+    }
+
+    /// Add a leading column with each session's short ID
+    pub fn show_ids(mut self, show_ids: bool) -> Self {
+        self.show_ids = show_ids;
+        self
+    }
+
+    pub fn theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    /// Where targets too long for their column are cut
+    pub fn truncation(mut self, truncation: Truncation) -> Self {
+        self.truncation = truncation;
+        self
+    }
+
+    /// Limits for the name, target and status column widths
+    pub fn column_widths(mut self, column_widths: ColumnWidths) -> Self {
+        self.column_widths = column_widths;
+        self
+    }
+
+    pub fn block(mut self, block: Block<'a>) -> Self {
+        self.block = Some(block);
+        self
+    }
+
+    /// Width of every column for a table `width` cells wide. Name, target
+    /// and status share the free space, then get clamped to their limits
+    /// with the unlimited ones taking up the difference.
+    fn resolve_widths(&self, width: u16) -> Vec<u16> {
+        // Fixed-size columns for short values, the rest of the space is shared
+        // between name, target and status so odd widths don't clip mid-column
+        let mut columns = vec![
+            (Constraint::Fill(2), self.column_widths.name),
+            (Constraint::Length(7), WidthLimits::default()),
+            (Constraint::Fill(3), self.column_widths.target),
+            (Constraint::Length(13), WidthLimits::default()),
+            (Constraint::Fill(2), self.column_widths.status),
+            (Constraint::Length(14), WidthLimits::default()),
+        ];
+        if self.show_ids {
+            columns.insert(0, (Constraint::Length(7), WidthLimits::default()));
+        }
+
+        let split = |constraints: Vec<Constraint>| -> Vec<u16> {
+            Layout::horizontal(constraints)
+                .flex(Flex::Start)
+                .spacing(1)
+                .split(Rect::new(0, 0, width, 1))
+                .iter()
+                .map(|r| r.width)
+                .collect()
+        };
+        let shared = split(columns.iter().map(|(c, _)| *c).collect());
+        if columns.iter().all(|(_, limits)| !limits.is_set()) {
+            return shared;
+        }
+        split(
+            columns
+                .iter()
+                .zip(shared)
+                .map(|((constraint, limits), w)| {
+                    if limits.is_set() {
+                        Constraint::Length(limits.apply(w))
+                    } else {
+                        *constraint
+                    }
+                })
+                .collect(),
+        )
+    }
+}
+
+impl Widget for SessionTable<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let inner = self.block.as_ref().map_or(area, |block| block.inner(area));
+        let widths = self.resolve_widths(inner.width);
+        let target_width = widths[if self.show_ids { 3 } else { 2 }] as usize;
+        let ports_width = widths[if self.show_ids { 4 } else { 3 }] as usize;
+        let status_width = widths[if self.show_ids { 5 } else { 4 }] as usize;
+
+        let mut header = vec![
+            Cell::from("Name").style(Style::default().add_modifier(Modifier::BOLD)),
+            Cell::from("Type").style(Style::default().add_modifier(Modifier::BOLD)),
+            Cell::from("Target").style(Style::default().add_modifier(Modifier::BOLD)),
+            Cell::from("Ports").style(Style::default().add_modifier(Modifier::BOLD)),
+            Cell::from("Status").style(Style::default().add_modifier(Modifier::BOLD)),
+            Cell::from("Uptime").style(Style::default().add_modifier(Modifier::BOLD)),
+        ];
+        if self.show_ids {
+            header.insert(
+                0,
+                Cell::from("ID").style(Style::default().add_modifier(Modifier::BOLD)),
+            );
+        }
+        let header = Row::new(header).height(1);
+
+        let rows: Vec<Row> = self
+            .sessions
+            .iter()
+            .map(|session| {
+                let mut status_text = status_label(session, self.accessible);
+                if status_text.chars().count() > status_width
+                    && let Some(compact) = reconnecting_label(session, self.accessible, true)
+                {
+                    status_text = compact;
+                }
+                let mut status_style = Style::default().fg(status_color(session, &self.theme));
+                let settled = session.status == SessionStatus::Running && !session.is_verifying();
+                let check = self.health.get(&session.id).filter(|_| settled);
+                if let Some(check) = check {
+                    status_text = health_label(check).to_string();
+                }
+                if settled && let Some(latency) = self.latencies.get(&session.id) {
+                    status_text.push_str(&format!(" {}", format_latency(*latency)));
+                    if *latency >= SLOW_LATENCY {
+                        status_style = Style::default().fg(self.theme.warning);
+                    }
+                }
+                if check.is_some_and(|c| !c.healthy) {
+                    status_style = Style::default().fg(self.theme.error);
+                }
+
+                let style = if Some(session.id) == self.selected {
+                    selected_row_style(&self.theme)
+                } else {
+                    Style::default()
+                };
+
+                let mut cells = vec![
+                    name_cell(session, &self.theme),
+                    Cell::from(session.session_type.short_str()),
+                    Cell::from(truncate(
+                        &session.target,
+                        target_width,
+                        self.truncation,
+                        self.accessible,
+                    )),
+                    // Forwards to a host behind the target can be long too
+                    Cell::from(truncate(
+                        &port_mapping(session, self.accessible),
+                        ports_width,
+                        self.truncation,
+                        self.accessible,
+                    )),
+                    Cell::from(status_text).style(status_style),
+                    uptime_cell(session, self.remind_after, self.accessible, &self.theme),
+                ];
+                if self.show_ids {
+                    cells.insert(
+                        0,
+                        Cell::from(session.short_id()).style(Style::default().fg(self.theme.muted)),
+                    );
+                }
+                Row::new(cells).style(style)
+            })
+            .collect();
+
+        let mut table = Table::new(rows, widths.into_iter().map(Constraint::Length))
+            .header(header)
+            .row_highlight_style(Style::default().add_modifier(Modifier::BOLD));
+        if let Some(block) = self.block {
+            table = table.block(block);
+        }
+
+        Widget::render(table, area, buf);
+    }
+}
+
+/// Shorten `text` to at most `width` characters, replacing the cut part
+/// with an ellipsis
+pub(crate) fn truncate(text: &str, width: usize, mode: Truncation, accessible: bool) -> String {
+    let len = text.chars().count();
+    if len <= width {
+        return text.to_string();
+    }
+    let ellipsis = if accessible { "..." } else { "…" };
+    let keep = width.saturating_sub(ellipsis.chars().count());
+    if keep == 0 {
+        return text.chars().take(width).collect();
+    }
+    match mode {
+        Truncation::End => {
+            let head: String = text.chars().take(keep).collect();
+            format!("{}{}", head, ellipsis)
+        }
+        // The end usually tells targets apart (pod names, ARN resources),
+        // so it gets the larger half
+        Truncation::Middle => {
+            let head_len = keep / 2;
+            let head: String = text.chars().take(head_len).collect();
+            let tail: String = text.chars().skip(len - (keep - head_len)).collect();
+            format!("{}{}{}", head, ellipsis, tail)
+        }
+    }
+}
+
+pub(crate) fn status_label(session: &Session, accessible: bool) -> String {
+    if let Some(label) = reconnecting_label(session, accessible, false) {
+        return label;
+    }
+    match &session.status {
+        SessionStatus::Error(err) => {
+            format!(
+                "Error: {}",
+                truncate(&err.message, 30, Truncation::End, accessible)
+            )
+        }
+        SessionStatus::Degraded(reason) => {
+            format!(
+                "Degraded: {}",
+                truncate(reason, 30, Truncation::End, accessible)
+            )
+        }
+        SessionStatus::Starting => format!("{} Starting", spinner_frame(accessible)),
+        SessionStatus::Stopping => format!("{} Stopping", spinner_frame(accessible)),
+        _ if session.is_verifying() => format!("{} Verifying", spinner_frame(accessible)),
+        SessionStatus::Stopped if session.paused => "Paused".to_string(),
+        SessionStatus::Running if session.reconnects > 0 => {
+            format!("Running ({} reconnects)", session.reconnects)
+        }
+        status => status.as_str().to_string(),
+    }
+}
+
+/// Label of a failed session waiting for its automatic restart; `compact`
+/// for narrow columns
+fn reconnecting_label(session: &Session, accessible: bool, compact: bool) -> Option<String> {
+    let restart = session.pending_restart.as_ref()?;
+    let wait = (restart.at - chrono::Utc::now()).num_seconds().max(0);
+    Some(if compact {
+        format!(
+            "{} Retry {} in {}s",
+            spinner_frame(accessible),
+            restart.attempt,
+            wait
+        )
+    } else {
+        format!(
+            "{} Reconnecting (attempt {}, next in {}s)",
+            spinner_frame(accessible),
+            restart.attempt,
+            wait
+        )
+    })
+}
+
+pub(crate) fn health_label(check: &HealthCheck) -> &'static str {
+    if check.healthy {
+        "Healthy"
+    } else {
+        "Unhealthy"
+    }
+}
+
+pub(crate) fn format_latency(latency: Duration) -> String {
+    if latency < Duration::from_millis(1) {
+        format!("{}µs", latency.as_micros())
+    } else {
+        format!("{}ms", latency.as_millis())
+    }
+}
+
+pub(crate) fn status_color(session: &Session, theme: &Theme) -> Color {
+    match &session.status {
+        SessionStatus::Starting => theme.warning,
+        SessionStatus::Stopping => theme.muted,
+        SessionStatus::Running if session.is_verifying() => theme.warning,
+        SessionStatus::Running => theme.success,
+        SessionStatus::Degraded(_) => theme.warning,
+        SessionStatus::Stopped => theme.muted,
+        SessionStatus::Error(_) if session.pending_restart.is_some() => theme.warning,
+        SessionStatus::Error(_) => theme.error,
+    }
+}
+
+pub(crate) fn status_glyph(status: &SessionStatus, accessible: bool) -> &'static str {
+    match (status, accessible) {
+        (SessionStatus::Starting, false) => "◌",
+        (SessionStatus::Stopping, false) => "◍",
+        (SessionStatus::Running, false) => "●",
+        (SessionStatus::Degraded(_), false) => "◐",
+        (SessionStatus::Stopped, false) => "○",
+        (SessionStatus::Error(_), false) => "✗",
+        (SessionStatus::Starting, true) => "[...]",
+        (SessionStatus::Stopping, true) => "[END]",
+        (SessionStatus::Running, true) => "[RUN]",
+        (SessionStatus::Degraded(_), true) => "[DEG]",
+        (SessionStatus::Stopped, true) => "[OFF]",
+        (SessionStatus::Error(_), true) => "[ERR]",
+    }
+}
+
+/// Uptime, badged when the session has been up longer than `remind_after`
+pub(crate) fn uptime_cell(
+    session: &Session,
+    remind_after: Option<chrono::Duration>,
+    accessible: bool,
+    theme: &Theme,
+) -> Cell<'static> {
+    let uptime = session.uptime_string();
+    if remind_after.is_some_and(|after| session.is_overdue(after)) {
+        let badge = if accessible { "(!)" } else { "⏰" };
+        Cell::from(format!("{} {}", badge, uptime)).style(Style::default().fg(theme.accent))
+    } else {
+        Cell::from(uptime)
+    }
+}
+
+pub(crate) fn port_mapping(session: &Session, accessible: bool) -> String {
+    if accessible {
+        session.port_mapping().replace('→', "->").replace('←', "<-")
+    } else {
+        session.port_mapping()
+    }
+}
+
+/// Name cell with the session's icon and accent color, if any, followed
+/// by its tags
+pub(crate) fn name_cell(session: &Session, theme: &Theme) -> Cell<'static> {
+    let style = session
+        .color
+        .as_deref()
+        .and_then(|c| c.parse::<Color>().ok())
+        .map(|c| Style::default().fg(c))
+        .unwrap_or_default();
+    let mut spans = vec![Span::styled(session.display_name(), style)];
+    for tag in &session.tags {
+        spans.push(Span::styled(
+            format!(" #{}", tag),
+            Style::default().fg(theme.muted),
+        ));
+    }
+    Cell::from(Line::from(spans))
+}
+
+pub(crate) fn selected_row_style(theme: &Theme) -> Style {
+    Style::default()
+        .bg(theme.selection_bg)
+        .fg(theme.selection_fg)
+        .add_modifier(Modifier::BOLD)
+}
@@ -0,0 +1,82 @@
+use crate::config::ThemeName;
+use ratatui::style::Color;
+
+/// Colors the UI draws with, by role. Picked with `theme:` in config.yaml.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Theme {
+    /// Key names in help bars and the focused form field
+    pub key: Color,
+    /// Screen titles
+    pub title: Color,
+    pub success: Color,
+    /// Transitional states, slow latency and pending changes
+    pub warning: Color,
+    pub error: Color,
+    /// Secondary text: hints, commands, placeholders
+    pub muted: Color,
+    /// Uptime reminders and informational popups
+    pub accent: Color,
+    /// Selected rows and suggestions
+    pub selection_bg: Color,
+    pub selection_fg: Color,
+    /// Behind popups so they cover the screen underneath
+    pub popup_bg: Color,
+}
+
+impl Theme {
+    /// The default, for dark terminal backgrounds
+    pub const DARK: Theme = Theme {
+        key: Color::Yellow,
+        title: Color::Cyan,
+        success: Color::Green,
+        warning: Color::Yellow,
+        error: Color::Red,
+        muted: Color::Gray,
+        accent: Color::Magenta,
+        selection_bg: Color::DarkGray,
+        selection_fg: Color::White,
+        popup_bg: Color::Black,
+    };
+
+    /// Darker foregrounds that stay readable on white/light backgrounds
+    pub const LIGHT: Theme = Theme {
+        key: Color::Blue,
+        title: Color::Blue,
+        success: Color::Indexed(28),
+        warning: Color::Indexed(130),
+        error: Color::Indexed(160),
+        muted: Color::DarkGray,
+        accent: Color::Magenta,
+        selection_bg: Color::Indexed(252),
+        selection_fg: Color::Black,
+        popup_bg: Color::White,
+    };
+
+    /// Bright colors only, with black-on-white selections
+    pub const HIGH_CONTRAST: Theme = Theme {
+        key: Color::LightYellow,
+        title: Color::LightCyan,
+        success: Color::LightGreen,
+        warning: Color::LightYellow,
+        error: Color::LightRed,
+        muted: Color::White,
+        accent: Color::LightMagenta,
+        selection_bg: Color::White,
+        selection_fg: Color::Black,
+        popup_bg: Color::Black,
+    };
+
+    pub fn named(name: ThemeName) -> Self {
+        match name {
+            ThemeName::Dark => Theme::DARK,
+            ThemeName::Light => Theme::LIGHT,
+            ThemeName::HighContrast => Theme::HIGH_CONTRAST,
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::DARK
+    }
+}
@@ -0,0 +1,93 @@
+use crate::ui::AppState;
+use crate::ui::theme::Theme;
+use ratatui::{
+    Frame,
+    layout::{Constraint, Layout, Rect},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph, Wrap},
+};
+
+pub fn render(frame: &mut Frame, state: &AppState, area: Rect) {
+    let chunks = Layout::vertical([
+        Constraint::Length(3),
+        Constraint::Min(0),
+        Constraint::Length(3),
+    ])
+    .split(area);
+
+    let title = Paragraph::new("Error Triage")
+        .style(
+            Style::default()
+                .fg(state.theme.title)
+                .add_modifier(Modifier::BOLD),
+        )
+        .block(Block::default().borders(Borders::ALL));
+    frame.render_widget(title, chunks[0]);
+
+    let groups = state.error_groups();
+    let mut lines = Vec::new();
+    if groups.is_empty() {
+        lines.push(Line::from(Span::styled(
+            "No sessions in error",
+            Style::default().fg(state.theme.success),
+        )));
+    }
+
+    for (group_idx, (category, indices)) in groups.iter().enumerate() {
+        let selected = group_idx == state.triage_selected;
+        let marker = if selected { "> " } else { "  " };
+        let mut header_style = Style::default()
+            .fg(state.theme.error)
+            .add_modifier(Modifier::BOLD);
+        if selected {
+            header_style = header_style.add_modifier(Modifier::REVERSED);
+        }
+        lines.push(Line::from(Span::styled(
+            format!("{}{} ({})", marker, category.as_str(), indices.len()),
+            header_style,
+        )));
+
+        for &idx in indices {
+            if let Some(session) = state.sessions.get(idx) {
+                let message = match &session.status {
+                    crate::models::SessionStatus::Error(err) => err.message.as_str(),
+                    _ => "",
+                };
+                lines.push(Line::from(vec![
+                    Span::raw("    "),
+                    Span::styled(
+                        session.display_name(),
+                        Style::default().add_modifier(Modifier::BOLD),
+                    ),
+                    Span::raw("  "),
+                    Span::styled(message.to_string(), Style::default().fg(state.theme.muted)),
+                ]));
+            }
+        }
+        lines.push(Line::from(""));
+    }
+
+    let list = Paragraph::new(lines).wrap(Wrap { trim: false }).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title("Errors by cause"),
+    );
+    frame.render_widget(list, chunks[1]);
+
+    render_help(frame, &state.theme, chunks[2]);
+}
+
+fn render_help(frame: &mut Frame, theme: &Theme, area: Rect) {
+    let help_text = Line::from(vec![
+        Span::styled("↑/↓", Style::default().fg(theme.key)),
+        Span::raw(" select group | "),
+        Span::styled("r", Style::default().fg(theme.key)),
+        Span::raw(" retry group | "),
+        Span::styled("Esc", Style::default().fg(theme.key)),
+        Span::raw(" back"),
+    ]);
+
+    let help = Paragraph::new(help_text).block(Block::default().borders(Borders::ALL));
+    frame.render_widget(help, area);
+}
@@ -0,0 +1,165 @@
+use crate::ui::AppState;
+use crate::ui::theme::Theme;
+use crate::usage::{UsageSummary, format_duration};
+use chrono::Duration;
+use ratatui::{
+    Frame,
+    layout::{Constraint, Layout, Rect},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Cell, Paragraph, Row, Table, Wrap},
+};
+
+pub fn render(frame: &mut Frame, state: &AppState, area: Rect) {
+    let Some(summary) = &state.usage_summary else {
+        return;
+    };
+
+    // Header and borders around the reliability rows, at most 10 of them
+    let reliability_height = summary.reliability.len().clamp(1, 10) as u16 + 3;
+    let chunks = Layout::vertical([
+        Constraint::Length(3),
+        Constraint::Min(0),
+        Constraint::Length(reliability_height),
+        Constraint::Length(5),
+        Constraint::Length(3),
+    ])
+    .split(area);
+
+    let title = Paragraph::new(format!("Usage Summary - {}", summary.period.as_str()))
+        .style(
+            Style::default()
+                .fg(state.theme.title)
+                .add_modifier(Modifier::BOLD),
+        )
+        .block(Block::default().borders(Borders::ALL));
+    frame.render_widget(title, chunks[0]);
+
+    let header = Row::new(vec![
+        Cell::from("Session").style(Style::default().add_modifier(Modifier::BOLD)),
+        Cell::from("Runs").style(Style::default().add_modifier(Modifier::BOLD)),
+        Cell::from("Uptime").style(Style::default().add_modifier(Modifier::BOLD)),
+        Cell::from("Crashes").style(Style::default().add_modifier(Modifier::BOLD)),
+    ]);
+    let rows: Vec<Row> = summary
+        .used
+        .iter()
+        .map(|usage| {
+            let crash_style = if usage.crashes > 0 {
+                Style::default().fg(state.theme.error)
+            } else {
+                Style::default()
+            };
+            Row::new(vec![
+                Cell::from(usage.name.clone()),
+                Cell::from(usage.runs.to_string()),
+                Cell::from(format_duration(usage.uptime)),
+                Cell::from(usage.crashes.to_string()).style(crash_style),
+            ])
+        })
+        .collect();
+    let widths = [
+        Constraint::Fill(1),
+        Constraint::Length(6),
+        Constraint::Length(10),
+        Constraint::Length(8),
+    ];
+    let table = Table::new(rows, widths).header(header).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(format!("Used ({})", summary.used.len())),
+    );
+    frame.render_widget(table, chunks[1]);
+
+    render_reliability(frame, state, summary, chunks[2]);
+
+    let unused_text = if summary.unused.is_empty() {
+        "Every session ran during this period".to_string()
+    } else {
+        summary.unused.join(", ")
+    };
+    let unused = Paragraph::new(unused_text)
+        .style(Style::default().fg(state.theme.muted))
+        .wrap(Wrap { trim: true })
+        .block(Block::default().borders(Borders::ALL).title(format!(
+            "Unused - archive candidates ({})",
+            summary.unused.len()
+        )));
+    frame.render_widget(unused, chunks[3]);
+
+    render_help(frame, &state.theme, chunks[4]);
+}
+
+/// Share of last week's runs that didn't fail, least reliable first, so
+/// flaky bastions stand out; all-time totals alongside
+fn render_reliability(frame: &mut Frame, state: &AppState, summary: &UsageSummary, area: Rect) {
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title("Reliability - Last 7 days");
+    if summary.reliability.is_empty() {
+        let empty = Paragraph::new("Nothing ran during the last 7 days")
+            .style(Style::default().fg(state.theme.muted))
+            .block(block);
+        frame.render_widget(empty, area);
+        return;
+    }
+
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let header = Row::new(
+        [
+            "Session",
+            "Reliable",
+            "Runs",
+            "Failed",
+            "All-time starts",
+            "Crashes",
+            "Uptime",
+        ]
+        .map(|title| Cell::from(title).style(bold)),
+    );
+    let rows: Vec<Row> = summary
+        .reliability
+        .iter()
+        .map(|r| {
+            let percent = r.percent();
+            let color = match percent {
+                100 => state.theme.success,
+                90.. => state.theme.warning,
+                _ => state.theme.error,
+            };
+            Row::new(vec![
+                Cell::from(r.name.clone()),
+                Cell::from(format!("{}%", percent)).style(Style::default().fg(color)),
+                Cell::from(r.runs.to_string()),
+                Cell::from(r.failures.to_string()),
+                Cell::from(r.stats.starts.to_string()),
+                Cell::from(r.stats.crashes.to_string()),
+                Cell::from(format_duration(Duration::seconds(
+                    r.stats.uptime_secs as i64,
+                ))),
+            ])
+        })
+        .collect();
+    let widths = [
+        Constraint::Fill(1),
+        Constraint::Length(9),
+        Constraint::Length(6),
+        Constraint::Length(8),
+        Constraint::Length(16),
+        Constraint::Length(8),
+        Constraint::Length(10),
+    ];
+    frame.render_widget(Table::new(rows, widths).header(header).block(block), area);
+}
+
+fn render_help(frame: &mut Frame, theme: &Theme, area: Rect) {
+    let help_text = Line::from(vec![
+        Span::styled("w", Style::default().fg(theme.key)),
+        Span::raw(" daily/weekly | "),
+        Span::styled("Esc", Style::default().fg(theme.key)),
+        Span::raw(" back"),
+    ]);
+
+    let help = Paragraph::new(help_text).block(Block::default().borders(Borders::ALL));
+    frame.render_widget(help, area);
+}
@@ -0,0 +1,161 @@
+use crate::models::{Session, SessionStats, SessionStatus};
+use crate::runs::{self, Run};
+use crate::storage::Storage;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SummaryPeriod {
+    Daily,
+    Weekly,
+}
+
+impl SummaryPeriod {
+    pub fn as_str(&self) -> &str {
+        match self {
+            SummaryPeriod::Daily => "Last 24 hours",
+            SummaryPeriod::Weekly => "Last 7 days",
+        }
+    }
+
+    pub fn duration(&self) -> Duration {
+        match self {
+            SummaryPeriod::Daily => Duration::days(1),
+            SummaryPeriod::Weekly => Duration::days(7),
+        }
+    }
+
+    pub fn toggle(&self) -> Self {
+        match self {
+            SummaryPeriod::Daily => SummaryPeriod::Weekly,
+            SummaryPeriod::Weekly => SummaryPeriod::Daily,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SessionUsage {
+    pub id: Uuid,
+    pub name: String,
+    pub runs: usize,
+    pub uptime: Duration,
+    pub crashes: usize,
+}
+
+/// How dependable a session was over the last week, next to its totals
+#[derive(Debug, Clone)]
+pub struct Reliability {
+    pub name: String,
+    /// Runs that ended during the week or are still up
+    pub runs: usize,
+    /// Of those, runs that crashed or failed to come up
+    pub failures: usize,
+    pub stats: SessionStats,
+}
+
+impl Reliability {
+    /// Share of the week's runs that didn't fail, in percent
+    pub fn percent(&self) -> u32 {
+        (self.runs.saturating_sub(self.failures) * 100 / self.runs.max(1)) as u32
+    }
+}
+
+/// What ran during a period, from the sessions' run histories
+#[derive(Debug, Clone)]
+pub struct UsageSummary {
+    pub period: SummaryPeriod,
+    /// Sessions that ran during the period, longest uptime first
+    pub used: Vec<SessionUsage>,
+    /// Sessions that never ran during the period, candidates for archiving
+    pub unused: Vec<String>,
+    /// Sessions that ran during the last week, least reliable first
+    pub reliability: Vec<Reliability>,
+}
+
+impl UsageSummary {
+    pub fn collect(storage: &Storage, sessions: &[Session], period: SummaryPeriod) -> Self {
+        let now = Utc::now();
+        let since = now - period.duration();
+
+        let mut used = Vec::new();
+        let mut unused = Vec::new();
+        let mut reliability = Vec::new();
+        for session in sessions {
+            let runs = runs::history(storage, &session.id);
+            let usage = session_usage(session, &runs, since, now);
+            if usage.runs > 0 {
+                used.push(usage);
+            } else {
+                unused.push(session.name.clone());
+            }
+            let week = session_usage(session, &runs, now - SummaryPeriod::Weekly.duration(), now);
+            if week.runs > 0 {
+                reliability.push(Reliability {
+                    name: session.name.clone(),
+                    runs: week.runs,
+                    failures: week.crashes,
+                    stats: session.stats.clone(),
+                });
+            }
+        }
+        used.sort_by_key(|usage| std::cmp::Reverse(usage.uptime));
+        reliability.sort_by_key(|r| (r.percent(), std::cmp::Reverse(r.failures)));
+
+        Self {
+            period,
+            used,
+            unused,
+            reliability,
+        }
+    }
+}
+
+fn session_usage(
+    session: &Session,
+    runs: &[Run],
+    since: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> SessionUsage {
+    let mut usage = SessionUsage {
+        id: session.id,
+        name: session.name.clone(),
+        runs: 0,
+        uptime: Duration::zero(),
+        crashes: 0,
+    };
+    let still_up = matches!(
+        session.status,
+        SessionStatus::Running | SessionStatus::Degraded(_) | SessionStatus::Starting
+    );
+    let last = runs.len().saturating_sub(1);
+    for (idx, run) in runs.iter().enumerate() {
+        let end = match &run.end {
+            Some(end) => end.at,
+            // Only the latest run can still be up; earlier ones lost their end
+            None if idx == last && still_up => now,
+            None => continue,
+        };
+        if end > since {
+            usage.runs += 1;
+            usage.uptime += end - run.started_at.max(since);
+        }
+        if run.failed() && end >= since {
+            usage.crashes += 1;
+        }
+    }
+    usage
+}
+
+/// Compact uptime such as "3h 12m" or "45m"
+pub fn format_duration(duration: Duration) -> String {
+    let minutes = duration.num_minutes();
+    if minutes >= 60 {
+        format!("{}h {}m", minutes / 60, minutes % 60)
+    } else if minutes > 0 {
+        format!("{}m", minutes)
+    } else {
+        format!("{}s", duration.num_seconds())
+    }
+}
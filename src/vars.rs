@@ -0,0 +1,64 @@
+//! `${VAR}` references in session fields, filled in from the environment
+//! when the command is built. A shared sessions file can then say
+//! `${USER}@bastion.prod` or `--context ${KUBE_CONTEXT}` and work for
+//! every teammate, while `sessions.yaml` keeps the references.
+
+use crate::models::Session;
+
+/// `text` with each `${NAME}` replaced by that environment variable and
+/// each `${NAME:-fallback}` by the fallback when the variable is unset or
+/// empty. Anything else, including a bare `$NAME`, is left alone.
+pub fn expand(text: &str) -> Result<String, String> {
+    let mut expanded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("${") {
+        expanded.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('}') else {
+            return Err(format!("Unclosed `${{` in {}", text));
+        };
+        let reference = &rest[start + 2..start + end];
+        let (name, fallback) = match reference.split_once(":-") {
+            Some((name, fallback)) => (name, Some(fallback)),
+            None => (reference, None),
+        };
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(format!("Invalid variable `${{{}}}` in {}", reference, text));
+        }
+        match (lookup(name), fallback) {
+            (Some(value), _) => expanded.push_str(&value),
+            (None, Some(fallback)) => expanded.push_str(fallback),
+            (None, None) => return Err(format!("Environment variable {} is not set", name)),
+        }
+        rest = &rest[start + end + 1..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// Set and non-empty value of `name`. `USER` falls back to the other
+/// names the login user goes by, as it is missing in some containers.
+fn lookup(name: &str) -> Option<String> {
+    let names: &[&str] = match name {
+        "USER" => &["USER", "LOGNAME", "USERNAME"],
+        _ => &[name],
+    };
+    names
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty())
+}
+
+/// Copy of `session` with the target, kube context, namespace and
+/// additional options expanded
+pub fn expand_session(session: &Session) -> Result<Session, String> {
+    let mut expanded = session.clone();
+    expanded.target = expand(&session.target)?;
+    expanded.kube_context = session.kube_context.as_deref().map(expand).transpose()?;
+    expanded.kube_namespace = session.kube_namespace.as_deref().map(expand).transpose()?;
+    expanded.additional_options = session
+        .additional_options
+        .iter()
+        .map(|opt| expand(opt))
+        .collect::<Result<_, _>>()?;
+    Ok(expanded)
+}
@@ -0,0 +1,204 @@
+//! `pfman watch`: a read-only status view for a small tmux pane.
+//!
+//! Sessions are re-read from disk on every refresh, so the view follows
+//! whatever the main pfman instance (or `pfman apply`) does. Nothing is
+//! started, stopped or written from here, except when attached to the
+//! supervising instance (see [`attach`]): then `s` asks it to start or stop
+//! the selected session over its IPC socket.
+
+use crate::config::Config;
+use crate::ipc;
+use crate::models::{Session, SessionError, SessionStatus};
+use crate::storage::Storage;
+use crate::ui::dashboard::{COMPACT_MAX_WIDTH, compact_table};
+use crate::ui::session_table::SessionTable;
+use crate::ui::strip_colors;
+use crate::ui::theme::Theme;
+use color_eyre::Result;
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::{
+    DefaultTerminal, Frame,
+    widgets::{Block, Borders},
+};
+use serde_json::json;
+use std::time::{Duration, Instant};
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, RefreshKind, System};
+
+const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Entry point for `pfman watch`
+pub fn run(_args: &[String]) -> Result<()> {
+    let storage = Storage::new()?;
+    let config = storage.load_config()?;
+
+    let terminal = ratatui::init();
+    let result = watch_loop(terminal, &storage, &config, None);
+    ratatui::restore();
+    result
+}
+
+/// The watch view of a client attached to the instance serving the IPC
+/// socket at `socket`, which starts and stops sessions for it
+pub fn attach(socket: &str) -> Result<()> {
+    let storage = Storage::new()?;
+    let config = storage.load_config()?;
+
+    let terminal = ratatui::init();
+    let result = watch_loop(terminal, &storage, &config, Some(socket));
+    ratatui::restore();
+    result
+}
+
+fn watch_loop(
+    mut terminal: DefaultTerminal,
+    storage: &Storage,
+    config: &Config,
+    socket: Option<&str>,
+) -> Result<()> {
+    let theme = Theme::named(config.theme);
+    let mut sessions = load(storage);
+    let mut last_refresh = Instant::now();
+    // Only attached clients select and act on sessions
+    let mut selected = socket.map(|_| 0);
+    let mut message: Option<String> = None;
+
+    loop {
+        if let Some(idx) = &mut selected {
+            *idx = (*idx).min(sessions.len().saturating_sub(1));
+        }
+        terminal.draw(|frame| {
+            render(
+                frame,
+                &sessions,
+                selected,
+                message.as_deref(),
+                config,
+                &theme,
+            )
+        })?;
+
+        // Short polls keep the spinner moving between refreshes
+        if event::poll(Duration::from_millis(250))?
+            && let Event::Key(key) = event::read()?
+            && key.kind == KeyEventKind::Press
+        {
+            match (key.modifiers, key.code) {
+                (KeyModifiers::CONTROL, KeyCode::Char('c')) | (_, KeyCode::Char('q' | 'Q')) => {
+                    return Ok(());
+                }
+                (_, KeyCode::Esc) => return Ok(()),
+                (_, KeyCode::Up | KeyCode::Char('k')) if let Some(idx) = &mut selected => {
+                    *idx = idx.saturating_sub(1);
+                }
+                (_, KeyCode::Down | KeyCode::Char('j')) if let Some(idx) = &mut selected => {
+                    *idx += 1;
+                }
+                (_, KeyCode::Char('s'))
+                    if let (Some(socket), Some(session)) =
+                        (socket, selected.and_then(|idx| sessions.get(idx))) =>
+                {
+                    message = Some(toggle(socket, session));
+                    sessions = load(storage);
+                    last_refresh = Instant::now();
+                }
+                _ => {}
+            }
+        }
+
+        if last_refresh.elapsed() >= REFRESH_INTERVAL {
+            sessions = load(storage);
+            last_refresh = Instant::now();
+        }
+    }
+}
+
+/// Ask the supervising instance to start `session`, or stop it if it's up;
+/// returns what to tell the user
+fn toggle(socket: &str, session: &Session) -> String {
+    let command = if session.pid.is_some() {
+        "stop"
+    } else {
+        "start"
+    };
+    let request = json!({ "command": command, "session": session.id.to_string() });
+    match ipc::request(socket, request) {
+        Ok(_) if command == "stop" => format!("Stopped {}", session.name),
+        Ok(_) => format!("Starting {}", session.name),
+        Err(err) => format!("{}: {}", session.name, err),
+    }
+}
+
+/// Saved sessions, with ones whose process is gone shown as exited even if
+/// no pfman instance has noticed yet
+fn load(storage: &Storage) -> Vec<Session> {
+    let mut sessions = storage.load_sessions().unwrap_or_default();
+    let pids: Vec<Pid> = sessions
+        .iter()
+        .filter_map(|s| s.pid)
+        .map(Pid::from_u32)
+        .collect();
+    let mut sys =
+        System::new_with_specifics(RefreshKind::new().with_processes(ProcessRefreshKind::new()));
+    sys.refresh_processes(ProcessesToUpdate::Some(&pids), true);
+
+    for session in sessions.iter_mut() {
+        if let Some(pid) = session.pid
+            && sys.process(Pid::from_u32(pid)).is_none()
+        {
+            session.status = SessionStatus::Error(SessionError::new("Process exited"));
+            session.pid = None;
+        }
+    }
+    sessions
+}
+
+fn render(
+    frame: &mut Frame,
+    sessions: &[Session],
+    selected: Option<usize>,
+    message: Option<&str>,
+    config: &Config,
+    theme: &Theme,
+) {
+    let area = frame.area();
+    let up = sessions.iter().filter(|s| s.status.is_running()).count();
+    let mut title = format!(
+        "pfman {}/{} up {}",
+        up,
+        sessions.len(),
+        chrono::Local::now().format("%H:%M:%S")
+    );
+    if selected.is_some() {
+        title.push_str(" (attached, s start/stop)");
+    }
+    if let Some(message) = message {
+        title.push_str(&format!(" {}", message));
+    }
+    // Borders only when there's room for at least one row inside them
+    let block = if area.height >= 3 {
+        Block::default().borders(Borders::ALL).title(title)
+    } else {
+        Block::default()
+    };
+
+    if area.width < COMPACT_MAX_WIDTH {
+        let refs: Vec<&Session> = sessions.iter().collect();
+        frame.render_widget(
+            compact_table(&refs, selected, config.accessible, theme).block(block),
+            area,
+        );
+    } else {
+        let table = SessionTable::new(sessions)
+            .selected(selected.and_then(|idx| sessions.get(idx)).map(|s| s.id))
+            .accessible(config.accessible)
+            .theme(*theme)
+            .truncation(config.truncation)
+            .column_widths(config.column_widths)
+            .block(block);
+        frame.render_widget(table, area);
+    }
+
+    if config.monochrome() {
+        strip_colors(frame, theme);
+    }
+}